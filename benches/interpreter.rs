@@ -0,0 +1,50 @@
+use codecrafters_interpreter::Lox;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+const FIB: &str = "
+  fun fib(n) {
+    if (n < 2) return n;
+    return fib(n - 1) + fib(n - 2);
+  }
+  print fib(18);
+";
+
+const STRING_CONCATENATION: &str = "
+  var s = \"\";
+  for (var i = 0; i < 1000; i = i + 1) {
+    s = s + \"x\";
+  }
+  print len(s);
+";
+
+const DEEP_CLOSURES: &str = "
+  fun wrap(f, depth) {
+    if (depth == 0) return f;
+    fun wrapped(x) { return f(x) + 1; }
+    return wrap(wrapped, depth - 1);
+  }
+  fun identity(x) { return x; }
+  var deep = wrap(identity, 100);
+  var total = 0;
+  for (var i = 0; i < 50; i = i + 1) {
+    total = total + deep(i);
+  }
+  print total;
+";
+
+fn run(source: &str) {
+  let mut output: Vec<u8> = vec![];
+  Lox::run(source, &mut output).unwrap();
+  black_box(output);
+}
+
+fn programs(c: &mut Criterion) {
+  c.bench_function("fib", |b| b.iter(|| run(FIB)));
+  c.bench_function("string concatenation", |b| {
+    b.iter(|| run(STRING_CONCATENATION))
+  });
+  c.bench_function("deep closures", |b| b.iter(|| run(DEEP_CLOSURES)));
+}
+
+criterion_group!(benches, programs);
+criterion_main!(benches);
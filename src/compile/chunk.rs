@@ -0,0 +1,173 @@
+use crate::scan::number_format::format_number;
+use std::fmt::{Display, Formatter};
+
+/// Bytecode instructions. Operands live inline: indexes into the constant table, stack slots,
+/// upvalue slots, argument counts or absolute jump targets.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OpCode {
+  Constant(usize),
+  Nil,
+  True,
+  False,
+  Pop,
+  GetLocal(usize),
+  SetLocal(usize),
+  GetGlobal(usize),
+  DefineGlobal(usize),
+  SetGlobal(usize),
+  GetUpvalue(usize),
+  SetUpvalue(usize),
+  GetProperty(usize),
+  SetProperty(usize),
+  GetSuper(usize),
+  Equal,
+  Greater,
+  Less,
+  Add,
+  Subtract,
+  Multiply,
+  Divide,
+  Power,
+  BitAnd,
+  BitOr,
+  BitXor,
+  BitNot,
+  ShiftLeft,
+  ShiftRight,
+  Not,
+  Negate,
+  Print,
+  Jump(usize),
+  JumpIfFalse(usize),
+  Loop(usize),
+  Call(usize),
+  Closure(usize, Vec<UpvalueRef>),
+  CloseUpvalue,
+  Return,
+  Class(usize),
+  Inherit,
+  Method(usize),
+}
+
+impl OpCode {
+  pub fn name(&self) -> &'static str {
+    match self {
+      OpCode::Constant(_) => "OP_CONSTANT",
+      OpCode::Nil => "OP_NIL",
+      OpCode::True => "OP_TRUE",
+      OpCode::False => "OP_FALSE",
+      OpCode::Pop => "OP_POP",
+      OpCode::GetLocal(_) => "OP_GET_LOCAL",
+      OpCode::SetLocal(_) => "OP_SET_LOCAL",
+      OpCode::GetGlobal(_) => "OP_GET_GLOBAL",
+      OpCode::DefineGlobal(_) => "OP_DEFINE_GLOBAL",
+      OpCode::SetGlobal(_) => "OP_SET_GLOBAL",
+      OpCode::GetUpvalue(_) => "OP_GET_UPVALUE",
+      OpCode::SetUpvalue(_) => "OP_SET_UPVALUE",
+      OpCode::GetProperty(_) => "OP_GET_PROPERTY",
+      OpCode::SetProperty(_) => "OP_SET_PROPERTY",
+      OpCode::GetSuper(_) => "OP_GET_SUPER",
+      OpCode::Equal => "OP_EQUAL",
+      OpCode::Greater => "OP_GREATER",
+      OpCode::Less => "OP_LESS",
+      OpCode::Add => "OP_ADD",
+      OpCode::Subtract => "OP_SUBTRACT",
+      OpCode::Multiply => "OP_MULTIPLY",
+      OpCode::Divide => "OP_DIVIDE",
+      OpCode::Power => "OP_POWER",
+      OpCode::BitAnd => "OP_BIT_AND",
+      OpCode::BitOr => "OP_BIT_OR",
+      OpCode::BitXor => "OP_BIT_XOR",
+      OpCode::BitNot => "OP_BIT_NOT",
+      OpCode::ShiftLeft => "OP_SHIFT_LEFT",
+      OpCode::ShiftRight => "OP_SHIFT_RIGHT",
+      OpCode::Not => "OP_NOT",
+      OpCode::Negate => "OP_NEGATE",
+      OpCode::Print => "OP_PRINT",
+      OpCode::Jump(_) => "OP_JUMP",
+      OpCode::JumpIfFalse(_) => "OP_JUMP_IF_FALSE",
+      OpCode::Loop(_) => "OP_LOOP",
+      OpCode::Call(_) => "OP_CALL",
+      OpCode::Closure(..) => "OP_CLOSURE",
+      OpCode::CloseUpvalue => "OP_CLOSE_UPVALUE",
+      OpCode::Return => "OP_RETURN",
+      OpCode::Class(_) => "OP_CLASS",
+      OpCode::Inherit => "OP_INHERIT",
+      OpCode::Method(_) => "OP_METHOD",
+    }
+  }
+}
+
+/// Where a closure finds a captured variable: a local slot of the enclosing function, or one of
+/// the enclosing function's own upvalues.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UpvalueRef {
+  pub is_local: bool,
+  pub index: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Constant {
+  Number(f64),
+  String(String),
+  Function(Function),
+}
+
+impl Display for Constant {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Constant::Number(value) => f.write_str(&format_number(*value)),
+      Constant::String(value) => f.write_str(value),
+      Constant::Function(function) => write!(f, "{function}"),
+    }
+  }
+}
+
+/// A compiled function. The top-level program is compiled as a function called `<script>`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+  pub name: String,
+  pub arity: usize,
+  pub upvalue_count: usize,
+  pub chunk: Chunk,
+}
+
+impl Function {
+  pub fn new(name: &str) -> Function {
+    Function {
+      name: name.to_string(),
+      arity: 0,
+      upvalue_count: 0,
+      chunk: Chunk::default(),
+    }
+  }
+}
+
+impl Display for Function {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(f, "<fn {}>", self.name)
+  }
+}
+
+/// Instructions of a function, with the source line of every instruction and the constants they
+/// refer to.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Chunk {
+  pub code: Vec<OpCode>,
+  pub lines: Vec<usize>,
+  pub constants: Vec<Constant>,
+}
+
+impl Chunk {
+  /// Appends an instruction and returns its offset.
+  pub fn write(&mut self, op: OpCode, line: usize) -> usize {
+    self.code.push(op);
+    self.lines.push(line);
+    self.code.len() - 1
+  }
+
+  pub fn add_constant(&mut self, constant: Constant) -> usize {
+    self.constants.push(constant);
+    self.constants.len() - 1
+  }
+}
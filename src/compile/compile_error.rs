@@ -0,0 +1,23 @@
+use thiserror::Error;
+
+/// Code the bytecode compiler can't compile yet. The tree-walking interpreter runs all of it.
+#[derive(Error, Debug, PartialEq)]
+pub enum CompileError {
+  #[error("[line {0}]: {1} can't be compiled to bytecode yet.")]
+  Unsupported(usize, String),
+}
+
+impl CompileError {
+  /// Stable code of the error, which `explain` describes.
+  pub fn code(&self) -> &'static str {
+    match self {
+      CompileError::Unsupported(..) => "E0301",
+    }
+  }
+
+  pub fn line(&self) -> usize {
+    match self {
+      CompileError::Unsupported(line, _) => *line,
+    }
+  }
+}
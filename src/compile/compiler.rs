@@ -0,0 +1,897 @@
+use crate::compile::chunk::{Chunk, Constant, Function, OpCode, UpvalueRef};
+use crate::compile::compile_error::CompileError;
+use crate::parse::expr::{Expr, ExprKind};
+use crate::parse::stmt::{Stmt, StmtKind};
+use crate::scan::token_kind::TokenKind;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FunctionKind {
+  Script,
+  Function,
+  Method,
+  Initializer,
+}
+
+struct Local {
+  name: String,
+  depth: usize,
+  captured: bool,
+}
+
+/// Jumps out of a loop body. They are patched once the loop is fully compiled.
+struct LoopState {
+  scope_depth: usize,
+  breaks: Vec<usize>,
+  continues: Vec<usize>,
+}
+
+struct FunctionState {
+  function: Function,
+  kind: FunctionKind,
+  locals: Vec<Local>,
+  upvalues: Vec<UpvalueRef>,
+  scope_depth: usize,
+  loops: Vec<LoopState>,
+}
+
+impl FunctionState {
+  fn new(name: &str, kind: FunctionKind) -> FunctionState {
+    // Slot zero holds the function being called. Inside methods it holds the instance instead.
+    let slot_zero = match kind {
+      FunctionKind::Method | FunctionKind::Initializer => "this",
+      FunctionKind::Script | FunctionKind::Function => "",
+    };
+
+    FunctionState {
+      function: Function::new(name),
+      kind,
+      locals: vec![Local {
+        name: slot_zero.to_string(),
+        depth: 0,
+        captured: false,
+      }],
+      upvalues: vec![],
+      scope_depth: 0,
+      loops: vec![],
+    }
+  }
+
+  fn resolve_local(&self, name: &str) -> Option<usize> {
+    self.locals.iter().rposition(|local| local.name == name)
+  }
+}
+
+/// Compiles a resolved program into bytecode, clox style: locals live in stack slots, globals
+/// are looked up by name and captured variables become upvalues. Statements chunks can't run yet
+/// are reported instead of being left out.
+pub struct Compiler {
+  states: Vec<FunctionState>,
+  line: usize,
+  errors: Vec<CompileError>,
+}
+
+impl Default for Compiler {
+  fn default() -> Self {
+    Compiler::new()
+  }
+}
+
+impl Compiler {
+  pub fn new() -> Compiler {
+    Compiler {
+      states: vec![],
+      line: 1,
+      errors: vec![],
+    }
+  }
+
+  pub fn compile(mut self, stmts: &[Stmt]) -> Result<Function, Vec<CompileError>> {
+    self
+      .states
+      .push(FunctionState::new("<script>", FunctionKind::Script));
+    for stmt in stmts {
+      self.stmt(stmt);
+    }
+    let script = self.end_function().0;
+    if self.errors.is_empty() {
+      Ok(script)
+    } else {
+      Err(self.errors)
+    }
+  }
+
+  fn unsupported(&mut self, line: usize, what: &str) {
+    self
+      .errors
+      .push(CompileError::Unsupported(line, what.to_string()));
+  }
+
+  fn stmt(&mut self, stmt: &Stmt) {
+    match &stmt.kind {
+      StmtKind::Expr(expr) => {
+        self.expr(expr);
+        self.emit(OpCode::Pop);
+      }
+      StmtKind::Print(expr, _) => {
+        self.expr(expr);
+        self.emit(OpCode::Print);
+      }
+      StmtKind::Var(name, value, line) => {
+        self.line = *line;
+        match value {
+          Some(value) => self.expr(value),
+          None => {
+            self.emit(OpCode::Nil);
+          }
+        }
+        self.define_variable(name);
+      }
+      StmtKind::ScopeBlock(stmts) => {
+        self.begin_scope();
+        for stmt in stmts {
+          self.stmt(stmt);
+        }
+        self.end_scope();
+      }
+      StmtKind::If {
+        condition,
+        then,
+        els,
+        line,
+      } => {
+        self.line = *line;
+        self.expr(condition);
+        let then_jump = self.emit(OpCode::JumpIfFalse(0));
+        self.emit(OpCode::Pop);
+        self.stmt(then);
+        let else_jump = self.emit(OpCode::Jump(0));
+        self.patch_jump(then_jump);
+        self.emit(OpCode::Pop);
+        if let Some(els) = els {
+          self.stmt(els);
+        }
+        self.patch_jump(else_jump);
+      }
+      StmtKind::While {
+        condition,
+        body,
+        line,
+      } => {
+        self.line = *line;
+        self.loop_stmt(Some(condition), body, None);
+      }
+      StmtKind::DoWhile {
+        body,
+        condition,
+        line,
+      } => {
+        self.line = *line;
+        self.do_while_stmt(body, condition);
+      }
+      StmtKind::For {
+        initializer,
+        condition,
+        increment,
+        body,
+        line,
+      } => {
+        self.begin_scope();
+        if let Some(initializer) = initializer {
+          self.stmt(initializer);
+        }
+        self.line = *line;
+        self.loop_stmt(condition.as_ref(), body, increment.as_ref());
+        self.end_scope();
+      }
+      StmtKind::Break(line) => {
+        self.line = *line;
+        self.loop_exit(true);
+      }
+      StmtKind::Continue(line) => {
+        self.line = *line;
+        self.loop_exit(false);
+      }
+      // Chunks can't raise errors yet, so they can't fail assertions either.
+      StmtKind::Assert { line, .. } => self.unsupported(*line, "`assert`"),
+      StmtKind::Throw(_, line) => self.unsupported(*line, "`throw`"),
+      // Chunks can't recover from errors.
+      StmtKind::Try { line, .. } => self.unsupported(*line, "`try`"),
+      // Chunks only hold the code of one file.
+      StmtKind::Import(_, line) => self.unsupported(*line, "`import`"),
+      StmtKind::Return(value, line) => {
+        self.line = *line;
+        if self.state().kind == FunctionKind::Initializer {
+          self.emit(OpCode::GetLocal(0));
+        } else {
+          self.expr(value);
+        }
+        self.emit(OpCode::Return);
+      }
+      StmtKind::Function { name, params, body } => {
+        // Local functions take their slot before the body is compiled, so they can recurse.
+        if self.state().scope_depth > 0 {
+          self.add_local(name);
+          self.function(name, params, body, FunctionKind::Function);
+        } else {
+          self.function(name, params, body, FunctionKind::Function);
+          self.define_variable(name);
+        }
+      }
+      StmtKind::Class {
+        name,
+        superclass,
+        methods,
+        class_methods,
+        getters,
+      } => {
+        if let Some(method) = class_methods.first() {
+          self.unsupported(method.span.line, "class methods");
+        }
+        if let Some(getter) = getters.first() {
+          self.unsupported(getter.span.line, "getters");
+        }
+        self.class(name, superclass.as_ref(), methods);
+      }
+    }
+  }
+
+  /// Compiles `while` and `for` loops. Loops without a condition have no exit jump.
+  fn loop_stmt(&mut self, condition: Option<&Expr>, body: &Stmt, increment: Option<&Expr>) {
+    let loop_start = self.chunk().code.len();
+    let exit_jump = condition.map(|condition| {
+      self.expr(condition);
+      let exit_jump = self.emit(OpCode::JumpIfFalse(0));
+      self.emit(OpCode::Pop);
+      exit_jump
+    });
+
+    let scope_depth = self.state().scope_depth;
+    self.state().loops.push(LoopState {
+      scope_depth,
+      breaks: vec![],
+      continues: vec![],
+    });
+    self.stmt(body);
+    let loop_state = self
+      .state()
+      .loops
+      .pop()
+      .expect("loop state was just pushed");
+
+    for jump in loop_state.continues {
+      self.patch_jump(jump);
+    }
+    if let Some(increment) = increment {
+      self.expr(increment);
+      self.emit(OpCode::Pop);
+    }
+    self.emit(OpCode::Loop(loop_start));
+
+    if let Some(exit_jump) = exit_jump {
+      self.patch_jump(exit_jump);
+      self.emit(OpCode::Pop);
+    }
+    for jump in loop_state.breaks {
+      self.patch_jump(jump);
+    }
+  }
+
+  /// The body runs first, so the only exit jump is after the condition, at the end.
+  fn do_while_stmt(&mut self, body: &Stmt, condition: &Expr) {
+    let loop_start = self.chunk().code.len();
+    let scope_depth = self.state().scope_depth;
+    self.state().loops.push(LoopState {
+      scope_depth,
+      breaks: vec![],
+      continues: vec![],
+    });
+    self.stmt(body);
+    let loop_state = self
+      .state()
+      .loops
+      .pop()
+      .expect("loop state was just pushed");
+
+    for jump in loop_state.continues {
+      self.patch_jump(jump);
+    }
+    self.expr(condition);
+    let exit_jump = self.emit(OpCode::JumpIfFalse(0));
+    self.emit(OpCode::Pop);
+    self.emit(OpCode::Loop(loop_start));
+
+    self.patch_jump(exit_jump);
+    self.emit(OpCode::Pop);
+    for jump in loop_state.breaks {
+      self.patch_jump(jump);
+    }
+  }
+
+  /// `break` and `continue` discard the locals declared inside the loop body before jumping. The
+  /// compiler keeps tracking them, since the code after the jump still uses their slots.
+  fn loop_exit(&mut self, is_break: bool) {
+    let Some(loop_depth) = self.state().loops.last().map(|l| l.scope_depth) else {
+      return;
+    };
+
+    let cleanup = self
+      .state()
+      .locals
+      .iter()
+      .rev()
+      .take_while(|local| local.depth > loop_depth)
+      .map(|local| {
+        if local.captured {
+          OpCode::CloseUpvalue
+        } else {
+          OpCode::Pop
+        }
+      })
+      .collect::<Vec<_>>();
+    for op in cleanup {
+      self.emit(op);
+    }
+
+    let jump = self.emit(OpCode::Jump(0));
+    if let Some(loop_state) = self.state().loops.last_mut() {
+      if is_break {
+        loop_state.breaks.push(jump);
+      } else {
+        loop_state.continues.push(jump);
+      }
+    }
+  }
+
+  fn function(&mut self, name: &str, params: &[String], body: &[Stmt], kind: FunctionKind) {
+    let declaration_line = self.line;
+    self.states.push(FunctionState::new(name, kind));
+    self.state().function.arity = params.len();
+    self.begin_scope();
+    for param in params {
+      self.add_local(param);
+    }
+    for stmt in body {
+      self.stmt(stmt);
+    }
+
+    let (function, upvalues) = self.end_function();
+    self.line = declaration_line;
+    let constant = self.constant(Constant::Function(function));
+    self.emit(OpCode::Closure(constant, upvalues));
+  }
+
+  fn end_function(&mut self) -> (Function, Vec<UpvalueRef>) {
+    if self.state().kind == FunctionKind::Initializer {
+      self.emit(OpCode::GetLocal(0));
+    } else {
+      self.emit(OpCode::Nil);
+    }
+    self.emit(OpCode::Return);
+
+    let state = self.states.pop().expect("function state was pushed");
+    let mut function = state.function;
+    function.upvalue_count = state.upvalues.len();
+    (function, state.upvalues)
+  }
+
+  fn class(&mut self, name: &str, superclass: Option<&Expr>, methods: &[Stmt]) {
+    let name_constant = self.identifier(name);
+    self.emit(OpCode::Class(name_constant));
+    self.define_variable(name);
+
+    if let Some(superclass) = superclass {
+      self.expr(superclass);
+      self.begin_scope();
+      self.add_local("super");
+      self.named_variable(name, false);
+      self.emit(OpCode::Inherit);
+    }
+
+    self.named_variable(name, false);
+    for method in methods {
+      if let StmtKind::Function { name, params, body } = &method.kind {
+        let kind = if name == "init" {
+          FunctionKind::Initializer
+        } else {
+          FunctionKind::Method
+        };
+        self.function(name, params, body, kind);
+        let name_constant = self.identifier(name);
+        self.emit(OpCode::Method(name_constant));
+      }
+    }
+    self.emit(OpCode::Pop);
+
+    if superclass.is_some() {
+      self.end_scope();
+    }
+  }
+
+  fn expr(&mut self, expr: &Expr) {
+    match &expr.kind {
+      ExprKind::LiteralNumber { value, .. } => {
+        let constant = self.constant(Constant::Number(*value));
+        self.emit(OpCode::Constant(constant));
+      }
+      ExprKind::LiteralString { value } => {
+        let constant = self.constant(Constant::String(value.clone()));
+        self.emit(OpCode::Constant(constant));
+      }
+      ExprKind::LiteralBool { value: true } => {
+        self.emit(OpCode::True);
+      }
+      ExprKind::LiteralBool { value: false } => {
+        self.emit(OpCode::False);
+      }
+      ExprKind::LiteralNil => {
+        self.emit(OpCode::Nil);
+      }
+      ExprKind::Group { expression } => self.expr(expression),
+      ExprKind::Unary { operator, right } => {
+        self.line = operator.line();
+        self.expr(right);
+        match operator.kind() {
+          TokenKind::Minus => self.emit(OpCode::Negate),
+          TokenKind::Tilde => self.emit(OpCode::BitNot),
+          _ => self.emit(OpCode::Not),
+        };
+      }
+      ExprKind::Binary {
+        left,
+        operator,
+        right,
+      } => {
+        self.line = operator.line();
+        self.expr(left);
+        self.expr(right);
+        let ops: &[OpCode] = match operator.kind() {
+          TokenKind::Plus => &[OpCode::Add],
+          TokenKind::Minus => &[OpCode::Subtract],
+          TokenKind::Star => &[OpCode::Multiply],
+          TokenKind::Slash => &[OpCode::Divide],
+          TokenKind::StarStar => &[OpCode::Power],
+          TokenKind::Ampersand => &[OpCode::BitAnd],
+          TokenKind::Pipe => &[OpCode::BitOr],
+          TokenKind::Caret => &[OpCode::BitXor],
+          TokenKind::LessLess => &[OpCode::ShiftLeft],
+          TokenKind::GreaterGreater => &[OpCode::ShiftRight],
+          TokenKind::EqualEqual => &[OpCode::Equal],
+          TokenKind::BangEqual => &[OpCode::Equal, OpCode::Not],
+          TokenKind::Greater => &[OpCode::Greater],
+          TokenKind::GreaterEqual => &[OpCode::Less, OpCode::Not],
+          TokenKind::Less => &[OpCode::Less],
+          TokenKind::LessEqual => &[OpCode::Greater, OpCode::Not],
+          _ => &[],
+        };
+        for op in ops {
+          self.emit(op.clone());
+        }
+      }
+      ExprKind::Logical {
+        left,
+        operator,
+        right,
+      } => {
+        self.line = operator.line();
+        self.expr(left);
+        if *operator.kind() == TokenKind::And {
+          let end_jump = self.emit(OpCode::JumpIfFalse(0));
+          self.emit(OpCode::Pop);
+          self.expr(right);
+          self.patch_jump(end_jump);
+        } else {
+          let else_jump = self.emit(OpCode::JumpIfFalse(0));
+          let end_jump = self.emit(OpCode::Jump(0));
+          self.patch_jump(else_jump);
+          self.emit(OpCode::Pop);
+          self.expr(right);
+          self.patch_jump(end_jump);
+        }
+      }
+      ExprKind::Call { line, callee, args } => {
+        self.expr(callee);
+        for arg in args {
+          self.expr(arg);
+        }
+        self.line = *line;
+        self.emit(OpCode::Call(args.len()));
+      }
+      ExprKind::Variable { name, line } => {
+        self.line = *line;
+        self.named_variable(name, false);
+      }
+      ExprKind::Assign { name, value, line } => {
+        self.expr(value);
+        self.line = *line;
+        self.named_variable(name, true);
+      }
+      ExprKind::Increment {
+        name,
+        operator,
+        postfix,
+        line,
+      } => {
+        self.line = *line;
+        // A postfix increment leaves the old value under the new one, and pops the new one.
+        if *postfix {
+          self.named_variable(name, false);
+        }
+        self.named_variable(name, false);
+        let one = self.constant(Constant::Number(1.0));
+        self.emit(OpCode::Constant(one));
+        self.emit(match operator.kind() {
+          TokenKind::PlusPlus => OpCode::Add,
+          _ => OpCode::Subtract,
+        });
+        self.named_variable(name, true);
+        if *postfix {
+          self.emit(OpCode::Pop);
+        }
+      }
+      ExprKind::This { line } => {
+        self.line = *line;
+        self.named_variable("this", false);
+      }
+      ExprKind::Super { method, line } => {
+        self.line = *line;
+        self.named_variable("this", false);
+        self.named_variable("super", false);
+        let name_constant = self.identifier(method);
+        self.emit(OpCode::GetSuper(name_constant));
+      }
+      ExprKind::Get { object, name, line } => {
+        self.expr(object);
+        self.line = *line;
+        let name_constant = self.identifier(name);
+        self.emit(OpCode::GetProperty(name_constant));
+      }
+      ExprKind::Set {
+        object,
+        name,
+        value,
+        line,
+      } => {
+        self.expr(object);
+        self.expr(value);
+        self.line = *line;
+        let name_constant = self.identifier(name);
+        self.emit(OpCode::SetProperty(name_constant));
+      }
+    }
+  }
+
+  fn named_variable(&mut self, name: &str, assign: bool) {
+    let current = self.states.len() - 1;
+    let op = if let Some(slot) = self.states[current].resolve_local(name) {
+      if assign {
+        OpCode::SetLocal(slot)
+      } else {
+        OpCode::GetLocal(slot)
+      }
+    } else if let Some(slot) = self.resolve_upvalue(current, name) {
+      if assign {
+        OpCode::SetUpvalue(slot)
+      } else {
+        OpCode::GetUpvalue(slot)
+      }
+    } else {
+      let name_constant = self.identifier(name);
+      if assign {
+        OpCode::SetGlobal(name_constant)
+      } else {
+        OpCode::GetGlobal(name_constant)
+      }
+    };
+    self.emit(op);
+  }
+
+  fn resolve_upvalue(&mut self, state: usize, name: &str) -> Option<usize> {
+    let enclosing = state.checked_sub(1)?;
+
+    if let Some(slot) = self.states[enclosing].resolve_local(name) {
+      self.states[enclosing].locals[slot].captured = true;
+      return Some(self.add_upvalue(
+        state,
+        UpvalueRef {
+          is_local: true,
+          index: slot,
+        },
+      ));
+    }
+
+    let index = self.resolve_upvalue(enclosing, name)?;
+    Some(self.add_upvalue(
+      state,
+      UpvalueRef {
+        is_local: false,
+        index,
+      },
+    ))
+  }
+
+  fn add_upvalue(&mut self, state: usize, upvalue: UpvalueRef) -> usize {
+    let upvalues = &mut self.states[state].upvalues;
+    if let Some(index) = upvalues.iter().position(|u| *u == upvalue) {
+      return index;
+    }
+    upvalues.push(upvalue);
+    upvalues.len() - 1
+  }
+
+  /// Globals are bound by name; locals just stay on the stack in their slot.
+  fn define_variable(&mut self, name: &str) {
+    if self.state().scope_depth > 0 {
+      self.add_local(name);
+    } else {
+      let name_constant = self.identifier(name);
+      self.emit(OpCode::DefineGlobal(name_constant));
+    }
+  }
+
+  fn add_local(&mut self, name: &str) {
+    let depth = self.state().scope_depth;
+    self.state().locals.push(Local {
+      name: name.to_string(),
+      depth,
+      captured: false,
+    });
+  }
+
+  fn begin_scope(&mut self) {
+    self.state().scope_depth += 1;
+  }
+
+  fn end_scope(&mut self) {
+    self.state().scope_depth -= 1;
+    let depth = self.state().scope_depth;
+
+    loop {
+      let op = match self.state().locals.last() {
+        Some(local) if local.depth > depth && local.captured => OpCode::CloseUpvalue,
+        Some(local) if local.depth > depth => OpCode::Pop,
+        _ => break,
+      };
+      self.emit(op);
+      self.state().locals.pop();
+    }
+  }
+
+  fn patch_jump(&mut self, offset: usize) {
+    let target = self.chunk().code.len();
+    if let Some(OpCode::Jump(to) | OpCode::JumpIfFalse(to)) = self.chunk().code.get_mut(offset) {
+      *to = target;
+    }
+  }
+
+  fn identifier(&mut self, name: &str) -> usize {
+    self.constant(Constant::String(name.to_string()))
+  }
+
+  fn constant(&mut self, constant: Constant) -> usize {
+    self.chunk().add_constant(constant)
+  }
+
+  fn emit(&mut self, op: OpCode) -> usize {
+    let line = self.line;
+    self.chunk().write(op, line)
+  }
+
+  fn chunk(&mut self) -> &mut Chunk {
+    &mut self.state().function.chunk
+  }
+
+  fn state(&mut self) -> &mut FunctionState {
+    self
+      .states
+      .last_mut()
+      .expect("there is always a function being compiled")
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parse::parser::LoxParser;
+  use crate::scan::scanner::Scanner;
+  use std::io::Cursor;
+
+  fn compile(src: &str) -> Function {
+    let mut cursor = Cursor::new(src);
+    let tokens = Scanner::new(&mut cursor).scan_tokens().0;
+    let stmts = LoxParser::new(tokens).parse().unwrap();
+    Compiler::new().compile(&stmts).unwrap()
+  }
+
+  fn compile_errors(src: &str) -> Vec<CompileError> {
+    let mut cursor = Cursor::new(src);
+    let tokens = Scanner::new(&mut cursor).scan_tokens().0;
+    let stmts = LoxParser::new(tokens).parse().unwrap();
+    Compiler::new().compile(&stmts).unwrap_err()
+  }
+
+  fn function_constant<'a>(function: &'a Function, name: &str) -> &'a Function {
+    function
+      .chunk
+      .constants
+      .iter()
+      .find_map(|constant| match constant {
+        Constant::Function(f) if f.name == name => Some(f),
+        _ => None,
+      })
+      .unwrap()
+  }
+
+  #[test]
+  fn imports_are_reported_instead_of_left_out() {
+    assert_eq!(
+      compile_errors("print 1;\nimport \"lib.lox\";"),
+      [CompileError::Unsupported(2, "`import`".to_string())]
+    );
+    assert_eq!(
+      compile_errors("import \"a.lox\";")[0].to_string(),
+      "[line 1]: `import` can't be compiled to bytecode yet."
+    );
+  }
+
+  #[test]
+  fn class_methods_and_getters_are_reported_instead_of_left_out() {
+    let src = "class A {\n  class s() { return 1; }\n  g { return 2; }\n  m() { return 3; }\n}";
+    assert_eq!(
+      compile_errors(src),
+      [
+        CompileError::Unsupported(2, "class methods".to_string()),
+        CompileError::Unsupported(3, "getters".to_string())
+      ]
+    );
+    assert_eq!(
+      function_constant(&compile("class A { m() {} }"), "m").name,
+      "m"
+    );
+  }
+
+  #[test]
+  fn try_catch_is_reported_instead_of_compiling_only_the_block() {
+    assert_eq!(
+      compile_errors("try {\n  print 1;\n} catch (e) {\n  print e;\n}"),
+      [CompileError::Unsupported(1, "`try`".to_string())]
+    );
+  }
+
+  #[test]
+  fn throw_is_reported_instead_of_doing_nothing() {
+    assert_eq!(
+      compile_errors("fun f() {\n  throw \"bad\";\n}"),
+      [CompileError::Unsupported(2, "`throw`".to_string())]
+    );
+  }
+
+  #[test]
+  fn assert_is_reported_instead_of_never_failing() {
+    assert_eq!(
+      compile_errors("assert(false);\nassert(1 > 2, \"big\");"),
+      [
+        CompileError::Unsupported(1, "`assert`".to_string()),
+        CompileError::Unsupported(2, "`assert`".to_string())
+      ]
+    );
+  }
+
+  #[test]
+  fn expressions_compile_to_stack_operations() {
+    let script = compile("print -(1 + 2) >= 3;");
+    assert_eq!(
+      script.chunk.code,
+      vec![
+        OpCode::Constant(0),
+        OpCode::Constant(1),
+        OpCode::Add,
+        OpCode::Negate,
+        OpCode::Constant(2),
+        OpCode::Less,
+        OpCode::Not,
+        OpCode::Print,
+        OpCode::Nil,
+        OpCode::Return,
+      ]
+    );
+  }
+
+  #[test]
+  fn block_variables_are_locals_and_top_level_ones_are_globals() {
+    let script = compile("var a = 1; { var b = a; print b; }");
+    assert_eq!(
+      script.chunk.code,
+      vec![
+        OpCode::Constant(0),
+        OpCode::DefineGlobal(1),
+        OpCode::GetGlobal(2),
+        OpCode::GetLocal(1),
+        OpCode::Print,
+        OpCode::Pop,
+        OpCode::Nil,
+        OpCode::Return,
+      ]
+    );
+  }
+
+  #[test]
+  fn jumps_point_to_their_targets() {
+    let script = compile("while (true) { if (false) break; }");
+    assert_eq!(
+      script.chunk.code,
+      vec![
+        OpCode::True,
+        OpCode::JumpIfFalse(10),
+        OpCode::Pop,
+        OpCode::False,
+        OpCode::JumpIfFalse(8),
+        OpCode::Pop,
+        OpCode::Jump(11),
+        OpCode::Jump(9),
+        OpCode::Pop,
+        OpCode::Loop(0),
+        OpCode::Pop,
+        OpCode::Nil,
+        OpCode::Return,
+      ]
+    );
+  }
+
+  #[test]
+  fn do_while_loops_check_their_condition_at_the_end() {
+    let script = compile("do { if (false) break; } while (true);");
+    assert_eq!(
+      script.chunk.code,
+      vec![
+        OpCode::False,
+        OpCode::JumpIfFalse(5),
+        OpCode::Pop,
+        OpCode::Jump(11),
+        OpCode::Jump(6),
+        OpCode::Pop,
+        OpCode::True,
+        OpCode::JumpIfFalse(10),
+        OpCode::Pop,
+        OpCode::Loop(0),
+        OpCode::Pop,
+        OpCode::Nil,
+        OpCode::Return,
+      ]
+    );
+  }
+
+  #[test]
+  fn captured_variables_become_upvalues() {
+    let script = compile("fun outer() { var x = 1; fun inner() { return x; } return inner; }");
+    let outer = function_constant(&script, "outer");
+    let inner = function_constant(outer, "inner");
+
+    assert_eq!(
+      outer.chunk.code[1],
+      OpCode::Closure(
+        1,
+        vec![UpvalueRef {
+          is_local: true,
+          index: 1
+        }]
+      )
+    );
+    assert_eq!(inner.upvalue_count, 1);
+    assert_eq!(inner.chunk.code[0], OpCode::GetUpvalue(0));
+  }
+
+  #[test]
+  fn initializers_return_this() {
+    let script = compile("class A { init() { return; } }");
+    let init = function_constant(&script, "init");
+    assert_eq!(
+      init.chunk.code,
+      vec![
+        OpCode::GetLocal(0),
+        OpCode::Return,
+        OpCode::GetLocal(0),
+        OpCode::Return
+      ]
+    );
+  }
+}
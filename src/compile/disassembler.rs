@@ -0,0 +1,149 @@
+use crate::compile::chunk::{Chunk, Constant, Function, OpCode};
+
+/// Human readable listing of a compiled function, followed by the listings of every function
+/// defined inside it. Each instruction shows its offset, its source line (`|` when it's the same
+/// line as the previous instruction), its name and its operands.
+pub fn disassemble(function: &Function) -> String {
+  let mut lines = vec![];
+  disassemble_function(function, &mut lines);
+  lines.join("\n")
+}
+
+fn disassemble_function(function: &Function, lines: &mut Vec<String>) {
+  let chunk = &function.chunk;
+  lines.push(format!("== {} ==", function.name));
+
+  for (offset, op) in chunk.code.iter().enumerate() {
+    let line = if offset > 0 && chunk.lines[offset] == chunk.lines[offset - 1] {
+      "   |".to_string()
+    } else {
+      format!("{:4}", chunk.lines[offset])
+    };
+    lines.push(format!(
+      "{:04} {} {}",
+      offset,
+      line,
+      instruction(chunk, offset, op)
+    ));
+
+    if let OpCode::Closure(_, upvalues) = op {
+      for upvalue in upvalues {
+        let kind = if upvalue.is_local { "local" } else { "upvalue" };
+        lines.push(format!(
+          "{:04}    |                      {} {}",
+          offset, kind, upvalue.index
+        ));
+      }
+    }
+  }
+
+  for constant in &chunk.constants {
+    if let Constant::Function(function) = constant {
+      lines.push(String::new());
+      disassemble_function(function, lines);
+    }
+  }
+}
+
+fn instruction(chunk: &Chunk, offset: usize, op: &OpCode) -> String {
+  match op {
+    OpCode::Constant(index)
+    | OpCode::GetGlobal(index)
+    | OpCode::DefineGlobal(index)
+    | OpCode::SetGlobal(index)
+    | OpCode::GetProperty(index)
+    | OpCode::SetProperty(index)
+    | OpCode::GetSuper(index)
+    | OpCode::Closure(index, _)
+    | OpCode::Class(index)
+    | OpCode::Method(index) => {
+      format!(
+        "{:<16} {:4} '{}'",
+        op.name(),
+        index,
+        chunk.constants[*index]
+      )
+    }
+    OpCode::GetLocal(slot)
+    | OpCode::SetLocal(slot)
+    | OpCode::GetUpvalue(slot)
+    | OpCode::SetUpvalue(slot)
+    | OpCode::Call(slot) => format!("{:<16} {:4}", op.name(), slot),
+    OpCode::Jump(target) | OpCode::JumpIfFalse(target) | OpCode::Loop(target) => {
+      format!("{:<16} {:4} -> {}", op.name(), offset, target)
+    }
+    _ => op.name().to_string(),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::compile::compiler::Compiler;
+  use crate::parse::parser::LoxParser;
+  use crate::scan::scanner::Scanner;
+  use std::io::Cursor;
+
+  fn disassemble_program(src: &str) -> String {
+    let mut cursor = Cursor::new(src);
+    let tokens = Scanner::new(&mut cursor).scan_tokens().0;
+    let stmts = LoxParser::new(tokens).parse().unwrap();
+    disassemble(&Compiler::new().compile(&stmts).unwrap())
+  }
+
+  #[test]
+  fn listing_shows_constants_and_lines() {
+    let listing = disassemble_program("var a = 1.5;\nprint a + \"s\";");
+    assert_eq!(
+      listing,
+      concat!(
+        "== <script> ==\n",
+        "0000    1 OP_CONSTANT         0 '1.5'\n",
+        "0001    | OP_DEFINE_GLOBAL    1 'a'\n",
+        "0002    2 OP_GET_GLOBAL       2 'a'\n",
+        "0003    | OP_CONSTANT         3 's'\n",
+        "0004    | OP_ADD\n",
+        "0005    | OP_PRINT\n",
+        "0006    | OP_NIL\n",
+        "0007    | OP_RETURN",
+      )
+    );
+  }
+
+  #[test]
+  fn jumps_show_their_target() {
+    let listing = disassemble_program("if (true) print 1;");
+    assert!(listing.contains("0001    | OP_JUMP_IF_FALSE    1 -> 6\n"));
+    assert!(listing.contains("0005    | OP_JUMP             5 -> 7\n"));
+  }
+
+  #[test]
+  fn functions_are_listed_after_the_script() {
+    let listing = disassemble_program("fun add(a, b) {\n  return a + b;\n}");
+    assert_eq!(
+      listing,
+      concat!(
+        "== <script> ==\n",
+        "0000    1 OP_CLOSURE          0 '<fn add>'\n",
+        "0001    | OP_DEFINE_GLOBAL    1 'add'\n",
+        "0002    | OP_NIL\n",
+        "0003    | OP_RETURN\n",
+        "\n",
+        "== add ==\n",
+        "0000    2 OP_GET_LOCAL        1\n",
+        "0001    | OP_GET_LOCAL        2\n",
+        "0002    | OP_ADD\n",
+        "0003    | OP_RETURN\n",
+        "0004    | OP_NIL\n",
+        "0005    | OP_RETURN",
+      )
+    );
+  }
+
+  #[test]
+  fn closures_list_their_upvalues() {
+    let listing = disassemble_program("{ var x = 1; fun f() { print x; } }");
+    assert!(listing.contains("0001    | OP_CLOSURE          1 '<fn f>'\n"));
+    assert!(listing.contains("0001    |                      local 1\n"));
+  }
+}
@@ -0,0 +1,4 @@
+pub mod chunk;
+pub mod compile_error;
+pub mod compiler;
+pub mod disassembler;
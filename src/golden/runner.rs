@@ -0,0 +1,208 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Runs every `.lox` file in a directory through the CLI (`run <file>`) and compares the result
+/// against its sibling golden files:
+///
+/// - `<name>.out`: expected stdout.
+/// - `<name>.err`: expected stderr.
+/// - `<name>.exit`: expected exit code.
+///
+/// A missing golden file means empty output (or exit code 0).
+///
+/// Blessing a program rewrites its golden files with what it does now.
+pub struct GoldenRunner {
+  executable: PathBuf,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct CaseResult {
+  pub path: PathBuf,
+  pub failures: Vec<String>,
+}
+
+impl CaseResult {
+  pub fn passed(&self) -> bool {
+    self.failures.is_empty()
+  }
+}
+
+struct Outcome {
+  stdout: String,
+  stderr: String,
+  exit_code: i32,
+}
+
+impl GoldenRunner {
+  pub fn new(executable: PathBuf) -> GoldenRunner {
+    GoldenRunner { executable }
+  }
+
+  pub fn run_dir(&self, dir: &Path) -> io::Result<Vec<CaseResult>> {
+    programs(dir)?
+      .iter()
+      .map(|path| self.run_case(path))
+      .collect()
+  }
+
+  pub fn run_case(&self, program: &Path) -> io::Result<CaseResult> {
+    let expected = Self::expected_outcome(program)?;
+    let actual = self.execute(program)?;
+
+    let mut failures = vec![];
+    if let Some(diff) = diff("stdout", &expected.stdout, &actual.stdout) {
+      failures.push(diff);
+    }
+    if let Some(diff) = diff("stderr", &expected.stderr, &actual.stderr) {
+      failures.push(diff);
+    }
+    if expected.exit_code != actual.exit_code {
+      failures.push(format!(
+        "exit code: expected {}, got {}",
+        expected.exit_code, actual.exit_code
+      ));
+    }
+
+    Ok(CaseResult {
+      path: program.to_path_buf(),
+      failures,
+    })
+  }
+
+  /// Runs the program and writes what it did as its golden files. Files that would hold empty
+  /// output or a 0 exit code are removed instead, as that's what a missing file means.
+  pub fn bless_case(&self, program: &Path) -> io::Result<()> {
+    let actual = self.execute(program)?;
+    let exit_code = if actual.exit_code == 0 {
+      String::new()
+    } else {
+      format!("{}\n", actual.exit_code)
+    };
+
+    write_optional(&program.with_extension("out"), &actual.stdout)?;
+    write_optional(&program.with_extension("err"), &actual.stderr)?;
+    write_optional(&program.with_extension("exit"), &exit_code)
+  }
+
+  fn execute(&self, program: &Path) -> io::Result<Outcome> {
+    let output = Command::new(&self.executable)
+      .arg("run")
+      .arg(program)
+      .output()?;
+
+    Ok(Outcome {
+      stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+      stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+      exit_code: output.status.code().unwrap_or(-1),
+    })
+  }
+
+  fn expected_outcome(program: &Path) -> io::Result<Outcome> {
+    let exit_code = read_optional(&program.with_extension("exit"))?;
+    let exit_code = exit_code.trim();
+    let exit_code = if exit_code.is_empty() {
+      0
+    } else {
+      exit_code
+        .parse()
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Invalid exit code file"))?
+    };
+
+    Ok(Outcome {
+      stdout: read_optional(&program.with_extension("out"))?,
+      stderr: read_optional(&program.with_extension("err"))?,
+      exit_code,
+    })
+  }
+}
+
+fn programs(dir: &Path) -> io::Result<Vec<PathBuf>> {
+  let mut programs = fs::read_dir(dir)?
+    .map(|entry| entry.map(|e| e.path()))
+    .collect::<io::Result<Vec<_>>>()?
+    .into_iter()
+    .filter(|path| path.extension().is_some_and(|ext| ext == "lox"))
+    .collect::<Vec<_>>();
+  programs.sort();
+  Ok(programs)
+}
+
+fn write_optional(path: &Path, content: &str) -> io::Result<()> {
+  if !content.is_empty() {
+    return fs::write(path, content);
+  }
+  match fs::remove_file(path) {
+    Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+    _ => Ok(()),
+  }
+}
+
+fn read_optional(path: &Path) -> io::Result<String> {
+  match fs::read_to_string(path) {
+    Ok(content) => Ok(content),
+    Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(String::new()),
+    Err(e) => Err(e),
+  }
+}
+
+/// Line by line comparison. Returns `None` when both texts are equal, otherwise a report where
+/// expected lines are prefixed with `-` and actual lines with `+`.
+pub fn diff(stream: &str, expected: &str, actual: &str) -> Option<String> {
+  if expected == actual {
+    return None;
+  }
+
+  let expected_lines = expected.lines().collect::<Vec<_>>();
+  let actual_lines = actual.lines().collect::<Vec<_>>();
+  let mut report = vec![format!("{stream} differs:")];
+
+  for i in 0..expected_lines.len().max(actual_lines.len()) {
+    match (expected_lines.get(i), actual_lines.get(i)) {
+      (Some(e), Some(a)) if e == a => report.push(format!("  {e}")),
+      (e, a) => {
+        if let Some(e) = e {
+          report.push(format!("- {e}"));
+        }
+        if let Some(a) = a {
+          report.push(format!("+ {a}"));
+        }
+      }
+    }
+  }
+
+  if expected_lines == actual_lines {
+    report.push("(difference in trailing new lines)".to_string());
+  }
+
+  Some(report.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn diff_of_equal_texts_is_none() {
+    assert_eq!(diff("stdout", "1\n2\n", "1\n2\n"), None);
+  }
+
+  #[test]
+  fn diff_marks_changed_lines() {
+    let report = diff("stdout", "1\n2\n", "1\n3\n").unwrap();
+    assert_eq!(report, "stdout differs:\n  1\n- 2\n+ 3");
+  }
+
+  #[test]
+  fn diff_marks_missing_and_extra_lines() {
+    let report = diff("stderr", "a\n", "a\nb\n").unwrap();
+    assert_eq!(report, "stderr differs:\n  a\n+ b");
+  }
+
+  #[test]
+  fn diff_reports_trailing_new_lines() {
+    let report = diff("stdout", "a\n", "a").unwrap();
+    assert_eq!(report, "stdout differs:\n  a\n(difference in trailing new lines)");
+  }
+}
@@ -1,17 +1,30 @@
 use crate::interpret::branching_scope::Node::Child;
+use crate::interpret::error::RuntimeError;
+use crate::interpret::lox_class::LoxClass;
+use crate::interpret::lox_fn::Callable;
 use crate::interpret::value::Value;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt::Debug;
 
 type Scope = HashMap<String, Value>;
 
+/// Scopes are collected once the arena holds this many nodes, and again each time it doubles.
+const FIRST_COLLECTION: usize = 1024;
+
+/// Most released scope maps kept around for reuse.
+const MAX_FREE_SCOPES: usize = 64;
+
 #[derive(Debug)]
 pub enum Node {
   Base,
   Child {
     data: Scope,
     parent: usize,
+    /// Children plus closures that captured this scope.
     ref_count: usize,
+    /// Whether the code that opened the scope is done with it. A released scope is only kept
+    /// while something still references it.
+    released: bool,
   },
 }
 
@@ -34,62 +47,132 @@ impl Node {
 pub struct BranchingScope {
   nodes: HashMap<usize, Node>,
   current: usize,
+  next_collection: usize,
+  /// Emptied maps of dropped scopes. New scopes reuse them, so blocks and calls run in a loop
+  /// don't allocate a map on every iteration.
+  free: Vec<Scope>,
 }
 
 impl BranchingScope {
+  /// Id of the global scope of [`BranchingScope::with_global_scope`].
+  pub const GLOBAL: usize = 1;
+
   pub fn empty() -> BranchingScope {
     let mut nodes = HashMap::new();
     nodes.insert(0, Node::Base);
-    BranchingScope { nodes, current: 0 }
+    BranchingScope {
+      nodes,
+      current: 0,
+      next_collection: FIRST_COLLECTION,
+      free: Vec::new(),
+    }
+  }
+
+  /// An arena with the global scope already open, as [`BranchingScope::GLOBAL`].
+  pub fn with_global_scope() -> BranchingScope {
+    let mut scopes = BranchingScope::empty();
+    scopes.current = BranchingScope::GLOBAL;
+    scopes.nodes.insert(
+      BranchingScope::GLOBAL,
+      Child {
+        data: Scope::new(),
+        parent: 0,
+        ref_count: 0,
+        released: false,
+      },
+    );
+    scopes
+  }
+
+  pub fn node_count(&self) -> usize {
+    self.nodes.len()
+  }
+
+  fn node_mut(&mut self, id: usize) -> Result<&mut Node, RuntimeError> {
+    self
+      .nodes
+      .get_mut(&id)
+      .ok_or(RuntimeError::MissingScope(id))
   }
 
-  fn add_ref_to_node(&mut self, id: usize) {
-    let current_node = self.nodes.get_mut(&id).unwrap();
-    match current_node {
+  fn add_ref_to_node(&mut self, id: usize) -> Result<(), RuntimeError> {
+    match self.node_mut(id)? {
       Node::Base => {}
       Child { ref_count, .. } => *ref_count += 1,
     }
+    Ok(())
   }
 
-  fn remove_ref_from_node(&mut self, id: usize) {
-    let current_node = self.nodes.get_mut(&id).unwrap();
-    match current_node {
+  fn remove_ref_from_node(&mut self, id: usize) -> Result<(), RuntimeError> {
+    match self.node_mut(id)? {
       Node::Base => {}
       Child { ref_count, .. } => {
         *ref_count -= 1;
       }
     }
+    Ok(())
   }
 
-  pub fn branch(&mut self, src: usize) -> usize {
+  /// Opens a new scope inside `src`. Fails if `src` was already dropped.
+  pub fn branch(&mut self, src: usize) -> Result<usize, RuntimeError> {
+    self.add_ref_to_node(src)?;
     self.current += 1;
     self.nodes.insert(
       self.current,
       Child {
-        data: HashMap::new(),
+        data: self.free.pop().unwrap_or_default(),
         parent: src,
         ref_count: 0,
+        released: false,
       },
     );
-    self.add_ref_to_node(src);
-    self.current
+    Ok(self.current)
+  }
+
+  /// Keeps `id` alive for a closure that captured it, even after the code that opened it
+  /// releases it.
+  pub fn capture(&mut self, id: usize) -> Result<(), RuntimeError> {
+    self.add_ref_to_node(id)
   }
 
-  pub fn release(&mut self, id: usize) -> usize {
-    let current_node = self.nodes.get(&id).unwrap();
-    let (ref_count, parent) = match current_node {
-      Node::Base => unreachable!(),
+  /// Marks `id` as no longer used by the code that opened it and returns its parent. The scope
+  /// is dropped right away unless something still references it; in that case it goes away
+  /// together with the last reference.
+  pub fn release(&mut self, id: usize) -> Result<usize, RuntimeError> {
+    let parent = match self.node_mut(id)? {
+      // The base has no parent, and is never released.
+      Node::Base => return Err(RuntimeError::MissingScope(id)),
       Child {
-        ref_count, parent, ..
-      } => (*ref_count, *parent),
+        parent, released, ..
+      } => {
+        *released = true;
+        *parent
+      }
     };
+    self.collect(id)?;
+    Ok(parent)
+  }
 
-    if ref_count == 0 {
-      self.nodes.remove(&id);
-      self.remove_ref_from_node(parent);
+  fn collect(&mut self, id: usize) -> Result<(), RuntimeError> {
+    let mut current = id;
+    while let Some(Child {
+      ref_count: 0,
+      released: true,
+      parent,
+      ..
+    }) = self.nodes.get(&current)
+    {
+      let parent = *parent;
+      if let Some(Child { mut data, .. }) = self.nodes.remove(&current) {
+        if self.free.len() < MAX_FREE_SCOPES {
+          data.clear();
+          self.free.push(data);
+        }
+      }
+      self.remove_ref_from_node(parent)?;
+      current = parent;
     }
-
-    parent
+    Ok(())
   }
 
   fn scope_mut(&mut self, id: usize) -> Option<&mut Scope> {
@@ -140,6 +223,108 @@ impl BranchingScope {
     s.insert(key.to_string(), value);
     Some(())
   }
+
+  /// Runs [`BranchingScope::collect_garbage`] when the arena has grown enough since the last run.
+  pub fn maybe_collect_garbage(&mut self) {
+    if self.nodes.len() >= self.next_collection {
+      self.collect_garbage();
+      self.next_collection = FIRST_COLLECTION.max(self.nodes.len() * 2);
+    }
+  }
+
+  /// Drops every scope that can't be reached from a scope still in use, following parents and
+  /// the closures stored in variables, instance fields and classes. Captured scopes are otherwise
+  /// kept forever, because values don't tell the arena when they go away.
+  ///
+  /// Only values stored in the arena count as references, so this must run when nothing else
+  /// holds one (e.g. between statements, outside any call).
+  pub fn collect_garbage(&mut self) {
+    let mut marked = HashSet::new();
+    let mut captured = HashSet::new();
+    let mut seen_instances = HashSet::new();
+    let mut pending: Vec<usize> = self
+      .nodes
+      .iter()
+      .filter(|(_, node)| {
+        matches!(
+          node,
+          Child {
+            released: false,
+            ..
+          }
+        )
+      })
+      .map(|(id, _)| *id)
+      .collect();
+
+    while let Some(id) = pending.pop() {
+      if !marked.insert(id) {
+        continue;
+      }
+      if let Some(Child { data, parent, .. }) = self.nodes.get(&id) {
+        pending.push(*parent);
+        let mut tracer = Tracer {
+          pending: &mut pending,
+          captured: &mut captured,
+          seen_instances: &mut seen_instances,
+        };
+        data.values().for_each(|value| tracer.trace_value(value));
+      }
+    }
+
+    self.nodes.retain(|id, _| *id == 0 || marked.contains(id));
+
+    let mut children = HashMap::new();
+    for node in self.nodes.values() {
+      if let Child { parent, .. } = node {
+        *children.entry(*parent).or_insert(0) += 1;
+      }
+    }
+    for (id, node) in self.nodes.iter_mut() {
+      if let Child { ref_count, .. } = node {
+        *ref_count = children.get(id).copied().unwrap_or(0) + usize::from(captured.contains(id));
+      }
+    }
+  }
+}
+
+/// Finds the scopes referenced by values, for [`BranchingScope::collect_garbage`].
+struct Tracer<'a> {
+  pending: &'a mut Vec<usize>,
+  captured: &'a mut HashSet<usize>,
+  seen_instances: &'a mut HashSet<usize>,
+}
+
+impl Tracer<'_> {
+  fn trace_value(&mut self, value: &Value) {
+    match value {
+      Value::Callable(Callable::Lox(fun)) => self.trace_context(fun.context_id()),
+      Value::Callable(Callable::Class(class)) => self.trace_class(class),
+      Value::Instance(instance) => {
+        if self.seen_instances.insert(instance.id()) {
+          self.trace_class(instance.class());
+          for value in instance.field_values() {
+            self.trace_value(&value);
+          }
+        }
+      }
+      _ => {}
+    }
+  }
+
+  fn trace_class(&mut self, class: &LoxClass) {
+    for method in class.methods() {
+      self.trace_context(method.context_id());
+    }
+    if let Some(superclass) = class.superclass() {
+      self.trace_class(superclass);
+    }
+  }
+
+  fn trace_context(&mut self, id: usize) {
+    self.captured.insert(id);
+    self.pending.push(id);
+  }
 }
 
 #[cfg(test)]
@@ -147,11 +332,137 @@ mod tests {
   use super::*;
 
   fn branch_with(scope: &mut BranchingScope, base: usize, key: &str, value: f64) -> usize {
-    let id = scope.branch(base);
+    let id = scope.branch(base).unwrap();
     scope.define(id, key, Value::Number(value));
     id
   }
 
+  #[test]
+  fn release_drops_a_scope_nobody_references() {
+    let mut list = BranchingScope::empty();
+    let branch1 = list.branch(0).unwrap();
+    let branch2 = list.branch(branch1).unwrap();
+
+    let parent = list.release(branch2).unwrap();
+
+    assert_eq!(parent, branch1);
+    assert!(!list.nodes.contains_key(&branch2));
+    assert!(list.nodes.contains_key(&branch1));
+  }
+
+  #[test]
+  fn released_scopes_are_reused_empty() {
+    let mut list = BranchingScope::empty();
+    let branch1 = list.branch(0).unwrap();
+    let branch2 = branch_with(&mut list, branch1, "foo", 1.0);
+    list.release(branch2).unwrap();
+
+    let branch3 = list.branch(branch1).unwrap();
+
+    assert!(list.free.is_empty());
+    assert!(list.get(branch3, "foo").is_none());
+    match list.nodes.get(&branch3) {
+      Some(Child { data, .. }) => assert!(data.capacity() > 0),
+      _ => panic!("branch3 is not a scope"),
+    }
+  }
+
+  #[test]
+  fn release_keeps_a_captured_scope() {
+    let mut list = BranchingScope::empty();
+    let branch1 = list.branch(0).unwrap();
+    let branch2 = branch_with(&mut list, branch1, "foo", 1.0);
+    list.capture(branch2).unwrap();
+
+    list.release(branch2).unwrap();
+
+    assert_eq!(*list.get(branch2, "foo").unwrap(), Value::Number(1.0));
+  }
+
+  #[test]
+  fn release_keeps_a_scope_until_its_last_child_is_released() {
+    let mut list = BranchingScope::empty();
+    let branch1 = list.branch(0).unwrap();
+    let branch2 = branch_with(&mut list, branch1, "foo", 1.0);
+    let branch3 = list.branch(branch2).unwrap();
+
+    list.release(branch2).unwrap();
+    assert_eq!(*list.get(branch3, "foo").unwrap(), Value::Number(1.0));
+
+    list.release(branch3).unwrap();
+    assert!(!list.nodes.contains_key(&branch3));
+    assert!(!list.nodes.contains_key(&branch2));
+    assert!(list.nodes.contains_key(&branch1));
+  }
+
+  #[test]
+  fn using_a_dropped_scope_is_an_error_instead_of_a_panic() {
+    let mut list = BranchingScope::empty();
+    let branch1 = list.branch(0).unwrap();
+    list.release(branch1).unwrap();
+
+    assert!(matches!(
+      list.branch(branch1),
+      Err(RuntimeError::MissingScope(id)) if id == branch1
+    ));
+    assert!(matches!(
+      list.release(branch1),
+      Err(RuntimeError::MissingScope(_))
+    ));
+  }
+
+  fn closure_over(scope: &mut BranchingScope, context: usize) -> Value {
+    scope.capture(context).unwrap();
+    Value::fun("f".to_string(), vec![], vec![], context)
+  }
+
+  #[test]
+  fn collect_garbage_drops_released_scopes_no_value_references() {
+    let mut list = BranchingScope::empty();
+    let global = list.branch(0).unwrap();
+    let block = list.branch(global).unwrap();
+    let closure = closure_over(&mut list, block);
+    list.define(global, "f", closure);
+    list.release(block).unwrap();
+
+    list.define(global, "f", Value::Nil);
+    list.collect_garbage();
+
+    assert!(!list.nodes.contains_key(&block));
+    assert!(list.nodes.contains_key(&global));
+  }
+
+  #[test]
+  fn collect_garbage_keeps_scopes_captured_by_reachable_closures() {
+    let mut list = BranchingScope::empty();
+    let global = list.branch(0).unwrap();
+    let block = branch_with(&mut list, global, "foo", 1.0);
+    let inner = list.branch(block).unwrap();
+    let closure = closure_over(&mut list, inner);
+    list.define(global, "f", closure);
+    list.release(inner).unwrap();
+    list.release(block).unwrap();
+
+    list.collect_garbage();
+
+    assert_eq!(*list.get(inner, "foo").unwrap(), Value::Number(1.0));
+  }
+
+  #[test]
+  fn collect_garbage_drops_scopes_that_only_reference_themselves() {
+    let mut list = BranchingScope::empty();
+    let global = list.branch(0).unwrap();
+    let block = list.branch(global).unwrap();
+    let closure = closure_over(&mut list, block);
+    list.define(block, "f", closure);
+    list.release(block).unwrap();
+    assert!(list.nodes.contains_key(&block));
+
+    list.collect_garbage();
+
+    assert!(!list.nodes.contains_key(&block));
+  }
+
   #[test]
   fn get_when_value_defined_at_top_it_finds_it() {
     let mut list = BranchingScope::empty();
@@ -179,9 +490,9 @@ mod tests {
   #[test]
   fn get_when_key_is_not_defined_it_does_not_find_it() {
     let mut list = BranchingScope::empty();
-    let branch1 = list.branch(0);
-    let branch2 = list.branch(branch1);
-    let branch3 = list.branch(branch2);
+    let branch1 = list.branch(0).unwrap();
+    let branch2 = list.branch(branch1).unwrap();
+    let branch3 = list.branch(branch2).unwrap();
 
     let value = list.get(branch3, "bar");
 
@@ -191,9 +502,9 @@ mod tests {
   #[test]
   fn get_do_not_see_keys_in_sibling_node() {
     let mut list = BranchingScope::empty();
-    let branch1 = list.branch(0);
-    let _branch2 = list.branch(branch1);
-    let branch3 = list.branch(branch1);
+    let branch1 = list.branch(0).unwrap();
+    let _branch2 = list.branch(branch1).unwrap();
+    let branch3 = list.branch(branch1).unwrap();
 
     let value = list.get(branch3, "bar");
 
@@ -203,8 +514,8 @@ mod tests {
   #[test]
   fn define_creates_value_at_current_level() {
     let mut list = BranchingScope::empty();
-    let branch1 = list.branch(0);
-    let branch2 = list.branch(branch1);
+    let branch1 = list.branch(0).unwrap();
+    let branch2 = list.branch(branch1).unwrap();
 
     list.define(branch2, "foo", Value::Number(3.0));
     let value = list.get(branch2, "foo").unwrap();
@@ -215,8 +526,8 @@ mod tests {
   #[test]
   fn define_does_not_create_values_at_parent_levels() {
     let mut list = BranchingScope::empty();
-    let branch1 = list.branch(0);
-    let branch2 = list.branch(branch1);
+    let branch1 = list.branch(0).unwrap();
+    let branch2 = list.branch(branch1).unwrap();
 
     list.define(branch2, "foo", Value::Number(3.0));
     let value = list.get(branch1, "foo");
@@ -228,7 +539,7 @@ mod tests {
   fn define_can_create_a_value_already_present_at_parent_level() {
     let mut list = BranchingScope::empty();
     let branch1 = branch_with(&mut list, 0, "foo", 5.0);
-    let branch2 = list.branch(branch1);
+    let branch2 = list.branch(branch1).unwrap();
 
     list.define(branch2, "foo", Value::Number(3.1));
     let value = list.get(branch1, "foo").unwrap();
@@ -240,21 +551,21 @@ mod tests {
   #[test]
   fn define_can_create_use_the_same_key_twice() {
     let mut list = BranchingScope::empty();
-    let branch1 = list.branch(0);
-    let branch2 = list.branch(branch1);
+    let branch1 = list.branch(0).unwrap();
+    let branch2 = list.branch(branch1).unwrap();
 
     list.define(branch2, "foo", Value::Number(3.1));
     let value = list.get(branch2, "foo").unwrap();
     assert_eq!(*value, Value::Number(3.1));
-    list.define(branch2, "foo", Value::String("another".to_string()));
+    list.define(branch2, "foo", Value::from("another"));
     let value = list.get(branch2, "foo").unwrap();
-    assert_eq!(*value, Value::String("another".to_string()));
+    assert_eq!(*value, Value::from("another"));
   }
 
   #[test]
   fn assign_when_variable_define_at_top_level_updates_the_value() {
     let mut list = BranchingScope::empty();
-    let branch1 = list.branch(0);
+    let branch1 = list.branch(0).unwrap();
     let branch2 = branch_with(&mut list, branch1, "foo", 2.0);
 
     list.assign(branch2, "foo", Value::Number(3.1)).unwrap();
@@ -266,7 +577,7 @@ mod tests {
   fn assign_when_variable_defined_at_perent_level_updates_the_value() {
     let mut list = BranchingScope::empty();
     let branch1 = branch_with(&mut list, 0, "foo", 2.0);
-    let branch2 = list.branch(branch1);
+    let branch2 = list.branch(branch1).unwrap();
 
     list.assign(branch2, "foo", Value::Number(3.1)).unwrap();
     let res = list.get(branch2, "foo").unwrap();
@@ -276,8 +587,8 @@ mod tests {
   #[test]
   fn assign_when_variable_is_not_defined_returns_none() {
     let mut list = BranchingScope::empty();
-    let branch1 = list.branch(0);
-    let branch2 = list.branch(branch1);
+    let branch1 = list.branch(0).unwrap();
+    let branch2 = list.branch(branch1).unwrap();
 
     let res = list.assign(branch2, "foo", Value::Number(3.1));
     assert!(res.is_none());
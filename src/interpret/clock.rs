@@ -0,0 +1,88 @@
+use std::cell::Cell;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Time source for `clock()` and `sleep()`. Targets without a system clock (like the browser)
+/// provide their own.
+pub trait Clock {
+  /// Seconds since the Unix epoch, with their fractional part.
+  fn now(&self) -> f64;
+
+  /// Waits until `duration` has passed on this clock.
+  fn sleep(&self, duration: Duration);
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+  fn now(&self) -> f64 {
+    // A system clock set before 1970 gives negative seconds instead of failing.
+    match SystemTime::now().duration_since(UNIX_EPOCH) {
+      Ok(since_the_epoch) => since_the_epoch.as_secs_f64(),
+      Err(error) => -error.duration().as_secs_f64(),
+    }
+  }
+
+  fn sleep(&self, duration: Duration) {
+    thread::sleep(duration);
+  }
+}
+
+/// A clock that doesn't follow real time, for tests and runs that must give the same output every
+/// time. It can stay still or move a fixed amount each time it's read. Sleeping returns at once and
+/// moves it forward as much as the sleep would have taken.
+pub struct FixedClock {
+  next: Cell<f64>,
+  step: f64,
+}
+
+impl FixedClock {
+  /// Always reads `time`.
+  pub fn new(time: f64) -> FixedClock {
+    FixedClock::stepping(time, 0.0)
+  }
+
+  /// Reads `start` first, and `step` seconds more every time after that.
+  pub fn stepping(start: f64, step: f64) -> FixedClock {
+    FixedClock {
+      next: Cell::new(start),
+      step,
+    }
+  }
+}
+
+impl Clock for FixedClock {
+  fn now(&self) -> f64 {
+    let now = self.next.get();
+    self.next.set(now + self.step);
+    now
+  }
+
+  fn sleep(&self, duration: Duration) {
+    self.next.set(self.next.get() + duration.as_secs_f64());
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn fixed_clock_stays_still_or_steps() {
+    let fixed = FixedClock::new(10.5);
+    assert_eq!([fixed.now(), fixed.now()], [10.5, 10.5]);
+
+    let stepping = FixedClock::stepping(1.0, 0.25);
+    assert_eq!(
+      [stepping.now(), stepping.now(), stepping.now()],
+      [1.0, 1.25, 1.5]
+    );
+  }
+
+  #[test]
+  fn sleeping_moves_a_fixed_clock_forward() {
+    let clock = FixedClock::new(10.0);
+    clock.sleep(Duration::from_millis(1500));
+    assert_eq!(clock.now(), 11.5);
+  }
+}
@@ -0,0 +1,171 @@
+use crate::interpret::hook::InterpreterHook;
+use crate::parse::stmt::{Stmt, StmtKind};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::rc::Rc;
+
+/// Records how many times each line of a program ran. A line runs once for every statement that
+/// starts on it. Clones share what was recorded, like [`crate::interpret::profiler::Profiler`].
+#[derive(Clone)]
+pub struct Coverage {
+  hits: Rc<RefCell<BTreeMap<usize, u64>>>,
+}
+
+impl Coverage {
+  /// Coverage for `stmts`, starting with every line that has a statement at zero hits.
+  pub fn new(stmts: &[Stmt]) -> Coverage {
+    let mut hits = BTreeMap::new();
+    executable_lines(stmts, &mut hits);
+    Coverage {
+      hits: Rc::new(RefCell::new(hits)),
+    }
+  }
+
+  /// Times each line with statements ran. Lines without statements are left out.
+  pub fn hits(&self) -> BTreeMap<usize, u64> {
+    self.hits.borrow().clone()
+  }
+
+  /// The coverage as an lcov tracefile for a single source file.
+  pub fn to_lcov(&self, source_name: &str) -> String {
+    let hits = self.hits.borrow();
+    let mut lcov = format!("SF:{source_name}\n");
+    for (line, count) in hits.iter() {
+      let _ = writeln!(lcov, "DA:{line},{count}");
+    }
+    let hit = hits.values().filter(|count| **count > 0).count();
+    let _ = write!(lcov, "LH:{hit}\nLF:{}\nend_of_record\n", hits.len());
+    lcov
+  }
+
+  /// The source with the hits of each line in front of it, like gcov: `-` for lines without
+  /// statements and `#####` for lines that never ran.
+  pub fn annotate(&self, source: &str) -> String {
+    let hits = self.hits.borrow();
+    let mut listing = String::new();
+    for (i, text) in source.lines().enumerate() {
+      let count = match hits.get(&(i + 1)) {
+        None => "-".to_string(),
+        Some(0) => "#####".to_string(),
+        Some(count) => count.to_string(),
+      };
+      let _ = writeln!(listing, "{count:>9}:{:>5}:{text}", i + 1);
+    }
+    listing
+  }
+}
+
+impl InterpreterHook for Coverage {
+  fn before_stmt(&mut self, stmt: &Stmt) {
+    if let Some(line) = covered_line(stmt) {
+      *self.hits.borrow_mut().entry(line).or_default() += 1;
+    }
+  }
+}
+
+/// Blocks and declarations aren't counted on their own, only the statements in their bodies.
+fn covered_line(stmt: &Stmt) -> Option<usize> {
+  match stmt.kind {
+    StmtKind::ScopeBlock(_) | StmtKind::Function { .. } | StmtKind::Class { .. } => None,
+    _ => Some(stmt.line()),
+  }
+}
+
+fn executable_lines(stmts: &[Stmt], lines: &mut BTreeMap<usize, u64>) {
+  for stmt in stmts {
+    if let Some(line) = covered_line(stmt) {
+      lines.insert(line, 0);
+    }
+    match &stmt.kind {
+      StmtKind::ScopeBlock(stmts) | StmtKind::Function { body: stmts, .. } => {
+        executable_lines(stmts, lines)
+      }
+      StmtKind::Class {
+        methods,
+        class_methods,
+        getters,
+        ..
+      } => {
+        executable_lines(methods, lines);
+        executable_lines(class_methods, lines);
+        executable_lines(getters, lines);
+      }
+      StmtKind::If { then, els, .. } => {
+        executable_lines(std::slice::from_ref(then), lines);
+        if let Some(els) = els {
+          executable_lines(std::slice::from_ref(els), lines);
+        }
+      }
+      StmtKind::While { body, .. } | StmtKind::DoWhile { body, .. } => {
+        executable_lines(std::slice::from_ref(body), lines)
+      }
+      StmtKind::For {
+        initializer, body, ..
+      } => {
+        if let Some(initializer) = initializer {
+          executable_lines(std::slice::from_ref(initializer), lines);
+        }
+        executable_lines(std::slice::from_ref(body), lines);
+      }
+      StmtKind::Try { body, handler, .. } => {
+        executable_lines(body, lines);
+        executable_lines(handler, lines);
+      }
+      _ => {}
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::interpret::interpreter::Interpreter;
+  use crate::interpret::natives::parse_program;
+
+  const PROGRAM: &str = "var a = 1;
+if (a > 1) {
+  print \"big\";
+} else {
+  print \"small\";
+}
+fun twice(x) {
+  return x * 2;
+}
+for (var i = 0; i < 3; i = i + 1) twice(i);";
+
+  fn run_with_coverage(src: &str) -> Coverage {
+    let stmts = parse_program(src);
+    let coverage = Coverage::new(&stmts);
+    let mut interpreter = Interpreter::new(vec![]).with_hook(coverage.clone());
+    interpreter.interpret_stmts(&stmts).unwrap();
+    coverage
+  }
+
+  #[test]
+  fn counts_how_many_times_each_line_ran() {
+    let hits = run_with_coverage(PROGRAM).hits();
+    assert_eq!(
+      hits.into_iter().collect::<Vec<_>>(),
+      [(1, 1), (2, 1), (3, 0), (5, 1), (8, 3), (10, 5)]
+    );
+  }
+
+  #[test]
+  fn lcov_lists_every_executable_line() {
+    let lcov = run_with_coverage("var a = 1;\nif (a > 1)\n  print a;").to_lcov("script.lox");
+    assert_eq!(
+      lcov,
+      "SF:script.lox\nDA:1,1\nDA:2,1\nDA:3,0\nLH:2\nLF:3\nend_of_record\n"
+    );
+  }
+
+  #[test]
+  fn annotated_listing_marks_lines_that_never_ran() {
+    let listing = run_with_coverage(PROGRAM).annotate(PROGRAM);
+    let lines = listing.lines().collect::<Vec<_>>();
+    assert_eq!(lines[0], "        1:    1:var a = 1;");
+    assert_eq!(lines[2], "    #####:    3:  print \"big\";");
+    assert_eq!(lines[3], "        -:    4:} else {");
+  }
+}
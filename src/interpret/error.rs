@@ -1,24 +1,174 @@
 use thiserror::Error;
+use crate::interpret::stack_trace::StackTrace;
 use crate::interpret::value::Value;
+use crate::scan::span::Span;
 
 #[derive(Error, Debug, PartialEq)]
 pub enum RuntimeError {
-  #[error("[line {0}]: Expected a number, got a {1}")]
-  NotANumber(usize, String),
-  #[error("[line {0}]: Operation {1} expected 2 numbers. Received {2} and {3}")]
-  WrongBinaryOperationType(usize, String, String, String),
+  #[error("[{0}]: Expected a number, got a {1}")]
+  NotANumber(Span, String),
+  #[error("[{0}]: Operation {1} expected 2 numbers. Received {2} and {3}")]
+  WrongBinaryOperationType(Span, String, String, String),
+  #[error("[{0}]: Operation {1} expected {2}, got {3}")]
+  InvalidBitwiseOperand(Span, String, String, String),
   #[error("Expression cannot be executed. Maybe there is an issue with the parser.")]
   InvalidExpression,
+  #[error("Scope {0} does not exist. Maybe there is an issue with the interpreter.")]
+  MissingScope(usize),
   #[error("[line {0}]: Undefined variable: {1}")]
   UndefinedVariable(usize, String),
   #[error("Cannot write to stdout")]
   CannotWriteToStdout,
-  #[error("[line {0}]: Tried to divide by zero")]
-  ZeroDivision(usize),
+  #[error("Cannot read from stdin")]
+  CannotReadFromStdin,
+  #[error("[{0}]: Tried to divide by zero")]
+  ZeroDivision(Span),
   #[error("[line {0}]: Expected function, got {1}")]
   NotAFunction(usize, String),
   #[error("[line {0}]: {1} expeted {2} arguments, but {3} received")]
   WrongNumberOfArguments(usize, String, usize, usize),
+  #[error("[line {0}]: Only instances have properties, got {1}")]
+  NotAnInstance(usize, String),
+  #[error("[line {0}]: Undefined property {1}")]
+  UndefinedProperty(usize, String),
+  #[error("[line {0}]: {1} expected {2}, got {3}")]
+  WrongArgumentType(usize, String, String, String),
+  #[error("[line {0}]: {1} index {2} is out of range for length {3}")]
+  IndexOutOfRange(usize, String, usize, usize),
+  #[error("[line {0}]: {1} cannot access {2}: {3}")]
+  FileAccess(usize, String, String, String),
+  #[error("[line {0}]: Superclass must be a class, got {1}")]
+  SuperclassMustBeAClass(usize, String),
+  #[error("[line {0}]: Stack overflow")]
+  StackOverflow(usize),
+  #[error("[line {0}]: Cannot import {1}: {2}")]
+  ImportFailed(usize, String, String),
+  #[error("[line {0}]: Import cycle: {1}")]
+  ImportCycle(usize, String),
+  #[error("[line {0}]: Assertion failed: {1}")]
+  AssertionFailed(usize, String),
+  /// A value raised by `throw`.
+  #[error("[line {0}]: {1}")]
+  Thrown(usize, Value),
+  #[error("Execution limit exceeded")]
+  ExecutionLimitExceeded,
+  /// An error that escaped from Lox functions, with the calls that were in progress.
+  #[error("{error}\n{trace}")]
+  Traced {
+    error: Box<RuntimeError>,
+    trace: StackTrace,
+  },
   #[error("return")]
-  Return(Value)
+  Return(Value),
+  #[error("break")]
+  Break,
+  #[error("continue")]
+  Continue,
+  /// Stops the whole program, raised by `exit()`. Hosts turn it into the exit code of the process.
+  #[error("exit {0}")]
+  Exit(u8),
+}
+
+impl RuntimeError {
+  /// The error itself, without the stack trace.
+  pub fn cause(&self) -> &RuntimeError {
+    match self {
+      RuntimeError::Traced { error, .. } => error,
+      error => error,
+    }
+  }
+
+  /// What went wrong, without where it happened or the stack trace.
+  pub fn message(&self) -> String {
+    let error = self.cause().to_string();
+    match error.split_once("]: ") {
+      Some((location, message)) if location.starts_with('[') => message.to_string(),
+      _ => error,
+    }
+  }
+
+  /// Whether `try` can recover from the error. Control flow, `exit()` and execution limits always
+  /// get through.
+  pub fn is_catchable(&self) -> bool {
+    !matches!(
+      self.cause(),
+      RuntimeError::Return(_)
+        | RuntimeError::Break
+        | RuntimeError::Continue
+        | RuntimeError::Exit(_)
+        | RuntimeError::ExecutionLimitExceeded
+    )
+  }
+
+  /// Stable code of the error, which `explain` describes. Traced errors have the code of the
+  /// error that was traced.
+  pub fn code(&self) -> &'static str {
+    match self {
+      RuntimeError::NotANumber(..) => "E1001",
+      RuntimeError::WrongBinaryOperationType(..) => "E1003",
+      RuntimeError::InvalidBitwiseOperand(..) => "E1004",
+      RuntimeError::InvalidExpression => "E1900",
+      RuntimeError::MissingScope(_) => "E1901",
+      RuntimeError::UndefinedVariable(..) => "E1002",
+      RuntimeError::CannotWriteToStdout => "E1020",
+      RuntimeError::CannotReadFromStdin => "E1021",
+      RuntimeError::ZeroDivision(_) => "E1005",
+      RuntimeError::NotAFunction(..) => "E1006",
+      RuntimeError::WrongNumberOfArguments(..) => "E1007",
+      RuntimeError::NotAnInstance(..) => "E1008",
+      RuntimeError::UndefinedProperty(..) => "E1009",
+      RuntimeError::WrongArgumentType(..) => "E1010",
+      RuntimeError::IndexOutOfRange(..) => "E1011",
+      RuntimeError::FileAccess(..) => "E1012",
+      RuntimeError::SuperclassMustBeAClass(..) => "E1013",
+      RuntimeError::StackOverflow(_) => "E1014",
+      RuntimeError::ImportFailed(..) => "E1015",
+      RuntimeError::ImportCycle(..) => "E1016",
+      RuntimeError::AssertionFailed(..) => "E1017",
+      RuntimeError::Thrown(..) => "E1018",
+      RuntimeError::ExecutionLimitExceeded => "E1019",
+      RuntimeError::Traced { error, .. } => error.code(),
+      RuntimeError::Return(_) => "E1902",
+      RuntimeError::Break => "E1903",
+      RuntimeError::Continue => "E1904",
+      RuntimeError::Exit(_) => "E1905",
+    }
+  }
+
+  /// Where the error happened, for the errors raised by an operator. The rest only know the line.
+  pub fn span(&self) -> Option<Span> {
+    match self.cause() {
+      RuntimeError::NotANumber(span, _)
+      | RuntimeError::WrongBinaryOperationType(span, ..)
+      | RuntimeError::InvalidBitwiseOperand(span, ..)
+      | RuntimeError::ZeroDivision(span) => Some(*span),
+      _ => None,
+    }
+  }
+
+  /// Line where the error happened, for the errors that know it.
+  pub fn line(&self) -> Option<usize> {
+    match self {
+      RuntimeError::NotANumber(span, _)
+      | RuntimeError::WrongBinaryOperationType(span, ..)
+      | RuntimeError::InvalidBitwiseOperand(span, ..)
+      | RuntimeError::ZeroDivision(span) => Some(span.line),
+      RuntimeError::UndefinedVariable(line, _)
+      | RuntimeError::NotAFunction(line, _)
+      | RuntimeError::WrongNumberOfArguments(line, ..)
+      | RuntimeError::NotAnInstance(line, _)
+      | RuntimeError::UndefinedProperty(line, _)
+      | RuntimeError::WrongArgumentType(line, ..)
+      | RuntimeError::IndexOutOfRange(line, ..)
+      | RuntimeError::SuperclassMustBeAClass(line, _)
+      | RuntimeError::FileAccess(line, ..)
+      | RuntimeError::StackOverflow(line)
+      | RuntimeError::ImportFailed(line, ..)
+      | RuntimeError::ImportCycle(line, _)
+      | RuntimeError::AssertionFailed(line, _)
+      | RuntimeError::Thrown(line, _) => Some(*line),
+      RuntimeError::Traced { error, .. } => error.line(),
+      _ => None,
+    }
+  }
 }
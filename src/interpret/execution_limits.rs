@@ -0,0 +1,80 @@
+use crate::interpret::error::RuntimeError;
+use std::time::{Duration, Instant};
+
+/// Bounds on how much work a program may do, so runaway loops in untrusted scripts end with
+/// [`RuntimeError::ExecutionLimitExceeded`]. There are no limits by default.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ExecutionLimits {
+  /// Statements and expressions that can be evaluated.
+  pub max_steps: Option<u64>,
+  /// Wall-clock time the program can run, counted from its first step. `Instant` is not
+  /// available on wasm32, so don't set it there.
+  pub timeout: Option<Duration>,
+}
+
+/// Tracks a program's progress against its limits.
+#[derive(Debug, Default)]
+pub(crate) struct ExecutionBudget {
+  limits: ExecutionLimits,
+  steps: u64,
+  started: Option<Instant>,
+}
+
+impl ExecutionBudget {
+  pub(crate) fn new(limits: ExecutionLimits) -> ExecutionBudget {
+    ExecutionBudget {
+      limits,
+      ..ExecutionBudget::default()
+    }
+  }
+
+  /// Counts one more step, failing once any limit is exceeded.
+  pub(crate) fn step(&mut self) -> Result<(), RuntimeError> {
+    self.steps += 1;
+    if self.limits.max_steps.is_some_and(|max| self.steps > max) {
+      return Err(RuntimeError::ExecutionLimitExceeded);
+    }
+    if let Some(timeout) = self.limits.timeout {
+      let started = *self.started.get_or_insert_with(Instant::now);
+      if started.elapsed() > timeout {
+        return Err(RuntimeError::ExecutionLimitExceeded);
+      }
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn without_limits_every_step_is_allowed() {
+    let mut budget = ExecutionBudget::new(ExecutionLimits::default());
+    for _ in 0..10_000 {
+      budget.step().unwrap();
+    }
+  }
+
+  #[test]
+  fn steps_past_the_maximum_fail() {
+    let mut budget = ExecutionBudget::new(ExecutionLimits {
+      max_steps: Some(2),
+      ..ExecutionLimits::default()
+    });
+    assert!(budget.step().is_ok());
+    assert!(budget.step().is_ok());
+    assert_eq!(budget.step(), Err(RuntimeError::ExecutionLimitExceeded));
+  }
+
+  #[test]
+  fn steps_after_the_timeout_fail() {
+    let mut budget = ExecutionBudget::new(ExecutionLimits {
+      timeout: Some(Duration::from_millis(10)),
+      ..ExecutionLimits::default()
+    });
+    assert!(budget.step().is_ok());
+    std::thread::sleep(Duration::from_millis(20));
+    assert_eq!(budget.step(), Err(RuntimeError::ExecutionLimitExceeded));
+  }
+}
@@ -0,0 +1,110 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+/// Files for `readFile()`, `writeFile()` and `appendFile()`. Interpreters start without access to
+/// any file; hosts choose what programs can touch by giving them a file system.
+pub trait FileSystem {
+  fn read(&self, path: &str) -> io::Result<String>;
+  fn write(&mut self, path: &str, contents: &str) -> io::Result<()>;
+  fn append(&mut self, path: &str, contents: &str) -> io::Result<()>;
+}
+
+/// Refuses every operation. The default, so programs can't touch files unless allowed.
+pub struct NoFileSystem;
+
+impl NoFileSystem {
+  fn denied() -> io::Error {
+    io::Error::new(
+      io::ErrorKind::PermissionDenied,
+      "file access is not allowed",
+    )
+  }
+}
+
+impl FileSystem for NoFileSystem {
+  fn read(&self, _path: &str) -> io::Result<String> {
+    Err(NoFileSystem::denied())
+  }
+
+  fn write(&mut self, _path: &str, _contents: &str) -> io::Result<()> {
+    Err(NoFileSystem::denied())
+  }
+
+  fn append(&mut self, _path: &str, _contents: &str) -> io::Result<()> {
+    Err(NoFileSystem::denied())
+  }
+}
+
+/// The files of the machine, with paths relative to the working directory.
+pub struct OsFileSystem;
+
+impl FileSystem for OsFileSystem {
+  fn read(&self, path: &str) -> io::Result<String> {
+    std::fs::read_to_string(path)
+  }
+
+  fn write(&mut self, path: &str, contents: &str) -> io::Result<()> {
+    std::fs::write(path, contents)
+  }
+
+  fn append(&mut self, path: &str, contents: &str) -> io::Result<()> {
+    std::fs::OpenOptions::new()
+      .append(true)
+      .create(true)
+      .open(path)?
+      .write_all(contents.as_bytes())
+  }
+}
+
+/// Files kept in memory, for tests and hosts without a disk. Clones share the same files, so a
+/// host can keep one to look at what the program wrote.
+#[derive(Clone, Default)]
+pub struct MemoryFileSystem {
+  files: Rc<RefCell<HashMap<String, String>>>,
+}
+
+impl MemoryFileSystem {
+  pub fn new() -> MemoryFileSystem {
+    MemoryFileSystem::default()
+  }
+
+  pub fn with_file(self, path: &str, contents: &str) -> MemoryFileSystem {
+    self
+      .files
+      .borrow_mut()
+      .insert(path.to_string(), contents.to_string());
+    self
+  }
+
+  pub fn file(&self, path: &str) -> Option<String> {
+    self.files.borrow().get(path).cloned()
+  }
+}
+
+impl FileSystem for MemoryFileSystem {
+  fn read(&self, path: &str) -> io::Result<String> {
+    self
+      .file(path)
+      .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no such file"))
+  }
+
+  fn write(&mut self, path: &str, contents: &str) -> io::Result<()> {
+    self
+      .files
+      .borrow_mut()
+      .insert(path.to_string(), contents.to_string());
+    Ok(())
+  }
+
+  fn append(&mut self, path: &str, contents: &str) -> io::Result<()> {
+    self
+      .files
+      .borrow_mut()
+      .entry(path.to_string())
+      .or_default()
+      .push_str(contents);
+    Ok(())
+  }
+}
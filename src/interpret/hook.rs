@@ -0,0 +1,21 @@
+use crate::interpret::stack_trace::CallFrame;
+use crate::interpret::value::Value;
+use crate::parse::expr::Expr;
+use crate::parse::stmt::Stmt;
+
+/// Watches a program as the interpreter runs it, e.g. to trace or profile it. Every method does
+/// nothing by default, so hooks only implement the events they care about.
+pub trait InterpreterHook {
+  /// Called right before running a statement.
+  fn before_stmt(&mut self, _stmt: &Stmt) {}
+
+  /// Called after evaluating an expression, with the value it produced. Expressions that fail
+  /// aren't reported.
+  fn after_expr(&mut self, _expr: &Expr, _value: &Value) {}
+
+  /// Called when a Lox function starts running, before its body.
+  fn before_call(&mut self, _frame: &CallFrame) {}
+
+  /// Called when a Lox function finishes, whether it returned or failed.
+  fn after_call(&mut self, _frame: &CallFrame) {}
+}
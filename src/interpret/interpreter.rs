@@ -1,98 +1,292 @@
 use crate::interpret::branching_scope::BranchingScope;
+use crate::interpret::clock::Clock;
 use crate::interpret::error::RuntimeError;
-use crate::interpret::lox_fn::{Callable, NativeFn};
+use crate::interpret::execution_limits::{ExecutionBudget, ExecutionLimits};
+use crate::interpret::file_system::FileSystem;
+use crate::interpret::hook::InterpreterHook;
+use crate::interpret::lox_class::{LoxClass, LoxInstance};
+use crate::interpret::lox_fn::{Callable, LoxFn};
+use crate::interpret::native_context::NativeContext;
+use crate::interpret::modules::{Import, LoadError, ModuleLoader};
+use crate::interpret::native_registry::NativeRegistry;
+use crate::interpret::random::Random;
+use crate::interpret::stack_trace::{CallFrame, StackTrace};
 use crate::interpret::value::Value;
-use crate::parse::expr::Expr;
-use crate::parse::stmt::Stmt;
+use crate::parse::expr::{Expr, ExprKind};
+use crate::parse::pretty_print::ToLoxSource;
+use crate::parse::stmt::{Stmt, StmtKind};
+use crate::parse::visitor::{ClassDeclaration, ExprVisitor, ForLoop, StmtVisitor};
 use crate::scan::token::Token;
 use crate::scan::token_kind::TokenKind;
-use std::io::Write;
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
 use std::slice;
-use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Nested Lox calls allowed before a call fails with [`RuntimeError::StackOverflow`].
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 1000;
 
 pub struct Interpreter<W: Write> {
   env: BranchingScope,
-  // global_id: usize,
+  global_id: usize,
   current_id: usize,
+  /// Lox calls in progress, outermost first. Scopes are only garbage collected between top-level
+  /// statements, while no Rust frame holds values the arena doesn't know about.
+  call_stack: Vec<CallFrame>,
+  max_call_depth: usize,
+  budget: ExecutionBudget,
   stdout: W,
+  native_context: NativeContext,
+  hooks: Vec<Box<dyn InterpreterHook>>,
+  modules: ModuleLoader,
+  /// Whether `+` turns its other operand into a string when one of them is a string.
+  lenient_concat: bool,
+  /// Class of the errors `catch` receives.
+  error_class: Rc<LoxClass>,
+  /// Whether [`Interpreter::interpret_stmts`] prints the value of expression statements, as a
+  /// REPL does.
+  echo_expression_results: bool,
 }
 
 impl<W: Write> Interpreter<W> {
   pub fn new(writer: W) -> Self {
-    let mut env = BranchingScope::empty();
-    let global_id = env.branch(0);
+    Self::with_natives(writer, NativeRegistry::with_defaults())
+  }
 
-    env.define(
-      global_id,
-      "clock",
-      Value::Callable(Callable::Native(NativeFn::new(
-        "clock".to_string(),
-        |_a| {
-          let start = SystemTime::now();
-          let since_the_epoch = start
-            .duration_since(UNIX_EPOCH)
-            .expect("Time went backwards");
-          Ok(Value::Number(since_the_epoch.as_secs() as f64))
-        }
-      ))),
-    );
+  /// Interpreter whose global scope starts with the natives in `registry`.
+  pub fn with_natives(writer: W, registry: NativeRegistry) -> Self {
+    let mut env = BranchingScope::with_global_scope();
+    let global_id = BranchingScope::GLOBAL;
+
+    for native in registry.natives() {
+      env.define(
+        global_id,
+        &native.name,
+        Value::Callable(Callable::Native(native.clone())),
+      );
+    }
 
     Interpreter {
       env,
-      // global_id,
+      global_id,
       current_id: global_id,
+      call_stack: vec![],
+      max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+      budget: ExecutionBudget::default(),
       stdout: writer,
+      native_context: NativeContext::default(),
+      hooks: vec![],
+      modules: ModuleLoader::default(),
+      lenient_concat: false,
+      echo_expression_results: false,
+      error_class: Rc::new(LoxClass::new("Error".to_string(), None, HashMap::new())),
     }
   }
 
+  /// Where `readLine()` reads from. Interpreters start with an empty input.
+  pub fn with_input(mut self, input: impl BufRead + 'static) -> Self {
+    self.native_context.input = Box::new(input);
+    self
+  }
+
+  /// Where `clock()` gets the time from. Interpreters start with the system clock.
+  pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+    self.native_context.clock = Box::new(clock);
+    self
+  }
+
+  /// Seeds `random()`, so it gives the same numbers on every run. Interpreters start seeded from
+  /// the current time.
+  pub fn with_random_seed(mut self, seed: u64) -> Self {
+    self.native_context.random = Random::seeded(seed);
+    self
+  }
+
+  /// Where runtime errors are reported, separate from what the program prints. Interpreters start
+  /// with stderr.
+  pub fn with_error_output(mut self, error_output: impl Write + 'static) -> Self {
+    self.native_context.error_output = Box::new(error_output);
+    self
+  }
+
+  /// Files that `readFile()`, `writeFile()` and `appendFile()` use. Interpreters start without
+  /// access to any file.
+  pub fn with_file_system(mut self, file_system: impl FileSystem + 'static) -> Self {
+    self.native_context.file_system = Box::new(file_system);
+    self
+  }
+
+  /// Arguments the program can read with `argc()` and `argv(i)`. Interpreters start without any.
+  pub fn with_args(mut self, args: Vec<String>) -> Self {
+    self.native_context.args = args;
+    self
+  }
+
+  /// How many Lox calls can be nested before a call fails with a stack overflow error. Each call
+  /// takes several Rust frames, so deep limits need a thread with a big enough stack.
+  pub fn with_max_call_depth(mut self, max_call_depth: usize) -> Self {
+    self.max_call_depth = max_call_depth;
+    self
+  }
+
+  /// Lets `+` join a string with any other value, as in `"count: " + 3`, instead of failing.
+  /// Interpreters start strict, like standard Lox.
+  pub fn with_lenient_concat(mut self, lenient_concat: bool) -> Self {
+    self.lenient_concat = lenient_concat;
+    self
+  }
+
+  /// Prints the value of each expression statement run by [`Interpreter::interpret_stmts`], like
+  /// `print` would. Statements inside blocks and functions stay silent. Interpreters start silent.
+  pub fn with_echo_expression_results(mut self, echo: bool) -> Self {
+    self.echo_expression_results = echo;
+    self
+  }
+
+  /// Stops programs that run too many steps or for too long. Interpreters start without limits.
+  pub fn with_limits(mut self, limits: ExecutionLimits) -> Self {
+    self.budget = ExecutionBudget::new(limits);
+    self
+  }
+
+  /// File of the program being run. Its imports are relative to its directory; without it, they
+  /// are relative to the working directory.
+  pub fn with_script_path(mut self, path: impl Into<PathBuf>) -> Self {
+    self.modules.set_script(path.into());
+    self
+  }
+
+  /// Directories to look for imports in when they aren't next to the file importing them, like
+  /// the `sources` of a project's `lox.toml`.
+  pub fn with_source_roots(mut self, roots: Vec<PathBuf>) -> Self {
+    self.modules.set_roots(roots);
+    self
+  }
+
+  /// Adds a hook that watches the program as it runs. Hooks are called in the order they were
+  /// added.
+  pub fn with_hook(mut self, hook: impl InterpreterHook + 'static) -> Self {
+    self.hooks.push(Box::new(hook));
+    self
+  }
+
+  /// Runs the top-level statements of a program.
   pub fn interpret_stmts(&mut self, stmts: &[Stmt]) -> Result<Value, RuntimeError> {
+    for stmt in stmts {
+      let value = self.interpret_stmt(stmt)?;
+      if let Some(value) = value.filter(|_| self.echo_expression_results) {
+        let text = value.display(self, stmt.line())?;
+        writeln!(self.stdout, "{text}").map_err(|_| RuntimeError::CannotWriteToStdout)?;
+      }
+    }
+    Ok(Value::Nil)
+  }
+
+  /// Runs statements nested in a block, a function or an imported file.
+  pub(crate) fn interpret_block(&mut self, stmts: &[Stmt]) -> Result<Value, RuntimeError> {
     for stmt in stmts {
       self.interpret_stmt(stmt)?;
     }
     Ok(Value::Nil)
   }
 
-  pub fn interpret_stmt(&mut self, stmt: &Stmt) -> Result<(), RuntimeError> {
-    match stmt {
-      Stmt::Expr(expr) => {
-        self.interpret_expr(expr)?;
-      }
-      Stmt::Print(expr) => {
-        let value = self.interpret_expr(expr)?;
-        writeln!(self.stdout, "{}", &value.to_string())
-          .map_err(|_| RuntimeError::CannotWriteToStdout)?;
-      }
-      Stmt::Var(name, expr, _) => {
-        let value = self.interpret_expr(expr)?;
-        self.env.define(self.current_id, name, value);
-      }
-      Stmt::ScopeBlock(stmts) => {
-        self.interpret_scope_block_stmt(stmts)?;
-      }
-      Stmt::If {
-        condition,
-        then,
-        els,
-      } => {
-        self.interpret_if(condition, then, els.as_ref().map(|b| &**b))?;
-      }
-      Stmt::While { condition, body } => {
-        self.interpret_while(condition, body)?;
+  /// Gives the value of expression statements, for REPLs to show.
+  pub fn interpret_stmt(&mut self, stmt: &Stmt) -> Result<Option<Value>, RuntimeError> {
+    self.budget.step()?;
+    if self.call_stack.is_empty() {
+      self.env.maybe_collect_garbage();
+    }
+    for hook in &mut self.hooks {
+      hook.before_stmt(stmt);
+    }
+    stmt.accept(self)
+  }
+
+  /// Failures show the condition as written, followed by the message when there is one.
+  fn interpret_assert(
+    &mut self,
+    condition: &Expr,
+    message: Option<&Expr>,
+    line: usize,
+  ) -> Result<(), RuntimeError> {
+    let value = self.interpret_expr(condition)?;
+    if self.is_truthy(&value) {
+      return Ok(());
+    }
+    let mut failure = condition.to_lox_source();
+    if let Some(message) = message {
+      let message = self.interpret_expr(message)?;
+      failure = format!("{failure} ({message})");
+    }
+    Err(RuntimeError::AssertionFailed(line, failure))
+  }
+
+  /// The handler gets thrown values as they are. For any other error it gets an `Error` instance
+  /// with the `message` and `line` of what went wrong.
+  fn interpret_try(
+    &mut self,
+    body: &[Stmt],
+    name: &str,
+    handler: &[Stmt],
+  ) -> Result<(), RuntimeError> {
+    let error = match self.interpret_scope_block_stmt(body) {
+      Err(error) if error.is_catchable() => error,
+      res => return res,
+    };
+
+    let value = match error.cause() {
+      RuntimeError::Thrown(_, value) => value.clone(),
+      _ => {
+        let instance = LoxInstance::new(Rc::clone(&self.error_class));
+        instance.set("message", Value::from(error.message()));
+        let line = error.line().map(|line| Value::Number(line as f64));
+        instance.set("line", line.unwrap_or(Value::Nil));
+        Value::Instance(instance)
       }
-      Stmt::Function { name, params, body } => {
-        self.interpret_function_definition(name, params, body)?;
+    };
+
+    let new_scope = self.env.branch(self.current_id)?;
+    self.current_id = new_scope;
+    self.env.define(new_scope, name, value);
+    let res = self.interpret_block(handler);
+    self.current_id = self.env.release(self.current_id)?;
+    res.map(|_| ())
+  }
+
+  /// Imported files run in the global scope, whatever scope imports them.
+  fn interpret_import(&mut self, path: &str, line: usize) -> Result<(), RuntimeError> {
+    let (file, stmts) = match self.modules.load(path) {
+      Ok(Import::AlreadyLoaded) => return Ok(()),
+      Ok(Import::Run(file, stmts)) => (file, stmts),
+      Err(LoadError::Failed(reason)) => {
+        return Err(RuntimeError::ImportFailed(line, path.to_string(), reason))
       }
-      Stmt::Return(expr) => self.interpret_return(expr)?,
-    }
-    Ok(())
+      Err(LoadError::Cycle(cycle)) => return Err(RuntimeError::ImportCycle(line, cycle)),
+    };
+
+    self.modules.start(file);
+    let enclosing = self.current_id;
+    self.current_id = self.global_id;
+    let res = self.interpret_block(&stmts);
+    self.current_id = enclosing;
+    self.modules.finish(res.is_ok());
+    res.map(|_| ())
   }
 
   fn interpret_scope_block_stmt(&mut self, stmts: &[Stmt]) -> Result<(), RuntimeError> {
-    let new_scope = self.env.branch(self.current_id);
+    self.in_new_scope(|interpreter| interpreter.interpret_block(stmts).map(|_| ()))
+  }
+
+  fn in_new_scope(
+    &mut self,
+    action: impl FnOnce(&mut Self) -> Result<(), RuntimeError>,
+  ) -> Result<(), RuntimeError> {
+    let new_scope = self.env.branch(self.current_id)?;
     self.current_id = new_scope;
-    self.interpret_stmts(stmts)?;
-    self.current_id = self.env.release(self.current_id);
-    Ok(())
+    let res = action(self);
+    self.current_id = self.env.release(self.current_id)?;
+    res
   }
 
   // fn branch(&self) -> Interpreter<W> {
@@ -110,18 +304,38 @@ impl<W: Write> Interpreter<W> {
   ) -> Result<(), RuntimeError> {
     let value = self.interpret_expr(condition)?;
     if self.is_truthy(&value) {
-      self.interpret_stmts(slice::from_ref(then))?;
+      self.interpret_block(slice::from_ref(then))?;
     } else {
       els
-        .map(|stmt| self.interpret_stmts(slice::from_ref(stmt)))
+        .map(|stmt| self.interpret_block(slice::from_ref(stmt)))
         .transpose()?;
     }
     Ok(())
   }
 
-  fn interpret_while(&mut self, condition: &Expr, body: &Stmt) -> Result<(), RuntimeError> {
-    while self.interpret_expr(condition).map(|v| self.is_truthy(&v))? {
-      self.interpret_stmt(body)?;
+  /// Runs `while` and `for` loops. Without a condition, the loop only stops with `break` or an
+  /// error.
+  fn interpret_loop(
+    &mut self,
+    condition: Option<&Expr>,
+    body: &Stmt,
+    increment: Option<&Expr>,
+  ) -> Result<(), RuntimeError> {
+    loop {
+      if let Some(condition) = condition {
+        let value = self.interpret_expr(condition)?;
+        if !self.is_truthy(&value) {
+          break;
+        }
+      }
+      match self.interpret_stmt(body) {
+        Ok(_) | Err(RuntimeError::Continue) => {}
+        Err(RuntimeError::Break) => break,
+        Err(e) => return Err(e),
+      }
+      if let Some(increment) = increment {
+        self.interpret_expr(increment)?;
+      }
     }
     Ok(())
   }
@@ -132,18 +346,83 @@ impl<W: Write> Interpreter<W> {
     params: &[String],
     body: &[Stmt],
   ) -> Result<Value, RuntimeError> {
-    let new_branch = self.env.branch(self.current_id);
+    self.env.capture(self.current_id)?;
     let fun = Value::fun(
       name.to_string(),
       params.to_vec(),
       body.to_vec(),
-      new_branch,
+      self.current_id,
     );
+    self.env.define(self.current_id, name, fun);
+    Ok(Value::Nil)
+  }
 
+  fn interpret_class_definition(
+    &mut self,
+    name: &str,
+    superclass: Option<&Expr>,
+    methods: &[Stmt],
+    class_methods: &[Stmt],
+    getters: &[Stmt],
+  ) -> Result<(), RuntimeError> {
+    let superclass = match superclass {
+      Some(
+        expr @ Expr {
+          kind: ExprKind::Variable { line, .. },
+          ..
+        },
+      ) => match self.interpret_expr(expr)? {
+        Value::Callable(Callable::Class(class)) => Some(class),
+        value => {
+          return Err(RuntimeError::SuperclassMustBeAClass(
+            *line,
+            value.type_name().to_string(),
+          ))
+        }
+      },
+      Some(_) => return Err(RuntimeError::InvalidExpression),
+      None => None,
+    };
+
+    let closure = self.env.branch(self.current_id)?;
+    if let Some(superclass) = &superclass {
+      self.env.define(
+        closure,
+        "super",
+        Value::Callable(Callable::Class(Rc::clone(superclass))),
+      );
+    }
+    self.env.capture(closure)?;
+    self.env.release(closure)?;
+
+    let functions = |stmts: &[Stmt]| {
+      let mut functions = HashMap::new();
+      for stmt in stmts {
+        let StmtKind::Function { name, params, body } = &stmt.kind else {
+          return Err(RuntimeError::InvalidExpression);
+        };
+        let function = LoxFn::new(name.to_string(), params.to_vec(), body.to_vec(), closure);
+        functions.insert(name.to_string(), function);
+      }
+      Ok(functions)
+    };
 
+    let mut instance_methods = functions(methods)?;
+    if let Some(init) = instance_methods.remove("init") {
+      instance_methods.insert("init".to_string(), init.initializer());
+    }
+    for (name, getter) in functions(getters)? {
+      instance_methods.insert(name, getter.getter());
+    }
 
-    self.env.define(self.current_id, name, fun);
-    Ok(Value::Nil)
+    let class = LoxClass::new(name.to_string(), superclass, instance_methods)
+      .with_class_methods(functions(class_methods)?);
+    self.env.define(
+      self.current_id,
+      name,
+      Value::Callable(Callable::Class(Rc::new(class))),
+    );
+    Ok(())
   }
 
   fn interpret_return(&mut self, expr: &Expr) -> Result<(), RuntimeError> {
@@ -152,48 +431,120 @@ impl<W: Write> Interpreter<W> {
   }
 
   pub fn interpret_expr(&mut self, expr: &Expr) -> Result<Value, RuntimeError> {
-    match expr {
-      Expr::LiteralNumber { value } => Ok(Value::Number(*value)),
-      Expr::LiteralNil => Ok(Value::Nil),
-      Expr::LiteralBool { value } => Ok(Value::Boolean(*value)),
-      Expr::Unary { operator, right } => self.unary(operator, right),
-      Expr::LiteralString { value } => Ok(Value::String(value.to_string())),
-      Expr::Group { expression } => self.interpret_expr(expression),
-      Expr::Binary {
-        left,
-        operator,
-        right,
-      } => self.binary(left, operator, right),
-      Expr::Variable { name, line } => self
-        .env
-        .get(self.current_id, name)
-        .ok_or(RuntimeError::UndefinedVariable(*line, name.to_string()))
-        .map(|v| v.clone()),
-      Expr::Assign { value, name, line } => {
-        let value = self.interpret_expr(value)?;
-        self
-          .env
-          .assign(self.current_id, name, value.clone())
-          .ok_or(RuntimeError::UndefinedVariable(*line, name.to_string()))?;
-        Ok(value)
-      }
-      Expr::Logical {
-        left,
-        operator,
-        right,
-      } => self.logical(left, operator, right),
-      Expr::Call { callee, args, line } => self.interpret_call(callee, args, *line),
+    self.budget.step()?;
+    let value = self.evaluate(expr)?;
+    for hook in &mut self.hooks {
+      hook.after_expr(expr, &value);
+    }
+    Ok(value)
+  }
+
+  fn evaluate(&mut self, expr: &Expr) -> Result<Value, RuntimeError> {
+    expr.accept(self)
+  }
+
+  fn interpret_get(
+    &mut self,
+    object: &Expr,
+    name: &str,
+    line: usize,
+  ) -> Result<Value, RuntimeError> {
+    match self.interpret_expr(object)? {
+      Value::Instance(instance) => {
+        // Fields shadow methods with the same name.
+        if let Some(value) = instance.field(name) {
+          return Ok(value);
+        }
+        let method = instance
+          .find_method(name)
+          .ok_or(RuntimeError::UndefinedProperty(line, name.to_string()))?
+          .clone();
+        self.bound_property(&method, instance, line)
+      }
+      Value::Callable(Callable::Class(class)) => {
+        let method = class
+          .find_class_method(name)
+          .ok_or(RuntimeError::UndefinedProperty(line, name.to_string()))?;
+        Ok(Value::Callable(Callable::Lox(method.clone())))
+      }
+      value => Err(RuntimeError::NotAnInstance(
+        line,
+        value.type_name().to_string(),
+      )),
+    }
+  }
+
+  fn interpret_super(&mut self, method: &str, line: usize) -> Result<Value, RuntimeError> {
+    let Some(Value::Callable(Callable::Class(superclass))) = self.get_var("super") else {
+      return Err(RuntimeError::UndefinedVariable(line, "super".to_string()));
+    };
+    let Some(Value::Instance(instance)) = self.get_var("this") else {
+      return Err(RuntimeError::UndefinedVariable(line, "this".to_string()));
+    };
+
+    let method = superclass
+      .find_method(method)
+      .ok_or(RuntimeError::UndefinedProperty(line, method.to_string()))?
+      .clone();
+    self.bound_property(&method, instance, line)
+  }
+
+  /// Reading a method gives it bound to the instance. Getters run right away instead.
+  fn bound_property(
+    &mut self,
+    method: &LoxFn,
+    instance: LoxInstance,
+    line: usize,
+  ) -> Result<Value, RuntimeError> {
+    let method = self.bind_method(method, instance)?;
+    if method.is_getter() {
+      return method.call(self, vec![], line);
     }
+    Ok(Value::Callable(Callable::Lox(method)))
+  }
+
+  pub fn bind_method(
+    &mut self,
+    method: &LoxFn,
+    instance: LoxInstance,
+  ) -> Result<LoxFn, RuntimeError> {
+    let scope = self.env.branch(method.context_id())?;
+    self.env.define(scope, "this", Value::Instance(instance));
+    self.env.capture(scope)?;
+    self.env.release(scope)?;
+    Ok(method.with_context(scope))
+  }
+
+  fn interpret_set(
+    &mut self,
+    object: &Expr,
+    name: &str,
+    value: &Expr,
+    line: usize,
+  ) -> Result<Value, RuntimeError> {
+    let instance = match self.interpret_expr(object)? {
+      Value::Instance(instance) => instance,
+      value => {
+        return Err(RuntimeError::NotAnInstance(
+          line,
+          value.type_name().to_string(),
+        ))
+      }
+    };
+    let value = self.interpret_expr(value)?;
+    instance.set(name, value.clone());
+    Ok(value)
   }
 
   fn unary(&mut self, operator: &Token, right: &Expr) -> Result<Value, RuntimeError> {
     let value = self.interpret_expr(right)?;
     Ok(match (value, operator.kind()) {
       (Value::Number(value), TokenKind::Minus) => Value::Number(-value),
+      (Value::Number(value), TokenKind::Tilde) => Value::Number(!integer(operator, value)? as f64),
       (val, TokenKind::Bang) => Value::Boolean(!self.is_truthy(&val)),
-      (value, TokenKind::Minus) => {
+      (value, TokenKind::Minus | TokenKind::Tilde) => {
         return Err(RuntimeError::NotANumber(
-          operator.line(),
+          operator.span(),
           value.type_name().to_string(),
         ))
       }
@@ -211,9 +562,10 @@ impl<W: Write> Interpreter<W> {
       (TokenKind::Plus, Value::Number(n1), Value::Number(n2)) => Value::Number(n1 + n2),
       (TokenKind::Minus, Value::Number(n1), Value::Number(n2)) => Value::Number(n1 - n2),
       (TokenKind::Star, Value::Number(n1), Value::Number(n2)) => Value::Number(n1 * n2),
+      (TokenKind::StarStar, Value::Number(n1), Value::Number(n2)) => Value::Number(n1.powf(*n2)),
       (TokenKind::Slash, Value::Number(n1), Value::Number(n2)) => {
         if *n2 == 0.0 {
-          return Err(RuntimeError::ZeroDivision(operator.line()));
+          return Err(RuntimeError::ZeroDivision(operator.span()));
         }
         Value::Number(n1 / n2)
       }
@@ -221,7 +573,22 @@ impl<W: Write> Interpreter<W> {
       (TokenKind::LessEqual, Value::Number(n1), Value::Number(n2)) => Value::Boolean(n1 <= n2),
       (TokenKind::Greater, Value::Number(n1), Value::Number(n2)) => Value::Boolean(n1 > n2),
       (TokenKind::GreaterEqual, Value::Number(n1), Value::Number(n2)) => Value::Boolean(n1 >= n2),
-      (TokenKind::Plus, Value::String(s1), Value::String(s2)) => Value::String(format!("{s1}{s2}")),
+      (
+        TokenKind::Ampersand
+        | TokenKind::Pipe
+        | TokenKind::Caret
+        | TokenKind::LessLess
+        | TokenKind::GreaterGreater,
+        Value::Number(n1),
+        Value::Number(n2),
+      ) => bitwise(operator, *n1, *n2)?,
+      (TokenKind::Plus, Value::String(s1), Value::String(s2)) => Value::from(format!("{s1}{s2}")),
+      (TokenKind::Plus, val1 @ Value::String(_), val2)
+      | (TokenKind::Plus, val1, val2 @ Value::String(_))
+        if self.lenient_concat =>
+      {
+        Value::from(format!("{val1}{val2}"))
+      }
       (
         TokenKind::Greater
         | TokenKind::GreaterEqual
@@ -229,12 +596,18 @@ impl<W: Write> Interpreter<W> {
         | TokenKind::LessEqual
         | TokenKind::Plus
         | TokenKind::Star
-        | TokenKind::Slash,
+        | TokenKind::StarStar
+        | TokenKind::Slash
+        | TokenKind::Ampersand
+        | TokenKind::Pipe
+        | TokenKind::Caret
+        | TokenKind::LessLess
+        | TokenKind::GreaterGreater,
         val1,
         val2,
       ) => {
         return Err(RuntimeError::WrongBinaryOperationType(
-          operator.line(),
+          operator.span(),
           operator.kind().symbol(),
           val1.type_name().to_string(),
           val2.type_name().to_string(),
@@ -296,12 +669,17 @@ impl<W: Write> Interpreter<W> {
     lox_fn.call(self, arg_values, line)
   }
 
+  /// Values of different types are never equal. Numbers follow IEEE 754, so `NaN != NaN`;
+  /// instances, functions and classes are only equal to themselves.
   fn are_equal(&self, val1: &Value, val2: &Value) -> bool {
     match (val1, val2) {
+      (Value::Nil, Value::Nil) => true,
       (Value::Number(n1), Value::Number(n2)) => n1 == n2,
       (Value::Boolean(b1), Value::Boolean(b2)) => b1 == b2,
       (Value::String(s1), Value::String(s2)) => s1 == s2,
-      (val1, val2) => val1 == val2,
+      (Value::Callable(c1), Value::Callable(c2)) => c1 == c2,
+      (Value::Instance(i1), Value::Instance(i2)) => i1 == i2,
+      _ => false,
     }
   }
 
@@ -310,134 +688,522 @@ impl<W: Write> Interpreter<W> {
   }
 
   fn is_falsey(&self, value: &Value) -> bool {
-    match value {
-      Value::Nil => true,
-      Value::Boolean(false) => true,
-      _ => false,
-    }
+    matches!(value, Value::Nil | Value::Boolean(false))
   }
 
+  /// Runs a Lox call in a new scope on top of `base_branch`. Errors that escape the call get the
+  /// stack trace of the calls in progress when they happened.
   pub fn with_branching(
     &mut self,
     base_branch: usize,
+    frame: CallFrame,
     action: impl FnOnce(&mut Interpreter<W>) -> Result<Value, RuntimeError>,
   ) -> Result<Value, RuntimeError> {
+    if self.call_stack.len() >= self.max_call_depth {
+      return Err(RuntimeError::StackOverflow(frame.call_line));
+    }
     let old = self.current_id;
-    let new_branch = self.env.branch(base_branch);
+    let new_branch = self.env.branch(base_branch)?;
     self.current_id = new_branch;
-    let res = action(self);
-    self.env.release(new_branch);
+    for hook in &mut self.hooks {
+      hook.before_call(&frame);
+    }
+    self.call_stack.push(frame);
+    let res = action(self).map_err(|error| self.traced(error));
+    let frame = self.call_stack.pop().expect("the frame of this call");
+    for hook in &mut self.hooks {
+      hook.after_call(&frame);
+    }
     self.current_id = old;
+    self.env.release(new_branch)?;
     res
   }
 
+  fn traced(&self, error: RuntimeError) -> RuntimeError {
+    match (&error, error.line()) {
+      (RuntimeError::Traced { .. }, _) | (_, None) => error,
+      (_, Some(line)) => RuntimeError::Traced {
+        error: Box::new(error),
+        trace: StackTrace::new(&self.call_stack, line),
+      },
+    }
+  }
+
   pub fn define_var(&mut self, name: &str, value: Value) {
     self.env.define(self.current_id, name, value)
   }
-}
 
-#[cfg(test)]
-mod tests {
-  use super::*;
-  use crate::parse::parser::LoxParser;
-  use crate::scan::scanner::Scanner;
-  use std::io::Cursor;
+  pub fn get_var(&self, name: &str) -> Option<Value> {
+    self.env.get(self.current_id, name).cloned()
+  }
 
-  fn interpret_program(src: &str) -> Result<String, RuntimeError> {
-    let mut cursor = Cursor::new(src);
-    let scanner = Scanner::new(&mut cursor);
-    let tokens = scanner.scan_tokens().0;
-    let stmts = LoxParser::new(tokens).parse().unwrap();
-    let mut fake_stdout: Vec<u8> = vec![];
-    let mut interpreter = Interpreter::new(&mut fake_stdout);
-    interpreter.interpret_stmts(&stmts)?;
-    Ok(String::from_utf8(fake_stdout).unwrap())
+  /// Defines a global variable, or replaces it, e.g. to give a program values before running it.
+  pub fn define_global(&mut self, name: &str, value: impl Into<Value>) {
+    self.env.define(self.global_id, name, value.into())
   }
 
-  #[test]
-  fn eval_number_1() {
-    let interpreted = interpret_program("print 1;");
-    let res = interpreted.unwrap();
+  /// Value of a global variable, e.g. to read what a program left behind after running it.
+  pub fn get_global(&self, name: &str) -> Option<Value> {
+    self.env.get(self.global_id, name).cloned()
+  }
 
-    assert_eq!(res, "1\n");
+  /// Calls the global function `name` from Rust. Errors from the call itself (like a wrong number
+  /// of arguments) report line 0, since there is no call site in the program.
+  pub fn call_function(&mut self, name: &str, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    match self.get_global(name) {
+      Some(Value::Callable(callable)) => callable.call(self, args, 0),
+      Some(other) => Err(RuntimeError::NotAFunction(0, other.type_name().to_string())),
+      None => Err(RuntimeError::UndefinedVariable(0, name.to_string())),
+    }
   }
 
-  #[test]
-  fn eval_number_2() {
-    let interpreted = interpret_program("print 2;");
-    let res = interpreted.unwrap();
+  pub fn report_error(&mut self, error: &RuntimeError) -> std::io::Result<()> {
+    writeln!(self.native_context.error_output(), "{error}")
+  }
 
-    assert_eq!(res, "2\n");
+  pub fn stdout(&mut self) -> &mut W {
+    &mut self.stdout
   }
 
-  #[test]
-  fn eval_nil() {
-    let interpreted = interpret_program("print nil;");
-    let res = interpreted.unwrap();
+  pub fn native_context(&mut self) -> &mut NativeContext {
+    &mut self.native_context
+  }
 
-    assert_eq!(res, "nil\n")
+  /// Files the program imported so far, including the ones that failed to load.
+  pub fn imported_files(&self) -> Vec<PathBuf> {
+    self.modules.imported().map(Path::to_path_buf).collect()
   }
 
-  #[test]
-  fn eval_true() {
-    let interpreted = interpret_program("print true;");
-    let res = interpreted.unwrap();
+  /// Writes what natives printed to the program's output.
+  pub(crate) fn flush_native_output(&mut self) -> Result<(), RuntimeError> {
+    if self.native_context.output.is_empty() {
+      return Ok(());
+    }
+    let output = std::mem::take(&mut self.native_context.output);
+    self
+      .stdout
+      .write_all(&output)
+      .map_err(|_| RuntimeError::CannotWriteToStdout)
+  }
+}
 
-    assert_eq!(res, "true\n")
+impl<W: Write> StmtVisitor<Result<Option<Value>, RuntimeError>> for Interpreter<W> {
+  fn visit_expression(&mut self, expr: &Expr) -> Result<Option<Value>, RuntimeError> {
+    self.interpret_expr(expr).map(Some)
   }
 
-  #[test]
-  fn eval_false() {
-    let interpreted = interpret_program("print false;");
-    let res = interpreted.unwrap();
+  fn visit_print(&mut self, expr: &Expr, line: usize) -> Result<Option<Value>, RuntimeError> {
+    let value = self.interpret_expr(expr)?;
+    let text = value.display(self, line)?;
+    writeln!(self.stdout, "{text}").map_err(|_| RuntimeError::CannotWriteToStdout)?;
+    Ok(None)
+  }
 
-    assert_eq!(res, "false\n")
+  fn visit_var(
+    &mut self,
+    name: &str,
+    value: Option<&Expr>,
+    _line: usize,
+  ) -> Result<Option<Value>, RuntimeError> {
+    let value = match value {
+      Some(value) => self.interpret_expr(value)?,
+      None => Value::Nil,
+    };
+    self.env.define(self.current_id, name, value);
+    Ok(None)
   }
 
-  #[test]
-  fn eval_minus_one() {
-    let interpreted = interpret_program("print -1;");
-    let res = interpreted.unwrap();
+  fn visit_block(&mut self, stmts: &[Stmt]) -> Result<Option<Value>, RuntimeError> {
+    self.interpret_scope_block_stmt(stmts)?;
+    Ok(None)
+  }
 
-    assert_eq!(res, "-1\n")
+  fn visit_if(
+    &mut self,
+    condition: &Expr,
+    then: &Stmt,
+    els: Option<&Stmt>,
+    _line: usize,
+  ) -> Result<Option<Value>, RuntimeError> {
+    self.interpret_if(condition, then, els)?;
+    Ok(None)
   }
 
-  #[test]
-  fn eval_minus_string() {
-    let interpreted = interpret_program("print \"foo\";");
-    let res = interpreted.unwrap();
+  fn visit_while(
+    &mut self,
+    condition: &Expr,
+    body: &Stmt,
+    _line: usize,
+  ) -> Result<Option<Value>, RuntimeError> {
+    self.interpret_loop(Some(condition), body, None)?;
+    Ok(None)
+  }
 
-    assert_eq!(res, "foo\n")
+  fn visit_do_while(
+    &mut self,
+    body: &Stmt,
+    condition: &Expr,
+    _line: usize,
+  ) -> Result<Option<Value>, RuntimeError> {
+    loop {
+      match self.interpret_stmt(body) {
+        Ok(_) | Err(RuntimeError::Continue) => {}
+        Err(RuntimeError::Break) => break,
+        Err(e) => return Err(e),
+      }
+      let value = self.interpret_expr(condition)?;
+      if !self.is_truthy(&value) {
+        break;
+      }
+    }
+    Ok(None)
   }
 
-  #[test]
-  fn eval_not_true_returns_false() {
-    let interpreted = interpret_program("print !true;");
-    let res = interpreted.unwrap();
+  fn visit_for(&mut self, for_loop: ForLoop) -> Result<Option<Value>, RuntimeError> {
+    self.in_new_scope(|interpreter| {
+      if let Some(initializer) = for_loop.initializer {
+        interpreter.interpret_stmt(initializer)?;
+      }
+      interpreter.interpret_loop(for_loop.condition, for_loop.body, for_loop.increment)
+    })?;
+    Ok(None)
+  }
 
-    assert_eq!(res, "false\n")
+  fn visit_assert(
+    &mut self,
+    condition: &Expr,
+    message: Option<&Expr>,
+    line: usize,
+  ) -> Result<Option<Value>, RuntimeError> {
+    self.interpret_assert(condition, message, line)?;
+    Ok(None)
   }
 
-  #[test]
-  fn eval_not_false_returns_true() {
-    let interpreted = interpret_program("print !false;");
-    let res = interpreted.unwrap();
+  fn visit_break(&mut self, _line: usize) -> Result<Option<Value>, RuntimeError> {
+    Err(RuntimeError::Break)
+  }
 
-    assert_eq!(res, "true\n")
+  fn visit_continue(&mut self, _line: usize) -> Result<Option<Value>, RuntimeError> {
+    Err(RuntimeError::Continue)
   }
 
-  #[test]
-  fn eval_not_a_positive_number_returns_false() {
-    // any number is truthy
-    let interpreted = interpret_program("print !1.0;");
-    let res = interpreted.unwrap();
+  fn visit_return(&mut self, value: &Expr, _line: usize) -> Result<Option<Value>, RuntimeError> {
+    self.interpret_return(value)?;
+    Ok(None)
+  }
 
-    assert_eq!(res, "false\n")
+  fn visit_throw(&mut self, value: &Expr, line: usize) -> Result<Option<Value>, RuntimeError> {
+    let value = self.interpret_expr(value)?;
+    Err(RuntimeError::Thrown(line, value))
   }
-  //
-  #[test]
-  fn eval_not_zero_returns_false() {
-    // any number is truthy
+
+  fn visit_try(
+    &mut self,
+    body: &[Stmt],
+    name: &str,
+    handler: &[Stmt],
+    _line: usize,
+  ) -> Result<Option<Value>, RuntimeError> {
+    self.interpret_try(body, name, handler)?;
+    Ok(None)
+  }
+
+  fn visit_import(&mut self, path: &str, line: usize) -> Result<Option<Value>, RuntimeError> {
+    self.interpret_import(path, line)?;
+    Ok(None)
+  }
+
+  fn visit_function(
+    &mut self,
+    name: &str,
+    params: &[String],
+    body: &[Stmt],
+  ) -> Result<Option<Value>, RuntimeError> {
+    self.interpret_function_definition(name, params, body)?;
+    Ok(None)
+  }
+
+  fn visit_class(&mut self, class: ClassDeclaration) -> Result<Option<Value>, RuntimeError> {
+    self.interpret_class_definition(
+      class.name,
+      class.superclass,
+      class.methods,
+      class.class_methods,
+      class.getters,
+    )?;
+    Ok(None)
+  }
+}
+
+impl<W: Write> ExprVisitor<Result<Value, RuntimeError>> for Interpreter<W> {
+  fn visit_literal_number(&mut self, value: f64) -> Result<Value, RuntimeError> {
+    Ok(Value::Number(value))
+  }
+
+  fn visit_literal_bool(&mut self, value: bool) -> Result<Value, RuntimeError> {
+    Ok(Value::Boolean(value))
+  }
+
+  fn visit_literal_string(&mut self, value: &str) -> Result<Value, RuntimeError> {
+    Ok(Value::from(value))
+  }
+
+  fn visit_literal_nil(&mut self) -> Result<Value, RuntimeError> {
+    Ok(Value::Nil)
+  }
+
+  fn visit_binary(
+    &mut self,
+    left: &Expr,
+    operator: &Token,
+    right: &Expr,
+  ) -> Result<Value, RuntimeError> {
+    self.binary(left, operator, right)
+  }
+
+  fn visit_logical(
+    &mut self,
+    left: &Expr,
+    operator: &Token,
+    right: &Expr,
+  ) -> Result<Value, RuntimeError> {
+    self.logical(left, operator, right)
+  }
+
+  fn visit_unary(&mut self, operator: &Token, right: &Expr) -> Result<Value, RuntimeError> {
+    self.unary(operator, right)
+  }
+
+  fn visit_call(
+    &mut self,
+    callee: &Expr,
+    args: &[Expr],
+    line: usize,
+  ) -> Result<Value, RuntimeError> {
+    self.interpret_call(callee, args, line)
+  }
+
+  fn visit_group(&mut self, expression: &Expr) -> Result<Value, RuntimeError> {
+    self.interpret_expr(expression)
+  }
+
+  fn visit_variable(&mut self, name: &str, line: usize) -> Result<Value, RuntimeError> {
+    self
+      .env
+      .get(self.current_id, name)
+      .ok_or(RuntimeError::UndefinedVariable(line, name.to_string()))
+      .cloned()
+  }
+
+  fn visit_assign(&mut self, name: &str, value: &Expr, line: usize) -> Result<Value, RuntimeError> {
+    let value = self.interpret_expr(value)?;
+    self
+      .env
+      .assign(self.current_id, name, value.clone())
+      .ok_or(RuntimeError::UndefinedVariable(line, name.to_string()))?;
+    Ok(value)
+  }
+
+  fn visit_increment(
+    &mut self,
+    name: &str,
+    operator: &Token,
+    postfix: bool,
+    line: usize,
+  ) -> Result<Value, RuntimeError> {
+    let old = match self.visit_variable(name, line)? {
+      Value::Number(old) => old,
+      value => {
+        return Err(RuntimeError::NotANumber(
+          operator.span(),
+          value.type_name().to_string(),
+        ))
+      }
+    };
+    let new = match operator.kind() {
+      TokenKind::PlusPlus => old + 1.0,
+      _ => old - 1.0,
+    };
+    self
+      .env
+      .assign(self.current_id, name, Value::Number(new))
+      .ok_or(RuntimeError::UndefinedVariable(line, name.to_string()))?;
+    // The old value is the one read, not `new` minus one, which rounding can change.
+    Ok(Value::Number(if postfix { old } else { new }))
+  }
+
+  fn visit_this(&mut self, line: usize) -> Result<Value, RuntimeError> {
+    self
+      .get_var("this")
+      .ok_or(RuntimeError::UndefinedVariable(line, "this".to_string()))
+  }
+
+  fn visit_super(&mut self, method: &str, line: usize) -> Result<Value, RuntimeError> {
+    self.interpret_super(method, line)
+  }
+
+  fn visit_get(&mut self, object: &Expr, name: &str, line: usize) -> Result<Value, RuntimeError> {
+    self.interpret_get(object, name, line)
+  }
+
+  fn visit_set(
+    &mut self,
+    object: &Expr,
+    name: &str,
+    value: &Expr,
+    line: usize,
+  ) -> Result<Value, RuntimeError> {
+    self.interpret_set(object, name, value, line)
+  }
+}
+
+/// Bitwise operators work on numbers as 64 bit integers. Shifts by less than 0 or more than 63
+/// bits are errors instead of wrapping around.
+fn bitwise(operator: &Token, left: f64, right: f64) -> Result<Value, RuntimeError> {
+  let left = integer(operator, left)?;
+  let right = integer(operator, right)?;
+  let shift = || {
+    u32::try_from(right)
+      .ok()
+      .filter(|bits| *bits < i64::BITS)
+      .ok_or_else(|| {
+        RuntimeError::InvalidBitwiseOperand(
+          operator.span(),
+          operator.kind().symbol(),
+          "a shift between 0 and 63".to_string(),
+          right.to_string(),
+        )
+      })
+  };
+
+  let result = match operator.kind() {
+    TokenKind::Ampersand => left & right,
+    TokenKind::Pipe => left | right,
+    TokenKind::Caret => left ^ right,
+    TokenKind::LessLess => left << shift()?,
+    TokenKind::GreaterGreater => left >> shift()?,
+    _ => return Err(RuntimeError::InvalidExpression),
+  };
+  Ok(Value::Number(result as f64))
+}
+
+/// Operand of a bitwise operator, which must be a whole number.
+fn integer(operator: &Token, value: f64) -> Result<i64, RuntimeError> {
+  if value.fract() == 0.0 && value.abs() <= i64::MAX as f64 {
+    return Ok(value as i64);
+  }
+  Err(RuntimeError::InvalidBitwiseOperand(
+    operator.span(),
+    operator.kind().symbol(),
+    "integers".to_string(),
+    Value::Number(value).to_string(),
+  ))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::interpret::clock::FixedClock;
+  use crate::parse::parser::LoxParser;
+  use crate::scan::scanner::Scanner;
+  use crate::scan::span::Span;
+  use std::io::Cursor;
+
+  fn interpret_program(src: &str) -> Result<String, RuntimeError> {
+    let mut cursor = Cursor::new(src);
+    let scanner = Scanner::new(&mut cursor);
+    let tokens = scanner.scan_tokens().0;
+    let stmts = LoxParser::new(tokens).parse().unwrap();
+    let mut fake_stdout: Vec<u8> = vec![];
+    let mut interpreter = Interpreter::new(&mut fake_stdout);
+    interpreter.interpret_stmts(&stmts)?;
+    Ok(String::from_utf8(fake_stdout).unwrap())
+  }
+
+  #[test]
+  fn eval_number_1() {
+    let interpreted = interpret_program("print 1;");
+    let res = interpreted.unwrap();
+
+    assert_eq!(res, "1\n");
+  }
+
+  #[test]
+  fn eval_number_2() {
+    let interpreted = interpret_program("print 2;");
+    let res = interpreted.unwrap();
+
+    assert_eq!(res, "2\n");
+  }
+
+  #[test]
+  fn eval_nil() {
+    let interpreted = interpret_program("print nil;");
+    let res = interpreted.unwrap();
+
+    assert_eq!(res, "nil\n")
+  }
+
+  #[test]
+  fn eval_true() {
+    let interpreted = interpret_program("print true;");
+    let res = interpreted.unwrap();
+
+    assert_eq!(res, "true\n")
+  }
+
+  #[test]
+  fn eval_false() {
+    let interpreted = interpret_program("print false;");
+    let res = interpreted.unwrap();
+
+    assert_eq!(res, "false\n")
+  }
+
+  #[test]
+  fn eval_minus_one() {
+    let interpreted = interpret_program("print -1;");
+    let res = interpreted.unwrap();
+
+    assert_eq!(res, "-1\n")
+  }
+
+  #[test]
+  fn eval_minus_string() {
+    let interpreted = interpret_program("print \"foo\";");
+    let res = interpreted.unwrap();
+
+    assert_eq!(res, "foo\n")
+  }
+
+  #[test]
+  fn eval_not_true_returns_false() {
+    let interpreted = interpret_program("print !true;");
+    let res = interpreted.unwrap();
+
+    assert_eq!(res, "false\n")
+  }
+
+  #[test]
+  fn eval_not_false_returns_true() {
+    let interpreted = interpret_program("print !false;");
+    let res = interpreted.unwrap();
+
+    assert_eq!(res, "true\n")
+  }
+
+  #[test]
+  fn eval_not_a_positive_number_returns_false() {
+    // any number is truthy
+    let interpreted = interpret_program("print !1.0;");
+    let res = interpreted.unwrap();
+
+    assert_eq!(res, "false\n")
+  }
+  //
+  #[test]
+  fn eval_not_zero_returns_false() {
+    // any number is truthy
     let interpreted = interpret_program("print !0;");
     let res = interpreted.unwrap();
 
@@ -642,7 +1408,10 @@ mod tests {
 
     let err = interpreted.unwrap_err();
 
-    assert_eq!(err, RuntimeError::NotANumber(1, "String".to_string()))
+    assert_eq!(
+      err,
+      RuntimeError::NotANumber(Span::new(1, 1, 0, 1), "String".to_string())
+    )
   }
 
   #[test]
@@ -651,7 +1420,10 @@ mod tests {
 
     let err = interpreted.unwrap_err();
 
-    assert_eq!(err, RuntimeError::NotANumber(1, "nil".to_string()))
+    assert_eq!(
+      err,
+      RuntimeError::NotANumber(Span::new(1, 1, 0, 1), "nil".to_string())
+    )
   }
 
   #[test]
@@ -660,7 +1432,10 @@ mod tests {
 
     let err = interpreted.unwrap_err();
 
-    assert_eq!(err, RuntimeError::NotANumber(1, "Boolean".to_string()))
+    assert_eq!(
+      err,
+      RuntimeError::NotANumber(Span::new(1, 1, 0, 1), "Boolean".to_string())
+    )
   }
 
   #[test]
@@ -671,7 +1446,7 @@ mod tests {
     assert_eq!(
       err,
       RuntimeError::WrongBinaryOperationType(
-        1,
+        Span::new(1, 3, 2, 1),
         "+".to_string(),
         "Number".to_string(),
         "Boolean".to_string()
@@ -685,7 +1460,7 @@ mod tests {
     assert_eq!(
       err,
       RuntimeError::WrongBinaryOperationType(
-        1,
+        Span::new(1, 6, 5, 1),
         "+".to_string(),
         "Boolean".to_string(),
         "Number".to_string()
@@ -701,7 +1476,7 @@ mod tests {
     assert_eq!(
       err,
       RuntimeError::WrongBinaryOperationType(
-        1,
+        Span::new(1, 3, 2, 1),
         "+".to_string(),
         "Number".to_string(),
         "String".to_string()
@@ -717,7 +1492,7 @@ mod tests {
     assert_eq!(
       err,
       RuntimeError::WrongBinaryOperationType(
-        1,
+        Span::new(1, 3, 2, 2),
         "<=".to_string(),
         "Number".to_string(),
         "String".to_string()
@@ -733,7 +1508,7 @@ mod tests {
     assert_eq!(
       err,
       RuntimeError::WrongBinaryOperationType(
-        1,
+        Span::new(1, 3, 2, 1),
         "*".to_string(),
         "Number".to_string(),
         "String".to_string()
@@ -749,7 +1524,7 @@ mod tests {
     assert_eq!(
       err,
       RuntimeError::WrongBinaryOperationType(
-        1,
+        Span::new(1, 3, 2, 1),
         "/".to_string(),
         "Number".to_string(),
         "String".to_string()
@@ -837,6 +1612,49 @@ mod tests {
     assert_eq!(res, "10\n");
   }
 
+  #[test]
+  fn break_exits_the_innermost_loop() {
+    let res = interpret_program(
+      "for (var i = 0; i < 3; i = i + 1) { while (true) { print i; break; } if (i == 1) break; }",
+    );
+    assert_eq!(res.unwrap(), "0\n1\n");
+  }
+
+  #[test]
+  fn continue_in_a_for_loop_still_runs_the_increment() {
+    let res = interpret_program(
+      "for (var i = 0; i < 5; i = i + 1) { if (i == 1 or i == 3) continue; print i; }",
+    );
+    assert_eq!(res.unwrap(), "0\n2\n4\n");
+  }
+
+  #[test]
+  fn continue_in_a_while_loop_checks_the_condition_again() {
+    let res =
+      interpret_program("var i = 0; while (i < 4) { i = i + 1; if (i == 2) continue; print i; }");
+    assert_eq!(res.unwrap(), "1\n3\n4\n");
+  }
+
+  #[test]
+  fn do_while_runs_the_body_before_checking_the_condition() {
+    let res = interpret_program("var a = 10; do { print a; a = a + 1; } while (a < 3); print a;");
+    assert_eq!(res.unwrap(), "10\n11\n");
+  }
+
+  #[test]
+  fn continue_in_a_do_while_loop_checks_the_condition() {
+    let res = interpret_program(
+      "var i = 0; do { i = i + 1; if (i == 2) continue; if (i == 4) break; print i; } while (i < 10);",
+    );
+    assert_eq!(res.unwrap(), "1\n3\n");
+  }
+
+  #[test]
+  fn break_inside_a_block_restores_the_outer_scope() {
+    let res = interpret_program("var a = 1; while (true) { var a = 2; break; } print a;");
+    assert_eq!(res.unwrap(), "1\n");
+  }
+
   #[test]
   fn execution_of_and() {
     let res1 = interpret_program("print true and 1").unwrap();
@@ -920,10 +1738,21 @@ mod tests {
   #[test]
   fn clock_is_defined_globally() {
     let res = interpret_program("print clock()").unwrap();
-    let parsed = res.trim().parse::<u64>();
+    let parsed = res.trim().parse::<f64>();
     assert!(parsed.is_ok());
   }
 
+  #[test]
+  fn clock_reads_the_configured_clock() {
+    let mut cursor = Cursor::new("print clock();");
+    let tokens = Scanner::new(&mut cursor).scan_tokens().0;
+    let stmts = LoxParser::new(tokens).parse().unwrap();
+    let mut fake_stdout: Vec<u8> = vec![];
+    let mut interpreter = Interpreter::new(&mut fake_stdout).with_clock(FixedClock::new(1234.5));
+    interpreter.interpret_stmts(&stmts).unwrap();
+    assert_eq!(String::from_utf8(fake_stdout).unwrap(), "1234.5\n");
+  }
+
   #[test]
   fn function_that_returns() {
     let res = interpret_program("fun foo() { return 10; } print foo();").unwrap();
@@ -950,4 +1779,597 @@ mod tests {
     let res = interpret_program(src).unwrap();
     assert_eq!(res, "1\n")
   }
+
+  #[test]
+  fn define_a_class_and_print_it() {
+    let res = interpret_program("class Foo {} print Foo;").unwrap();
+    assert_eq!(res, "Foo\n");
+  }
+
+  #[test]
+  fn calling_a_class_creates_an_instance() {
+    let res = interpret_program("class Foo { bar() {} } print Foo();").unwrap();
+    assert_eq!(res, "Foo instance\n");
+  }
+
+  #[test]
+  fn init_receives_the_call_arguments() {
+    let res = interpret_program("class Foo { init(a, b) { print a + b; } } Foo(1, 2);").unwrap();
+    assert_eq!(res, "3\n");
+  }
+
+  #[test]
+  fn class_without_init_takes_no_arguments() {
+    let res = interpret_program("class Foo {} Foo(1);").unwrap_err();
+    assert_eq!(
+      res,
+      RuntimeError::WrongNumberOfArguments(1, "Foo".to_string(), 0, 1)
+    );
+  }
+
+  #[test]
+  fn instances_are_only_equal_to_themselves() {
+    let res = interpret_program("class Foo {} var a = Foo(); print a == a; print a == Foo();");
+    assert_eq!(res.unwrap(), "true\nfalse\n");
+  }
+
+  #[test]
+  fn fields_can_be_set_and_read() {
+    let res = interpret_program("class Foo {} var a = Foo(); a.b = 10; print a.b;").unwrap();
+    assert_eq!(res, "10\n");
+  }
+
+  #[test]
+  fn setting_a_field_returns_the_value() {
+    let res = interpret_program("class Foo {} var a = Foo(); print a.b = 10;").unwrap();
+    assert_eq!(res, "10\n");
+  }
+
+  #[test]
+  fn instances_share_fields_between_references() {
+    let res = interpret_program("class Foo {} var a = Foo(); var b = a; b.c = 1; print a.c;");
+    assert_eq!(res.unwrap(), "1\n");
+  }
+
+  #[test]
+  fn reading_an_undefined_property_is_an_error() {
+    let res = interpret_program("class Foo {} Foo().bar;").unwrap_err();
+    assert_eq!(res, RuntimeError::UndefinedProperty(1, "bar".to_string()));
+  }
+
+  #[test]
+  fn only_instances_have_properties() {
+    let res = interpret_program("var a = 1; a.b;").unwrap_err();
+    assert_eq!(res, RuntimeError::NotAnInstance(1, "Number".to_string()));
+    let res = interpret_program("\"str\".b = 1;").unwrap_err();
+    assert_eq!(res, RuntimeError::NotAnInstance(1, "String".to_string()));
+  }
+
+  #[test]
+  fn methods_can_be_read_as_properties() {
+    let res = interpret_program("class Foo { bar() { return 1; } } print Foo().bar();");
+    assert_eq!(res.unwrap(), "1\n");
+  }
+
+  #[test]
+  fn methods_can_use_this() {
+    let src = "
+      class Person {
+        greet() { print \"hi \" + this.name; }
+      }
+      var p = Person();
+      p.name = \"ana\";
+      p.greet();
+    ";
+    assert_eq!(interpret_program(src).unwrap(), "hi ana\n");
+  }
+
+  #[test]
+  fn init_can_set_fields_on_this() {
+    let src = "
+      class Point {
+        init(x, y) { this.x = x; this.y = y; }
+        sum() { return this.x + this.y; }
+      }
+      print Point(1, 2).sum();
+    ";
+    assert_eq!(interpret_program(src).unwrap(), "3\n");
+  }
+
+  #[test]
+  fn calling_init_directly_returns_the_instance() {
+    let src = "
+      class Foo { init() { return; } }
+      var foo = Foo();
+      print foo.init() == foo;
+    ";
+    assert_eq!(interpret_program(src).unwrap(), "true\n");
+  }
+
+  #[test]
+  fn bound_methods_remember_their_instance() {
+    let src = "
+      class Counter {
+        init() { this.count = 0; }
+        inc() { this.count = this.count + 1; return this.count; }
+      }
+      var counter = Counter();
+      var inc = counter.inc;
+      inc();
+      print inc();
+      print counter.count;
+    ";
+    assert_eq!(interpret_program(src).unwrap(), "2\n2\n");
+  }
+
+  #[test]
+  fn closures_inside_methods_capture_this() {
+    let src = "
+      class Foo {
+        getClosure() {
+          fun closure() { return this.name; }
+          return closure;
+        }
+      }
+      var foo = Foo();
+      foo.name = \"foo\";
+      print foo.getClosure()();
+    ";
+    assert_eq!(interpret_program(src).unwrap(), "foo\n");
+  }
+
+  fn interpret_with_interpreter(src: &str) -> (Interpreter<Vec<u8>>, Result<Value, RuntimeError>) {
+    let mut cursor = Cursor::new(src);
+    let tokens = Scanner::new(&mut cursor).scan_tokens().0;
+    let stmts = LoxParser::new(tokens).parse().unwrap();
+    let mut interpreter = Interpreter::new(vec![]);
+    let res = interpreter.interpret_stmts(&stmts);
+    (interpreter, res)
+  }
+
+  #[test]
+  fn calls_deeper_than_the_limit_are_a_stack_overflow() {
+    let src = "
+      fun countdown(n) {
+        if (n == 0) return 0;
+        return countdown(n - 1);
+      }
+      print countdown(19);
+      countdown(20);
+    ";
+    let mut cursor = Cursor::new(src);
+    let tokens = Scanner::new(&mut cursor).scan_tokens().0;
+    let stmts = LoxParser::new(tokens).parse().unwrap();
+    let mut interpreter = Interpreter::new(vec![]).with_max_call_depth(20);
+
+    let res = interpreter.interpret_stmts(&stmts);
+
+    assert_eq!(res.unwrap_err().cause(), &RuntimeError::StackOverflow(4));
+    assert_eq!(String::from_utf8(interpreter.stdout).unwrap(), "0\n");
+  }
+
+  #[test]
+  fn errors_in_functions_show_the_calls_in_progress() {
+    let src = "
+      fun inner(x) {
+        return -x;
+      }
+      fun outer() {
+        return inner(\"a\");
+      }
+      outer();
+    ";
+    let error = interpret_program(src).unwrap_err();
+    assert_eq!(
+      error.to_string(),
+      concat!(
+        "[line 3, column 16]: Expected a number, got a String\n",
+        "[line 3] in inner()\n",
+        "[line 6] in outer()\n",
+        "[line 8] in script"
+      )
+    );
+  }
+
+  #[test]
+  fn errors_outside_functions_have_no_trace() {
+    let error = interpret_program("print -\"a\";").unwrap_err();
+    assert_eq!(
+      error.to_string(),
+      "[line 1, column 7]: Expected a number, got a String"
+    );
+  }
+
+  #[test]
+  fn programs_stop_once_they_exceed_the_step_limit() {
+    let mut cursor = Cursor::new("print 1; var i = 0; while (true) { i = i + 1; }");
+    let tokens = Scanner::new(&mut cursor).scan_tokens().0;
+    let stmts = LoxParser::new(tokens).parse().unwrap();
+    let mut interpreter = Interpreter::new(vec![]).with_limits(ExecutionLimits {
+      max_steps: Some(100),
+      ..ExecutionLimits::default()
+    });
+
+    let res = interpreter.interpret_stmts(&stmts);
+
+    assert_eq!(res.unwrap_err(), RuntimeError::ExecutionLimitExceeded);
+    assert_eq!(String::from_utf8(interpreter.stdout).unwrap(), "1\n");
+  }
+
+  #[test]
+  fn reading_variables_does_not_copy_strings_or_functions() {
+    let (interpreter, res) = interpret_with_interpreter("var s = \"text\"; fun f() { print s; }");
+    res.unwrap();
+
+    let (Some(Value::String(s1)), Some(Value::String(s2))) =
+      (interpreter.get_global("s"), interpreter.get_global("s"))
+    else {
+      panic!("s is not a string");
+    };
+    assert!(Rc::ptr_eq(&s1, &s2));
+    assert_eq!(interpreter.get_global("f"), interpreter.get_global("f"));
+  }
+
+  #[test]
+  fn each_closure_keeps_its_own_captured_scope_across_calls() {
+    let src = "
+      fun makeCounter() {
+        var i = 0;
+        fun count() { i = i + 1; return i; }
+        return count;
+      }
+      var a = makeCounter();
+      var b = makeCounter();
+      print a();
+      print a();
+      print b();
+      print a();
+      print b();
+    ";
+    assert_eq!(interpret_program(src).unwrap(), "1\n2\n1\n3\n2\n");
+  }
+
+  #[test]
+  fn expression_results_are_echoed_only_when_asked() {
+    let src = "1 + 2; var a = \"s\"; a; { 4; } fun f() { 5; } f();";
+    let mut cursor = Cursor::new(src);
+    let tokens = Scanner::new(&mut cursor).scan_tokens().0;
+    let stmts = LoxParser::new(tokens).parse().unwrap();
+
+    let mut interpreter = Interpreter::new(vec![]).with_echo_expression_results(true);
+    interpreter.interpret_stmts(&stmts).unwrap();
+    assert_eq!(
+      String::from_utf8(interpreter.stdout).unwrap(),
+      "3\ns\nnil\n"
+    );
+
+    assert_eq!(interpret_program(src).unwrap(), "");
+  }
+
+  #[test]
+  fn scopes_of_discarded_closures_are_collected() {
+    let src = "
+      fun makeCounter() {
+        var i = 0;
+        fun count() { i = i + 1; return i; }
+        return count;
+      }
+      var counter;
+      for (var n = 0; n < 5000; n = n + 1) {
+        counter = makeCounter();
+        counter();
+      }
+    ";
+    let (interpreter, res) = interpret_with_interpreter(src);
+    res.unwrap();
+    assert!(interpreter.env.node_count() < 3000);
+  }
+
+  #[test]
+  fn closures_keep_their_scopes_across_garbage_collection() {
+    let src = "
+      fun makeCounter() {
+        var i = 0;
+        fun count() { i = i + 1; return i; }
+        return count;
+      }
+      class Holder { init(f) { this.f = f; } }
+      var kept = makeCounter();
+      var held = Holder(makeCounter());
+      kept();
+      for (var n = 0; n < 3000; n = n + 1) {
+        makeCounter()();
+      }
+      print kept();
+      print held.f();
+    ";
+    let (interpreter, res) = interpret_with_interpreter(src);
+    res.unwrap();
+    assert_eq!(String::from_utf8(interpreter.stdout).unwrap(), "2\n1\n");
+  }
+
+  #[test]
+  fn class_methods_are_called_on_the_class() {
+    let src = "
+      class Math { class square(n) { return n * n; } }
+      print Math.square(3);
+      print Math().square;
+    ";
+    assert_eq!(
+      interpret_program(src).unwrap_err(),
+      RuntimeError::UndefinedProperty(4, "square".to_string())
+    );
+  }
+
+  #[test]
+  fn getters_run_when_read() {
+    let src = "
+      class Box {
+        init() { this.reads = 0; }
+        size { this.reads = this.reads + 1; return 42; }
+      }
+      var b = Box();
+      print b.size;
+      print b.size;
+      print b.reads;
+    ";
+    assert_eq!(interpret_program(src).unwrap(), "42\n42\n2\n");
+  }
+
+  #[test]
+  fn subclasses_inherit_methods() {
+    let src = "
+      class A { foo() { print \"A.foo\"; } }
+      class B < A {}
+      B().foo();
+    ";
+    assert_eq!(interpret_program(src).unwrap(), "A.foo\n");
+  }
+
+  #[test]
+  fn subclasses_inherit_init() {
+    let src = "
+      class A { init(x) { this.x = x; } }
+      class B < A {}
+      print B(3).x;
+    ";
+    assert_eq!(interpret_program(src).unwrap(), "3\n");
+  }
+
+  #[test]
+  fn super_calls_the_superclass_method() {
+    let src = "
+      class A { name() { return \"A\"; } }
+      class B < A { name() { return \"B>\" + super.name(); } }
+      print B().name();
+    ";
+    assert_eq!(interpret_program(src).unwrap(), "B>A\n");
+  }
+
+  #[test]
+  fn super_is_resolved_from_the_class_defining_the_method() {
+    let src = "
+      class A { method() { print \"A method\"; } }
+      class B < A {
+        method() { print \"B method\"; }
+        test() { super.method(); }
+      }
+      class C < B {}
+      C().test();
+    ";
+    assert_eq!(interpret_program(src).unwrap(), "A method\n");
+  }
+
+  #[test]
+  fn superclass_must_be_a_class() {
+    let res = interpret_program("var A = 1; class B < A {}").unwrap_err();
+    assert_eq!(
+      res,
+      RuntimeError::SuperclassMustBeAClass(1, "Number".to_string())
+    );
+  }
+
+  #[test]
+  fn catch_receives_the_message_and_line_of_the_error() {
+    let src = "
+      try {
+        print \"before\";
+        nil.field;
+        print \"after\";
+      } catch (e) {
+        print e;
+        print e.message;
+        print e.line;
+      }
+      print \"done\";
+    ";
+    assert_eq!(
+      interpret_program(src).unwrap(),
+      "before\nError instance\nOnly instances have properties, got nil\n4\ndone\n"
+    );
+  }
+
+  #[test]
+  fn catch_recovers_from_errors_raised_in_called_functions() {
+    let src = "
+      fun inner() { return 1 + \"a\"; }
+      fun outer() { inner(); }
+      try { outer(); } catch (e) { print e.line; }
+      print \"still running\";
+    ";
+    assert_eq!(interpret_program(src).unwrap(), "2\nstill running\n");
+  }
+
+  #[test]
+  fn errors_in_the_handler_are_not_caught_again() {
+    let src = "try { undefined; } catch (e) { print unknown; }";
+    assert_eq!(
+      interpret_program(src).unwrap_err(),
+      RuntimeError::UndefinedVariable(1, "unknown".to_string())
+    );
+  }
+
+  #[test]
+  fn return_break_and_exit_go_through_try() {
+    let src = "
+      fun f() { try { return 1; } catch (e) { return 2; } }
+      print f();
+      while (true) { try { break; } catch (e) { print \"caught\"; } }
+      try { exit(2); } catch (e) { print \"caught\"; }
+    ";
+    let mut cursor = Cursor::new(src);
+    let tokens = Scanner::new(&mut cursor).scan_tokens().0;
+    let stmts = LoxParser::new(tokens).parse().unwrap();
+    let mut output: Vec<u8> = vec![];
+    let res = Interpreter::new(&mut output).interpret_stmts(&stmts);
+    assert_eq!(res.unwrap_err(), RuntimeError::Exit(2));
+    assert_eq!(String::from_utf8(output).unwrap(), "1\n");
+  }
+
+  #[test]
+  fn catch_receives_thrown_values_as_they_are() {
+    let src = "
+      class Problem { init(code) { this.code = code; } }
+      try { throw \"bad input\"; } catch (e) { print e; }
+      fun fail() { throw Problem(42); }
+      try { fail(); } catch (e) { print e.code; }
+    ";
+    assert_eq!(interpret_program(src).unwrap(), "bad input\n42\n");
+  }
+
+  #[test]
+  fn uncaught_thrown_values_stop_the_program() {
+    let res = interpret_program("print 1;\nthrow \"bad input\";\nprint 2;").unwrap_err();
+    assert_eq!(res, RuntimeError::Thrown(2, Value::from("bad input")));
+    assert_eq!(res.to_string(), "[line 2]: bad input");
+  }
+
+  #[test]
+  fn caught_errors_can_be_thrown_again() {
+    let src = "
+      try {
+        try { nil(); } catch (e) { throw e; }
+      } catch (e) {
+        print e.message;
+      }
+    ";
+    assert_eq!(
+      interpret_program(src).unwrap(),
+      "Expected function, got nil\n"
+    );
+  }
+
+  #[test]
+  fn failed_asserts_show_the_condition_and_message() {
+    assert_eq!(
+      interpret_program("assert(1 < 2, missing); print \"ok\";").unwrap(),
+      "ok\n"
+    );
+    assert_eq!(
+      interpret_program("var a = 1;\nassert(a  >  1);").unwrap_err(),
+      RuntimeError::AssertionFailed(2, "a > 1".to_string())
+    );
+    assert_eq!(
+      interpret_program("assert(nil, \"needs \" + \"a value\");").unwrap_err(),
+      RuntimeError::AssertionFailed(1, "nil (needs a value)".to_string())
+    );
+  }
+
+  #[test]
+  fn prefix_increments_give_the_new_value_and_postfix_the_old_one() {
+    let src = "
+      var a = 1;
+      print ++a;
+      print a++;
+      print a;
+      print --a;
+      print a--;
+      print a;
+      for (var i = 0; i < 3; i++) print i;
+    ";
+    assert_eq!(
+      interpret_program(src).unwrap(),
+      "2\n2\n3\n2\n2\n1\n0\n1\n2\n"
+    );
+  }
+
+  #[test]
+  fn postfix_increments_give_back_exactly_the_old_value() {
+    let src =
+      "var a = 0.1; print a++; var b = 0.3; print b--; var c = 2 ** 53; print c++ == 2 ** 53;";
+    assert_eq!(interpret_program(src).unwrap(), "0.1\n0.3\ntrue\n");
+  }
+
+  #[test]
+  fn exponents_raise_numbers_to_a_power() {
+    assert_eq!(
+      interpret_program("print 2 ** 10; print 2 ** 3 ** 2; print -2 ** 2; print 4 ** 0.5;")
+        .unwrap(),
+      "1024\n512\n4\n2\n"
+    );
+    assert_eq!(
+      interpret_program("print 2 ** \"a\";").unwrap_err(),
+      RuntimeError::WrongBinaryOperationType(
+        Span::new(1, 9, 8, 2),
+        "**".to_string(),
+        "Number".to_string(),
+        "String".to_string()
+      )
+    );
+  }
+
+  #[test]
+  fn bitwise_operators_work_on_integers() {
+    let src =
+      "print 12 & 10; print 12 | 10; print 12 ^ 10; print ~5; print 1 << 10; print -16 >> 2;";
+    assert_eq!(interpret_program(src).unwrap(), "8\n14\n6\n-6\n1024\n-4\n");
+  }
+
+  #[test]
+  fn bitwise_operators_reject_fractions_and_long_shifts() {
+    assert_eq!(
+      interpret_program("print 1.5 | 1;").unwrap_err(),
+      RuntimeError::InvalidBitwiseOperand(
+        Span::new(1, 11, 10, 1),
+        "|".to_string(),
+        "integers".to_string(),
+        "1.5".to_string()
+      )
+    );
+    assert_eq!(
+      interpret_program("print 1 << 64;").unwrap_err(),
+      RuntimeError::InvalidBitwiseOperand(
+        Span::new(1, 9, 8, 2),
+        "<<".to_string(),
+        "a shift between 0 and 63".to_string(),
+        "64".to_string()
+      )
+    );
+    assert!(matches!(
+      interpret_program("print ~\"a\";").unwrap_err(),
+      RuntimeError::NotANumber(..)
+    ));
+  }
+
+  #[test]
+  fn lenient_concat_turns_the_other_operand_into_a_string() {
+    let src = "print \"count: \" + 3; print nil + \"!\"; print \"ok? \" + true;";
+    let mut cursor = Cursor::new(src);
+    let tokens = Scanner::new(&mut cursor).scan_tokens().0;
+    let stmts = LoxParser::new(tokens).parse().unwrap();
+    let mut output: Vec<u8> = vec![];
+    Interpreter::new(&mut output)
+      .with_lenient_concat(true)
+      .interpret_stmts(&stmts)
+      .unwrap();
+    assert_eq!(
+      String::from_utf8(output).unwrap(),
+      "count: 3\nnil!\nok? true\n"
+    );
+
+    assert!(matches!(
+      interpret_program("print \"count: \" + 3;").unwrap_err(),
+      RuntimeError::WrongBinaryOperationType(..)
+    ));
+  }
 }
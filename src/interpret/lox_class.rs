@@ -0,0 +1,163 @@
+use crate::interpret::error::RuntimeError;
+use crate::interpret::interpreter::Interpreter;
+use crate::interpret::lox_fn::LoxFn;
+use crate::interpret::value::Value;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::{Debug, Display, Formatter};
+use std::io::Write;
+use std::rc::Rc;
+
+#[derive(Debug, PartialEq)]
+pub struct LoxClass {
+  pub name: String,
+  superclass: Option<Rc<LoxClass>>,
+  methods: HashMap<String, LoxFn>,
+  /// Methods called on the class itself, like `Math.square(2)`.
+  class_methods: HashMap<String, LoxFn>,
+}
+
+impl LoxClass {
+  pub fn new(
+    name: String,
+    superclass: Option<Rc<LoxClass>>,
+    methods: HashMap<String, LoxFn>,
+  ) -> LoxClass {
+    LoxClass {
+      name,
+      superclass,
+      methods,
+      class_methods: HashMap::new(),
+    }
+  }
+
+  pub fn with_class_methods(mut self, class_methods: HashMap<String, LoxFn>) -> LoxClass {
+    self.class_methods = class_methods;
+    self
+  }
+
+  /// Looks up the method in this class and then up the superclass chain.
+  pub fn find_method(&self, name: &str) -> Option<&LoxFn> {
+    self
+      .methods
+      .get(name)
+      .or_else(|| self.superclass.as_ref()?.find_method(name))
+  }
+
+  /// Class methods are inherited too, so subclasses can be used in their place.
+  pub fn find_class_method(&self, name: &str) -> Option<&LoxFn> {
+    self
+      .class_methods
+      .get(name)
+      .or_else(|| self.superclass.as_ref()?.find_class_method(name))
+  }
+
+  /// Instance and class methods declared in this class.
+  pub(crate) fn methods(&self) -> impl Iterator<Item = &LoxFn> {
+    self.methods.values().chain(self.class_methods.values())
+  }
+
+  pub(crate) fn superclass(&self) -> Option<&Rc<LoxClass>> {
+    self.superclass.as_ref()
+  }
+
+  pub fn arity(&self) -> usize {
+    self.find_method("init").map(LoxFn::arity).unwrap_or(0)
+  }
+
+  /// Calling a class creates a new instance. When the class defines `init`, the arguments are
+  /// passed to it; otherwise the class takes no arguments.
+  pub fn call<W: Write>(
+    self: &Rc<Self>,
+    interpreter: &mut Interpreter<W>,
+    args: Vec<Value>,
+    line: usize,
+  ) -> Result<Value, RuntimeError> {
+    let instance = LoxInstance::new(Rc::clone(self));
+
+    match self.find_method("init") {
+      Some(init) => {
+        interpreter
+          .bind_method(init, instance.clone())?
+          .call(interpreter, args, line)?;
+      }
+      None if !args.is_empty() => {
+        return Err(RuntimeError::WrongNumberOfArguments(
+          line,
+          self.name.clone(),
+          0,
+          args.len(),
+        ))
+      }
+      None => {}
+    }
+
+    Ok(Value::Instance(instance))
+  }
+}
+
+impl Display for LoxClass {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    f.write_str(&self.name)
+  }
+}
+
+/// Instances are handles: cloning an instance value gives another reference to the same object,
+/// and two instances are equal only when they are the same object.
+#[derive(Clone)]
+pub struct LoxInstance {
+  class: Rc<LoxClass>,
+  fields: Rc<RefCell<HashMap<String, Value>>>,
+}
+
+impl LoxInstance {
+  pub fn new(class: Rc<LoxClass>) -> LoxInstance {
+    LoxInstance {
+      class,
+      fields: Rc::new(RefCell::new(HashMap::new())),
+    }
+  }
+
+  pub fn field(&self, name: &str) -> Option<Value> {
+    self.fields.borrow().get(name).cloned()
+  }
+
+  pub fn find_method(&self, name: &str) -> Option<&LoxFn> {
+    self.class.find_method(name)
+  }
+
+  pub fn set(&self, name: &str, value: Value) {
+    self.fields.borrow_mut().insert(name.to_string(), value);
+  }
+
+  pub(crate) fn class(&self) -> &Rc<LoxClass> {
+    &self.class
+  }
+
+  pub(crate) fn field_values(&self) -> Vec<Value> {
+    self.fields.borrow().values().cloned().collect()
+  }
+
+  /// Identifies the object behind this handle; clones of the same instance share it.
+  pub(crate) fn id(&self) -> usize {
+    Rc::as_ptr(&self.fields) as usize
+  }
+}
+
+impl PartialEq for LoxInstance {
+  fn eq(&self, other: &Self) -> bool {
+    Rc::ptr_eq(&self.fields, &other.fields)
+  }
+}
+
+impl Debug for LoxInstance {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(f, "<{} instance>", self.class.name)
+  }
+}
+
+impl Display for LoxInstance {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{} instance", self.class.name)
+  }
+}
@@ -1,14 +1,19 @@
-use std::fmt::{Debug, Formatter};
+use std::fmt::{Debug, Display, Formatter};
 use crate::interpret::error::RuntimeError;
 use crate::interpret::interpreter::Interpreter;
+use crate::interpret::lox_class::LoxClass;
+use crate::interpret::native_context::NativeContext;
+use crate::interpret::stack_trace::CallFrame;
 use crate::interpret::value::Value;
 use crate::parse::stmt::Stmt;
 use std::io::Write;
+use std::rc::Rc;
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, Clone)]
 pub enum Callable {
   Lox(LoxFn),
   Native(NativeFn),
+  Class(Rc<LoxClass>),
 }
 
 impl Callable {
@@ -20,77 +25,190 @@ impl Callable {
   ) -> Result<Value, RuntimeError> {
     match self {
       Callable::Lox(fun) => fun.call(interpreter, args, line),
-      Callable::Native(fun) => fun.call(interpreter, args, line)
+      Callable::Native(fun) => fun.call(interpreter, args, line),
+      Callable::Class(class) => class.call(interpreter, args, line),
     }
   }
 
-  pub fn to_string(&self) -> String {
+  pub fn name(&self) -> &str {
     match self {
-      Callable::Lox(fun) => fun.to_string(),
-      Callable::Native(fun) => fun.to_string()
+      Callable::Lox(fun) => &fun.name,
+      Callable::Native(fun) => &fun.name,
+      Callable::Class(class) => &class.name,
+    }
+  }
+
+  /// Number of arguments a call must pass. Classes take the arguments of their `init`.
+  pub fn arity(&self) -> usize {
+    match self {
+      Callable::Lox(fun) => fun.arity(),
+      Callable::Native(fun) => fun.arity(),
+      Callable::Class(class) => class.arity(),
+    }
+  }
+}
+
+/// Callables are equal only when they are the same function, native or class.
+impl PartialEq for Callable {
+  fn eq(&self, other: &Self) -> bool {
+    match (self, other) {
+      (Callable::Lox(fun), Callable::Lox(other)) => fun == other,
+      (Callable::Native(fun), Callable::Native(other)) => fun == other,
+      (Callable::Class(class), Callable::Class(other)) => Rc::ptr_eq(class, other),
+      _ => false,
+    }
+  }
+}
+
+impl Display for Callable {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Callable::Lox(fun) => write!(f, "{fun}"),
+      Callable::Native(fun) => write!(f, "{fun}"),
+      Callable::Class(class) => write!(f, "{class}"),
     }
   }
 }
 
-type NativeLambda = fn(Vec<Value>) -> Result<Value, RuntimeError>;
+/// Natives receive the interpreter's native context, their arguments and the line of the call, to
+/// report errors.
+pub type NativeLambda = fn(&mut NativeContext, Vec<Value>, usize) -> Result<Value, RuntimeError>;
 #[derive(Clone)]
 pub struct NativeFn {
-  name: String,
+  pub name: Rc<str>,
+  arity: usize,
   implementation: NativeLambda,
+  /// Whether instance arguments are turned into strings with their `toString` first.
+  displays_args: bool,
 }
 
 impl NativeFn {
-  pub fn new(name: String, implementation: NativeLambda) -> NativeFn {
+  pub fn new(name: String, arity: usize, implementation: NativeLambda) -> NativeFn {
     NativeFn {
-      name,
-      implementation
+      name: name.into(),
+      arity,
+      implementation,
+      displays_args: false,
     }
   }
 
+  /// For natives that show values, like `str`: instances come in already shown as strings, since
+  /// natives can't call their `toString` methods themselves.
+  pub fn displaying_args(mut self) -> NativeFn {
+    self.displays_args = true;
+    self
+  }
+
+  pub fn arity(&self) -> usize {
+    self.arity
+  }
+
   pub fn call<W: Write>(
     &self,
-    _interpreter: &mut Interpreter<W>,
+    interpreter: &mut Interpreter<W>,
     args: Vec<Value>,
-    _line: usize,
+    line: usize,
   ) -> Result<Value, RuntimeError> {
-    (self.implementation)(args)
+    if args.len() != self.arity {
+      return Err(RuntimeError::WrongNumberOfArguments(
+        line,
+        self.name.to_string(),
+        self.arity,
+        args.len(),
+      ));
+    }
+    let args = if self.displays_args {
+      args
+        .into_iter()
+        .map(|arg| match arg {
+          Value::Instance(_) => arg.display(interpreter, line).map(Value::from),
+          arg => Ok(arg),
+        })
+        .collect::<Result<_, _>>()?
+    } else {
+      args
+    };
+    let result = (self.implementation)(interpreter.native_context(), args, line);
+    interpreter.flush_native_output()?;
+    result
   }
+}
 
-  pub fn to_string(&self) -> String {
-    format!("<nativefn {}>", &self.name)
+impl Display for NativeFn {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    f.write_str("<native fn>")
   }
 }
 
 impl Debug for NativeFn {
   fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-    f.write_str("<fn ")?;
-    f.write_str(&self.name)?;
-    f.write_str(">")?;
-    Ok(())
+    write!(f, "{self}")
   }
 }
 
 impl PartialEq for NativeFn {
   fn eq(&self, other: &Self) -> bool {
-    self.name == other.name
+    self.name == other.name && self.implementation as usize == other.implementation as usize
   }
 }
 
-#[derive(Debug, PartialEq, Clone)]
+/// Function values are handles: copies of a function are equal to each other, but two functions
+/// are never equal just because they look the same.
+#[derive(Debug, Clone)]
 pub struct LoxFn {
-  pub name: String,
+  pub name: Rc<str>,
+  declaration: Rc<Declaration>,
+  context_id: usize,
+  is_initializer: bool,
+  /// Getters run as soon as their property is read, without being called.
+  is_getter: bool,
+}
+
+#[derive(Debug)]
+struct Declaration {
   params: Vec<String>,
   body: Vec<Stmt>,
-  context_id: usize,
 }
 
 impl LoxFn {
   pub fn new(name: String, params: Vec<String>, body: Vec<Stmt>, context_id: usize) -> LoxFn {
     LoxFn {
-      name,
-      params,
-      body,
+      name: name.into(),
+      declaration: Rc::new(Declaration { params, body }),
+      context_id,
+      is_initializer: false,
+      is_getter: false,
+    }
+  }
+
+  /// Initializers always return the instance they were bound to.
+  pub fn initializer(mut self) -> LoxFn {
+    self.is_initializer = true;
+    self
+  }
+
+  pub fn getter(mut self) -> LoxFn {
+    self.is_getter = true;
+    self
+  }
+
+  pub fn is_getter(&self) -> bool {
+    self.is_getter
+  }
+
+  pub fn arity(&self) -> usize {
+    self.declaration.params.len()
+  }
+
+  pub fn context_id(&self) -> usize {
+    self.context_id
+  }
+
+  /// Same function, running on top of another scope. Used to bind methods to an instance.
+  pub fn with_context(&self, context_id: usize) -> LoxFn {
+    LoxFn {
       context_id,
+      ..self.clone()
     }
   }
 
@@ -100,32 +218,53 @@ impl LoxFn {
     mut args: Vec<Value>,
     line: usize,
   ) -> Result<Value, RuntimeError> {
-    if args.len() != self.params.len() {
+    let Declaration { params, body } = self.declaration.as_ref();
+    if args.len() != params.len() {
       return Err(RuntimeError::WrongNumberOfArguments(
         line,
-        self.name.clone(),
-        self.params.len(),
+        self.name.to_string(),
+        params.len(),
         args.len(),
       ));
     }
 
-    interpreter.with_branching(self.context_id, move |inter| {
+    let frame = CallFrame::new(self.name.clone(), line);
+    interpreter.with_branching(self.context_id, frame, move |inter| {
       args
         .drain(..)
         .enumerate()
-        .for_each(|(index, value)| inter.define_var(&self.params[index], value));
+        .for_each(|(index, value)| inter.define_var(&params[index], value));
+
+      let call_res = inter.interpret_block(body);
+      if self.is_initializer {
+        return match call_res {
+          Ok(_) | Err(RuntimeError::Return(_)) => inter
+            .get_var("this")
+            .ok_or(RuntimeError::UndefinedVariable(line, "this".to_string())),
+          Err(e) => Err(e),
+        };
+      }
 
-      let call_res = inter.interpret_stmts(&self.body);
       if let Err(RuntimeError::Return(value)) = call_res {
-        return Ok(value)
+        Ok(value)
       } else {
-        return call_res
+        call_res
       }
     })
   }
+}
 
-  pub fn to_string(&self) -> String {
-    format!("<fn {}>", self.name)
+/// The same declaration bound to the same scope: copies of one function value, or a method bound
+/// to the same instance scope.
+impl PartialEq for LoxFn {
+  fn eq(&self, other: &Self) -> bool {
+    Rc::ptr_eq(&self.declaration, &other.declaration) && self.context_id == other.context_id
+  }
+}
+
+impl Display for LoxFn {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(f, "<fn {}>", self.name)
   }
 }
 
@@ -135,17 +274,37 @@ mod tests {
   #[test]
   fn native_function_can_be_created_and_called() {
     let callable = Callable::Native(NativeFn {
-      name: "foo".to_string(),
-      implementation: |vec| {
+      name: "foo".into(),
+      arity: 1,
+      implementation: |_context, vec, _line| {
         let res = format!("{:?}", vec);
-        Ok(Value::String(res))
+        Ok(Value::String(res.into()))
       },
+      displays_args: false,
     });
     let mut fake_stdout: Vec<u8> = vec![];
 
     let mut inter = Interpreter::new(&mut fake_stdout);
 
     let coso = callable.call(&mut inter, vec![Value::Number(1.0)], 10).unwrap();
-    assert_eq!(coso, Value::String("[Number(1.0)]".to_string()));
+    assert_eq!(coso, Value::from("[Number(1.0)]"));
+  }
+
+  #[test]
+  fn callables_expose_name_and_arity() {
+    let lox = Callable::Lox(LoxFn::new(
+      "add".to_string(),
+      vec!["a".to_string(), "b".to_string()],
+      vec![],
+      0,
+    ));
+    let native = Callable::Native(NativeFn::new("now".to_string(), 0, |_, _, _| {
+      Ok(Value::Nil)
+    }));
+
+    assert_eq!((lox.name(), lox.arity()), ("add", 2));
+    assert_eq!((native.name(), native.arity()), ("now", 0));
+    assert_eq!(lox.to_string(), "<fn add>");
+    assert_eq!(native.to_string(), "<native fn>");
   }
 }
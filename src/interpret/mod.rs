@@ -1,5 +1,19 @@
+pub mod clock;
+pub mod coverage;
 pub mod error;
+pub mod execution_limits;
+pub mod file_system;
+pub mod hook;
 pub mod interpreter;
-mod value;
+pub mod native_context;
+pub mod native_registry;
+pub mod natives;
+pub mod profiler;
+pub mod random;
+pub mod stack_trace;
+pub mod tracer;
+pub mod value;
 mod branching_scope;
+mod lox_class;
 mod lox_fn;
+mod modules;
@@ -0,0 +1,127 @@
+use crate::parse::parser::LoxParser;
+use crate::parse::stmt::Stmt;
+use crate::resolve::resolver::Resolver;
+use crate::scan::scanner::Scanner;
+use std::collections::{BTreeSet, HashSet};
+use std::path::{Path, PathBuf};
+
+/// Keeps track of the files a program imports, so each one runs once and cycles are caught.
+#[derive(Default)]
+pub(crate) struct ModuleLoader {
+  /// The file of the program being run, if it has one. Its imports are relative to its directory.
+  script: Option<PathBuf>,
+  /// Where imports that aren't next to the file importing them are looked for, in order.
+  roots: Vec<PathBuf>,
+  /// Files being imported right now, innermost last.
+  importing: Vec<PathBuf>,
+  loaded: HashSet<PathBuf>,
+  /// Every file an import found, even if it failed to parse or run.
+  imported: BTreeSet<PathBuf>,
+}
+
+/// What to do with an import.
+pub(crate) enum Import {
+  /// The file already ran: importing it again does nothing.
+  AlreadyLoaded,
+  Run(PathBuf, Vec<Stmt>),
+}
+
+impl ModuleLoader {
+  pub fn set_script(&mut self, script: PathBuf) {
+    self.script = Some(script.canonicalize().unwrap_or(script));
+  }
+
+  pub fn set_roots(&mut self, roots: Vec<PathBuf>) {
+    self.roots = roots;
+  }
+
+  /// Finds and parses the file at `path`, relative to the file importing it or else to one of the
+  /// roots.
+  pub fn load(&mut self, path: &str) -> Result<Import, LoadError> {
+    let importer = self.importing.last().or(self.script.as_ref());
+    let dir = importer
+      .and_then(|file| file.parent())
+      .unwrap_or(Path::new(""));
+    let found = self
+      .roots
+      .iter()
+      .map(|root| root.join(path).canonicalize())
+      .find_map(Result::ok);
+    let file = match (dir.join(path).canonicalize(), found) {
+      (Ok(file), _) | (Err(_), Some(file)) => file,
+      (Err(e), None) => return Err(LoadError::Failed(e.to_string())),
+    };
+    self.imported.insert(file.clone());
+
+    if self.loaded.contains(&file) {
+      return Ok(Import::AlreadyLoaded);
+    }
+    if self.script.as_ref() == Some(&file) || self.importing.contains(&file) {
+      return Err(LoadError::Cycle(self.cycle(&file)));
+    }
+
+    let source = std::fs::read_to_string(&file).map_err(|e| LoadError::Failed(e.to_string()))?;
+    let stmts = parse_module(&source).map_err(LoadError::Failed)?;
+    Ok(Import::Run(file, stmts))
+  }
+
+  /// Call before running an imported file, with the path [`ModuleLoader::load`] gave.
+  pub fn start(&mut self, file: PathBuf) {
+    self.importing.push(file);
+  }
+
+  /// Call after running the file of the last [`ModuleLoader::start`]. Files that failed can be
+  /// imported again.
+  pub fn finish(&mut self, succeeded: bool) {
+    if let Some(file) = self.importing.pop() {
+      if succeeded {
+        self.loaded.insert(file);
+      }
+    }
+  }
+
+  pub fn imported(&self) -> impl Iterator<Item = &Path> {
+    self.imported.iter().map(PathBuf::as_path)
+  }
+
+  /// The files in the cycle that importing `file` again would close, starting and ending with it.
+  fn cycle(&self, file: &Path) -> String {
+    let files = self.script.iter().chain(&self.importing);
+    let mut chain = files
+      .skip_while(|f| *f != file)
+      .map(|f| f.display().to_string())
+      .collect::<Vec<_>>();
+    chain.push(file.display().to_string());
+    chain.join(" -> ")
+  }
+}
+
+pub(crate) enum LoadError {
+  /// Why the file can't be imported: it can't be read, or it has errors.
+  Failed(String),
+  /// The files in the import cycle.
+  Cycle(String),
+}
+
+fn parse_module(source: &str) -> Result<Vec<Stmt>, String> {
+  let mut bytes = source.as_bytes();
+  let (tokens, errors) = Scanner::new(&mut bytes).scan_tokens();
+  if !errors.is_empty() {
+    return Err(join(&errors));
+  }
+  let stmts = LoxParser::new(tokens)
+    .parse()
+    .map_err(|errors| join(&errors))?;
+  Resolver::new()
+    .resolve(&stmts)
+    .map_err(|errors| join(&errors))?;
+  Ok(stmts)
+}
+
+fn join<E: ToString>(errors: &[E]) -> String {
+  errors
+    .iter()
+    .map(|e| e.to_string())
+    .collect::<Vec<_>>()
+    .join("\n")
+}
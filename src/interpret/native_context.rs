@@ -0,0 +1,65 @@
+use crate::interpret::clock::{Clock, SystemClock};
+use crate::interpret::file_system::{FileSystem, NoFileSystem};
+use crate::interpret::random::Random;
+use std::io::{self, BufRead, Write};
+
+/// What natives can use besides their arguments. Natives are plain function pointers, so instead
+/// of making them generic like the interpreter's output, these are kept behind trait objects.
+pub struct NativeContext {
+  pub(crate) input: Box<dyn BufRead>,
+  pub(crate) clock: Box<dyn Clock>,
+  pub(crate) error_output: Box<dyn Write>,
+  /// What natives wrote to the program's output. The interpreter moves it to its own output
+  /// after each native call, since natives can't see the type of its writer.
+  pub(crate) output: Vec<u8>,
+  pub(crate) args: Vec<String>,
+  pub(crate) file_system: Box<dyn FileSystem>,
+  pub(crate) random: Random,
+}
+
+impl NativeContext {
+  pub fn input(&mut self) -> &mut dyn BufRead {
+    self.input.as_mut()
+  }
+
+  pub fn clock(&self) -> &dyn Clock {
+    self.clock.as_ref()
+  }
+
+  pub fn error_output(&mut self) -> &mut dyn Write {
+    self.error_output.as_mut()
+  }
+
+  /// Where natives write what the program prints, like the `print` statement does.
+  pub fn output(&mut self) -> &mut dyn Write {
+    &mut self.output
+  }
+
+  pub fn file_system(&mut self) -> &mut dyn FileSystem {
+    self.file_system.as_mut()
+  }
+
+  /// The generator behind `random()`, which `setSeed(n)` seeds again.
+  pub fn random(&mut self) -> &mut Random {
+    &mut self.random
+  }
+
+  /// Arguments the program was started with, for `argc()` and `argv(i)`.
+  pub fn args(&self) -> &[String] {
+    &self.args
+  }
+}
+
+impl Default for NativeContext {
+  fn default() -> Self {
+    NativeContext {
+      input: Box::new(io::empty()),
+      clock: Box::new(SystemClock),
+      error_output: Box::new(io::stderr()),
+      output: vec![],
+      args: vec![],
+      file_system: Box::new(NoFileSystem),
+      random: Random::default(),
+    }
+  }
+}
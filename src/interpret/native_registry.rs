@@ -0,0 +1,161 @@
+use crate::interpret::error::RuntimeError;
+use crate::interpret::lox_fn::{NativeFn, NativeLambda};
+use crate::interpret::native_context::NativeContext;
+use crate::interpret::natives::{files, functions, io, math, number_arg, process, strings, types};
+use crate::interpret::value::Value;
+use std::time::Duration;
+
+/// Native functions to define as globals when an interpreter is created. Embedders can register
+/// their own natives to extend Lox without touching the interpreter. When a name is registered
+/// twice, the last registration wins.
+#[derive(Debug, Clone, Default)]
+pub struct NativeRegistry {
+  natives: Vec<NativeFn>,
+}
+
+impl NativeRegistry {
+  /// An empty registry: programs won't see any native function.
+  pub fn new() -> NativeRegistry {
+    NativeRegistry::default()
+  }
+
+  /// The natives every Lox program can use.
+  pub fn with_defaults() -> NativeRegistry {
+    let mut registry = NativeRegistry::new();
+    registry
+      .register("clock", 0, clock)
+      .register("sleep", 1, sleep);
+    strings::register(&mut registry);
+    math::register(&mut registry);
+    io::register(&mut registry);
+    functions::register(&mut registry);
+    process::register(&mut registry);
+    files::register(&mut registry);
+    types::register(&mut registry);
+    registry
+  }
+
+  pub fn register(&mut self, name: &str, arity: usize, implementation: NativeLambda) -> &mut Self {
+    self
+      .natives
+      .push(NativeFn::new(name.to_string(), arity, implementation));
+    self
+  }
+
+  /// Like [`NativeRegistry::register`], for natives that show their arguments. Instances are
+  /// passed as what their `toString` method returns.
+  pub fn register_displaying(
+    &mut self,
+    name: &str,
+    arity: usize,
+    implementation: NativeLambda,
+  ) -> &mut Self {
+    let native = NativeFn::new(name.to_string(), arity, implementation);
+    self.natives.push(native.displaying_args());
+    self
+  }
+
+  pub fn natives(&self) -> &[NativeFn] {
+    &self.natives
+  }
+}
+
+fn clock(
+  context: &mut NativeContext,
+  _args: Vec<Value>,
+  _line: usize,
+) -> Result<Value, RuntimeError> {
+  Ok(Value::Number(context.clock().now()))
+}
+
+/// `sleep(ms)`: pauses the program for `ms` milliseconds of the interpreter's clock.
+fn sleep(
+  context: &mut NativeContext,
+  args: Vec<Value>,
+  line: usize,
+) -> Result<Value, RuntimeError> {
+  let ms = number_arg("sleep", &args, 0, line)?;
+  let duration = Duration::try_from_secs_f64(ms / 1000.0).map_err(|_| {
+    RuntimeError::WrongArgumentType(
+      line,
+      "sleep".to_string(),
+      "a non-negative number".to_string(),
+      args[0].to_string(),
+    )
+  })?;
+  context.clock().sleep(duration);
+  Ok(Value::Nil)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::interpret::clock::FixedClock;
+  use crate::interpret::interpreter::Interpreter;
+  use crate::interpret::natives::{parse_program, run_in};
+
+  fn run_with(registry: NativeRegistry, src: &str) -> Result<String, RuntimeError> {
+    run_in(Interpreter::with_natives(vec![], registry), src)
+  }
+
+  fn double(
+    _context: &mut NativeContext,
+    args: Vec<Value>,
+    _line: usize,
+  ) -> Result<Value, RuntimeError> {
+    match args.first() {
+      Some(Value::Number(n)) => Ok(Value::Number(n * 2.0)),
+      _ => Ok(Value::Nil),
+    }
+  }
+
+  #[test]
+  fn registered_natives_are_globals() {
+    let mut registry = NativeRegistry::with_defaults();
+    registry.register("double", 1, double);
+    assert_eq!(run_with(registry, "print double(21);").unwrap(), "42\n");
+  }
+
+  #[test]
+  fn natives_check_their_arity() {
+    let mut registry = NativeRegistry::new();
+    registry.register("double", 1, double);
+    assert_eq!(
+      run_with(registry, "double(1, 2);").unwrap_err(),
+      RuntimeError::WrongNumberOfArguments(1, "double".to_string(), 1, 2)
+    );
+  }
+
+  #[test]
+  fn sleep_waits_on_the_interpreter_clock() {
+    let stmts = parse_program("print clock(); sleep(250); print clock(); sleep(-1);");
+    let mut output: Vec<u8> = vec![];
+    let mut interpreter = Interpreter::new(&mut output).with_clock(FixedClock::new(5.0));
+    assert_eq!(
+      interpreter.interpret_stmts(&stmts).unwrap_err(),
+      RuntimeError::WrongArgumentType(
+        1,
+        "sleep".to_string(),
+        "a non-negative number".to_string(),
+        "-1".to_string()
+      )
+    );
+    assert_eq!(String::from_utf8(output).unwrap(), "5\n5.25\n");
+  }
+
+  #[test]
+  fn empty_registry_defines_nothing() {
+    let res = run_with(NativeRegistry::new(), "clock();");
+    assert_eq!(
+      res.unwrap_err(),
+      RuntimeError::UndefinedVariable(1, "clock".to_string())
+    );
+  }
+
+  #[test]
+  fn later_registrations_replace_earlier_ones() {
+    let mut registry = NativeRegistry::with_defaults();
+    registry.register("clock", 1, double);
+    assert_eq!(run_with(registry, "print clock(2);").unwrap(), "4\n");
+  }
+}
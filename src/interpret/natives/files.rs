@@ -0,0 +1,104 @@
+use crate::interpret::error::RuntimeError;
+use crate::interpret::native_context::NativeContext;
+use crate::interpret::native_registry::NativeRegistry;
+use crate::interpret::natives::string_arg;
+use crate::interpret::value::Value;
+use std::io;
+
+pub fn register(registry: &mut NativeRegistry) {
+  registry.register("readFile", 1, read_file);
+  registry.register("writeFile", 2, write_file);
+  registry.register("appendFile", 2, append_file);
+}
+
+fn file_error(line: usize, name: &str, path: &str, error: io::Error) -> RuntimeError {
+  RuntimeError::FileAccess(line, name.to_string(), path.to_string(), error.to_string())
+}
+
+/// Whole contents of a file.
+fn read_file(
+  context: &mut NativeContext,
+  args: Vec<Value>,
+  line: usize,
+) -> Result<Value, RuntimeError> {
+  let path = string_arg("readFile", &args, 0, line)?;
+  let contents = context
+    .file_system()
+    .read(path)
+    .map_err(|e| file_error(line, "readFile", path, e))?;
+  Ok(Value::from(contents))
+}
+
+/// Replaces the contents of a file, creating it if needed.
+fn write_file(
+  context: &mut NativeContext,
+  args: Vec<Value>,
+  line: usize,
+) -> Result<Value, RuntimeError> {
+  let path = string_arg("writeFile", &args, 0, line)?;
+  let contents = string_arg("writeFile", &args, 1, line)?;
+  context
+    .file_system()
+    .write(path, contents)
+    .map_err(|e| file_error(line, "writeFile", path, e))?;
+  Ok(Value::Nil)
+}
+
+/// Adds to the end of a file, creating it if needed.
+fn append_file(
+  context: &mut NativeContext,
+  args: Vec<Value>,
+  line: usize,
+) -> Result<Value, RuntimeError> {
+  let path = string_arg("appendFile", &args, 0, line)?;
+  let contents = string_arg("appendFile", &args, 1, line)?;
+  context
+    .file_system()
+    .append(path, contents)
+    .map_err(|e| file_error(line, "appendFile", path, e))?;
+  Ok(Value::Nil)
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::interpret::error::RuntimeError;
+  use crate::interpret::file_system::MemoryFileSystem;
+  use crate::interpret::interpreter::Interpreter;
+  use crate::interpret::natives::run_in;
+
+  #[test]
+  fn files_can_be_read_written_and_appended() {
+    let files = MemoryFileSystem::new().with_file("in.txt", "hello");
+    let interpreter = Interpreter::new(vec![]).with_file_system(files.clone());
+    let src = "
+      var text = readFile(\"in.txt\");
+      writeFile(\"out.txt\", text + \" world\");
+      appendFile(\"out.txt\", \"!\");
+      appendFile(\"new.txt\", \"created\");
+      print readFile(\"out.txt\");
+    ";
+    assert_eq!(run_in(interpreter, src).unwrap(), "hello world!\n");
+    assert_eq!(files.file("new.txt").as_deref(), Some("created"));
+  }
+
+  #[test]
+  fn files_are_not_accessible_by_default() {
+    let res = run_in(Interpreter::new(vec![]), "readFile(\"secret.txt\");");
+    assert_eq!(
+      res.unwrap_err(),
+      RuntimeError::FileAccess(
+        1,
+        "readFile".to_string(),
+        "secret.txt".to_string(),
+        "file access is not allowed".to_string()
+      )
+    );
+  }
+
+  #[test]
+  fn reading_a_missing_file_is_an_error() {
+    let interpreter = Interpreter::new(vec![]).with_file_system(MemoryFileSystem::new());
+    let res = run_in(interpreter, "readFile(\"missing.txt\");");
+    assert!(matches!(res, Err(RuntimeError::FileAccess(1, ..))));
+  }
+}
@@ -0,0 +1,59 @@
+use crate::interpret::error::RuntimeError;
+use crate::interpret::native_context::NativeContext;
+use crate::interpret::native_registry::NativeRegistry;
+use crate::interpret::natives::callable_arg;
+use crate::interpret::value::Value;
+
+pub fn register(registry: &mut NativeRegistry) {
+  registry.register("arity", 1, arity);
+}
+
+/// `arity(f)`: how many arguments `f` expects. Works on functions, natives and classes.
+fn arity(
+  _context: &mut NativeContext,
+  args: Vec<Value>,
+  line: usize,
+) -> Result<Value, RuntimeError> {
+  let callable = callable_arg("arity", &args, 0, line)?;
+  Ok(Value::Number(callable.arity() as f64))
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::interpret::error::RuntimeError;
+  use crate::interpret::natives::run;
+
+  #[test]
+  fn arity_counts_parameters_of_any_callable() {
+    let src = "
+      fun add(a, b) { return a + b; }
+      class Point { init(x, y) {} }
+      class Empty {}
+      print arity(add);
+      print arity(clock);
+      print arity(Point);
+      print arity(Empty);
+      print arity(Point(1, 2).init);
+    ";
+    assert_eq!(run(src).unwrap(), "2\n0\n2\n0\n2\n");
+  }
+
+  #[test]
+  fn arity_rejects_values_that_are_not_callable() {
+    assert_eq!(
+      run("arity(1);").unwrap_err(),
+      RuntimeError::WrongArgumentType(
+        1,
+        "arity".to_string(),
+        "a function".to_string(),
+        "Number".to_string()
+      )
+    );
+  }
+
+  #[test]
+  fn functions_print_distinctly_from_natives() {
+    let src = "fun f() {} class C {} print f; print clock; print C;";
+    assert_eq!(run(src).unwrap(), "<fn f>\n<native fn>\nC\n");
+  }
+}
@@ -0,0 +1,151 @@
+use crate::interpret::error::RuntimeError;
+use crate::interpret::native_context::NativeContext;
+use crate::interpret::native_registry::NativeRegistry;
+use crate::interpret::value::Value;
+
+pub fn register(registry: &mut NativeRegistry) {
+  registry.register("readLine", 0, read_line);
+  registry.register_displaying("println", 1, println);
+  registry.register_displaying("write", 1, write);
+  registry.register_displaying("eprint", 1, eprint);
+}
+
+/// Like the `print` statement, as a function.
+fn println(
+  context: &mut NativeContext,
+  args: Vec<Value>,
+  _line: usize,
+) -> Result<Value, RuntimeError> {
+  writeln!(context.output(), "{}", args[0]).map_err(|_| RuntimeError::CannotWriteToStdout)?;
+  Ok(Value::Nil)
+}
+
+/// Prints without a newline, so a line can be written in parts.
+fn write(
+  context: &mut NativeContext,
+  args: Vec<Value>,
+  _line: usize,
+) -> Result<Value, RuntimeError> {
+  write!(context.output(), "{}", args[0]).map_err(|_| RuntimeError::CannotWriteToStdout)?;
+  Ok(Value::Nil)
+}
+
+/// Prints a line to the error output instead, for messages that aren't part of the result.
+fn eprint(
+  context: &mut NativeContext,
+  args: Vec<Value>,
+  _line: usize,
+) -> Result<Value, RuntimeError> {
+  writeln!(context.error_output(), "{}", args[0]).map_err(|_| RuntimeError::CannotWriteToStdout)?;
+  Ok(Value::Nil)
+}
+
+/// Next line of input without its line ending, or `nil` once the input is over.
+fn read_line(
+  context: &mut NativeContext,
+  _args: Vec<Value>,
+  _line: usize,
+) -> Result<Value, RuntimeError> {
+  let mut line = String::new();
+  let read = context
+    .input()
+    .read_line(&mut line)
+    .map_err(|_| RuntimeError::CannotReadFromStdin)?;
+  if read == 0 {
+    return Ok(Value::Nil);
+  }
+
+  if line.ends_with('\n') {
+    line.pop();
+    if line.ends_with('\r') {
+      line.pop();
+    }
+  }
+  Ok(Value::from(line))
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::interpret::error::RuntimeError;
+  use crate::interpret::interpreter::Interpreter;
+  use crate::interpret::natives::{parse_program, run_in};
+  use std::cell::RefCell;
+  use std::io::{Cursor, Write};
+  use std::rc::Rc;
+
+  /// Error output shared with the test, since the interpreter owns it.
+  #[derive(Clone, Default)]
+  struct SharedOutput(Rc<RefCell<Vec<u8>>>);
+
+  impl Write for SharedOutput {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+      self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+      Ok(())
+    }
+  }
+
+  fn run_with_input(src: &str, input: &str) -> Result<String, RuntimeError> {
+    run_in(
+      Interpreter::new(vec![]).with_input(Cursor::new(input.to_string())),
+      src,
+    )
+  }
+
+  #[test]
+  fn read_line_returns_each_line_without_its_ending() {
+    let res = run_with_input("print readLine(); print readLine();", "first\r\nsecond\n");
+    assert_eq!(res.unwrap(), "first\nsecond\n");
+  }
+
+  #[test]
+  fn read_line_returns_the_last_line_without_a_newline() {
+    assert_eq!(
+      run_with_input("print readLine();", "last").unwrap(),
+      "last\n"
+    );
+  }
+
+  #[test]
+  fn read_line_returns_nil_when_input_is_over() {
+    let res = run_with_input("print readLine(); print readLine();", "only\n");
+    assert_eq!(res.unwrap(), "only\nnil\n");
+  }
+
+  #[test]
+  fn programs_can_loop_over_their_input() {
+    let src = "var total = 0; var line = readLine(); while (line != nil) { total = total + len(line); line = readLine(); } print total;";
+    assert_eq!(run_with_input(src, "ab\ncde\n\nf\n").unwrap(), "6\n");
+  }
+
+  #[test]
+  fn write_and_println_go_to_the_output_in_order_with_print() {
+    let src = "write(\"a\"); write(1); println(\"!\"); print \"b\"; println(nil);";
+    assert_eq!(run_with_input(src, "").unwrap(), "a1!\nb\nnil\n");
+  }
+
+  #[test]
+  fn eprint_writes_a_line_to_the_error_output() {
+    let stmts = parse_program("eprint(\"oops\"); print 1;");
+    let mut output: Vec<u8> = vec![];
+    let errors = SharedOutput::default();
+    Interpreter::new(&mut output)
+      .with_error_output(errors.clone())
+      .interpret_stmts(&stmts)
+      .unwrap();
+    assert_eq!(String::from_utf8(output).unwrap(), "1\n");
+    assert_eq!(String::from_utf8(errors.0.take()).unwrap(), "oops\n");
+  }
+
+  #[test]
+  fn default_input_is_empty() {
+    let stmts = parse_program("print readLine();");
+    let mut output: Vec<u8> = vec![];
+    Interpreter::new(&mut output)
+      .interpret_stmts(&stmts)
+      .unwrap();
+    assert_eq!(String::from_utf8(output).unwrap(), "nil\n");
+  }
+}
@@ -0,0 +1,154 @@
+use crate::interpret::error::RuntimeError;
+use crate::interpret::native_context::NativeContext;
+use crate::interpret::native_registry::NativeRegistry;
+use crate::interpret::natives::{index_arg, number_arg};
+use crate::interpret::random::Random;
+use crate::interpret::value::Value;
+
+pub fn register(registry: &mut NativeRegistry) {
+  registry
+    .register("floor", 1, floor)
+    .register("ceil", 1, ceil)
+    .register("abs", 1, abs)
+    .register("sqrt", 1, sqrt)
+    .register("pow", 2, pow)
+    .register("min", 2, min)
+    .register("max", 2, max)
+    .register("random", 0, random)
+    .register("setSeed", 1, set_seed);
+}
+
+fn floor(
+  _context: &mut NativeContext,
+  args: Vec<Value>,
+  line: usize,
+) -> Result<Value, RuntimeError> {
+  Ok(Value::Number(number_arg("floor", &args, 0, line)?.floor()))
+}
+
+fn ceil(
+  _context: &mut NativeContext,
+  args: Vec<Value>,
+  line: usize,
+) -> Result<Value, RuntimeError> {
+  Ok(Value::Number(number_arg("ceil", &args, 0, line)?.ceil()))
+}
+
+fn abs(_context: &mut NativeContext, args: Vec<Value>, line: usize) -> Result<Value, RuntimeError> {
+  Ok(Value::Number(number_arg("abs", &args, 0, line)?.abs()))
+}
+
+/// Negative numbers give NaN, like any other float operation without a real result.
+fn sqrt(
+  _context: &mut NativeContext,
+  args: Vec<Value>,
+  line: usize,
+) -> Result<Value, RuntimeError> {
+  Ok(Value::Number(number_arg("sqrt", &args, 0, line)?.sqrt()))
+}
+
+fn pow(_context: &mut NativeContext, args: Vec<Value>, line: usize) -> Result<Value, RuntimeError> {
+  let base = number_arg("pow", &args, 0, line)?;
+  let exponent = number_arg("pow", &args, 1, line)?;
+  Ok(Value::Number(base.powf(exponent)))
+}
+
+fn min(_context: &mut NativeContext, args: Vec<Value>, line: usize) -> Result<Value, RuntimeError> {
+  let a = number_arg("min", &args, 0, line)?;
+  let b = number_arg("min", &args, 1, line)?;
+  Ok(Value::Number(a.min(b)))
+}
+
+fn max(_context: &mut NativeContext, args: Vec<Value>, line: usize) -> Result<Value, RuntimeError> {
+  let a = number_arg("max", &args, 0, line)?;
+  let b = number_arg("max", &args, 1, line)?;
+  Ok(Value::Number(a.max(b)))
+}
+
+/// A number in `[0, 1)`.
+fn random(
+  context: &mut NativeContext,
+  _args: Vec<Value>,
+  _line: usize,
+) -> Result<Value, RuntimeError> {
+  Ok(Value::Number(context.random().next_number()))
+}
+
+/// Makes `random()` start over from `seed`, which has to be a non-negative integer. Returns nil.
+fn set_seed(
+  context: &mut NativeContext,
+  args: Vec<Value>,
+  line: usize,
+) -> Result<Value, RuntimeError> {
+  let seed = index_arg("setSeed", &args, 0, line)?;
+  *context.random() = Random::seeded(seed as u64);
+  Ok(Value::Nil)
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::interpret::error::RuntimeError;
+  use crate::interpret::natives::run;
+
+  #[test]
+  fn rounding_functions() {
+    let res = run("print floor(1.7); print ceil(1.2); print floor(-1.5); print abs(-3);");
+    assert_eq!(res.unwrap(), "1\n2\n-2\n3\n");
+  }
+
+  #[test]
+  fn sqrt_and_pow() {
+    assert_eq!(
+      run("print sqrt(16); print pow(2, 10);").unwrap(),
+      "4\n1024\n"
+    );
+  }
+
+  #[test]
+  fn min_and_max() {
+    assert_eq!(
+      run("print min(1, -2); print max(1, -2);").unwrap(),
+      "-2\n1\n"
+    );
+  }
+
+  #[test]
+  fn random_is_between_zero_and_one() {
+    let res = run(
+      "var ok = true; for (var i = 0; i < 100; i = i + 1) { var r = random(); if (r < 0 or r >= 1) ok = false; } print ok;",
+    );
+    assert_eq!(res.unwrap(), "true\n");
+  }
+
+  #[test]
+  fn set_seed_makes_random_repeat() {
+    let res = run("setSeed(7); var a = random(); var b = random(); setSeed(7); print a == random() and b == random();");
+    assert_eq!(res.unwrap(), "true\n");
+  }
+
+  #[test]
+  fn set_seed_takes_a_non_negative_integer() {
+    assert_eq!(
+      run("setSeed(1.5);").unwrap_err(),
+      RuntimeError::WrongArgumentType(
+        1,
+        "setSeed".to_string(),
+        "a non-negative integer".to_string(),
+        "1.5".to_string()
+      )
+    );
+  }
+
+  #[test]
+  fn non_numbers_are_errors_with_the_call_line() {
+    assert_eq!(
+      run("\n\npow(2, \"a\");").unwrap_err(),
+      RuntimeError::WrongArgumentType(
+        3,
+        "pow".to_string(),
+        "a Number".to_string(),
+        "String".to_string()
+      )
+    );
+  }
+}
@@ -0,0 +1,111 @@
+pub mod files;
+pub mod functions;
+pub mod io;
+pub mod math;
+pub mod process;
+pub mod strings;
+pub mod types;
+
+use crate::interpret::error::RuntimeError;
+#[cfg(test)]
+use crate::interpret::interpreter::Interpreter;
+use crate::interpret::lox_fn::Callable;
+use crate::interpret::value::Value;
+#[cfg(test)]
+use crate::parse::{parser::LoxParser, stmt::Stmt};
+#[cfg(test)]
+use crate::scan::scanner::Scanner;
+
+/// Argument checks shared by the natives. They report errors on the line of the call.
+pub(crate) fn string_arg<'a>(
+  name: &str,
+  args: &'a [Value],
+  index: usize,
+  line: usize,
+) -> Result<&'a str, RuntimeError> {
+  match &args[index] {
+    Value::String(value) => Ok(value),
+    other => Err(RuntimeError::WrongArgumentType(
+      line,
+      name.to_string(),
+      "a String".to_string(),
+      other.type_name().to_string(),
+    )),
+  }
+}
+
+pub(crate) fn number_arg(
+  name: &str,
+  args: &[Value],
+  index: usize,
+  line: usize,
+) -> Result<f64, RuntimeError> {
+  match &args[index] {
+    Value::Number(n) => Ok(*n),
+    other => Err(RuntimeError::WrongArgumentType(
+      line,
+      name.to_string(),
+      "a Number".to_string(),
+      other.type_name().to_string(),
+    )),
+  }
+}
+
+pub(crate) fn index_arg(
+  name: &str,
+  args: &[Value],
+  index: usize,
+  line: usize,
+) -> Result<usize, RuntimeError> {
+  match &args[index] {
+    Value::Number(n) if *n >= 0.0 && n.fract() == 0.0 => Ok(*n as usize),
+    other => Err(RuntimeError::WrongArgumentType(
+      line,
+      name.to_string(),
+      "a non-negative integer".to_string(),
+      other.to_string(),
+    )),
+  }
+}
+
+pub(crate) fn callable_arg<'a>(
+  name: &str,
+  args: &'a [Value],
+  index: usize,
+  line: usize,
+) -> Result<&'a Callable, RuntimeError> {
+  match &args[index] {
+    Value::Callable(callable) => Ok(callable),
+    other => Err(RuntimeError::WrongArgumentType(
+      line,
+      name.to_string(),
+      "a function".to_string(),
+      other.type_name().to_string(),
+    )),
+  }
+}
+
+/// The statements of `src`, for tests. It has to parse.
+#[cfg(test)]
+pub(crate) fn parse_program(src: &str) -> Vec<Stmt> {
+  let mut bytes = src.as_bytes();
+  let tokens = Scanner::new(&mut bytes).scan_tokens().0;
+  LoxParser::new(tokens).parse().unwrap()
+}
+
+/// Runs `src` in `interpreter` and gives what it printed, for tests that set the interpreter up
+/// first, like with some input.
+#[cfg(test)]
+pub(crate) fn run_in(
+  mut interpreter: Interpreter<Vec<u8>>,
+  src: &str,
+) -> Result<String, RuntimeError> {
+  interpreter.interpret_stmts(&parse_program(src))?;
+  Ok(String::from_utf8(std::mem::take(interpreter.stdout())).unwrap())
+}
+
+/// Runs `src` with the default natives and gives what it printed, for tests.
+#[cfg(test)]
+pub(crate) fn run(src: &str) -> Result<String, RuntimeError> {
+  run_in(Interpreter::new(vec![]), src)
+}
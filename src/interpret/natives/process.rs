@@ -0,0 +1,102 @@
+use crate::interpret::error::RuntimeError;
+use crate::interpret::native_context::NativeContext;
+use crate::interpret::native_registry::NativeRegistry;
+use crate::interpret::natives::index_arg;
+use crate::interpret::value::Value;
+
+pub fn register(registry: &mut NativeRegistry) {
+  registry.register("argc", 0, argc);
+  registry.register("argv", 1, argv);
+  registry.register("exit", 1, exit);
+}
+
+/// How many arguments the program was given.
+fn argc(
+  context: &mut NativeContext,
+  _args: Vec<Value>,
+  _line: usize,
+) -> Result<Value, RuntimeError> {
+  Ok(Value::Number(context.args().len() as f64))
+}
+
+/// The argument at an index, starting at 0.
+fn argv(context: &mut NativeContext, args: Vec<Value>, line: usize) -> Result<Value, RuntimeError> {
+  let index = index_arg("argv", &args, 0, line)?;
+  let program_args = context.args();
+  program_args
+    .get(index)
+    .map(|arg| Value::from(arg.as_str()))
+    .ok_or_else(|| {
+      RuntimeError::IndexOutOfRange(line, "argv".to_string(), index, program_args.len())
+    })
+}
+
+/// Stops the program with an exit code between 0 and 255.
+fn exit(
+  _context: &mut NativeContext,
+  args: Vec<Value>,
+  line: usize,
+) -> Result<Value, RuntimeError> {
+  match &args[0] {
+    Value::Number(n) if (0.0..=255.0).contains(n) && n.fract() == 0.0 => {
+      Err(RuntimeError::Exit(*n as u8))
+    }
+    other => Err(RuntimeError::WrongArgumentType(
+      line,
+      "exit".to_string(),
+      "an exit code between 0 and 255".to_string(),
+      other.to_string(),
+    )),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::interpret::error::RuntimeError;
+  use crate::interpret::interpreter::Interpreter;
+  use crate::interpret::natives::run_in;
+
+  fn run_with_args(src: &str, args: &[&str]) -> Result<String, RuntimeError> {
+    let args = args.iter().map(|arg| arg.to_string()).collect();
+    run_in(Interpreter::new(vec![]).with_args(args), src)
+  }
+
+  #[test]
+  fn programs_can_read_their_arguments() {
+    let src = "for (var i = 0; i < argc(); i = i + 1) print argv(i);";
+    assert_eq!(run_with_args(src, &["a", "b c"]).unwrap(), "a\nb c\n");
+  }
+
+  #[test]
+  fn programs_start_without_arguments() {
+    assert_eq!(run_with_args("print argc();", &[]).unwrap(), "0\n");
+  }
+
+  #[test]
+  fn argv_past_the_last_argument_is_an_error() {
+    assert_eq!(
+      run_with_args("argv(1);", &["only"]).unwrap_err(),
+      RuntimeError::IndexOutOfRange(1, "argv".to_string(), 1, 1)
+    );
+  }
+
+  #[test]
+  fn exit_stops_the_program_from_inside_functions() {
+    let src =
+      "fun quit() { exit(3); print \"unreachable\"; } print \"before\"; quit(); print \"after\";";
+    assert_eq!(run_with_args(src, &[]).unwrap_err(), RuntimeError::Exit(3));
+  }
+
+  #[test]
+  fn exit_codes_must_fit_in_a_byte() {
+    assert_eq!(
+      run_with_args("exit(256);", &[]).unwrap_err(),
+      RuntimeError::WrongArgumentType(
+        1,
+        "exit".to_string(),
+        "an exit code between 0 and 255".to_string(),
+        "256".to_string()
+      )
+    );
+  }
+}
@@ -0,0 +1,255 @@
+use crate::interpret::error::RuntimeError;
+use crate::interpret::native_context::NativeContext;
+use crate::interpret::native_registry::NativeRegistry;
+use crate::interpret::natives::{index_arg, number_arg, string_arg};
+use crate::interpret::value::Value;
+use crate::scan::number_format::parse_number;
+
+/// String functions work on chars, not bytes, so `len("ñ")` is 1.
+pub fn register(registry: &mut NativeRegistry) {
+  registry
+    .register("len", 1, len)
+    .register("substr", 3, substr)
+    .register("upper", 1, upper)
+    .register("lower", 1, lower)
+    .register("char_at", 2, char_at)
+    .register("parseNumber", 1, parse_number_native)
+    .register("toFixed", 2, to_fixed)
+    .register_displaying("str", 1, str);
+}
+
+fn len(_context: &mut NativeContext, args: Vec<Value>, line: usize) -> Result<Value, RuntimeError> {
+  let s = string_arg("len", &args, 0, line)?;
+  Ok(Value::Number(s.chars().count() as f64))
+}
+
+/// `substr(s, start, len)`: up to `len` chars starting at `start`.
+fn substr(
+  _context: &mut NativeContext,
+  args: Vec<Value>,
+  line: usize,
+) -> Result<Value, RuntimeError> {
+  let s = string_arg("substr", &args, 0, line)?;
+  let start = index_arg("substr", &args, 1, line)?;
+  let len = index_arg("substr", &args, 2, line)?;
+
+  let char_count = s.chars().count();
+  if start > char_count {
+    return Err(RuntimeError::IndexOutOfRange(
+      line,
+      "substr".to_string(),
+      start,
+      char_count,
+    ));
+  }
+  Ok(Value::from(
+    s.chars().skip(start).take(len).collect::<String>(),
+  ))
+}
+
+fn upper(
+  _context: &mut NativeContext,
+  args: Vec<Value>,
+  line: usize,
+) -> Result<Value, RuntimeError> {
+  let s = string_arg("upper", &args, 0, line)?;
+  Ok(Value::from(s.to_uppercase()))
+}
+
+fn lower(
+  _context: &mut NativeContext,
+  args: Vec<Value>,
+  line: usize,
+) -> Result<Value, RuntimeError> {
+  let s = string_arg("lower", &args, 0, line)?;
+  Ok(Value::from(s.to_lowercase()))
+}
+
+fn char_at(
+  _context: &mut NativeContext,
+  args: Vec<Value>,
+  line: usize,
+) -> Result<Value, RuntimeError> {
+  let s = string_arg("char_at", &args, 0, line)?;
+  let index = index_arg("char_at", &args, 1, line)?;
+  s.chars()
+    .nth(index)
+    .map(|c| Value::from(c.to_string()))
+    .ok_or_else(|| {
+      RuntimeError::IndexOutOfRange(line, "char_at".to_string(), index, s.chars().count())
+    })
+}
+
+/// `parseNumber(s)`: the number `s` spells, or `nil` if it isn't one. Surrounding whitespace and a
+/// leading `-` are allowed, and so are the `0x` and `0b` prefixes of number literals.
+fn parse_number_native(
+  _context: &mut NativeContext,
+  args: Vec<Value>,
+  line: usize,
+) -> Result<Value, RuntimeError> {
+  let s = string_arg("parseNumber", &args, 0, line)?.trim();
+  let (sign, digits) = match s.strip_prefix('-') {
+    Some(digits) => (-1.0, digits),
+    None => (1.0, s),
+  };
+  let is_numeric = |c: char| c.is_ascii_alphanumeric() || ".+-".contains(c);
+  let number = match digits.chars().next() {
+    Some(c) if c.is_ascii_digit() || c == '.' => digits
+      .chars()
+      .all(is_numeric)
+      .then(|| parse_number(digits))
+      .flatten(),
+    _ => None,
+  };
+  Ok(number.map_or(Value::Nil, |n| Value::Number(sign * n)))
+}
+
+/// Most decimals `toFixed` gives, like JavaScript. More would only pad with zeros, and a huge
+/// count would run out of memory.
+const MAX_FIXED_DIGITS: usize = 100;
+
+/// `toFixed(n, digits)`: `n` rounded to exactly `digits` decimals.
+fn to_fixed(
+  _context: &mut NativeContext,
+  args: Vec<Value>,
+  line: usize,
+) -> Result<Value, RuntimeError> {
+  let n = number_arg("toFixed", &args, 0, line)?;
+  let digits = index_arg("toFixed", &args, 1, line)?;
+  if digits > MAX_FIXED_DIGITS {
+    return Err(RuntimeError::WrongArgumentType(
+      line,
+      "toFixed".to_string(),
+      format!("at most {MAX_FIXED_DIGITS} digits"),
+      digits.to_string(),
+    ));
+  }
+  Ok(Value::from(format!("{n:.digits$}")))
+}
+
+/// Same text `print` would show.
+fn str(
+  _context: &mut NativeContext,
+  args: Vec<Value>,
+  _line: usize,
+) -> Result<Value, RuntimeError> {
+  Ok(Value::from(args[0].to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::interpret::error::RuntimeError;
+  use crate::interpret::natives::run;
+
+  #[test]
+  fn len_counts_chars() {
+    assert_eq!(
+      run("print len(\"\"); print len(\"año\");").unwrap(),
+      "0\n3\n"
+    );
+  }
+
+  #[test]
+  fn substr_takes_a_range_of_chars() {
+    let res = run("print substr(\"hello\", 1, 3); print substr(\"hello\", 3, 10);");
+    assert_eq!(res.unwrap(), "ell\nlo\n");
+  }
+
+  #[test]
+  fn substr_start_past_the_end_is_an_error() {
+    assert_eq!(
+      run("substr(\"abc\", 4, 1);").unwrap_err(),
+      RuntimeError::IndexOutOfRange(1, "substr".to_string(), 4, 3)
+    );
+  }
+
+  #[test]
+  fn upper_and_lower_change_case() {
+    let res = run("print upper(\"Año\"); print lower(\"AñO\");");
+    assert_eq!(res.unwrap(), "AÑO\naño\n");
+  }
+
+  #[test]
+  fn char_at_returns_a_one_char_string() {
+    assert_eq!(run("print char_at(\"año\", 1);").unwrap(), "ñ\n");
+    assert_eq!(
+      run("char_at(\"año\", 3);").unwrap_err(),
+      RuntimeError::IndexOutOfRange(1, "char_at".to_string(), 3, 3)
+    );
+  }
+
+  #[test]
+  fn parse_number_reads_numbers_or_returns_nil() {
+    let res = run(
+      "print parseNumber(\" 42 \"); print parseNumber(\"-1.5e-1\"); print parseNumber(\"0xff\");
+       print parseNumber(\"12abc\"); print parseNumber(\"\"); print parseNumber(\"inf\");",
+    );
+    assert_eq!(res.unwrap(), "42\n-0.15\n255\nnil\nnil\nnil\n");
+  }
+
+  #[test]
+  fn to_fixed_rounds_to_the_given_decimals() {
+    let res = run("print toFixed(3.14159, 2); print toFixed(2, 3); print toFixed(2.5, 0);");
+    assert_eq!(res.unwrap(), "3.14\n2.000\n2\n");
+  }
+
+  #[test]
+  fn to_fixed_gives_at_most_100_decimals() {
+    assert_eq!(run("print len(toFixed(1, 100));").unwrap(), "102\n");
+    assert_eq!(
+      run("toFixed(1, 1000000000000000000);").unwrap_err().to_string(),
+      "[line 1]: toFixed expected at most 100 digits, got 1000000000000000000"
+    );
+  }
+
+  #[test]
+  fn str_converts_any_value() {
+    let res = run("print str(1.5) + str(nil) + str(true) + str(\"s\"); print str(len);");
+    assert_eq!(res.unwrap(), "1.5niltrues\n<native fn>\n");
+  }
+
+  #[test]
+  fn str_uses_the_to_string_method_of_instances() {
+    let src = "
+      class Point {
+        init(x, y) { this.x = x; this.y = y; }
+        toString() { return \"(\" + str(this.x) + \", \" + str(this.y) + \")\"; }
+      }
+      class Plain {}
+      print \"at \" + str(Point(1, 2));
+      print Point(3, 4);
+      print str(Plain());
+    ";
+    assert_eq!(run(src).unwrap(), "at (1, 2)\n(3, 4)\nPlain instance\n");
+  }
+
+  #[test]
+  fn wrong_argument_types_are_errors_with_the_call_line() {
+    assert_eq!(
+      run("\nlen(1);").unwrap_err(),
+      RuntimeError::WrongArgumentType(
+        2,
+        "len".to_string(),
+        "a String".to_string(),
+        "Number".to_string()
+      )
+    );
+    assert_eq!(
+      run("char_at(\"abc\", 1.5);").unwrap_err(),
+      RuntimeError::WrongArgumentType(
+        1,
+        "char_at".to_string(),
+        "a non-negative integer".to_string(),
+        "1.5".to_string()
+      )
+    );
+  }
+
+  #[test]
+  fn wrong_number_of_arguments_is_an_error() {
+    assert_eq!(
+      run("upper();").unwrap_err(),
+      RuntimeError::WrongNumberOfArguments(1, "upper".to_string(), 1, 0)
+    );
+  }
+}
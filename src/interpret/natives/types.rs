@@ -0,0 +1,74 @@
+use crate::interpret::error::RuntimeError;
+use crate::interpret::native_context::NativeContext;
+use crate::interpret::native_registry::NativeRegistry;
+use crate::interpret::value::Value;
+
+/// Type checks, so programs can look at a value before using it instead of failing on it.
+pub fn register(registry: &mut NativeRegistry) {
+  registry
+    .register("type", 1, type_of)
+    .register("isNumber", 1, is_number)
+    .register("isString", 1, is_string)
+    .register("isNil", 1, is_nil);
+}
+
+/// The name type errors use for the value, like `Number` or `instance`.
+fn type_of(
+  _context: &mut NativeContext,
+  args: Vec<Value>,
+  _line: usize,
+) -> Result<Value, RuntimeError> {
+  Ok(Value::from(args[0].type_name()))
+}
+
+fn is_number(
+  _context: &mut NativeContext,
+  args: Vec<Value>,
+  _line: usize,
+) -> Result<Value, RuntimeError> {
+  Ok(Value::Boolean(matches!(args[0], Value::Number(_))))
+}
+
+fn is_string(
+  _context: &mut NativeContext,
+  args: Vec<Value>,
+  _line: usize,
+) -> Result<Value, RuntimeError> {
+  Ok(Value::Boolean(matches!(args[0], Value::String(_))))
+}
+
+fn is_nil(
+  _context: &mut NativeContext,
+  args: Vec<Value>,
+  _line: usize,
+) -> Result<Value, RuntimeError> {
+  Ok(Value::Boolean(matches!(args[0], Value::Nil)))
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::interpret::natives::run;
+
+  #[test]
+  fn type_names_every_kind_of_value() {
+    let res = run(
+      "class A {} fun f() {}
+      print type(1); print type(\"a\"); print type(nil); print type(true);
+      print type(f); print type(clock); print type(A); print type(A());",
+    );
+    assert_eq!(
+      res.unwrap(),
+      "Number\nString\nnil\nBoolean\nfunction\nfunction\nclass\ninstance\n"
+    );
+  }
+
+  #[test]
+  fn checks_tell_types_apart() {
+    let res = run(
+      "print isNumber(1); print isNumber(\"1\");
+      print isString(\"a\"); print isString(nil);
+      print isNil(nil); print isNil(false);",
+    );
+    assert_eq!(res.unwrap(), "true\nfalse\ntrue\nfalse\ntrue\nfalse\n");
+  }
+}
@@ -0,0 +1,156 @@
+use crate::interpret::hook::InterpreterHook;
+use crate::interpret::stack_trace::CallFrame;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+/// Calls and time spent in one function. Functions are told apart by name, so methods with the
+/// same name in different classes share their stats.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FunctionStats {
+  pub calls: u64,
+  /// Time from the outermost call until it returned, including the functions it called.
+  /// Recursive calls don't add to it again.
+  pub total: Duration,
+}
+
+#[derive(Default)]
+struct Recording {
+  stats: HashMap<Rc<str>, FunctionStats>,
+  /// When each call in progress started, innermost last.
+  started: Vec<Instant>,
+  /// Calls in progress for each function, to only time the outermost one.
+  active: HashMap<Rc<str>, usize>,
+}
+
+/// Counts calls and measures time per Lox function. The interpreter owns the hook, so clones
+/// share what was recorded and one of them can be kept to read the report after running.
+#[derive(Clone, Default)]
+pub struct Profiler {
+  recording: Rc<RefCell<Recording>>,
+}
+
+impl Profiler {
+  pub fn new() -> Profiler {
+    Profiler::default()
+  }
+
+  /// Stats of every function called so far, the ones that took longest first.
+  pub fn report(&self) -> ProfileReport {
+    let recording = self.recording.borrow();
+    let mut functions = recording
+      .stats
+      .iter()
+      .map(|(name, stats)| (name.to_string(), stats.clone()))
+      .collect::<Vec<_>>();
+    functions.sort_by(|(name1, stats1), (name2, stats2)| {
+      (stats2.total, stats2.calls, name1).cmp(&(stats1.total, stats1.calls, name2))
+    });
+    ProfileReport { functions }
+  }
+}
+
+impl InterpreterHook for Profiler {
+  fn before_call(&mut self, frame: &CallFrame) {
+    let mut recording = self.recording.borrow_mut();
+    recording.started.push(Instant::now());
+    *recording.active.entry(frame.function.clone()).or_default() += 1;
+    recording
+      .stats
+      .entry(frame.function.clone())
+      .or_default()
+      .calls += 1;
+  }
+
+  fn after_call(&mut self, frame: &CallFrame) {
+    let mut recording = self.recording.borrow_mut();
+    let Some(started) = recording.started.pop() else {
+      return;
+    };
+    let active = recording.active.entry(frame.function.clone()).or_default();
+    *active -= 1;
+    if *active == 0 {
+      let stats = recording.stats.entry(frame.function.clone()).or_default();
+      stats.total += started.elapsed();
+    }
+  }
+}
+
+pub struct ProfileReport {
+  pub functions: Vec<(String, FunctionStats)>,
+}
+
+impl Display for ProfileReport {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    writeln!(f, "{:<20}{:>10}{:>14}", "function", "calls", "total")?;
+    for (name, stats) in &self.functions {
+      writeln!(f, "{name:<20}{:>10}{:>14.3?}", stats.calls, stats.total)?;
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::interpret::interpreter::Interpreter;
+  use crate::parse::parser::LoxParser;
+  use crate::scan::scanner::Scanner;
+  use std::io::Cursor;
+
+  fn profile(src: &str) -> ProfileReport {
+    let mut cursor = Cursor::new(src);
+    let tokens = Scanner::new(&mut cursor).scan_tokens().0;
+    let stmts = LoxParser::new(tokens).parse().unwrap();
+    let profiler = Profiler::new();
+    let mut interpreter = Interpreter::new(vec![]).with_hook(profiler.clone());
+    interpreter.interpret_stmts(&stmts).unwrap();
+    profiler.report()
+  }
+
+  fn calls(report: &ProfileReport) -> Vec<(&str, u64)> {
+    report
+      .functions
+      .iter()
+      .map(|(name, stats)| (name.as_str(), stats.calls))
+      .collect()
+  }
+
+  #[test]
+  fn counts_every_call_including_recursive_ones() {
+    let report = profile(
+      "
+      fun fib(n) { if (n < 2) return n; return fib(n - 1) + fib(n - 2); }
+      fun main() { return fib(5); }
+      main();
+      ",
+    );
+    assert_eq!(calls(&report), [("main", 1), ("fib", 15)]);
+  }
+
+  #[test]
+  fn callers_take_at_least_as_long_as_what_they_call() {
+    let report = profile(
+      "
+      fun leaf() { var i = 0; while (i < 100) i = i + 1; }
+      fun caller() { leaf(); leaf(); }
+      caller();
+      ",
+    );
+    assert_eq!(calls(&report), [("caller", 1), ("leaf", 2)]);
+    assert!(report.functions[0].1.total >= report.functions[1].1.total);
+  }
+
+  #[test]
+  fn report_lists_one_line_per_function() {
+    let report = profile("fun f() {} f(); f();");
+    let printed = report.to_string();
+    let lines = printed.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("function"));
+    assert!(lines[1].starts_with("f "));
+    assert_eq!(lines[1].split_whitespace().nth(1), Some("2"));
+  }
+}
@@ -0,0 +1,68 @@
+/// Where `random()` gets its numbers from: xorshift64*, which is plenty for scripts and avoids a
+/// dependency. The same seed always gives the same numbers, so runs can be reproduced.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Random {
+  state: u64,
+}
+
+impl Random {
+  pub fn seeded(seed: u64) -> Random {
+    // Mixes the seed with a step of splitmix64, so close seeds don't start out alike. xorshift gets
+    // stuck on zero, so that state is skipped.
+    let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^= z >> 31;
+    Random { state: z.max(1) }
+  }
+
+  /// A number in `[0, 1)`.
+  pub fn next_number(&mut self) -> f64 {
+    let mut x = self.state;
+    x ^= x >> 12;
+    x ^= x << 25;
+    x ^= x >> 27;
+    self.state = x;
+    let next = x.wrapping_mul(0x2545_F491_4F6C_DD1D);
+    // The top 53 bits fit exactly in the mantissa of an f64.
+    (next >> 11) as f64 / (1u64 << 53) as f64
+  }
+}
+
+/// Seeded from the current time, so every run is different.
+#[cfg(not(target_arch = "wasm32"))]
+impl Default for Random {
+  fn default() -> Self {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    let nanos = SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .map(|d| d.as_nanos() as u64)
+      .unwrap_or(0);
+    Random::seeded(nanos)
+  }
+}
+
+/// There's no system clock to seed from on `wasm32-unknown-unknown`.
+#[cfg(target_arch = "wasm32")]
+impl Default for Random {
+  fn default() -> Self {
+    Random::seeded(0)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn the_same_seed_gives_the_same_numbers() {
+    let numbers = |seed| {
+      let mut random = Random::seeded(seed);
+      (0..5).map(|_| random.next_number()).collect::<Vec<_>>()
+    };
+    assert_eq!(numbers(42), numbers(42));
+    assert_ne!(numbers(42), numbers(43));
+    assert!(numbers(0).iter().all(|n| (0.0..1.0).contains(n)));
+  }
+}
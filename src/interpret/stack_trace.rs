@@ -0,0 +1,98 @@
+use std::fmt::{Display, Formatter};
+use std::rc::Rc;
+
+/// Traces longer than this only show the frames at both ends, so a stack overflow doesn't print
+/// a thousand lines.
+const MAX_SHOWN_FRAMES: usize = 20;
+
+/// A Lox call in progress: the function that was called and the line it was called from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallFrame {
+  pub function: Rc<str>,
+  pub call_line: usize,
+}
+
+impl CallFrame {
+  pub fn new(function: Rc<str>, call_line: usize) -> CallFrame {
+    CallFrame {
+      function,
+      call_line,
+    }
+  }
+}
+
+/// Where a runtime error happened, innermost frame first: the line each function was running
+/// when the error stopped it, ending with the top-level script.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StackTrace {
+  lines: Vec<(Option<Rc<str>>, usize)>,
+}
+
+impl StackTrace {
+  /// Trace for an error at `error_line` with `calls` in progress, outermost call first. Every
+  /// call was made from the line its caller was running.
+  pub fn new(calls: &[CallFrame], error_line: usize) -> StackTrace {
+    let mut lines = vec![];
+    let mut line = error_line;
+    for frame in calls.iter().rev() {
+      lines.push((Some(frame.function.clone()), line));
+      line = frame.call_line;
+    }
+    lines.push((None, line));
+    StackTrace { lines }
+  }
+}
+
+impl Display for StackTrace {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    let hidden = self.lines.len().saturating_sub(MAX_SHOWN_FRAMES);
+    for (i, (function, line)) in self.lines.iter().enumerate() {
+      if hidden > 0 && i == MAX_SHOWN_FRAMES / 2 {
+        writeln!(f, "... {hidden} more frames")?;
+      }
+      if hidden > 0 && (MAX_SHOWN_FRAMES / 2..MAX_SHOWN_FRAMES / 2 + hidden).contains(&i) {
+        continue;
+      }
+      match function {
+        Some(function) => write!(f, "[line {line}] in {function}()")?,
+        None => write!(f, "[line {line}] in script")?,
+      }
+      if i + 1 < self.lines.len() {
+        writeln!(f)?;
+      }
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn frame(function: &str, call_line: usize) -> CallFrame {
+    CallFrame::new(function.into(), call_line)
+  }
+
+  #[test]
+  fn each_frame_shows_the_line_its_function_was_running() {
+    let trace = StackTrace::new(&[frame("outer", 9), frame("inner", 5)], 2);
+    assert_eq!(
+      trace.to_string(),
+      "[line 2] in inner()\n[line 5] in outer()\n[line 9] in script"
+    );
+  }
+
+  #[test]
+  fn errors_outside_functions_only_show_the_script() {
+    assert_eq!(StackTrace::new(&[], 3).to_string(), "[line 3] in script");
+  }
+
+  #[test]
+  fn long_traces_hide_the_middle_frames() {
+    let calls = vec![frame("f", 1); 100];
+    let trace = StackTrace::new(&calls, 1).to_string();
+    assert_eq!(trace.lines().count(), MAX_SHOWN_FRAMES + 1);
+    assert!(trace.contains("... 81 more frames"));
+    assert!(trace.ends_with("[line 1] in script"));
+  }
+}
@@ -0,0 +1,107 @@
+use crate::interpret::hook::InterpreterHook;
+use crate::interpret::value::Value;
+use crate::parse::expr::Expr;
+use crate::parse::pretty_print::ToLoxSource;
+use crate::parse::stmt::Stmt;
+use std::io::Write;
+
+/// Logs every statement the interpreter runs and every expression it evaluates, with its line
+/// and value. Literals are skipped, since their value is already in the source.
+pub struct Tracer<W: Write> {
+  out: W,
+}
+
+impl<W: Write> Tracer<W> {
+  pub fn new(out: W) -> Tracer<W> {
+    Tracer { out }
+  }
+
+  fn log(&mut self, line: usize, message: &str) {
+    // Tracing is best effort: a trace that can't be written shouldn't stop the program.
+    let _ = writeln!(self.out, "[line {line}] {message}");
+  }
+}
+
+impl<W: Write> InterpreterHook for Tracer<W> {
+  fn before_stmt(&mut self, stmt: &Stmt) {
+    let source = stmt.to_lox_source();
+    // Blocks and functions only show their first line.
+    let first_line = source.lines().next().unwrap_or_default();
+    self.log(stmt.line(), &format!("exec {first_line}"));
+  }
+
+  fn after_expr(&mut self, expr: &Expr, value: &Value) {
+    if expr.is_literal() {
+      return;
+    }
+    self.log(
+      expr.line(),
+      &format!("eval {} => {value}", expr.to_lox_source()),
+    );
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::interpret::interpreter::Interpreter;
+  use crate::parse::parser::LoxParser;
+  use crate::scan::scanner::Scanner;
+  use std::cell::RefCell;
+  use std::io::Cursor;
+  use std::rc::Rc;
+
+  /// Output shared with the test, since the interpreter owns the tracer.
+  #[derive(Clone, Default)]
+  struct SharedOutput(Rc<RefCell<Vec<u8>>>);
+
+  impl Write for SharedOutput {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+      self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+      Ok(())
+    }
+  }
+
+  fn trace(src: &str) -> String {
+    let mut cursor = Cursor::new(src);
+    let tokens = Scanner::new(&mut cursor).scan_tokens().0;
+    let stmts = LoxParser::new(tokens).parse().unwrap();
+    let output = SharedOutput::default();
+    let mut interpreter = Interpreter::new(vec![]).with_hook(Tracer::new(output.clone()));
+    interpreter.interpret_stmts(&stmts).unwrap();
+    let traced = output.0.borrow().clone();
+    String::from_utf8(traced).unwrap()
+  }
+
+  #[test]
+  fn statements_and_expressions_are_logged_with_their_values() {
+    let res = trace("var a = 1;\nprint a + 2;");
+    assert_eq!(
+      res,
+      concat!(
+        "[line 1] exec var a = 1;\n",
+        "[line 2] exec print a + 2;\n",
+        "[line 2] eval a => 1\n",
+        "[line 2] eval a + 2 => 3\n",
+      )
+    );
+  }
+
+  #[test]
+  fn blocks_only_show_their_first_line() {
+    let res = trace("fun f() {\n  return 1;\n}\nf();");
+    assert_eq!(
+      res,
+      concat!(
+        "[line 1] exec fun f() {\n",
+        "[line 4] exec f();\n",
+        "[line 4] eval f => <fn f>\n",
+        "[line 2] exec return 1;\n",
+        "[line 4] eval f() => 1\n",
+      )
+    );
+  }
+}
@@ -1,38 +1,144 @@
-use crate::parse::stmt::Stmt;
+use crate::interpret::error::RuntimeError;
+use crate::interpret::interpreter::Interpreter;
+use crate::interpret::lox_class::LoxInstance;
 use crate::interpret::lox_fn::{Callable, LoxFn};
+use crate::parse::stmt::Stmt;
+use crate::scan::number_format::format_number;
+use std::fmt::{Display, Formatter};
+use std::io::Write;
+use std::rc::Rc;
+use thiserror::Error;
 
+/// Cloning a value is cheap: strings are shared and functions, classes and instances are handles,
+/// so reading a variable never copies text or function bodies.
 #[derive(Debug, PartialEq, Clone)]
 pub enum Value {
   Number(f64),
   Nil,
   Boolean(bool),
-  String(String),
+  String(Rc<str>),
   Callable(Callable),
+  Instance(LoxInstance),
 }
 
+/// A value couldn't be converted to the Rust type a host asked for.
+#[derive(Error, Debug, PartialEq)]
+#[error("Expected a {expected}, got a {got}")]
+pub struct ValueConversionError {
+  pub expected: &'static str,
+  pub got: &'static str,
+}
 
 impl Value {
   pub fn fun(name: String, params: Vec<String>, body: Vec<Stmt>, context_id: usize) -> Value {
     Value::Callable(Callable::Lox(LoxFn::new(name, params, body, context_id)))
   }
 
-  pub fn to_string(&self) -> String {
-    match self {
-      Value::Number(value) => format!("{value}"),
-      Value::Nil => "nil".to_string(),
-      Value::Boolean(value) => format!("{value}"),
-      Value::String(value) => value.to_string(),
-      Value::Callable(fun) => fun.to_string(),
-    }
-  }
-
   pub fn type_name(&self) -> &'static str {
     match self {
       Value::Number(_) => "Number",
       Value::Nil => "nil",
       Value::Boolean(_) => "Boolean",
       Value::String(_) => "String",
+      Value::Callable(Callable::Class(_)) => "class",
       Value::Callable(_) => "function",
+      Value::Instance(_) => "instance",
+    }
+  }
+
+  /// How `print` and `str` show the value. Instances with a `toString` method show what it
+  /// returns; every other value shows as its `Display`.
+  pub fn display<W: Write>(
+    &self,
+    interpreter: &mut Interpreter<W>,
+    line: usize,
+  ) -> Result<String, RuntimeError> {
+    let Value::Instance(instance) = self else {
+      return Ok(self.to_string());
+    };
+    let Some(to_string) = instance.find_method("toString").cloned() else {
+      return Ok(self.to_string());
+    };
+    let to_string = interpreter.bind_method(&to_string, instance.clone())?;
+    Ok(to_string.call(interpreter, vec![], line)?.to_string())
+  }
+}
+
+impl Display for Value {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Value::Number(value) => f.write_str(&format_number(*value)),
+      Value::Nil => write!(f, "nil"),
+      Value::Boolean(value) => write!(f, "{value}"),
+      Value::String(value) => write!(f, "{value}"),
+      Value::Callable(fun) => write!(f, "{fun}"),
+      Value::Instance(instance) => write!(f, "{instance}"),
+    }
+  }
+}
+
+impl From<f64> for Value {
+  fn from(value: f64) -> Self {
+    Value::Number(value)
+  }
+}
+
+impl From<bool> for Value {
+  fn from(value: bool) -> Self {
+    Value::Boolean(value)
+  }
+}
+
+impl From<&str> for Value {
+  fn from(value: &str) -> Self {
+    Value::String(value.into())
+  }
+}
+
+impl From<String> for Value {
+  fn from(value: String) -> Self {
+    Value::String(value.into())
+  }
+}
+
+impl TryFrom<Value> for f64 {
+  type Error = ValueConversionError;
+
+  fn try_from(value: Value) -> Result<Self, Self::Error> {
+    match value {
+      Value::Number(n) => Ok(n),
+      other => Err(ValueConversionError {
+        expected: "Number",
+        got: other.type_name(),
+      }),
+    }
+  }
+}
+
+impl TryFrom<Value> for bool {
+  type Error = ValueConversionError;
+
+  fn try_from(value: Value) -> Result<Self, Self::Error> {
+    match value {
+      Value::Boolean(b) => Ok(b),
+      other => Err(ValueConversionError {
+        expected: "Boolean",
+        got: other.type_name(),
+      }),
+    }
+  }
+}
+
+impl TryFrom<Value> for String {
+  type Error = ValueConversionError;
+
+  fn try_from(value: Value) -> Result<Self, Self::Error> {
+    match value {
+      Value::String(s) => Ok(s.to_string()),
+      other => Err(ValueConversionError {
+        expected: "String",
+        got: other.type_name(),
+      }),
     }
   }
 }
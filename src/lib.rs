@@ -0,0 +1,23 @@
+//! A tree-walking Lox interpreter. `Lox::run` runs a whole program; the modules expose each stage
+//! (scanner, parser, resolver, interpreter) for embedders that need more control.
+
+pub mod compile;
+pub mod golden;
+pub mod interpret;
+pub mod lint;
+pub mod lox;
+pub mod optimize;
+pub mod parse;
+pub mod project;
+pub mod repl;
+pub mod report;
+pub mod resolve;
+pub mod scan;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+pub use interpret::interpreter::Interpreter;
+pub use interpret::value::Value;
+pub use lox::{Lox, LoxError};
+pub use parse::parser::LoxParser;
+pub use scan::scanner::Scanner;
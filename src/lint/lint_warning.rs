@@ -0,0 +1,14 @@
+use thiserror::Error;
+
+/// Problems that don't stop a program from running, but are most likely mistakes.
+#[derive(Error, Debug, PartialEq)]
+pub enum LintWarning {
+  #[error("[line {0}]: Local variable '{1}' is never read.")]
+  UnusedVariable(usize, String),
+  #[error("[line {0}]: Variable '{1}' is read before it is declared.")]
+  ReadBeforeDeclaration(usize, String),
+  #[error("[line {0}]: Code after '{1}' is never executed.")]
+  UnreachableCode(usize, String),
+  #[error("[line {0}]: Condition is always {1}.")]
+  ConstantCondition(usize, bool),
+}
@@ -0,0 +1,412 @@
+use crate::lint::lint_warning::LintWarning;
+use crate::parse::expr::{Expr, ExprKind};
+use crate::parse::stmt::{Stmt, StmtKind};
+use std::collections::HashSet;
+use std::slice;
+
+struct Local {
+  name: String,
+  line: usize,
+  used: bool,
+}
+
+struct Scope {
+  locals: Vec<Local>,
+  /// Names declared further down in this scope that haven't been reached yet.
+  declared_later: HashSet<String>,
+  /// Names read from functions that run later, before their declaration was reached.
+  used_later: HashSet<String>,
+  function_depth: usize,
+}
+
+impl Scope {
+  fn new(stmts: &[Stmt], function_depth: usize) -> Scope {
+    let declared_later = stmts
+      .iter()
+      .filter_map(|stmt| match &stmt.kind {
+        StmtKind::Var(name, ..)
+        | StmtKind::Function { name, .. }
+        | StmtKind::Class { name, .. } => Some(name.clone()),
+        _ => None,
+      })
+      .collect();
+
+    Scope {
+      locals: vec![],
+      declared_later,
+      used_later: HashSet::new(),
+      function_depth,
+    }
+  }
+}
+
+/// Walks the AST looking for likely mistakes: unused locals, variables read before their
+/// declaration, code after a `return`, `break`, `continue` or `throw`, and conditions that never change.
+/// Globals are never reported as unused, since other code (like the REPL) may still read them.
+#[derive(Default)]
+pub struct Linter {
+  scopes: Vec<Scope>,
+  function_depth: usize,
+  warnings: Vec<LintWarning>,
+}
+
+impl Linter {
+  pub fn new() -> Linter {
+    Linter::default()
+  }
+
+  pub fn lint(mut self, stmts: &[Stmt]) -> Vec<LintWarning> {
+    self.scopes.push(Scope::new(stmts, 0));
+    self.lint_stmts(stmts);
+    self.scopes.pop();
+    self.warnings
+  }
+
+  fn lint_stmts(&mut self, stmts: &[Stmt]) {
+    for (i, stmt) in stmts.iter().enumerate() {
+      self.lint_stmt(stmt);
+
+      let jump = match &stmt.kind {
+        StmtKind::Return(_, line) => Some((*line, "return")),
+        StmtKind::Break(line) => Some((*line, "break")),
+        StmtKind::Continue(line) => Some((*line, "continue")),
+        StmtKind::Throw(_, line) => Some((*line, "throw")),
+        _ => None,
+      };
+      if let Some((line, keyword)) = jump.filter(|_| i + 1 < stmts.len()) {
+        self
+          .warnings
+          .push(LintWarning::UnreachableCode(line, keyword.to_string()));
+      }
+    }
+  }
+
+  fn lint_block(&mut self, stmts: &[Stmt]) {
+    self.begin_scope(stmts);
+    self.lint_stmts(stmts);
+    self.end_scope();
+  }
+
+  fn lint_stmt(&mut self, stmt: &Stmt) {
+    match &stmt.kind {
+      StmtKind::Expr(expr)
+      | StmtKind::Print(expr, _)
+      | StmtKind::Return(expr, _)
+      | StmtKind::Throw(expr, _) => self.lint_expr(expr),
+      StmtKind::Var(name, value, line) => {
+        if let Some(value) = value {
+          self.lint_expr(value);
+        }
+        self.declare(name, *line, false);
+      }
+      StmtKind::ScopeBlock(stmts) => self.lint_block(stmts),
+      StmtKind::If {
+        condition,
+        then,
+        els,
+        line,
+      } => {
+        self.lint_expr(condition);
+        if let Some(value) = constant_truthiness(condition) {
+          self
+            .warnings
+            .push(LintWarning::ConstantCondition(*line, value));
+        }
+        self.lint_stmt(then);
+        if let Some(els) = els {
+          self.lint_stmt(els);
+        }
+      }
+      StmtKind::While {
+        condition,
+        body,
+        line,
+      } => {
+        self.lint_loop_condition(condition, *line);
+        self.lint_stmt(body);
+      }
+      // `do { ... } while (false);` runs its body once on purpose, so its condition isn't checked.
+      StmtKind::DoWhile {
+        body, condition, ..
+      } => {
+        self.lint_stmt(body);
+        self.lint_expr(condition);
+      }
+      StmtKind::For {
+        initializer,
+        condition,
+        increment,
+        body,
+        line,
+      } => {
+        let initializer = initializer
+          .as_deref()
+          .map(slice::from_ref)
+          .unwrap_or_default();
+        self.begin_scope(initializer);
+        self.lint_stmts(initializer);
+        if let Some(condition) = condition {
+          self.lint_loop_condition(condition, *line);
+        }
+        self.lint_stmt(body);
+        if let Some(increment) = increment {
+          self.lint_expr(increment);
+        }
+        self.end_scope();
+      }
+      StmtKind::Assert {
+        condition, message, ..
+      } => {
+        self.lint_expr(condition);
+        if let Some(message) = message {
+          self.lint_expr(message);
+        }
+      }
+      StmtKind::Try {
+        body,
+        name,
+        handler,
+        ..
+      } => {
+        self.lint_block(body);
+        // Like parameters, the error is never reported as unused.
+        self.begin_scope(handler);
+        self.declare(name, 0, true);
+        self.lint_stmts(handler);
+        self.end_scope();
+      }
+      StmtKind::Break(_) | StmtKind::Continue(_) | StmtKind::Import(..) => {}
+      StmtKind::Function { name, params, body } => {
+        // Functions are values too, but an unused one is not what this lint is about.
+        self.declare(name, 0, true);
+        self.lint_function(params, body);
+      }
+      StmtKind::Class {
+        name,
+        superclass,
+        methods,
+        class_methods,
+        getters,
+      } => {
+        if let Some(superclass) = superclass {
+          self.lint_expr(superclass);
+        }
+        self.declare(name, 0, true);
+        for method in methods.iter().chain(class_methods).chain(getters) {
+          if let StmtKind::Function { params, body, .. } = &method.kind {
+            self.lint_function(params, body);
+          }
+        }
+      }
+    }
+  }
+
+  /// Parameters are never reported as unused: callers have to pass them anyway.
+  fn lint_function(&mut self, params: &[String], body: &[Stmt]) {
+    self.function_depth += 1;
+    self.begin_scope(body);
+    for param in params {
+      self.declare(param, 0, true);
+    }
+    self.lint_stmts(body);
+    self.end_scope();
+    self.function_depth -= 1;
+  }
+
+  fn lint_expr(&mut self, expr: &Expr) {
+    match &expr.kind {
+      ExprKind::LiteralNumber { .. }
+      | ExprKind::LiteralBool { .. }
+      | ExprKind::LiteralString { .. }
+      | ExprKind::LiteralNil
+      | ExprKind::This { .. }
+      | ExprKind::Super { .. } => {}
+      ExprKind::Binary { left, right, .. } | ExprKind::Logical { left, right, .. } => {
+        self.lint_expr(left);
+        self.lint_expr(right);
+      }
+      ExprKind::Unary { right, .. } => self.lint_expr(right),
+      ExprKind::Group { expression } => self.lint_expr(expression),
+      ExprKind::Call { callee, args, .. } => {
+        self.lint_expr(callee);
+        for arg in args {
+          self.lint_expr(arg);
+        }
+      }
+      ExprKind::Variable { name, line } | ExprKind::Increment { name, line, .. } => {
+        self.read(name, *line)
+      }
+      // Assigning doesn't count as using a variable.
+      ExprKind::Assign { value, .. } => self.lint_expr(value),
+      ExprKind::Get { object, .. } => self.lint_expr(object),
+      ExprKind::Set { object, value, .. } => {
+        self.lint_expr(object);
+        self.lint_expr(value);
+      }
+    }
+  }
+
+  /// Reading a variable declared later in the same scope is a mistake, unless the read happens in
+  /// a function, which may run after the declaration.
+  fn read(&mut self, name: &str, line: usize) {
+    let function_depth = self.function_depth;
+    for scope in self.scopes.iter_mut().rev() {
+      if let Some(local) = scope.locals.iter_mut().rev().find(|l| l.name == name) {
+        local.used = true;
+        return;
+      }
+      if scope.declared_later.contains(name) {
+        if scope.function_depth == function_depth {
+          self
+            .warnings
+            .push(LintWarning::ReadBeforeDeclaration(line, name.to_string()));
+        } else {
+          scope.used_later.insert(name.to_string());
+        }
+        return;
+      }
+    }
+  }
+
+  fn declare(&mut self, name: &str, line: usize, used: bool) {
+    let Some(scope) = self.scopes.last_mut() else {
+      return;
+    };
+    scope.declared_later.remove(name);
+    let used = used || scope.used_later.remove(name);
+    scope.locals.push(Local {
+      name: name.to_string(),
+      line,
+      used,
+    });
+  }
+
+  /// `while (true)` is the usual way to write an endless loop, so only conditions that are always
+  /// false are reported.
+  fn lint_loop_condition(&mut self, condition: &Expr, line: usize) {
+    self.lint_expr(condition);
+    if constant_truthiness(condition) == Some(false) {
+      self
+        .warnings
+        .push(LintWarning::ConstantCondition(line, false));
+    }
+  }
+
+  fn begin_scope(&mut self, stmts: &[Stmt]) {
+    self.scopes.push(Scope::new(stmts, self.function_depth));
+  }
+
+  fn end_scope(&mut self) {
+    if let Some(scope) = self.scopes.pop() {
+      for local in scope.locals.into_iter().filter(|l| !l.used) {
+        self
+          .warnings
+          .push(LintWarning::UnusedVariable(local.line, local.name));
+      }
+    }
+  }
+}
+
+/// Whether a condition is always truthy or always falsey, when it doesn't depend on anything.
+pub(crate) fn constant_truthiness(expr: &Expr) -> Option<bool> {
+  match &expr.kind {
+    ExprKind::LiteralBool { value } => Some(*value),
+    ExprKind::LiteralNil => Some(false),
+    ExprKind::LiteralNumber { .. } | ExprKind::LiteralString { .. } => Some(true),
+    ExprKind::Group { expression } => constant_truthiness(expression),
+    _ => None,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parse::parser::LoxParser;
+  use crate::scan::scanner::Scanner;
+  use std::io::Cursor;
+
+  fn lint_program(src: &str) -> Vec<LintWarning> {
+    let mut cursor = Cursor::new(src);
+    let tokens = Scanner::new(&mut cursor).scan_tokens().0;
+    let stmts = LoxParser::new(tokens).parse().unwrap();
+    Linter::new().lint(&stmts)
+  }
+
+  #[test]
+  fn clean_program_has_no_warnings() {
+    let src = "var a = 1; fun f(x) { var y = x + a; return y; } { var b = f(1); print b; }";
+    assert_eq!(lint_program(src), vec![]);
+  }
+
+  #[test]
+  fn unused_locals_are_reported() {
+    let src = "{\n  var a = 1;\n  var b = 2;\n  b = 3;\n}\nfun f(unused) {\n  var c;\n}";
+    assert_eq!(
+      lint_program(src),
+      vec![
+        LintWarning::UnusedVariable(2, "a".to_string()),
+        LintWarning::UnusedVariable(3, "b".to_string()),
+        LintWarning::UnusedVariable(7, "c".to_string()),
+      ]
+    );
+  }
+
+  #[test]
+  fn unused_globals_are_not_reported() {
+    assert_eq!(lint_program("var a = 1;"), vec![]);
+  }
+
+  #[test]
+  fn shadowed_locals_are_tracked_separately() {
+    let src = "{\n  var a = 1;\n  {\n    var a = 2;\n    print a;\n  }\n}";
+    assert_eq!(
+      lint_program(src),
+      vec![LintWarning::UnusedVariable(2, "a".to_string())]
+    );
+  }
+
+  #[test]
+  fn reads_before_the_declaration_are_reported() {
+    let src = "print a;\nvar a = 1;\n{\n  print b;\n  var b = 2;\n  print b;\n}";
+    assert_eq!(
+      lint_program(src),
+      vec![
+        LintWarning::ReadBeforeDeclaration(1, "a".to_string()),
+        LintWarning::ReadBeforeDeclaration(4, "b".to_string()),
+      ]
+    );
+  }
+
+  #[test]
+  fn functions_can_read_variables_declared_after_them() {
+    let src = "fun f() { return g() + later; } fun g() { return 1; } var later = 1;
+      { fun h() { return local; } var local = 1; print h(); }";
+    assert_eq!(lint_program(src), vec![]);
+  }
+
+  #[test]
+  fn code_after_a_jump_is_reported() {
+    let src = "fun f() {\n  return 1;\n  print 2;\n}\nwhile (x) {\n  break;\n  print 3;\n}\n{\n  throw 1;\n  print 4;\n}";
+    assert_eq!(
+      lint_program(src),
+      vec![
+        LintWarning::UnreachableCode(2, "return".to_string()),
+        LintWarning::UnreachableCode(6, "break".to_string()),
+        LintWarning::UnreachableCode(10, "throw".to_string()),
+      ]
+    );
+  }
+
+  #[test]
+  fn constant_conditions_are_reported() {
+    let src = "if (true) print 1;\nif ((nil)) print 2;\nwhile (false) print 3;\nwhile (true) break;\nfor (;;) break;";
+    assert_eq!(
+      lint_program(src),
+      vec![
+        LintWarning::ConstantCondition(1, true),
+        LintWarning::ConstantCondition(2, false),
+        LintWarning::ConstantCondition(3, false),
+      ]
+    );
+  }
+}
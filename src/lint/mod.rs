@@ -0,0 +1,2 @@
+pub mod lint_warning;
+pub mod linter;
@@ -0,0 +1,145 @@
+use crate::interpret::error::RuntimeError;
+use crate::interpret::interpreter::Interpreter;
+use crate::interpret::value::Value;
+use crate::optimize::dead_code::DeadCodeEliminator;
+use crate::parse::parse_error::ParseError;
+use crate::parse::parser::LoxParser;
+use crate::resolve::resolve_error::ResolveError;
+use crate::resolve::resolver::Resolver;
+use crate::scan::scan_error::ScanError;
+use crate::scan::scanner::Scanner;
+use std::io::{Cursor, Write};
+use thiserror::Error;
+
+/// Everything that can stop a program, grouped by the stage that found it. Every stage except
+/// the interpreter reports all the errors it finds.
+#[derive(Error, Debug, PartialEq)]
+pub enum LoxError {
+  #[error("{}", join(.0))]
+  Scan(Vec<ScanError>),
+  #[error("{}", join(.0))]
+  Parse(Vec<ParseError>),
+  #[error("{}", join(.0))]
+  Resolve(Vec<ResolveError>),
+  #[error(transparent)]
+  Runtime(#[from] RuntimeError),
+}
+
+fn join<E: ToString>(errors: &[E]) -> String {
+  errors
+    .iter()
+    .map(|e| e.to_string())
+    .collect::<Vec<_>>()
+    .join("\n")
+}
+
+/// Entry point for embedding the interpreter.
+pub struct Lox;
+
+impl Lox {
+  /// Runs a program, writing whatever it prints to `out`.
+  pub fn run<W: Write>(source: &str, out: &mut W) -> Result<(), LoxError> {
+    Lox::run_in(source, &mut Interpreter::new(out))
+  }
+
+  /// Runs a program in an interpreter the caller configured, e.g. with its own input or clock.
+  pub fn run_in<W: Write>(source: &str, interpreter: &mut Interpreter<W>) -> Result<(), LoxError> {
+    let mut cursor = Cursor::new(source);
+    let (tokens, errors) = Scanner::new(&mut cursor).scan_tokens();
+    if !errors.is_empty() {
+      return Err(LoxError::Scan(errors));
+    }
+
+    let stmts = LoxParser::new(tokens).parse().map_err(LoxError::Parse)?;
+    Resolver::new().resolve(&stmts).map_err(LoxError::Resolve)?;
+    let (stmts, _) = DeadCodeEliminator::new().eliminate(stmts);
+
+    interpreter.interpret_stmts(&stmts)?;
+    Ok(())
+  }
+
+  /// Evaluates a single expression, without the `;` a statement would need, and returns its value.
+  pub fn evaluate(source: &str) -> Result<Value, LoxError> {
+    Lox::evaluate_in(source, &mut Interpreter::new(std::io::sink()))
+  }
+
+  /// Evaluates a single expression in an interpreter the caller configured, so it can use the
+  /// globals and functions that earlier programs defined.
+  pub fn evaluate_in<W: Write>(
+    source: &str,
+    interpreter: &mut Interpreter<W>,
+  ) -> Result<Value, LoxError> {
+    let mut cursor = Cursor::new(source);
+    let (tokens, errors) = Scanner::new(&mut cursor).scan_tokens();
+    if !errors.is_empty() {
+      return Err(LoxError::Scan(errors));
+    }
+
+    let expr = LoxParser::new(tokens)
+      .parse_expression()
+      .map_err(|e| LoxError::Parse(vec![e]))?;
+    Ok(interpreter.interpret_expr(&expr)?)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn run(src: &str) -> Result<String, LoxError> {
+    let mut out: Vec<u8> = vec![];
+    Lox::run(src, &mut out)?;
+    Ok(String::from_utf8(out).unwrap())
+  }
+
+  #[test]
+  fn runs_a_program() {
+    let res = run("fun twice(x) { return x * 2; } print twice(21);");
+    assert_eq!(res.unwrap(), "42\n");
+  }
+
+  #[test]
+  fn scan_errors_stop_the_program() {
+    assert!(matches!(run("print 1; @"), Err(LoxError::Scan(_))));
+  }
+
+  #[test]
+  fn parse_errors_are_all_reported() {
+    let Err(LoxError::Parse(errors)) = run("var = 1;\nprint ;") else {
+      panic!("expected parse errors");
+    };
+    assert_eq!(errors.len(), 2);
+  }
+
+  #[test]
+  fn resolve_errors_stop_the_program() {
+    assert!(matches!(run("return 1;"), Err(LoxError::Resolve(_))));
+  }
+
+  #[test]
+  fn runtime_errors_keep_what_was_printed() {
+    let mut out: Vec<u8> = vec![];
+    let res = Lox::run("print 1; print -\"a\";", &mut out);
+    assert!(matches!(res, Err(LoxError::Runtime(_))));
+    assert_eq!(String::from_utf8(out).unwrap(), "1\n");
+  }
+
+  #[test]
+  fn evaluates_an_expression() {
+    assert_eq!(Lox::evaluate("1 + 2 * 3"), Ok(Value::Number(7.0)));
+  }
+
+  #[test]
+  fn evaluation_rejects_statements_and_trailing_tokens() {
+    assert!(matches!(Lox::evaluate("1 + 2;"), Err(LoxError::Parse(_))));
+    assert!(matches!(Lox::evaluate("print 1"), Err(LoxError::Parse(_))));
+  }
+
+  #[test]
+  fn evaluation_sees_what_earlier_programs_defined() {
+    let mut interpreter = Interpreter::new(vec![]);
+    Lox::run_in("fun square(x) { return x * x; }", &mut interpreter).unwrap();
+    let res = Lox::evaluate_in("square(4) + 1", &mut interpreter);
+    assert_eq!(res, Ok(Value::Number(17.0)));
+  }
+}
@@ -1,20 +1,47 @@
-mod interpret;
-mod parse;
-mod scan;
-
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::fmt::{Display, Formatter};
 use std::fs::File;
+use std::io::{BufWriter, Cursor, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
 use std::process::ExitCode;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
 
-use crate::interpret::error::RuntimeError;
-use crate::interpret::interpreter::Interpreter;
-use crate::parse::expr::Expr;
-use crate::parse::parse_error::ParseError;
-use crate::parse::print_ast::PrintAst;
-use crate::parse::stmt::Stmt;
-use crate::scan::token::Token;
-use parse::parser::LoxParser;
-use scan::scanner::Scanner;
+use codecrafters_interpreter::compile::compile_error::CompileError;
+use codecrafters_interpreter::compile::compiler::Compiler;
+use codecrafters_interpreter::compile::disassembler::disassemble;
+use codecrafters_interpreter::golden::runner::GoldenRunner;
+use codecrafters_interpreter::interpret::clock::FixedClock;
+use codecrafters_interpreter::interpret::coverage::Coverage;
+use codecrafters_interpreter::interpret::error::RuntimeError;
+use codecrafters_interpreter::interpret::execution_limits::ExecutionLimits;
+use codecrafters_interpreter::interpret::file_system::OsFileSystem;
+use codecrafters_interpreter::interpret::interpreter::Interpreter;
+use codecrafters_interpreter::interpret::profiler::Profiler;
+use codecrafters_interpreter::interpret::tracer::Tracer;
+use codecrafters_interpreter::lint::linter::Linter;
+use codecrafters_interpreter::optimize::dead_code::DeadCodeEliminator;
+use codecrafters_interpreter::parse::ast_cache::{cache_path, read_cache, write_cache};
+use codecrafters_interpreter::parse::ast_format::{
+  deserialize_ast, serialize_ast, AstFormat, AstFormatError,
+};
+use codecrafters_interpreter::parse::dot::DotGraph;
+use codecrafters_interpreter::parse::expr::Expr;
+use codecrafters_interpreter::parse::parse_error::ParseError;
+use codecrafters_interpreter::parse::parser::LoxParser;
+use codecrafters_interpreter::parse::pretty_print::PrettyPrinter;
+use codecrafters_interpreter::parse::print_ast::PrintAst;
+use codecrafters_interpreter::parse::stmt::Stmt;
+use codecrafters_interpreter::project::manifest::{Manifest, ManifestError};
+use codecrafters_interpreter::repl::session::Repl;
+use codecrafters_interpreter::report::diagnostic::Diagnostic;
+use codecrafters_interpreter::report::error_codes::find_error_code;
+use codecrafters_interpreter::resolve::resolve_error::ResolveError;
+use codecrafters_interpreter::resolve::resolver::Resolver;
+use codecrafters_interpreter::scan::scan_error::ScanError;
+use codecrafters_interpreter::scan::scanner::Scanner;
+use codecrafters_interpreter::scan::token::Token;
+use codecrafters_interpreter::scan::token_format::{format_tokens, TokenFormat};
 
 #[derive(Debug, Parser)] // requires `derive` feature
 #[command(name = "git")]
@@ -22,31 +49,294 @@ use scan::scanner::Scanner;
 struct Cli {
   #[command(subcommand)]
   command: Commands,
+  /// How errors are printed to stderr
+  #[arg(long, value_enum, global = true, default_value = "text")]
+  error_format: ErrorFormat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum ErrorFormat {
+  /// Messages for people, like `[line 1]: Undefined variable: x`
+  Text,
+  /// One JSON object per error and line, with its code, message, line, column and span
+  Json,
 }
 
 #[derive(Debug, Subcommand)]
 enum Commands {
   #[command(arg_required_else_help = true)]
-  Tokenize { file_path: String },
+  Tokenize {
+    file_path: String,
+    #[arg(long, value_enum, default_value = "text")]
+    format: TokenFormat,
+  },
 
   #[command(arg_required_else_help = true)]
-  Parse { file_path: String },
+  Parse {
+    file_path: String,
+    #[arg(long, value_enum, default_value = "text")]
+    format: ParseFormat,
+  },
+  #[command(arg_required_else_help = true)]
+  Evaluate {
+    /// Path of the program, or `-` to read it from stdin
+    #[arg(required_unless_present = "eval")]
+    file_path: Option<String>,
+    /// Evaluates this code instead of a file
+    #[arg(long, short, conflicts_with = "file_path")]
+    eval: Option<String>,
+  },
+  /// Runs a program. Without a file, runs the `main` of the `lox.toml` in the working directory or
+  /// its parents
+  Run {
+    /// Path of the program, or `-` to read it from stdin
+    file_path: Option<String>,
+    /// Runs this code instead of a file
+    #[arg(long, short, conflicts_with = "file_path")]
+    eval: Option<String>,
+    /// Reads the file as an AST serialized with `ast` instead of Lox source
+    #[arg(long, value_enum)]
+    ast: Option<AstFormat>,
+    /// Prints how long scanning, parsing, resolving and interpreting took to stderr
+    #[arg(long)]
+    time: bool,
+    #[command(flatten)]
+    options: Box<RunOptions>,
+  },
+  /// Serializes the AST of a program
+  #[command(arg_required_else_help = true)]
+  Ast {
+    file_path: String,
+    #[arg(long, value_enum, default_value = "json")]
+    format: AstFormat,
+    /// Writes the AST to this file instead of stdout
+    #[arg(long, short)]
+    output: Option<String>,
+  },
+  /// Prints a program back as formatted Lox source. Comments are not kept
+  #[command(arg_required_else_help = true)]
+  Fmt {
+    file_path: String,
+    /// Rewrites the file instead of printing it
+    #[arg(long, short)]
+    write: bool,
+  },
+  /// Scans, parses and resolves a program without running it, and reports likely mistakes. Exits
+  /// with 65 if the program has errors
   #[command(arg_required_else_help = true)]
-  Evaluate { file_path: String },
+  Check {
+    /// Path of the program, or `-` to read it from stdin
+    file_path: String,
+    /// Also rejects reading a variable declared without a value before assigning to it, like
+    /// `run --strict`
+    #[arg(long)]
+    strict: bool,
+  },
+  /// Compiles a program to bytecode and prints the instructions
   #[command(arg_required_else_help = true)]
-  Run { file_path: String },
+  Disassemble { file_path: String },
+  /// Starts an interactive session. `:save FILE` and `:load FILE` write and replay its statements
+  Repl,
+  /// Runs every `.lox` file in a directory and compares it against its golden files
+  Test {
+    #[arg(default_value = "examples")]
+    dir: String,
+    /// Rewrites the golden files of failing programs with their current output
+    #[arg(long)]
+    bless: bool,
+  },
+  /// Describes an error code, like `E1002`, with examples
+  #[command(arg_required_else_help = true)]
+  Explain { code: String },
+}
+
+/// How `run` loads and executes a program.
+#[derive(Debug, Args)]
+struct RunOptions {
+  /// Saves the parsed program next to its file, with `.loxc` added to its name. Later runs read it
+  /// instead of parsing the file again, for as long as the file doesn't change
+  #[arg(long, conflicts_with = "ast")]
+  emit_cache: bool,
+  /// Writes what the program prints to this file instead of stdout
+  #[arg(long, short)]
+  output: Option<String>,
+  /// Discards what the program prints, so only its exit status and `--time` are reported. For
+  /// timing programs without their output getting in the way
+  #[arg(long, short, conflicts_with = "output")]
+  quiet: bool,
+  /// Writes runtime errors to this file instead of stderr
+  #[arg(long)]
+  error_output: Option<String>,
+  /// Stops the program after evaluating this many statements and expressions
+  #[arg(long)]
+  max_steps: Option<u64>,
+  /// Stops the program after running for this many milliseconds
+  #[arg(long, value_name = "MS")]
+  timeout: Option<u64>,
+  /// Logs every statement run and every expression evaluated, with its line and value, to stderr
+  #[arg(long)]
+  trace: bool,
+  /// Writes the trace to this file instead of stderr
+  #[arg(long, requires = "trace")]
+  trace_output: Option<String>,
+  /// Prints how many times each function was called and how long it took to stderr
+  #[arg(long)]
+  profile: bool,
+  /// Writes how many times each line ran to this file
+  #[arg(long, value_name = "FILE")]
+  coverage: Option<String>,
+  /// Format of the coverage file
+  #[arg(long, value_enum, default_value = "lcov", requires = "coverage")]
+  coverage_format: CoverageFormat,
+  /// Lets the program read and write files with `readFile()`, `writeFile()` and `appendFile()`
+  #[arg(long)]
+  allow_fs: bool,
+  /// Lets `+` join strings with any other value, as in `"count: " + 3`
+  #[arg(long)]
+  lenient_concat: bool,
+  /// Prints warnings about the code that can never run, which is removed before running, to
+  /// stderr
+  #[arg(long)]
+  warnings: bool,
+  /// Rejects programs that read a variable declared without a value, like `var a;`, before
+  /// assigning to it
+  #[arg(long)]
+  strict: bool,
+  /// Makes `clock()` always return these seconds instead of the current time, so runs are
+  /// reproducible
+  #[arg(long, value_name = "SECONDS")]
+  fixed_clock: Option<f64>,
+  /// Seeds `random()`, so it gives the same numbers on every run
+  #[arg(long)]
+  seed: Option<u64>,
+  /// Runs the program again every time its file or a file it imports changes
+  #[arg(long)]
+  watch: bool,
+  /// Arguments for the program, read with `argc()` and `argv(i)`
+  #[arg(last = true)]
+  args: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum ParseFormat {
+  /// The AST as s-expressions on one line, as codecrafters expects
+  Text,
+  /// A Graphviz graph of the AST, to draw with `dot -Tsvg`
+  Dot,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum CoverageFormat {
+  /// An lcov tracefile, for coverage tools
+  Lcov,
+  /// The source with the hits of each line in front of it
+  Annotated,
+}
+
+/// Where the program of a command comes from.
+enum Source {
+  File(String),
+  Stdin,
+  Inline(String),
+}
+
+impl Source {
+  /// Without either, the program is read from stdin.
+  fn from_args(file_path: Option<String>, eval: Option<String>) -> Source {
+    match (file_path, eval) {
+      (_, Some(code)) => Source::Inline(code),
+      (Some(path), None) if path == "-" => Source::Stdin,
+      (Some(path), None) => Source::File(path),
+      (None, None) => Source::Stdin,
+    }
+  }
+
+  /// Name of the source in reports, like coverage files.
+  fn name(&self) -> String {
+    match self {
+      Source::File(path) => path.clone(),
+      Source::Stdin => "<stdin>".to_string(),
+      Source::Inline(_) => "<eval>".to_string(),
+    }
+  }
+
+  fn open(self) -> std::io::Result<Box<dyn Read>> {
+    Ok(match self {
+      Source::File(path) => Box::new(File::open(path)?),
+      Source::Stdin => Box::new(std::io::stdin()),
+      Source::Inline(code) => Box::new(Cursor::new(code)),
+    })
+  }
+}
+
+/// Lox calls recurse on the Rust stack, and debug builds take around 15KB per call. This leaves
+/// room for the interpreter to reach its call depth limit and report a stack overflow instead of
+/// crashing.
+const INTERPRETER_STACK_SIZE: usize = 64 * 1024 * 1024;
+
+/// How often `run --watch` checks whether the files of the program changed.
+const WATCH_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How long each phase of `run` took, for `--time`.
+#[derive(Default)]
+struct Timings {
+  phases: Vec<(&'static str, Duration)>,
+}
+
+impl Timings {
+  fn measure<T>(&mut self, phase: &'static str, action: impl FnOnce() -> T) -> T {
+    let start = Instant::now();
+    let result = action();
+    self.phases.push((phase, start.elapsed()));
+    result
+  }
+}
+
+impl Display for Timings {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    for (phase, duration) in &self.phases {
+      writeln!(f, "{phase:<10}{duration:>12.3?}")?;
+    }
+    Ok(())
+  }
 }
 
 struct ReportError {
   exit_code: u8,
-  errors: Vec<String>,
+  errors: Vec<ReportedError>,
 }
 
-impl From<Vec<String>> for ReportError {
-  fn from(value: Vec<String>) -> Self {
-    ReportError {
-      errors: value,
-      exit_code: 65,
+/// An error for the user: its text, and the diagnostic that `--error-format json` prints instead.
+struct ReportedError {
+  text: String,
+  diagnostic: Diagnostic,
+}
+
+impl ReportedError {
+  fn of<E: Display>(error: &E) -> ReportedError
+  where
+    for<'a> Diagnostic: From<&'a E>,
+  {
+    ReportedError {
+      text: error.to_string(),
+      diagnostic: Diagnostic::from(error),
+    }
+  }
+
+  fn print(&self, format: ErrorFormat, mut output: impl Write) -> std::io::Result<()> {
+    match format {
+      ErrorFormat::Text => writeln!(output, "{}", self.text),
+      ErrorFormat::Json => writeln!(output, "{}", self.diagnostic.to_json()),
+    }
+  }
+}
+
+/// Errors that don't come from the source, like files that can't be written.
+impl From<String> for ReportedError {
+  fn from(text: String) -> Self {
+    ReportedError {
+      diagnostic: Diagnostic::new("Error", text.clone()),
+      text,
     }
   }
 }
@@ -54,34 +344,94 @@ impl From<Vec<String>> for ReportError {
 impl From<std::io::Error> for ReportError {
   fn from(_value: std::io::Error) -> Self {
     ReportError {
-      errors: vec!["Cannot read source file".to_string()],
+      errors: vec!["Cannot read source file".to_string().into()],
       exit_code: 1,
     }
   }
 }
 
-impl From<ParseError> for ReportError {
-  fn from(value: ParseError) -> Self {
+impl From<ScanError> for ReportError {
+  fn from(value: ScanError) -> Self {
+    ReportError {
+      errors: vec![ReportedError::of(&value)],
+      exit_code: 1,
+    }
+  }
+}
+
+impl From<Vec<ScanError>> for ReportError {
+  fn from(value: Vec<ScanError>) -> Self {
     ReportError {
       exit_code: 65,
-      errors: vec![value.to_string()],
+      errors: value.iter().map(ReportedError::of).collect(),
+    }
+  }
+}
+
+impl From<Vec<ParseError>> for ReportError {
+  fn from(value: Vec<ParseError>) -> Self {
+    ReportError {
+      exit_code: 65,
+      errors: value.iter().map(ReportedError::of).collect(),
+    }
+  }
+}
+
+impl From<Vec<ResolveError>> for ReportError {
+  fn from(value: Vec<ResolveError>) -> Self {
+    ReportError {
+      exit_code: 65,
+      errors: value.iter().map(ReportedError::of).collect(),
+    }
+  }
+}
+
+impl From<Vec<CompileError>> for ReportError {
+  fn from(value: Vec<CompileError>) -> Self {
+    ReportError {
+      exit_code: 65,
+      errors: value.iter().map(ReportedError::of).collect(),
+    }
+  }
+}
+
+impl From<AstFormatError> for ReportError {
+  fn from(value: AstFormatError) -> Self {
+    ReportError {
+      exit_code: 65,
+      errors: vec![value.to_string().into()],
+    }
+  }
+}
+
+impl From<ManifestError> for ReportError {
+  fn from(value: ManifestError) -> Self {
+    ReportError {
+      exit_code: 1,
+      errors: vec![value.to_string().into()],
     }
   }
 }
 
 impl From<RuntimeError> for ReportError {
   fn from(value: RuntimeError) -> Self {
+    if let RuntimeError::Exit(code) = value {
+      return ReportError {
+        exit_code: code,
+        errors: vec![],
+      };
+    }
     ReportError {
       exit_code: 70,
-      errors: vec![value.to_string()],
+      errors: vec![ReportedError::of(&value)],
     }
   }
 }
 
-fn scan(input: &mut File) -> Result<Vec<Token>, ReportError> {
+fn scan(input: &mut impl Read) -> Result<Vec<Token>, ReportError> {
   let scanner = Scanner::new(input);
-  let (tokens, errors) = scanner.scan_tokens();
-  if errors.len() > 0 {
+  let (tokens, errors) = scanner.try_scan_tokens()?;
+  if !errors.is_empty() {
     Err(errors)?
   } else {
     Ok(tokens)
@@ -89,87 +439,453 @@ fn scan(input: &mut File) -> Result<Vec<Token>, ReportError> {
 }
 
 fn exec_main(cli: Cli) -> Result<String, ReportError> {
+  let error_format = cli.error_format;
   match cli.command {
-    Commands::Tokenize { file_path } => {
+    Commands::Tokenize { file_path, format } => {
       let mut input = File::open(&file_path)?;
-      let (tokens, errors) = Scanner::new(&mut input).scan_tokens();
-      let strings = tokens.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+      let (tokens, errors) = Scanner::new(&mut input).try_scan_tokens()?;
+      let formatted = format_tokens(&tokens, format);
 
-      if errors.len() > 0 {
-        for error in errors {
-          eprintln!("{error}")
-        }
-        for line in strings {
-          println!("{line}")
-        }
-        return Err(ReportError {
-          errors: vec![],
-          exit_code: 65,
-        });
+      if !errors.is_empty() {
+        println!("{formatted}");
+        return Err(errors.into());
       }
 
-      let strings = tokens.iter().map(|t| t.to_string()).collect::<Vec<_>>();
-      Ok(strings.join("\n"))
+      Ok(formatted)
     }
-    Commands::Parse { file_path } => {
+    Commands::Parse { file_path, format } => {
       let mut input = File::open(&file_path)?;
       let tokens = scan(&mut input)?;
       let ast = parse(tokens)?;
-      let printer = PrintAst::new();
 
-      Ok(printer.print_stmts(&ast))
+      Ok(match format {
+        ParseFormat::Text => PrintAst::new().print_stmts(&ast),
+        ParseFormat::Dot => DotGraph::new().print_stmts(&ast),
+      })
     }
-    Commands::Evaluate { file_path } => {
-      let mut input = File::open(&file_path)?;
+    Commands::Evaluate { file_path, eval } => {
+      let mut input = Source::from_args(file_path, eval).open()?;
       let tokens = scan(&mut input)?;
-      let vec = parse(tokens)?;
-      let ast = vec.first().unwrap();
-      let expr = match ast {
-        Stmt::Expr(expr) => expr,
-        Stmt::Print(expr) => expr,
-        _ => panic!("Evaluate can only evaluate a single expression"),
+      let expr = LoxParser::new(tokens)
+        .parse_expression()
+        .map_err(|e| vec![e])?;
+
+      Ok(interpret_expr(&expr)?)
+    }
+    Commands::Run {
+      file_path,
+      eval,
+      ast,
+      time,
+      options,
+    } => {
+      let manifest = match (&file_path, &eval) {
+        (None, None) => Some(Manifest::discover(&std::env::current_dir()?)?),
+        _ => None,
       };
+      let file_path = file_path.or_else(|| {
+        let main = manifest.as_ref().map(|manifest| &manifest.main);
+        main.map(|main| main.display().to_string())
+      });
+      let source_roots = manifest
+        .map(|manifest| manifest.sources)
+        .unwrap_or_default();
+      let source = Source::from_args(file_path, eval);
+      if options.watch {
+        let Source::File(path) = source else {
+          return Err(ReportError {
+            exit_code: 1,
+            errors: vec!["--watch needs a program file".to_string().into()],
+          });
+        };
+        return watch(path, error_format, |source, imported| {
+          let roots = source_roots.clone();
+          run(source, roots, ast, time, &options, error_format, imported)
+        });
+      }
+      run(
+        source,
+        source_roots,
+        ast,
+        time,
+        &options,
+        error_format,
+        &mut vec![],
+      )
+    }
+    Commands::Ast {
+      file_path,
+      format,
+      output,
+    } => {
+      let mut input = File::open(&file_path)?;
+      let tokens = scan(&mut input)?;
+      let ast = serialize_ast(&parse(tokens)?, format)?;
+
+      match output {
+        Some(path) => {
+          std::fs::write(path, ast).map_err(|_| ReportError {
+            exit_code: 1,
+            errors: vec!["Cannot write output file".to_string().into()],
+          })?;
+          Ok(String::new())
+        }
+        None => Ok(ast),
+      }
+    }
+    Commands::Fmt { file_path, write } => {
+      let mut input = File::open(&file_path)?;
+      let tokens = scan(&mut input)?;
+      let formatted = PrettyPrinter::new().print_stmts(&parse(tokens)?);
+
+      if write {
+        std::fs::write(&file_path, formatted + "\n").map_err(|_| ReportError {
+          exit_code: 1,
+          errors: vec!["Cannot write output file".to_string().into()],
+        })?;
+        Ok(String::new())
+      } else {
+        Ok(formatted)
+      }
+    }
+    Commands::Check { file_path, strict } => {
+      let mut input = Source::from_args(Some(file_path), None).open()?;
+      let tokens = scan(&mut input)?;
+      let stmts = parse(tokens)?;
+      Resolver::new().with_strict(strict).resolve(&stmts)?;
 
-      Ok(interpret_expr(expr)?)
+      let warnings = Linter::new().lint(&stmts);
+      if warnings.is_empty() {
+        return Ok("No problems found".to_string());
+      }
+      let lines = warnings
+        .iter()
+        .map(|warning| format!("warning: {warning}"))
+        .collect::<Vec<_>>();
+      Ok(lines.join("\n"))
     }
-    Commands::Run { file_path } => {
+    Commands::Disassemble { file_path } => {
       let mut input = File::open(&file_path)?;
       let tokens = scan(&mut input)?;
       let stmts = parse(tokens)?;
-      Ok(interpret(stmts)?)
+      Resolver::new().resolve(&stmts)?;
+      Ok(disassemble(&Compiler::new().compile(&stmts)?))
     }
+    Commands::Repl => {
+      let mut repl = Repl::new(std::io::stdin().lock(), std::io::stdout());
+      match repl.run()? {
+        0 => Ok(String::new()),
+        code => Err(ReportError {
+          exit_code: code,
+          errors: vec![],
+        }),
+      }
+    }
+    Commands::Test { dir, bless } => run_golden_tests(&dir, bless),
+    Commands::Explain { code } => match find_error_code(&code) {
+      Some(error) => Ok(format!(
+        "{}: {}\n\n{}",
+        error.code, error.name, error.explanation
+      )),
+      None => Err(ReportError {
+        exit_code: 1,
+        errors: vec![format!("Unknown error code {code}").into()],
+      }),
+    },
+  }
+}
+
+fn run_golden_tests(dir: &str, bless: bool) -> Result<String, ReportError> {
+  let runner = GoldenRunner::new(std::env::current_exe()?);
+  let results = runner.run_dir(Path::new(dir)).map_err(|e| ReportError {
+    exit_code: 1,
+    errors: vec![format!("Cannot run tests in {dir}: {e}").into()],
+  })?;
+
+  if bless {
+    let failing = results.iter().filter(|r| !r.passed()).collect::<Vec<_>>();
+    for result in &failing {
+      runner.bless_case(&result.path).map_err(|e| ReportError {
+        exit_code: 1,
+        errors: vec![format!("Cannot bless {}: {e}", result.path.display()).into()],
+      })?;
+    }
+    return Ok(format!(
+      "{} passed, {} blessed",
+      results.len() - failing.len(),
+      failing.len()
+    ));
+  }
+
+  let mut errors = vec![];
+  for result in results.iter().filter(|r| !r.passed()) {
+    errors.push(format!("FAIL {}", result.path.display()).into());
+    errors.extend(result.failures.iter().cloned().map(ReportedError::from));
+  }
+
+  let failed = results.iter().filter(|r| !r.passed()).count();
+  let summary = format!("{} passed, {} failed", results.len() - failed, failed);
+
+  if failed > 0 {
+    errors.push(summary.into());
+    Err(ReportError {
+      exit_code: 1,
+      errors,
+    })
+  } else {
+    Ok(summary)
   }
 }
 
 fn interpret_expr(expr: &Expr) -> Result<String, RuntimeError> {
   let stdout = std::io::stdout().lock();
   let mut interpreter = Interpreter::new(stdout);
-  interpreter.interpret_expr(&expr).map(|v| v.to_string())
+  interpreter.interpret_expr(expr).map(|v| v.to_string())
 }
 
-fn interpret(stmts: Vec<Stmt>) -> Result<String, RuntimeError> {
-  let stdout = std::io::stdout().lock();
-  let mut interpreter = Interpreter::new(stdout);
-  interpreter.interpret_stmts(&stmts)?;
-  Ok(String::new())
+/// Scans, parses, resolves and runs a program for `run`. `imported` gets the files the program
+/// imported, even if it failed.
+fn run(
+  source: Source,
+  source_roots: Vec<PathBuf>,
+  ast: Option<AstFormat>,
+  time: bool,
+  options: &RunOptions,
+  error_format: ErrorFormat,
+  imported: &mut Vec<PathBuf>,
+) -> Result<String, ReportError> {
+  let source_name = source.name();
+  let script = match &source {
+    Source::File(path) => Some(path.clone()),
+    _ => None,
+  };
+  let mut text = String::new();
+  source.open()?.read_to_string(&mut text)?;
+
+  if options.emit_cache && script.is_none() {
+    return Err(ReportError {
+      exit_code: 1,
+      errors: vec!["--emit-cache needs a program file".to_string().into()],
+    });
+  }
+
+  let mut timings = Timings::default();
+  let cache = script
+    .as_ref()
+    .filter(|_| ast.is_none())
+    .map(|script| cache_path(Path::new(script)));
+  let cached = cache
+    .as_deref()
+    .and_then(|cache| timings.measure("cache", || read_cache(cache, &text)));
+  let from_cache = cached.is_some();
+  let stmts = match (cached, ast) {
+    (Some(stmts), _) => stmts,
+    (None, Some(format)) => timings.measure("parse", || deserialize_ast(&text, format))?,
+    (None, None) => {
+      let tokens = timings.measure("scan", || scan(&mut text.as_bytes()))?;
+      timings.measure("parse", || parse(tokens))?
+    }
+  };
+  timings.measure("resolve", || {
+    Resolver::new().with_strict(options.strict).resolve(&stmts)
+  })?;
+  if let Some(cache) = cache.filter(|_| options.emit_cache && !from_cache) {
+    write_cache(&cache, &text, &stmts).map_err(|e| ReportError {
+      exit_code: 1,
+      errors: vec![format!("Cannot write cache file {}: {e}", cache.display()).into()],
+    })?;
+  }
+  // Coverage starts from the whole program, so the code removed shows up as never run.
+  let coverage = options.coverage.is_some().then(|| Coverage::new(&stmts));
+  let (stmts, warnings) = DeadCodeEliminator::new().eliminate(stmts);
+  if options.warnings {
+    for warning in warnings {
+      eprintln!("warning: {warning}");
+    }
+  }
+  let result = timings.measure("interpret", || {
+    interpret(
+      &stmts,
+      script,
+      source_roots,
+      options,
+      coverage.clone(),
+      error_format,
+      imported,
+    )
+  });
+  if time {
+    eprint!("{timings}");
+  }
+
+  if let (Some(coverage), Some(path)) = (coverage, &options.coverage) {
+    let report = match options.coverage_format {
+      CoverageFormat::Lcov => coverage.to_lcov(&source_name),
+      CoverageFormat::Annotated => coverage.annotate(&text),
+    };
+    std::fs::write(path, report).map_err(|_| ReportError {
+      exit_code: 1,
+      errors: vec![format!("Cannot write output file {path}").into()],
+    })?;
+  }
+  result
+}
+
+/// Runs the program at `path` again every time it or one of the files it imported changes, until
+/// the process is stopped. Errors are printed instead of ending the watch.
+fn watch(
+  path: String,
+  error_format: ErrorFormat,
+  mut run: impl FnMut(Source, &mut Vec<PathBuf>) -> Result<String, ReportError>,
+) -> Result<String, ReportError> {
+  loop {
+    if std::io::stdout().is_terminal() {
+      print!("\x1b[2J\x1b[H");
+    }
+    let mut files = vec![PathBuf::from(&path)];
+    if let Err(report) = run(Source::File(path.clone()), &mut files) {
+      for error in report.errors {
+        let _ = error.print(error_format, std::io::stderr());
+      }
+    }
+    let _ = std::io::stdout().flush();
+    eprintln!("Watching {path} for changes");
+
+    let modified = modification_times(&files);
+    while modification_times(&files) == modified {
+      thread::sleep(WATCH_INTERVAL);
+    }
+  }
 }
 
-fn parse(tokens: Vec<Token>) -> Result<Vec<Stmt>, ParseError> {
+fn modification_times(files: &[PathBuf]) -> Vec<Option<SystemTime>> {
+  files
+    .iter()
+    .map(|file| {
+      std::fs::metadata(file)
+        .and_then(|meta| meta.modified())
+        .ok()
+    })
+    .collect()
+}
+
+/// Runtime errors are written to the error output of the interpreter, in `error_format`. Imports
+/// are relative to `script`, the file of the program, or else to one of the `source_roots`. The
+/// profile is printed once the program stops, even if it failed. `imported` gets the files the
+/// program imported.
+fn interpret(
+  stmts: &[Stmt],
+  script: Option<String>,
+  source_roots: Vec<PathBuf>,
+  options: &RunOptions,
+  coverage: Option<Coverage>,
+  error_format: ErrorFormat,
+  imported: &mut Vec<PathBuf>,
+) -> Result<String, ReportError> {
+  let stdout: Box<dyn Write> = match &options.output {
+    _ if options.quiet => Box::new(std::io::sink()),
+    Some(path) => Box::new(create_output_file(path)?),
+    None => Box::new(std::io::stdout().lock()),
+  };
+  let stderr: Box<dyn Write> = match &options.error_output {
+    Some(path) => Box::new(create_output_file(path)?),
+    None => Box::new(std::io::stderr()),
+  };
+  let limits = ExecutionLimits {
+    max_steps: options.max_steps,
+    timeout: options.timeout.map(Duration::from_millis),
+  };
+
+  let mut interpreter = Interpreter::new(stdout)
+    .with_input(std::io::stdin().lock())
+    .with_error_output(stderr)
+    .with_limits(limits)
+    .with_lenient_concat(options.lenient_concat)
+    .with_args(options.args.clone())
+    .with_source_roots(source_roots);
+  if options.allow_fs {
+    interpreter = interpreter.with_file_system(OsFileSystem);
+  }
+  if let Some(time) = options.fixed_clock {
+    interpreter = interpreter.with_clock(FixedClock::new(time));
+  }
+  if let Some(seed) = options.seed {
+    interpreter = interpreter.with_random_seed(seed);
+  }
+  if let Some(script) = script {
+    interpreter = interpreter.with_script_path(script);
+  }
+  match (options.trace, &options.trace_output) {
+    (true, Some(path)) => {
+      let file = BufWriter::new(create_output_file(path)?);
+      interpreter = interpreter.with_hook(Tracer::new(file));
+    }
+    (true, None) => interpreter = interpreter.with_hook(Tracer::new(std::io::stderr())),
+    (false, _) => {}
+  }
+  let profiler = Profiler::new();
+  if options.profile {
+    interpreter = interpreter.with_hook(profiler.clone());
+  }
+  if let Some(coverage) = coverage {
+    interpreter = interpreter.with_hook(coverage);
+  }
+
+  let result = interpreter.interpret_stmts(stmts);
+  imported.extend(interpreter.imported_files());
+  if options.profile {
+    eprint!("{}", profiler.report());
+  }
+  match result {
+    Ok(_) | Err(RuntimeError::Exit(0)) => Ok(String::new()),
+    Err(RuntimeError::Exit(code)) => Err(ReportError {
+      exit_code: code,
+      errors: vec![],
+    }),
+    Err(error) => {
+      let output = interpreter.native_context().error_output();
+      ReportedError::of(&error).print(error_format, output)?;
+      Err(ReportError {
+        exit_code: 70,
+        errors: vec![],
+      })
+    }
+  }
+}
+
+fn create_output_file(path: &str) -> Result<File, ReportError> {
+  File::create(path).map_err(|_| ReportError {
+    exit_code: 1,
+    errors: vec![format!("Cannot write output file {path}").into()],
+  })
+}
+
+fn parse(tokens: Vec<Token>) -> Result<Vec<Stmt>, Vec<ParseError>> {
   let parser = LoxParser::new(tokens);
   parser.parse()
 }
 
 fn main() -> ExitCode {
   let args = Cli::parse();
+  let error_format = args.error_format;
+  let quiet = matches!(&args.command, Commands::Run { options, .. } if options.quiet);
+
+  let result = thread::Builder::new()
+    .stack_size(INTERPRETER_STACK_SIZE)
+    .spawn(move || exec_main(args))
+    .expect("Cannot start the interpreter thread")
+    .join()
+    .unwrap_or_else(|panic| std::panic::resume_unwind(panic));
 
-  match exec_main(args) {
+  match result {
+    Ok(_) if quiet => ExitCode::from(0),
     Ok(msg) => {
       println!("{}", msg);
       ExitCode::from(0)
     }
     Err(report) => {
-      for msg in report.errors {
-        eprintln!("{}", msg)
+      for error in report.errors {
+        let _ = error.print(error_format, std::io::stderr());
       }
       ExitCode::from(report.exit_code)
     }
@@ -0,0 +1,260 @@
+use crate::lint::lint_warning::LintWarning;
+use crate::lint::linter::constant_truthiness;
+use crate::parse::stmt::{Stmt, StmtKind};
+
+/// Removes code that can never run before the program runs: statements after a `return`,
+/// `break`, `continue` or `throw`, the branch an `if` with a constant condition never takes and
+/// `while (false)` loops. An `if` whose condition is always true is replaced by its branch, so a
+/// `return` in it also ends the block. Every removal comes with a warning, since dead code is
+/// most likely a mistake.
+#[derive(Default)]
+pub struct DeadCodeEliminator {
+  warnings: Vec<LintWarning>,
+}
+
+impl DeadCodeEliminator {
+  pub fn new() -> DeadCodeEliminator {
+    DeadCodeEliminator::default()
+  }
+
+  pub fn eliminate(mut self, stmts: Vec<Stmt>) -> (Vec<Stmt>, Vec<LintWarning>) {
+    let stmts = self.stmts(stmts);
+    (stmts, self.warnings)
+  }
+
+  fn stmts(&mut self, stmts: Vec<Stmt>) -> Vec<Stmt> {
+    let mut kept = vec![];
+    let mut stmts = stmts.into_iter();
+    while let Some(stmt) = stmts.next() {
+      let Some(stmt) = self.stmt(stmt) else {
+        continue;
+      };
+      let jump = always_jumps(&stmt);
+      kept.push(stmt);
+      if let Some((line, keyword)) = jump {
+        if stmts.len() > 0 {
+          let keyword = keyword.to_string();
+          self
+            .warnings
+            .push(LintWarning::UnreachableCode(line, keyword));
+        }
+        break;
+      }
+    }
+    kept
+  }
+
+  /// The statement without its dead code, or nothing when none of it can run.
+  fn stmt(&mut self, stmt: Stmt) -> Option<Stmt> {
+    let Stmt { id, span, kind } = stmt;
+    let kind = match kind {
+      StmtKind::ScopeBlock(stmts) => StmtKind::ScopeBlock(self.stmts(stmts)),
+      StmtKind::If {
+        condition,
+        then,
+        els,
+        line,
+      } => match constant_truthiness(&condition) {
+        Some(value) => {
+          self
+            .warnings
+            .push(LintWarning::ConstantCondition(line, value));
+          let taken = if value {
+            Some(*then)
+          } else {
+            els.map(|els| *els)
+          };
+          return taken.and_then(|stmt| self.stmt(stmt));
+        }
+        None => StmtKind::If {
+          condition,
+          then: self.branch(*then),
+          els: els.map(|els| self.branch(*els)),
+          line,
+        },
+      },
+      StmtKind::While {
+        condition, line, ..
+      } if constant_truthiness(&condition) == Some(false) => {
+        self
+          .warnings
+          .push(LintWarning::ConstantCondition(line, false));
+        return None;
+      }
+      StmtKind::While {
+        condition,
+        body,
+        line,
+      } => StmtKind::While {
+        condition,
+        body: self.branch(*body),
+        line,
+      },
+      StmtKind::DoWhile {
+        body,
+        condition,
+        line,
+      } => StmtKind::DoWhile {
+        body: self.branch(*body),
+        condition,
+        line,
+      },
+      StmtKind::For {
+        initializer,
+        condition: Some(condition),
+        line,
+        ..
+      } if constant_truthiness(&condition) == Some(false) => {
+        self
+          .warnings
+          .push(LintWarning::ConstantCondition(line, false));
+        // The initializer still runs, in a scope of its own.
+        let initializer = initializer.and_then(|initializer| self.stmt(*initializer))?;
+        StmtKind::ScopeBlock(vec![initializer])
+      }
+      StmtKind::For {
+        initializer,
+        condition,
+        increment,
+        body,
+        line,
+      } => StmtKind::For {
+        initializer: initializer.and_then(|initializer| self.stmt(*initializer).map(Box::new)),
+        condition,
+        increment,
+        body: self.branch(*body),
+        line,
+      },
+      StmtKind::Try {
+        body,
+        name,
+        handler,
+        line,
+      } => StmtKind::Try {
+        body: self.stmts(body),
+        name,
+        handler: self.stmts(handler),
+        line,
+      },
+      StmtKind::Function { name, params, body } => StmtKind::Function {
+        name,
+        params,
+        body: self.stmts(body),
+      },
+      StmtKind::Class {
+        name,
+        superclass,
+        methods,
+        class_methods,
+        getters,
+      } => StmtKind::Class {
+        name,
+        superclass,
+        methods: self.stmts(methods),
+        class_methods: self.stmts(class_methods),
+        getters: self.stmts(getters),
+      },
+      kind => kind,
+    };
+    Some(Stmt::new(id, span, kind))
+  }
+
+  /// Branches and loop bodies can't be removed, so a body with nothing left becomes `{}`.
+  fn branch(&mut self, stmt: Stmt) -> Box<Stmt> {
+    let (id, span) = (stmt.id, stmt.span);
+    let stmt = self.stmt(stmt);
+    Box::new(stmt.unwrap_or_else(|| Stmt::new(id, span, StmtKind::ScopeBlock(vec![]))))
+  }
+}
+
+/// The line and keyword of the jump that always ends a statement, if it has one. `try` never
+/// does, since its handler may catch what the body throws.
+fn always_jumps(stmt: &Stmt) -> Option<(usize, &'static str)> {
+  match &stmt.kind {
+    StmtKind::Return(_, line) => Some((*line, "return")),
+    StmtKind::Break(line) => Some((*line, "break")),
+    StmtKind::Continue(line) => Some((*line, "continue")),
+    StmtKind::Throw(_, line) => Some((*line, "throw")),
+    StmtKind::ScopeBlock(stmts) => stmts.last().and_then(always_jumps),
+    StmtKind::If {
+      then,
+      els: Some(els),
+      ..
+    } => always_jumps(els).and(always_jumps(then)),
+    _ => None,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parse::parser::LoxParser;
+  use crate::parse::pretty_print::ToLoxSource;
+  use crate::scan::scanner::Scanner;
+  use std::io::Cursor;
+
+  fn eliminate(src: &str) -> (String, Vec<LintWarning>) {
+    let mut cursor = Cursor::new(src);
+    let tokens = Scanner::new(&mut cursor).scan_tokens().0;
+    let stmts = LoxParser::new(tokens).parse().unwrap();
+    let (stmts, warnings) = DeadCodeEliminator::new().eliminate(stmts);
+    (stmts.to_lox_source(), warnings)
+  }
+
+  #[test]
+  fn statements_after_a_jump_are_removed() {
+    let (source, warnings) = eliminate("fun f() {\n  return 1;\n  print 2;\n  print 3;\n}");
+    assert_eq!(source, "fun f() {\n  return 1;\n}");
+    assert_eq!(
+      warnings,
+      [LintWarning::UnreachableCode(2, "return".to_string())]
+    );
+  }
+
+  #[test]
+  fn constant_conditions_keep_only_the_branch_that_runs() {
+    let (source, warnings) = eliminate("if (false) print 1; else print 2;\nwhile (nil) print 3;");
+    assert_eq!(source, "print 2;");
+    assert_eq!(
+      warnings,
+      [
+        LintWarning::ConstantCondition(1, false),
+        LintWarning::ConstantCondition(2, false)
+      ]
+    );
+  }
+
+  #[test]
+  fn for_loops_that_never_run_keep_their_initializer() {
+    let (source, warnings) = eliminate("for (var i = f(); false;) print i;\nfor (; nil;) print 1;");
+    assert_eq!(source, "{\n  var i = f();\n}");
+    assert_eq!(
+      warnings,
+      [
+        LintWarning::ConstantCondition(1, false),
+        LintWarning::ConstantCondition(2, false)
+      ]
+    );
+  }
+
+  #[test]
+  fn a_return_in_an_always_taken_branch_ends_the_block() {
+    let (source, warnings) = eliminate("fun f() {\n  if (true) return 1;\n  print 2;\n}");
+    assert_eq!(source, "fun f() {\n  return 1;\n}");
+    assert_eq!(
+      warnings,
+      [
+        LintWarning::ConstantCondition(2, true),
+        LintWarning::UnreachableCode(2, "return".to_string())
+      ]
+    );
+  }
+
+  #[test]
+  fn jumps_in_only_one_branch_or_in_try_keep_what_follows() {
+    let src = "fun f(a) {\n  if (a) return 1;\n  try {\n    throw 2;\n  } catch (e) {\n    print e;\n  }\n  print 3;\n}";
+    let (source, warnings) = eliminate(src);
+    assert_eq!(source, src);
+    assert_eq!(warnings, []);
+  }
+}
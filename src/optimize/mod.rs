@@ -0,0 +1 @@
+pub mod dead_code;
@@ -0,0 +1,126 @@
+use crate::parse::stmt::Stmt;
+use std::path::{Path, PathBuf};
+
+/// Start of every cache file.
+const MAGIC: &[u8; 4] = b"LOXC";
+/// Changes whenever the AST does, so caches written by other versions are parsed again instead of
+/// read wrong. A test checks it changes along with the AST.
+const FORMAT_VERSION: u32 = 3;
+/// The magic, the format version and the hash of the source.
+const HEADER_LENGTH: usize = 16;
+
+/// Where the cache of the program at `source` goes: next to it, with `.loxc` added to its name.
+/// Adding it instead of replacing the extension keeps the cache of `a.loxc` from being `a.loxc`.
+pub fn cache_path(source: &Path) -> PathBuf {
+  let mut path = source.as_os_str().to_owned();
+  path.push(".loxc");
+  PathBuf::from(path)
+}
+
+/// Saves the AST of `source` to `path`, in CBOR after a header with a hash of the source.
+pub fn write_cache(path: &Path, source: &str, stmts: &[Stmt]) -> std::io::Result<()> {
+  let mut bytes = MAGIC.to_vec();
+  bytes.extend(FORMAT_VERSION.to_le_bytes());
+  bytes.extend(hash(source.as_bytes()).to_le_bytes());
+  ciborium::into_writer(stmts, &mut bytes).map_err(std::io::Error::other)?;
+  std::fs::write(path, bytes)
+}
+
+/// The AST saved in the cache at `path`, if there is one for exactly `source`. Missing, stale and
+/// unreadable caches give `None`, so the program is parsed instead.
+pub fn read_cache(path: &Path, source: &str) -> Option<Vec<Stmt>> {
+  let bytes = std::fs::read(path).ok()?;
+  let (header, ast) = bytes.split_at_checked(HEADER_LENGTH)?;
+  let mut expected = MAGIC.to_vec();
+  expected.extend(FORMAT_VERSION.to_le_bytes());
+  expected.extend(hash(source.as_bytes()).to_le_bytes());
+  if header != expected {
+    return None;
+  }
+  ciborium::from_reader(ast).ok()
+}
+
+/// 64 bit FNV-1a, which gives the same hash on every platform and Rust version, unlike
+/// [`std::hash::DefaultHasher`].
+fn hash(bytes: &[u8]) -> u64 {
+  bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+    (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parse::parser::LoxParser;
+  use crate::scan::scanner::Scanner;
+
+  const SOURCE: &str = "class A < B { init(x) { this.x = -x; } }\nvar a = A(1.5);\nprint a.x;";
+
+  fn parse(src: &str) -> Vec<Stmt> {
+    let mut bytes = src.as_bytes();
+    let tokens = Scanner::new(&mut bytes).scan_tokens().0;
+    LoxParser::new(tokens).parse().unwrap()
+  }
+
+  fn cache_file(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("lox-cache-{name}-{}.loxc", std::process::id()))
+  }
+
+  #[test]
+  fn cache_round_trip() {
+    let path = cache_file("round-trip");
+    write_cache(&path, SOURCE, &parse(SOURCE)).unwrap();
+    let cached = read_cache(&path, SOURCE);
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(cached, Some(parse(SOURCE)));
+  }
+
+  #[test]
+  fn caches_of_other_sources_are_not_read() {
+    let path = cache_file("stale");
+    write_cache(&path, SOURCE, &parse(SOURCE)).unwrap();
+    let stale = read_cache(&path, "print 1;");
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(stale, None);
+    assert_eq!(read_cache(&path, SOURCE), None);
+  }
+
+  /// Uses every kind of statement and expression, so changing any of them changes how it's saved.
+  const EVERY_NODE: &str = "
+    import \"lib.lox\";
+    var a = nil;
+    { print -a + (1 * 2) and true or !false; }
+    if (a) a = \"s\"; else a = 3;
+    while (a < 1) { break; }
+    do { continue; } while (a);
+    for (var i = 0; i < 1; i++) --a;
+    assert(a, \"a\");
+    try { throw a; } catch (e) {}
+    fun f(x) { return f(x.y); }
+    class A < B { init() { this.x = super.init; } class make() {} size { return 1; } }
+  ";
+
+  #[test]
+  fn the_format_version_changes_with_the_ast() {
+    let mut bytes = vec![];
+    ciborium::into_writer(&parse(EVERY_NODE), &mut bytes).unwrap();
+    let fingerprint = hash(&bytes);
+    // When the AST changes, caches written before can't be read right: bump FORMAT_VERSION and
+    // update the fingerprint.
+    assert_eq!((FORMAT_VERSION, fingerprint), (3, 0x16a3c96804397fbf));
+  }
+
+  #[test]
+  fn a_loxc_program_has_a_different_cache() {
+    assert_eq!(
+      cache_path(Path::new("dir/main.loxc")),
+      Path::new("dir/main.loxc.loxc")
+    );
+  }
+
+  #[test]
+  fn hashes_are_stable() {
+    assert_eq!(hash(b""), 0xcbf29ce484222325);
+    assert_eq!(hash(b"a"), 0xaf63dc4c8601ec8c);
+  }
+}
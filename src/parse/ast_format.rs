@@ -0,0 +1,114 @@
+use crate::parse::sexp::{from_sexp, to_sexp};
+use crate::parse::stmt::Stmt;
+use clap::ValueEnum;
+use thiserror::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum AstFormat {
+  Json,
+  Ron,
+  Sexp,
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum AstFormatError {
+  #[error("Cannot serialize AST: {0}")]
+  Serialize(String),
+  #[error("Cannot read AST: {0}")]
+  Deserialize(String),
+}
+
+pub fn serialize_ast(stmts: &[Stmt], format: AstFormat) -> Result<String, AstFormatError> {
+  let serialize_error = |e: &dyn ToString| AstFormatError::Serialize(e.to_string());
+
+  match format {
+    AstFormat::Json => serde_json::to_string_pretty(stmts).map_err(|e| serialize_error(&e)),
+    AstFormat::Ron => {
+      ron::ser::to_string_pretty(stmts, Default::default()).map_err(|e| serialize_error(&e))
+    }
+    AstFormat::Sexp => serde_json::to_value(stmts)
+      .map(|value| to_sexp(&value))
+      .map_err(|e| serialize_error(&e)),
+  }
+}
+
+pub fn deserialize_ast(src: &str, format: AstFormat) -> Result<Vec<Stmt>, AstFormatError> {
+  let deserialize_error = |e: &dyn ToString| AstFormatError::Deserialize(e.to_string());
+
+  match format {
+    AstFormat::Json => serde_json::from_str(src).map_err(|e| deserialize_error(&e)),
+    AstFormat::Ron => ron::from_str(src).map_err(|e| deserialize_error(&e)),
+    AstFormat::Sexp => {
+      let value = from_sexp(src).map_err(|e| deserialize_error(&e))?;
+      serde_json::from_value(value).map_err(|e| deserialize_error(&e))
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parse::parser::LoxParser;
+  use crate::scan::scanner::Scanner;
+  use std::io::Cursor;
+
+  fn parse(src: &str) -> Vec<Stmt> {
+    let mut cursor = Cursor::new(src);
+    let tokens = Scanner::new(&mut cursor).scan_tokens().0;
+    LoxParser::new(tokens).parse().unwrap()
+  }
+
+  const PROGRAM: &str = "
+    var a = 1.5;
+    fun add(x, y) { return x + y; }
+    if (a >= 1 and !false) { print add(a, -2); } else { print \"no\\n\"; }
+    for (var i = 0; i < 3; i = i + 1) print nil;
+  ";
+
+  #[test]
+  fn json_round_trip() {
+    let stmts = parse(PROGRAM);
+    let serialized = serialize_ast(&stmts, AstFormat::Json).unwrap();
+    assert_eq!(deserialize_ast(&serialized, AstFormat::Json).unwrap(), stmts);
+  }
+
+  #[test]
+  fn ron_round_trip() {
+    let stmts = parse(PROGRAM);
+    let serialized = serialize_ast(&stmts, AstFormat::Ron).unwrap();
+    assert_eq!(deserialize_ast(&serialized, AstFormat::Ron).unwrap(), stmts);
+  }
+
+  #[test]
+  fn sexp_round_trip() {
+    let stmts = parse(PROGRAM);
+    let serialized = serialize_ast(&stmts, AstFormat::Sexp).unwrap();
+    assert_eq!(deserialize_ast(&serialized, AstFormat::Sexp).unwrap(), stmts);
+  }
+
+  #[test]
+  fn sexp_output_is_canonical() {
+    let serialized = serialize_ast(&parse("print 1 + 2;"), AstFormat::Sexp).unwrap();
+    assert_eq!(
+      serialized,
+      concat!(
+        "(\n",
+        "  (:id 3 :kind (:Print ((:id 2 :kind (:Binary (",
+        ":left (:id 0 :kind (:LiteralNumber (:lexeme \"1\" :value 1.0)) ",
+        ":span (:byte_offset 6 :column 7 :length 1 :line 1)) ",
+        ":operator (:kind \"Plus\" :lexeme \"+\" :span (:byte_offset 8 :column 9 :length 1 :line 1)) ",
+        ":right (:id 1 :kind (:LiteralNumber (:lexeme \"2\" :value 2.0)) ",
+        ":span (:byte_offset 10 :column 11 :length 1 :line 1)))) ",
+        ":span (:byte_offset 6 :column 7 :length 5 :line 1)) 1)) ",
+        ":span (:byte_offset 0 :column 1 :length 12 :line 1))\n",
+        ")"
+      )
+    );
+  }
+
+  #[test]
+  fn invalid_input_is_a_deserialize_error() {
+    let res = deserialize_ast("(:Print)", AstFormat::Sexp);
+    assert!(matches!(res, Err(AstFormatError::Deserialize(_))));
+  }
+}
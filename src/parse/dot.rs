@@ -0,0 +1,350 @@
+use crate::parse::expr::Expr;
+use crate::parse::stmt::Stmt;
+use crate::parse::visitor::{ClassDeclaration, ExprVisitor, ForLoop, StmtVisitor};
+use crate::scan::number_format::format_number;
+use crate::scan::token::Token;
+
+/// Renders an AST as a Graphviz graph, for `dot -Tsvg`. Every statement and expression is a box
+/// labelled with what it is: its kind, operator, name or value. Edges go from each node to its
+/// children, labelled with the part they are when the node has more than one kind of child.
+#[derive(Default)]
+pub struct DotGraph {
+  lines: Vec<String>,
+  next_id: usize,
+}
+
+/// Visitors return the id of the node they added.
+type GraphNode = usize;
+
+impl DotGraph {
+  pub fn new() -> DotGraph {
+    DotGraph::default()
+  }
+
+  /// A graph whose root is the whole program.
+  pub fn print_stmts(mut self, stmts: &[Stmt]) -> String {
+    let root = self.node("program");
+    self.stmt_children(root, stmts, "");
+
+    let mut graph = vec![
+      "digraph ast {".to_string(),
+      "  node [shape=box];".to_string(),
+    ];
+    graph.append(&mut self.lines);
+    graph.push("}".to_string());
+    graph.join("\n")
+  }
+
+  fn node(&mut self, label: &str) -> GraphNode {
+    let id = self.next_id;
+    self.next_id += 1;
+    self
+      .lines
+      .push(format!("  n{id} [label=\"{}\"];", escape(label)));
+    id
+  }
+
+  fn edge(&mut self, from: GraphNode, to: GraphNode, label: &str) {
+    if label.is_empty() {
+      self.lines.push(format!("  n{from} -> n{to};"));
+    } else {
+      self
+        .lines
+        .push(format!("  n{from} -> n{to} [label=\"{label}\"];"));
+    }
+  }
+
+  fn expr_child(&mut self, parent: GraphNode, expr: &Expr, label: &str) {
+    let child = expr.accept(self);
+    self.edge(parent, child, label);
+  }
+
+  fn stmt_child(&mut self, parent: GraphNode, stmt: &Stmt, label: &str) {
+    let child = stmt.accept(self);
+    self.edge(parent, child, label);
+  }
+
+  fn stmt_children(&mut self, parent: GraphNode, stmts: &[Stmt], label: &str) {
+    for stmt in stmts {
+      self.stmt_child(parent, stmt, label);
+    }
+  }
+
+  fn with_expr(&mut self, label: &str, expr: &Expr) -> GraphNode {
+    let id = self.node(label);
+    self.expr_child(id, expr, "");
+    id
+  }
+}
+
+/// Quotes and backslashes end or escape a DOT string, and a line break has to be written as `\n`.
+fn escape(label: &str) -> String {
+  label
+    .replace('\\', "\\\\")
+    .replace('"', "\\\"")
+    .replace('\n', "\\n")
+}
+
+impl StmtVisitor<GraphNode> for DotGraph {
+  fn visit_expression(&mut self, expr: &Expr) -> GraphNode {
+    self.with_expr("expression", expr)
+  }
+
+  fn visit_print(&mut self, expr: &Expr, _line: usize) -> GraphNode {
+    self.with_expr("print", expr)
+  }
+
+  fn visit_var(&mut self, name: &str, value: Option<&Expr>, _line: usize) -> GraphNode {
+    let id = self.node(&format!("var {name}"));
+    if let Some(value) = value {
+      self.expr_child(id, value, "");
+    }
+    id
+  }
+
+  fn visit_block(&mut self, stmts: &[Stmt]) -> GraphNode {
+    let id = self.node("block");
+    self.stmt_children(id, stmts, "");
+    id
+  }
+
+  fn visit_if(
+    &mut self,
+    condition: &Expr,
+    then: &Stmt,
+    els: Option<&Stmt>,
+    _line: usize,
+  ) -> GraphNode {
+    let id = self.node("if");
+    self.expr_child(id, condition, "condition");
+    self.stmt_child(id, then, "then");
+    if let Some(els) = els {
+      self.stmt_child(id, els, "else");
+    }
+    id
+  }
+
+  fn visit_while(&mut self, condition: &Expr, body: &Stmt, _line: usize) -> GraphNode {
+    let id = self.node("while");
+    self.expr_child(id, condition, "condition");
+    self.stmt_child(id, body, "body");
+    id
+  }
+
+  fn visit_do_while(&mut self, body: &Stmt, condition: &Expr, _line: usize) -> GraphNode {
+    let id = self.node("do while");
+    self.stmt_child(id, body, "body");
+    self.expr_child(id, condition, "condition");
+    id
+  }
+
+  fn visit_for(&mut self, for_loop: ForLoop) -> GraphNode {
+    let id = self.node("for");
+    if let Some(initializer) = for_loop.initializer {
+      self.stmt_child(id, initializer, "initializer");
+    }
+    if let Some(condition) = for_loop.condition {
+      self.expr_child(id, condition, "condition");
+    }
+    if let Some(increment) = for_loop.increment {
+      self.expr_child(id, increment, "increment");
+    }
+    self.stmt_child(id, for_loop.body, "body");
+    id
+  }
+
+  fn visit_assert(&mut self, condition: &Expr, message: Option<&Expr>, _line: usize) -> GraphNode {
+    let id = self.node("assert");
+    self.expr_child(id, condition, "condition");
+    if let Some(message) = message {
+      self.expr_child(id, message, "message");
+    }
+    id
+  }
+
+  fn visit_break(&mut self, _line: usize) -> GraphNode {
+    self.node("break")
+  }
+
+  fn visit_continue(&mut self, _line: usize) -> GraphNode {
+    self.node("continue")
+  }
+
+  fn visit_return(&mut self, value: &Expr, _line: usize) -> GraphNode {
+    self.with_expr("return", value)
+  }
+
+  fn visit_throw(&mut self, value: &Expr, _line: usize) -> GraphNode {
+    self.with_expr("throw", value)
+  }
+
+  fn visit_try(&mut self, body: &[Stmt], name: &str, handler: &[Stmt], _line: usize) -> GraphNode {
+    let id = self.node(&format!("try catch ({name})"));
+    self.stmt_children(id, body, "try");
+    self.stmt_children(id, handler, "catch");
+    id
+  }
+
+  fn visit_import(&mut self, path: &str, _line: usize) -> GraphNode {
+    self.node(&format!("import \"{path}\""))
+  }
+
+  fn visit_function(&mut self, name: &str, params: &[String], body: &[Stmt]) -> GraphNode {
+    let id = self.node(&format!("fun {name}({})", params.join(", ")));
+    self.stmt_children(id, body, "");
+    id
+  }
+
+  fn visit_class(&mut self, class: ClassDeclaration) -> GraphNode {
+    let id = self.node(&format!("class {}", class.name));
+    if let Some(superclass) = class.superclass {
+      self.expr_child(id, superclass, "superclass");
+    }
+    self.stmt_children(id, class.methods, "method");
+    self.stmt_children(id, class.class_methods, "class method");
+    self.stmt_children(id, class.getters, "getter");
+    id
+  }
+}
+
+impl ExprVisitor<GraphNode> for DotGraph {
+  fn visit_literal_number(&mut self, value: f64) -> GraphNode {
+    self.node(&format_number(value))
+  }
+
+  fn visit_literal_bool(&mut self, value: bool) -> GraphNode {
+    self.node(&value.to_string())
+  }
+
+  fn visit_literal_string(&mut self, value: &str) -> GraphNode {
+    self.node(&format!("\"{value}\""))
+  }
+
+  fn visit_literal_nil(&mut self) -> GraphNode {
+    self.node("nil")
+  }
+
+  fn visit_binary(&mut self, left: &Expr, operator: &Token, right: &Expr) -> GraphNode {
+    let id = self.node(&operator.kind().symbol());
+    self.expr_child(id, left, "");
+    self.expr_child(id, right, "");
+    id
+  }
+
+  fn visit_logical(&mut self, left: &Expr, operator: &Token, right: &Expr) -> GraphNode {
+    self.visit_binary(left, operator, right)
+  }
+
+  fn visit_unary(&mut self, operator: &Token, right: &Expr) -> GraphNode {
+    self.with_expr(&operator.kind().symbol(), right)
+  }
+
+  fn visit_call(&mut self, callee: &Expr, args: &[Expr], _line: usize) -> GraphNode {
+    let id = self.node("call");
+    self.expr_child(id, callee, "callee");
+    for arg in args {
+      self.expr_child(id, arg, "argument");
+    }
+    id
+  }
+
+  fn visit_group(&mut self, expression: &Expr) -> GraphNode {
+    self.with_expr("group", expression)
+  }
+
+  fn visit_variable(&mut self, name: &str, _line: usize) -> GraphNode {
+    self.node(name)
+  }
+
+  fn visit_assign(&mut self, name: &str, value: &Expr, _line: usize) -> GraphNode {
+    self.with_expr(&format!("{name} ="), value)
+  }
+
+  fn visit_increment(
+    &mut self,
+    name: &str,
+    operator: &Token,
+    postfix: bool,
+    _line: usize,
+  ) -> GraphNode {
+    let symbol = operator.kind().symbol();
+    match postfix {
+      true => self.node(&format!("{name}{symbol}")),
+      false => self.node(&format!("{symbol}{name}")),
+    }
+  }
+
+  fn visit_this(&mut self, _line: usize) -> GraphNode {
+    self.node("this")
+  }
+
+  fn visit_super(&mut self, method: &str, _line: usize) -> GraphNode {
+    self.node(&format!("super.{method}"))
+  }
+
+  fn visit_get(&mut self, object: &Expr, name: &str, _line: usize) -> GraphNode {
+    self.with_expr(&format!(".{name}"), object)
+  }
+
+  fn visit_set(&mut self, object: &Expr, name: &str, value: &Expr, _line: usize) -> GraphNode {
+    let id = self.node(&format!(".{name} ="));
+    self.expr_child(id, object, "object");
+    self.expr_child(id, value, "value");
+    id
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parse::parser::LoxParser;
+  use crate::scan::scanner::Scanner;
+  use std::io::Cursor;
+
+  fn graph(src: &str) -> String {
+    let mut cursor = Cursor::new(src);
+    let tokens = Scanner::new(&mut cursor).scan_tokens().0;
+    let stmts = LoxParser::new(tokens).parse().unwrap();
+    DotGraph::new().print_stmts(&stmts)
+  }
+
+  #[test]
+  fn nodes_are_labelled_with_operators_and_values() {
+    assert_eq!(
+      graph("print 1 + -2;"),
+      [
+        "digraph ast {",
+        "  node [shape=box];",
+        "  n0 [label=\"program\"];",
+        "  n1 [label=\"print\"];",
+        "  n2 [label=\"+\"];",
+        "  n3 [label=\"1\"];",
+        "  n2 -> n3;",
+        "  n4 [label=\"-\"];",
+        "  n5 [label=\"2\"];",
+        "  n4 -> n5;",
+        "  n2 -> n4;",
+        "  n1 -> n2;",
+        "  n0 -> n1;",
+        "}",
+      ]
+      .join("\n")
+    );
+  }
+
+  #[test]
+  fn edges_name_the_parts_of_statements() {
+    let dot = graph("if (a) print a; else b = 1;");
+    assert!(dot.contains("n1 [label=\"if\"];"));
+    assert!(dot.contains("n1 -> n2 [label=\"condition\"];"));
+    assert!(dot.contains("n1 -> n3 [label=\"then\"];"));
+    assert!(dot.contains("n1 -> n5 [label=\"else\"];"));
+    assert!(dot.contains("[label=\"b =\"];"));
+  }
+
+  #[test]
+  fn quotes_and_backslashes_in_labels_are_escaped() {
+    let dot = graph(r#"print "C:\lox";"#);
+    assert!(dot.contains(r#"[label="\"C:\\lox\""];"#));
+  }
+}
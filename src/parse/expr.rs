@@ -1,9 +1,22 @@
+use crate::parse::node_id::NodeId;
+use crate::scan::span::Span;
 use crate::scan::token::Token;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, PartialEq, Clone)]
-pub enum Expr {
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Expr {
+  pub id: NodeId,
+  /// From the first token of the expression to its last one.
+  pub span: Span,
+  pub kind: ExprKind,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub enum ExprKind {
   LiteralNumber {
     value: f64,
+    /// As written in the source, like `0x10` or `1.50`.
+    lexeme: String,
   },
   LiteralBool {
     value: bool,
@@ -43,4 +56,51 @@ pub enum Expr {
     value: Box<Expr>,
     line: usize,
   },
+  /// `++name`, `name--` and the like. `operator` is `++` or `--`. Postfix increments give the
+  /// value from before.
+  Increment {
+    name: String,
+    operator: Token,
+    postfix: bool,
+    line: usize,
+  },
+  This {
+    line: usize,
+  },
+  Super {
+    method: String,
+    line: usize,
+  },
+  Get {
+    object: Box<Expr>,
+    name: String,
+    line: usize,
+  },
+  Set {
+    object: Box<Expr>,
+    name: String,
+    value: Box<Expr>,
+    line: usize,
+  },
+}
+
+impl Expr {
+  pub fn new(id: NodeId, span: Span, kind: ExprKind) -> Expr {
+    Expr { id, span, kind }
+  }
+
+  /// Line where the expression starts.
+  pub fn line(&self) -> usize {
+    self.span.line
+  }
+
+  pub fn is_literal(&self) -> bool {
+    matches!(
+      self.kind,
+      ExprKind::LiteralNumber { .. }
+        | ExprKind::LiteralBool { .. }
+        | ExprKind::LiteralString { .. }
+        | ExprKind::LiteralNil
+    )
+  }
 }
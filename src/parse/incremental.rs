@@ -0,0 +1,439 @@
+use crate::parse::expr::{Expr, ExprKind};
+use crate::parse::parse_error::ParseError;
+use crate::parse::parser::{Declaration, LoxParser};
+use crate::parse::stmt::{Stmt, StmtKind};
+use crate::scan::incremental::{IncrementalScanner, TextEdit, TokenChange};
+use crate::scan::scan_error::ScanError;
+use crate::scan::span::Span;
+use crate::scan::token::Token;
+
+/// Keeps the parse of a source up to date as it's edited. An edit parses again the top-level
+/// statements around it until one starts at a token that started a statement before the edit and
+/// wasn't changed by it. The ones after that are kept, moved to where they are now, so editing a
+/// big file stays fast.
+///
+/// The statements and errors are the same as parsing the whole source again would give, except
+/// for the [`crate::parse::node_id::NodeId`]s: statements parsed again get new ones, and the ones
+/// kept keep theirs.
+pub struct IncrementalParser {
+  scanner: IncrementalScanner,
+  declarations: Vec<Declaration>,
+  next_id: usize,
+}
+
+impl IncrementalParser {
+  /// Parses `scanner`, which has to be without trivia. Errors from scanning are left to whoever
+  /// scanned the source.
+  pub fn new(scanner: IncrementalScanner) -> IncrementalParser {
+    let mut parser = IncrementalParser {
+      scanner,
+      declarations: vec![],
+      next_id: 0,
+    };
+    parser.parse_from(0, None);
+    parser
+  }
+
+  pub fn source(&self) -> &str {
+    self.scanner.source()
+  }
+
+  pub fn stmts(&self) -> impl Iterator<Item = &Stmt> {
+    self.declarations.iter().filter_map(|d| d.stmt.as_ref())
+  }
+
+  pub fn errors(&self) -> impl Iterator<Item = &ParseError> {
+    self.declarations.iter().flat_map(|d| &d.errors)
+  }
+
+  /// Applies `edit` to the source and parses again the statements it changed. Gives what the
+  /// scanner changed, with the errors in the part it scanned again.
+  pub fn edit(&mut self, edit: TextEdit) -> TokenChange {
+    let change = self.scanner.edit(edit);
+    // A statement reads up to the first token of the next one, to see it has ended, like an `if`
+    // looking for an `else`. So the last one that starts before the changed tokens is parsed again
+    // too, even when it ends before them.
+    let first = self
+      .declarations
+      .partition_point(|d| d.token < change.old.start)
+      .saturating_sub(1);
+    self.parse_from(first, Some(&change));
+    change
+  }
+
+  /// Parses the declarations from the one at `first` on, until one starts at a token after
+  /// `change` that an old one started at too. Without a change, parses until the end.
+  fn parse_from(&mut self, first: usize, change: Option<&TokenChange>) {
+    let start = self.declarations.get(first).map_or(0, |d| d.token);
+    let tokens = self.scanner.tokens()[start..].iter().cloned();
+    let mut parser =
+      LoxParser::from_tokens(tokens.map(Ok::<Token, ScanError>)).with_next_id(self.next_id);
+
+    let mut parsed = vec![];
+    let mut old_end = self.declarations.len();
+    while let Some(mut declaration) = parser.next_declaration() {
+      declaration.token += start;
+      parsed.push(declaration);
+      let Some(change) = change else { continue };
+      // Tokens from the end of the change on are the ones that were there before, moved.
+      let next = start + parser.pos();
+      if next < change.new.end {
+        continue;
+      }
+      let old_token = next - change.new.end + change.old.end;
+      let index = first + self.declarations[first..].partition_point(|d| d.token < old_token);
+      if self
+        .declarations
+        .get(index)
+        .is_some_and(|d| d.token == old_token)
+      {
+        let from = self.declarations[index].start;
+        for declaration in &mut self.declarations[index..] {
+          move_declaration(declaration, from, parser.start());
+          declaration.token = declaration.token - change.old.end + change.new.end;
+        }
+        old_end = index;
+        break;
+      }
+    }
+
+    self.next_id = parser.next_id();
+    self.declarations.splice(first..old_end, parsed);
+  }
+}
+
+/// Moves everything in `declaration` as the source moved from `from` to `to`.
+fn move_declaration(declaration: &mut Declaration, from: Span, to: Span) {
+  declaration.start = declaration.start.moved(from, to);
+  if let Some(stmt) = &mut declaration.stmt {
+    move_stmt(stmt, from, to);
+  }
+  for span in declaration
+    .errors
+    .iter_mut()
+    .filter_map(ParseError::span_mut)
+  {
+    *span = span.moved(from, to);
+  }
+}
+
+fn move_line(line: &mut usize, from: Span, to: Span) {
+  *line = *line + to.line - from.line;
+}
+
+fn move_stmts(stmts: &mut [Stmt], from: Span, to: Span) {
+  for stmt in stmts {
+    move_stmt(stmt, from, to);
+  }
+}
+
+fn move_stmt(stmt: &mut Stmt, from: Span, to: Span) {
+  stmt.span = stmt.span.moved(from, to);
+  match &mut stmt.kind {
+    StmtKind::Expr(expr) => move_expr(expr, from, to),
+    StmtKind::Print(expr, line) | StmtKind::Return(expr, line) | StmtKind::Throw(expr, line) => {
+      move_expr(expr, from, to);
+      move_line(line, from, to);
+    }
+    StmtKind::Var(_, value, line) => {
+      if let Some(value) = value {
+        move_expr(value, from, to);
+      }
+      move_line(line, from, to);
+    }
+    StmtKind::ScopeBlock(stmts) | StmtKind::Function { body: stmts, .. } => {
+      move_stmts(stmts, from, to)
+    }
+    StmtKind::If {
+      condition,
+      then,
+      els,
+      line,
+    } => {
+      move_expr(condition, from, to);
+      move_stmt(then, from, to);
+      if let Some(els) = els {
+        move_stmt(els, from, to);
+      }
+      move_line(line, from, to);
+    }
+    StmtKind::While {
+      condition,
+      body,
+      line,
+    }
+    | StmtKind::DoWhile {
+      body,
+      condition,
+      line,
+    } => {
+      move_expr(condition, from, to);
+      move_stmt(body, from, to);
+      move_line(line, from, to);
+    }
+    StmtKind::For {
+      initializer,
+      condition,
+      increment,
+      body,
+      line,
+    } => {
+      if let Some(initializer) = initializer {
+        move_stmt(initializer, from, to);
+      }
+      for expr in condition.iter_mut().chain(increment) {
+        move_expr(expr, from, to);
+      }
+      move_stmt(body, from, to);
+      move_line(line, from, to);
+    }
+    StmtKind::Assert {
+      condition,
+      message,
+      line,
+    } => {
+      move_expr(condition, from, to);
+      if let Some(message) = message {
+        move_expr(message, from, to);
+      }
+      move_line(line, from, to);
+    }
+    StmtKind::Break(line) | StmtKind::Continue(line) | StmtKind::Import(_, line) => {
+      move_line(line, from, to)
+    }
+    StmtKind::Try {
+      body,
+      handler,
+      line,
+      ..
+    } => {
+      move_stmts(body, from, to);
+      move_stmts(handler, from, to);
+      move_line(line, from, to);
+    }
+    StmtKind::Class {
+      superclass,
+      methods,
+      class_methods,
+      getters,
+      ..
+    } => {
+      if let Some(superclass) = superclass {
+        move_expr(superclass, from, to);
+      }
+      move_stmts(methods, from, to);
+      move_stmts(class_methods, from, to);
+      move_stmts(getters, from, to);
+    }
+  }
+}
+
+fn move_expr(expr: &mut Expr, from: Span, to: Span) {
+  expr.span = expr.span.moved(from, to);
+  match &mut expr.kind {
+    ExprKind::LiteralNumber { .. }
+    | ExprKind::LiteralBool { .. }
+    | ExprKind::LiteralString { .. }
+    | ExprKind::LiteralNil => {}
+    ExprKind::Binary {
+      left,
+      operator,
+      right,
+    }
+    | ExprKind::Logical {
+      left,
+      operator,
+      right,
+    } => {
+      move_expr(left, from, to);
+      move_token(operator, from, to);
+      move_expr(right, from, to);
+    }
+    ExprKind::Unary { operator, right } => {
+      move_token(operator, from, to);
+      move_expr(right, from, to);
+    }
+    ExprKind::Call { line, callee, args } => {
+      move_expr(callee, from, to);
+      for arg in args {
+        move_expr(arg, from, to);
+      }
+      move_line(line, from, to);
+    }
+    ExprKind::Group { expression } => move_expr(expression, from, to),
+    ExprKind::Variable { line, .. } | ExprKind::This { line } | ExprKind::Super { line, .. } => {
+      move_line(line, from, to)
+    }
+    ExprKind::Assign { value, line, .. } => {
+      move_expr(value, from, to);
+      move_line(line, from, to);
+    }
+    ExprKind::Increment { operator, line, .. } => {
+      move_token(operator, from, to);
+      move_line(line, from, to);
+    }
+    ExprKind::Get { object, line, .. } => {
+      move_expr(object, from, to);
+      move_line(line, from, to);
+    }
+    ExprKind::Set {
+      object,
+      value,
+      line,
+      ..
+    } => {
+      move_expr(object, from, to);
+      move_expr(value, from, to);
+      move_line(line, from, to);
+    }
+  }
+}
+
+fn move_token(token: &mut Token, from: Span, to: Span) {
+  token.move_to(token.span().moved(from, to));
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::interpret::random::Random;
+  use crate::scan::scanner::Scanner;
+
+  fn scanner(source: &str) -> IncrementalScanner {
+    let mut bytes = source.as_bytes();
+    let tokens = Scanner::new(&mut bytes).scan_tokens().0;
+    IncrementalScanner::new(source.to_string(), tokens)
+  }
+
+  /// The AST without node ids, which differ between parsing again and reusing statements.
+  fn without_ids(debug: String) -> String {
+    let mut rest = debug.as_str();
+    let mut stripped = String::new();
+    while let Some(start) = rest.find("NodeId(") {
+      stripped.push_str(&rest[..start]);
+      rest = &rest[start..];
+      rest = &rest[rest.find(')').unwrap() + 1..];
+    }
+    stripped + rest
+  }
+
+  /// Checks `parser` has the statements and errors that parsing its source from scratch gives.
+  fn assert_parses_like_the_parser(parser: &IncrementalParser) {
+    let full = IncrementalParser::new(scanner(parser.source()));
+    assert_eq!(
+      without_ids(format!("{:?}", parser.stmts().collect::<Vec<_>>())),
+      without_ids(format!("{:?}", full.stmts().collect::<Vec<_>>())),
+      "statements of {:?}",
+      parser.source()
+    );
+    assert_eq!(
+      parser.errors().collect::<Vec<_>>(),
+      full.errors().collect::<Vec<_>>(),
+      "errors of {:?}",
+      parser.source()
+    );
+  }
+
+  /// Replaces the first `old` in `source` with `new`, and checks the result is the same as parsing
+  /// the edited source from scratch.
+  fn edit(source: &str, old: &str, new: &str) -> IncrementalParser {
+    let mut parser = IncrementalParser::new(scanner(source));
+    let start = source.find(old).unwrap();
+    parser.edit(TextEdit {
+      range: start..start + old.len(),
+      text: new.to_string(),
+    });
+    assert_eq!(parser.source(), source.replacen(old, new, 1));
+    assert_parses_like_the_parser(&parser);
+    parser
+  }
+
+  #[test]
+  fn parses_like_the_parser() {
+    let source = "var a = 1;\nprint a +;\nfun f() { return a; }";
+    let mut bytes = source.as_bytes();
+    let tokens = Scanner::new(&mut bytes).scan_tokens().0;
+    let errors = LoxParser::new(tokens).parse().unwrap_err();
+
+    let parser = IncrementalParser::new(scanner(source));
+    assert_eq!(parser.stmts().count(), 2);
+    assert_eq!(
+      parser.errors().collect::<Vec<_>>(),
+      errors.iter().collect::<Vec<_>>()
+    );
+  }
+
+  #[test]
+  fn statements_after_an_edit_are_kept_and_moved() {
+    let source = "var a = 1;\nvar b = 1;\nvar c = 2;\nprint a + c;\nfun f() {\n  return a;\n}";
+    let before = IncrementalParser::new(scanner(source));
+    let after = edit(source, "2", "(2 +\n 3)");
+
+    let ids = |parser: &IncrementalParser| parser.stmts().map(|s| s.id).collect::<Vec<_>>();
+    let (before, after) = (ids(&before), ids(&after));
+    assert_eq!(before[0], after[0]);
+    assert_ne!(before[2], after[2]);
+    assert_eq!(before[3..], after[3..]);
+  }
+
+  #[test]
+  fn edits_that_change_statement_boundaries() {
+    edit("print 1;\nprint 2;\nprint 3;", ";\nprint 2", "\n+ 2");
+    edit("print 1;\nprint 2;", "1", "1; var a = 2");
+    edit("{ print 1; }\nprint 2;\nprint 3;", "}", "");
+    edit("if (a) print 1;\nprint 2;", "\nprint 2;", " else print 2;");
+    edit("print 1;\nprint 2;", "print 1;\n", "");
+  }
+
+  #[test]
+  fn errors_after_an_edit_move_with_their_statements() {
+    edit("print 1;\nvar = 2;\nprint 3 +;", "print 1;", "print\n  1;");
+    edit("print 1;\nprint 2 +;", "1;", "");
+  }
+
+  #[test]
+  fn edits_at_the_end_of_the_source() {
+    edit("print 1;", ";", "; print 2;");
+    edit("", "", "print 1;");
+    edit("print 1;\n", "\n", "\nprint");
+  }
+
+  #[test]
+  fn edits_that_change_errors() {
+    edit(" (", " (", "else ");
+    edit("{", "", "");
+    edit("\n3.+ 3;", "\n3.", "class A {");
+  }
+
+  #[test]
+  fn random_edits_parse_like_the_parser() {
+    const PIECES: &[&str] = &[
+      "var", "a", "b", "=", "1", "3.", "+", "-", "*", ";", ",", ".", "(", ")", "{", "}", "print",
+      "if", "else", "while", "for", "fun", "f", "return", "class", "A", "<", "this", "\"s", "//",
+      " ", "\n",
+    ];
+    let mut random = Random::seeded(3626);
+    let pick = |random: &mut Random, n: usize| (random.next_number() * n as f64) as usize;
+    let text = |random: &mut Random, pieces: usize| {
+      (0..pick(random, pieces))
+        .map(|_| PIECES[pick(random, PIECES.len())])
+        .collect::<Vec<_>>()
+        .join(" ")
+    };
+
+    for _ in 0..2000 {
+      let source = text(&mut random, 16);
+      let mut parser = IncrementalParser::new(scanner(&source));
+      for _ in 0..4 {
+        let length = parser.source().len();
+        let start = pick(&mut random, length + 1);
+        let end = start + pick(&mut random, length - start + 1).min(6);
+        parser.edit(TextEdit {
+          range: start..end,
+          text: text(&mut random, 4),
+        });
+        assert_parses_like_the_parser(&parser);
+      }
+    }
+  }
+}
@@ -1,5 +1,13 @@
+pub mod ast_cache;
+pub mod ast_format;
+pub mod dot;
 pub mod expr;
+pub mod incremental;
+pub mod node_id;
 pub mod parse_error;
 pub mod parser;
+pub mod pretty_print;
 pub mod print_ast;
+mod sexp;
 pub mod stmt;
+pub mod visitor;
@@ -0,0 +1,7 @@
+use serde::{Deserialize, Serialize};
+
+/// Tells apart the expressions and statements of a parsed program, so tools can keep data about
+/// a node outside the AST. The parser numbers nodes from 0 in the order it finishes them, so ids
+/// are only unique among the nodes of one parse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct NodeId(pub usize);
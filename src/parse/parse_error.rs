@@ -1,11 +1,108 @@
+use crate::scan::span::Span;
 use thiserror::Error;
 
 #[derive(Error, Debug, PartialEq)]
 pub enum ParseError {
-  #[error("Malformed expression [line {0}]: {1}")]
-  MalformedExpression(usize, String),
+  #[error("Malformed expression [{0}]: {1}")]
+  MalformedExpression(Span, String),
   #[error("Unexpected end of file")]
   UnexpectedEndOfFile,
-  #[error("[line {0}]: Expected function name after fun.")]
-  MissingFunctionName(usize),
+  #[error("[{0}]: Expected function name after fun.")]
+  MissingFunctionName(Span),
+  #[error("[{0}]: Expected parameter name.")]
+  MissingParameterName(Span),
+  #[error("[{0}]: Expected '{{' before function body.")]
+  MissingFunctionBody(Span),
+  #[error("[{0}]: Expected class name after class.")]
+  MissingClassName(Span),
+  #[error("[{0}]: Expected superclass name after <.")]
+  MissingSuperclassName(Span),
+  #[error("[{0}]: A class can't inherit from itself.")]
+  InheritFromSelf(Span),
+  #[error("[{0}]: Can't have more than 255 arguments.")]
+  TooManyArguments(Span),
+  #[error("[{0}]: Can't have more than 255 parameters.")]
+  TooManyParameters(Span),
+  #[error("[{0}]: Expected a path string after import.")]
+  MissingImportPath(Span),
+  #[error("[{0}]: Code is nested too deeply.")]
+  TooDeeplyNested(Span),
+  #[error("[{0}]: Invalid number '{1}'.")]
+  InvalidNumber(Span, String),
+  /// The span is where the `;` should go: right after what comes before it.
+  #[error("[{0}]: Expected ';' after {1}. Add one at the end of the statement.")]
+  MissingSemicolon(Span, String),
+  /// A binary operator at the start of an expression, like `+ 3`.
+  #[error("[{0}]: Binary operator '{1}' missing left-hand operand.")]
+  MissingLeftOperand(Span, String),
+  /// An error from scanning, when parsing straight from a scanner.
+  #[error("{0}")]
+  Scan(String),
+}
+
+impl ParseError {
+  /// Stable code of the error, which `explain` describes.
+  pub fn code(&self) -> &'static str {
+    match self {
+      ParseError::MalformedExpression(..) => "E0101",
+      ParseError::UnexpectedEndOfFile => "E0102",
+      ParseError::MissingFunctionName(_) => "E0103",
+      ParseError::MissingParameterName(_) => "E0104",
+      ParseError::MissingFunctionBody(_) => "E0105",
+      ParseError::MissingClassName(_) => "E0106",
+      ParseError::MissingSuperclassName(_) => "E0107",
+      ParseError::InheritFromSelf(_) => "E0108",
+      ParseError::TooManyArguments(_) => "E0109",
+      ParseError::TooManyParameters(_) => "E0110",
+      ParseError::MissingImportPath(_) => "E0111",
+      ParseError::TooDeeplyNested(_) => "E0112",
+      ParseError::InvalidNumber(..) => "E0113",
+      ParseError::Scan(_) => "E0114",
+      ParseError::MissingSemicolon(..) => "E0115",
+      ParseError::MissingLeftOperand(..) => "E0116",
+    }
+  }
+
+  /// Where the error is in the source. Errors at the end of the file, and scan errors passed
+  /// along as text, don't have one.
+  pub fn span(&self) -> Option<Span> {
+    match self {
+      ParseError::MalformedExpression(span, _)
+      | ParseError::MissingFunctionName(span)
+      | ParseError::MissingParameterName(span)
+      | ParseError::MissingFunctionBody(span)
+      | ParseError::MissingClassName(span)
+      | ParseError::MissingSuperclassName(span)
+      | ParseError::InheritFromSelf(span)
+      | ParseError::TooManyArguments(span)
+      | ParseError::TooManyParameters(span)
+      | ParseError::MissingImportPath(span)
+      | ParseError::TooDeeplyNested(span)
+      | ParseError::InvalidNumber(span, _)
+      | ParseError::MissingSemicolon(span, _)
+      | ParseError::MissingLeftOperand(span, _) => Some(*span),
+      ParseError::UnexpectedEndOfFile | ParseError::Scan(_) => None,
+    }
+  }
+
+  /// The span of [`ParseError::span`], to move the error along with its code.
+  pub(crate) fn span_mut(&mut self) -> Option<&mut Span> {
+    match self {
+      ParseError::MalformedExpression(span, _)
+      | ParseError::MissingFunctionName(span)
+      | ParseError::MissingParameterName(span)
+      | ParseError::MissingFunctionBody(span)
+      | ParseError::MissingClassName(span)
+      | ParseError::MissingSuperclassName(span)
+      | ParseError::InheritFromSelf(span)
+      | ParseError::TooManyArguments(span)
+      | ParseError::TooManyParameters(span)
+      | ParseError::MissingImportPath(span)
+      | ParseError::TooDeeplyNested(span)
+      | ParseError::InvalidNumber(span, _)
+      | ParseError::MissingSemicolon(span, _)
+      | ParseError::MissingLeftOperand(span, _) => Some(span),
+      ParseError::UnexpectedEndOfFile | ParseError::Scan(_) => None,
+    }
+  }
 }
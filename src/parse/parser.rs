@@ -1,50 +1,301 @@
-use crate::parse::expr::Expr;
+use crate::parse::expr::{Expr, ExprKind};
+use crate::parse::node_id::NodeId;
 use crate::parse::parse_error::ParseError;
-use crate::parse::stmt::Stmt;
+use crate::parse::stmt::{Stmt, StmtKind};
+use crate::scan::number_format::parse_number;
+use crate::scan::scan_error::ScanError;
+use crate::scan::span::Span;
 use crate::scan::token::Token;
 use crate::scan::token_kind::TokenKind;
-use std::cell::RefCell;
 
-pub struct LoxParser {
-  tokens: Vec<Token>,
-  current_pos: RefCell<usize>,
+/// Most arguments a call can pass and parameters a function can declare, as in jlox.
+const MAX_ARGUMENTS: usize = 255;
+
+/// Parses one operand of a binary operator.
+type Operand<P> = fn(&mut P) -> Result<Expr, ParseError>;
+
+/// Levels of nesting allowed before parsing fails with [`ParseError::TooDeeplyNested`]. Every
+/// stage walks the AST recursively, so without a limit deep enough input overflows the stack.
+/// Debug builds take around 40KB of stack per level: threads with less than 16MB of stack should
+/// lower the limit with [`LoxParser::with_max_depth`].
+pub const DEFAULT_MAX_NESTING: usize = 256;
+
+/// A top-level statement and the errors found while parsing it. Statements in blocks that have
+/// errors are skipped without failing the whole declaration, so it can have both.
+pub(crate) struct Declaration {
+  /// Span of its first token.
+  pub start: Span,
+  /// Index of its first token, counting from the first one the parser got.
+  pub token: usize,
+  pub stmt: Option<Stmt>,
+  pub errors: Vec<ParseError>,
+}
+
+/// Tokens that were already scanned, as [`LoxParser::new`] takes them.
+pub type ScannedTokens =
+  std::iter::Map<std::vec::IntoIter<Token>, fn(Token) -> Result<Token, ScanError>>;
+
+/// Parses tokens as they come from any iterator, looking only one token ahead. A
+/// [`crate::scan::scanner::Scanner`] can be parsed directly, without scanning the whole program
+/// first.
+pub struct LoxParser<I: Iterator<Item = Result<Token, ScanError>> = ScannedTokens> {
+  tokens: I,
+  /// The token after the last consumed one, read ahead so it can be looked at.
+  next: Option<Token>,
+  /// The last consumed token.
+  previous: Option<Token>,
+  /// How many tokens were consumed, to tell whether parsing moved forward.
+  consumed: usize,
+  /// Errors that came from the tokens, reported with the parse errors.
+  scan_errors: Vec<ParseError>,
+  /// Errors in statements inside blocks. The parser skips those statements and goes on with the
+  /// rest of the block.
+  block_errors: Vec<ParseError>,
+  /// Id for the next node built.
+  next_id: usize,
+  /// Nested groups, unary operators, statements and blocks being parsed right now.
+  depth: usize,
+  max_depth: usize,
 }
 
 impl LoxParser {
+  /// Comments in `tokens`, from scanning with trivia, are skipped.
   pub fn new(tokens: Vec<Token>) -> LoxParser {
-    LoxParser {
+    LoxParser::from_tokens(tokens.into_iter().map(Ok as fn(Token) -> _))
+  }
+}
+
+impl<I: Iterator<Item = Result<Token, ScanError>>> LoxParser<I> {
+  /// Parser that takes the tokens one at a time. Comments are skipped like in
+  /// [`LoxParser::new`].
+  pub fn from_tokens(tokens: I) -> LoxParser<I> {
+    let mut parser = LoxParser {
       tokens,
-      current_pos: RefCell::new(0),
+      next: None,
+      previous: None,
+      consumed: 0,
+      scan_errors: vec![],
+      block_errors: vec![],
+      next_id: 0,
+      depth: 0,
+      max_depth: DEFAULT_MAX_NESTING,
+    };
+    parser.next = parser.read_token();
+    parser
+  }
+
+  /// Parsing fails once code nests deeper than `max_depth` levels.
+  pub fn with_max_depth(mut self, max_depth: usize) -> LoxParser<I> {
+    self.max_depth = max_depth;
+    self
+  }
+
+  /// Numbers nodes from `next_id` on, to parse part of a program whose other nodes have ids below
+  /// it.
+  pub(crate) fn with_next_id(mut self, next_id: usize) -> LoxParser<I> {
+    self.next_id = next_id;
+    self
+  }
+
+  /// Id the next node built will get.
+  pub(crate) fn next_id(&self) -> usize {
+    self.next_id
+  }
+
+  fn read_token(&mut self) -> Option<Token> {
+    loop {
+      match self.tokens.next()? {
+        Ok(token) if matches!(token.kind(), TokenKind::Comment(_)) => {}
+        Ok(token) => return Some(token),
+        Err(error) => self.scan_errors.push(ParseError::Scan(error.to_string())),
+      }
+    }
+  }
+
+  fn inc(&mut self) {
+    self.previous = self.next.take();
+    self.next = self.read_token();
+    self.consumed += 1;
+  }
+
+  /// How many tokens were consumed, which is the index of the next one.
+  pub(crate) fn pos(&self) -> usize {
+    self.consumed
+  }
+
+  /// Runs `parse` one level of nesting deeper.
+  fn nested<T>(
+    &mut self,
+    parse: impl FnOnce(&mut Self) -> Result<T, ParseError>,
+  ) -> Result<T, ParseError> {
+    self.deepen()?;
+    let result = parse(self);
+    self.depth -= 1;
+    result
+  }
+
+  /// Goes one level of nesting deeper until [`LoxParser::chain`] ends.
+  fn deepen(&mut self) -> Result<(), ParseError> {
+    if self.depth >= self.max_depth {
+      return Err(ParseError::TooDeeplyNested(self.start()));
     }
+    self.depth += 1;
+    Ok(())
+  }
+
+  /// Runs `parse`, which builds a chain of nodes on top of each other, like `a + b + c` or
+  /// `f()()`. It calls [`LoxParser::deepen`] for every link, since a long chain is as deep as
+  /// nested parentheses for every stage that walks the AST.
+  fn chain<T>(
+    &mut self,
+    parse: impl FnOnce(&mut Self) -> Result<T, ParseError>,
+  ) -> Result<T, ParseError> {
+    let depth = self.depth;
+    let result = parse(self);
+    self.depth = depth;
+    result
+  }
+
+  fn node_id(&mut self) -> NodeId {
+    self.next_id += 1;
+    NodeId(self.next_id - 1)
+  }
+
+  /// Span of the next token, where the node parsed next starts.
+  pub(crate) fn start(&self) -> Span {
+    self.peek().map(Token::span).unwrap_or_default()
+  }
+
+  fn previous_span(&self) -> Span {
+    self.previous.as_ref().map(Token::span).unwrap_or_default()
   }
 
-  fn inc(&self) {
-    *self.current_pos.borrow_mut() += 1;
+  /// Expression from `start` up to the last consumed token.
+  fn expr(&mut self, start: Span, kind: ExprKind) -> Expr {
+    let span = start.to(self.previous_span());
+    Expr::new(self.node_id(), span, kind)
   }
 
-  fn pos(&self) -> usize {
-    *self.current_pos.borrow()
+  /// Statement from `start` up to the last consumed token.
+  fn stmt(&mut self, start: Span, kind: StmtKind) -> Stmt {
+    let span = start.to(self.previous_span());
+    Stmt::new(self.node_id(), span, kind)
   }
 
-  pub fn parse(mut self) -> Result<Vec<Stmt>, ParseError> {
+  /// Parses the whole program. After an error the parser skips to the next statement and keeps
+  /// going, so every syntax error in the program is reported at once.
+  pub fn parse(mut self) -> Result<Vec<Stmt>, Vec<ParseError>> {
     let mut stmts = vec![];
+    let mut errors = vec![];
+    while let Some(mut declaration) = self.next_declaration() {
+      errors.append(&mut declaration.errors);
+      stmts.extend(declaration.stmt);
+    }
+    errors.append(&mut self.scan_errors);
+
+    if errors.is_empty() {
+      Ok(stmts)
+    } else {
+      Err(errors)
+    }
+  }
+
+  /// Parses the next top-level statement, or gives `None` at the end of the program. After an
+  /// error it skips to the start of the next statement.
+  pub(crate) fn next_declaration(&mut self) -> Option<Declaration> {
+    if self.peek().is_none() || self.is_at_end() {
+      return None;
+    }
+    let start = self.start();
+    let pos = self.pos();
+    let result = self.declaration();
+    let mut errors = std::mem::take(&mut self.scan_errors);
+    errors.append(&mut self.block_errors);
+    let stmt = match result {
+      Ok(stmt) => Some(stmt),
+      Err(error) => {
+        errors.push(error);
+        self.synchronize(pos, false);
+        None
+      }
+    };
+    Some(Declaration {
+      start,
+      token: pos,
+      stmt,
+      errors,
+    })
+  }
+
+  /// Parses exactly one expression, with no `;` after it. Anything left after the expression is
+  /// an error.
+  pub fn parse_expression(mut self) -> Result<Expr, ParseError> {
+    let expr = self.expression();
+    if !self.scan_errors.is_empty() {
+      return Err(self.scan_errors.remove(0));
+    }
+    let expr = expr?;
+    match self.peek() {
+      Some(token) if *token.kind() != TokenKind::Eof => Err(ParseError::MalformedExpression(
+        token.span(),
+        format!("Expected end of expression, got {}", token.lexeme()),
+      )),
+      _ => Ok(expr),
+    }
+  }
+
+  /// Skips tokens until the start of the next statement: right after a `;` or right before a
+  /// keyword that starts a statement. Inside a block it also stops right before a `}`, which may
+  /// close it. Always moves forward at least one token from `start`.
+  fn synchronize(&mut self, start: usize, in_block: bool) {
+    if self.pos() == start {
+      self.inc();
+    }
+
     while !self.is_at_end() {
-      stmts.push(self.declaration()?)
+      let previous = self.previous.as_ref();
+      if previous.is_some_and(|t| *t.kind() == TokenKind::Semicolon) {
+        return;
+      }
+
+      match self.peek_kind() {
+        None => return,
+        Some(
+          TokenKind::Class
+          | TokenKind::Fun
+          | TokenKind::Var
+          | TokenKind::For
+          | TokenKind::If
+          | TokenKind::While
+          | TokenKind::Do
+          | TokenKind::Print
+          | TokenKind::Assert
+          | TokenKind::Return
+          | TokenKind::Throw
+          | TokenKind::Try
+          | TokenKind::Import,
+        ) => return,
+        Some(TokenKind::RightBrace) if in_block => return,
+        Some(_) => self.inc(),
+      }
     }
-    Ok(stmts)
   }
 
   fn declaration(&mut self) -> Result<Stmt, ParseError> {
-    if self.advance_if_match(&[TokenKind::Var]).is_some() {
-      self.var_declaration()
+    let start = self.start();
+    let kind = if self.advance_if_match(&[TokenKind::Var]).is_some() {
+      self.var_declaration()?
     } else if self.advance_if_match(&[TokenKind::Fun]).is_some() {
-      self.function_declaration()
+      self.function_declaration()?
+    } else if self.advance_if_match(&[TokenKind::Class]).is_some() {
+      self.class_declaration()?
     } else {
-      self.statement()
-    }
+      return self.statement();
+    };
+    Ok(self.stmt(start, kind))
   }
 
-  fn var_declaration(&mut self) -> Result<Stmt, ParseError> {
+  fn var_declaration(&mut self) -> Result<StmtKind, ParseError> {
     let token = self.next_token()?;
     let line = token.line();
     if let TokenKind::Identifier(name) = token.kind() {
@@ -52,149 +303,290 @@ impl LoxParser {
       let stmt = if self.peek_kind().is_some_and(|k| *k == TokenKind::Equal) {
         self.consume(TokenKind::Equal)?;
         let expr = self.expression()?;
-        Stmt::Var(name, expr, line)
+        StmtKind::Var(name, Some(expr), line)
       } else {
-        Stmt::Var(name, Expr::LiteralNil, line)
+        StmtKind::Var(name, None, line)
       };
-      self.consume(TokenKind::Semicolon)?;
+      self.consume_semicolon("variable declaration")?;
       Ok(stmt)
     } else {
       Err(ParseError::MalformedExpression(
-        line,
-        format!("Expected identifier, got {}", token.symbol()),
+        token.span(),
+        format!("Expected identifier, got {}", token.lexeme()),
       ))
     }
   }
 
-  fn function_declaration(&mut self) -> Result<Stmt, ParseError> {
+  fn function_declaration(&mut self) -> Result<StmtKind, ParseError> {
+    let name = self.function_name()?;
+    self.consume(TokenKind::LeftParen)?;
+    let params = self.parameters()?;
+    self.function_body(name, params)
+  }
+
+  fn function_name(&mut self) -> Result<String, ParseError> {
+    let identifier = self.next_token()?;
+    if let TokenKind::Identifier(name) = identifier.kind() {
+      Ok(name.to_string())
+    } else {
+      Err(ParseError::MissingFunctionName(identifier.span()))
+    }
+  }
+
+  /// The parameters after the `(` of a function, up to and including the `)`.
+  fn parameters(&mut self) -> Result<Vec<String>, ParseError> {
+    let mut params = vec![];
+    if self.advance_if_match(&[TokenKind::RightParen]).is_none() {
+      loop {
+        let identifier = self.next_token()?;
+        if params.len() == MAX_ARGUMENTS {
+          return Err(ParseError::TooManyParameters(identifier.span()));
+        }
+        if let TokenKind::Identifier(param) = identifier.kind() {
+          params.push(param.to_string())
+        } else {
+          return Err(ParseError::MissingParameterName(identifier.span()));
+        }
+
+        if self.end_of_list()? {
+          break;
+        }
+      }
+    }
+    Ok(params)
+  }
+
+  fn function_body(&mut self, name: String, params: Vec<String>) -> Result<StmtKind, ParseError> {
+    let brace = self.next_token()?;
+    if *brace.kind() != TokenKind::LeftBrace {
+      return Err(ParseError::MissingFunctionBody(brace.span()));
+    }
+    let body = self.block_of_stmts()?;
+
+    Ok(StmtKind::Function { name, params, body })
+  }
+
+  fn class_declaration(&mut self) -> Result<StmtKind, ParseError> {
     let identifier = self.next_token()?;
     let name = if let TokenKind::Identifier(name) = identifier.kind() {
       name.to_string()
     } else {
-      return Err(ParseError::MissingFunctionName(identifier.line()))
+      return Err(ParseError::MissingClassName(identifier.span()));
     };
 
-    self.consume(TokenKind::LeftParen)?;
-
-    let mut params = vec![];
-    while self.advance_if_match(&[TokenKind::RightParen]).is_none() {
+    let superclass = if self.advance_if_match(&[TokenKind::Less]).is_some() {
       let identifier = self.next_token()?;
-      if let TokenKind::Identifier(param) = identifier.kind() {
-        params.push(param.to_string())
-      } else {
-        return Err(ParseError::MalformedExpression(identifier.line(), identifier.symbol()))
+      let line = identifier.line();
+      let span = identifier.span();
+      match identifier.kind() {
+        TokenKind::Identifier(superclass) if *superclass == name => {
+          return Err(ParseError::InheritFromSelf(span))
+        }
+        TokenKind::Identifier(superclass) => {
+          let name = superclass.to_string();
+          Some(self.expr(span, ExprKind::Variable { name, line }))
+        }
+        _ => return Err(ParseError::MissingSuperclassName(span)),
       }
+    } else {
+      None
+    };
 
-      match self.peek_kind() {
-        Some(TokenKind::RightParen) => continue,
-        _ => self.consume(TokenKind::Comma)?
-      };
+    self.consume(TokenKind::LeftBrace)?;
+
+    let mut methods = vec![];
+    let mut class_methods = vec![];
+    let mut getters = vec![];
+    while self
+      .peek_kind()
+      .is_some_and(|k| *k != TokenKind::RightBrace && *k != TokenKind::Eof)
+    {
+      let start = self.start();
+      if self.advance_if_match(&[TokenKind::Class]).is_some() {
+        let method = self.function_declaration()?;
+        class_methods.push(self.stmt(start, method));
+        continue;
+      }
+      let name = self.function_name()?;
+      if self.peek_kind() == Some(&TokenKind::LeftBrace) {
+        let getter = self.function_body(name, vec![])?;
+        getters.push(self.stmt(start, getter));
+      } else {
+        self.consume(TokenKind::LeftParen)?;
+        let params = self.parameters()?;
+        let method = self.function_body(name, params)?;
+        methods.push(self.stmt(start, method));
+      }
     }
 
-    self.consume(TokenKind::LeftBrace)?;
-    let body = self.block_of_stmts()?;
+    self.consume(TokenKind::RightBrace)?;
 
-    Ok(Stmt::Function {
+    Ok(StmtKind::Class {
       name,
-      params,
-      body
+      superclass,
+      methods,
+      class_methods,
+      getters,
     })
   }
 
   fn statement(&mut self) -> Result<Stmt, ParseError> {
-    let stmt = match self
+    self.nested(Self::nested_statement)
+  }
+
+  fn nested_statement(&mut self) -> Result<Stmt, ParseError> {
+    let start = self.start();
+    let kind = match self
       .advance_if_match(&[
         TokenKind::Print,
         TokenKind::If,
         TokenKind::LeftBrace,
         TokenKind::While,
+        TokenKind::Do,
         TokenKind::For,
-        TokenKind::Return
+        TokenKind::Return,
+        TokenKind::Break,
+        TokenKind::Continue,
+        TokenKind::Assert,
+        TokenKind::Throw,
+        TokenKind::Try,
+        TokenKind::Import,
       ])
-      .map(|t| t.kind())
+      .map(|t| (t.kind(), t.line()))
     {
-      Some(TokenKind::Print) => self.print_stmt()?,
-      Some(TokenKind::If) => self.if_stmt()?,
-      Some(TokenKind::LeftBrace) => self.scope_block()?,
-      Some(TokenKind::While) => self.while_stmt()?,
-      Some(TokenKind::For) => self.for_stmt()?,
-      Some(TokenKind::Return) => self.return_stmt()?,
+      Some((TokenKind::Print, line)) => self.print_stmt(line)?,
+      Some((TokenKind::If, line)) => self.if_stmt(line)?,
+      Some((TokenKind::LeftBrace, _)) => self.scope_block()?,
+      Some((TokenKind::While, line)) => self.while_stmt(line)?,
+      Some((TokenKind::Do, line)) => self.do_while_stmt(line)?,
+      Some((TokenKind::For, line)) => self.for_stmt(line)?,
+      Some((TokenKind::Return, line)) => self.return_stmt(line)?,
+      Some((TokenKind::Assert, line)) => self.assert_stmt(line)?,
+      Some((TokenKind::Throw, line)) => {
+        let value = self.expression()?;
+        self.consume_semicolon("thrown value")?;
+        StmtKind::Throw(value, line)
+      }
+      Some((TokenKind::Try, line)) => self.try_stmt(line)?,
+      Some((TokenKind::Import, line)) => self.import_stmt(line)?,
+      Some((TokenKind::Break, line)) => {
+        self.consume_semicolon("'break'")?;
+        StmtKind::Break(line)
+      }
+      Some((TokenKind::Continue, line)) => {
+        self.consume_semicolon("'continue'")?;
+        StmtKind::Continue(line)
+      }
       _ => self.expression_stmt()?,
     };
 
-    Ok(stmt)
+    Ok(self.stmt(start, kind))
   }
 
-  fn print_stmt(&mut self) -> Result<Stmt, ParseError> {
-    let stmt = Stmt::Print(self.expression()?);
+  fn print_stmt(&mut self, line: usize) -> Result<StmtKind, ParseError> {
+    let stmt = StmtKind::Print(self.expression()?, line);
 
     if !self.is_at_end() {
-      self.consume(TokenKind::Semicolon)?;
+      self.consume_semicolon("value")?;
     }
 
     Ok(stmt)
   }
 
-  fn if_stmt(&mut self) -> Result<Stmt, ParseError> {
+  fn if_stmt(&mut self, line: usize) -> Result<StmtKind, ParseError> {
     self.consume(TokenKind::LeftParen)?;
     let condition = self.expression()?;
     self.consume(TokenKind::RightParen)?;
-    let then = self.statement().map(|s| Box::new(s))?;
+    let then = self.statement().map(Box::new)?;
 
     let els = self
       .advance_if_match(&[TokenKind::Else])
       .map(|_| ())
-      .map(|_| self.statement().map(|s| Box::new(s)))
+      .map(|_| self.statement().map(Box::new))
       .transpose()?;
 
-    Ok(Stmt::If {
+    Ok(StmtKind::If {
       condition,
       then,
       els,
+      line,
     })
   }
 
   fn block_of_stmts(&mut self) -> Result<Vec<Stmt>, ParseError> {
+    self.nested(Self::nested_block_of_stmts)
+  }
+
+  fn nested_block_of_stmts(&mut self) -> Result<Vec<Stmt>, ParseError> {
     let mut stmts = vec![];
 
     while self
       .peek_kind()
-      .is_some_and(|k| *k != TokenKind::RightBrace)
+      .is_some_and(|k| *k != TokenKind::RightBrace && *k != TokenKind::Eof)
     {
-      stmts.push(self.declaration()?)
+      let start = self.pos();
+      match self.declaration() {
+        Ok(stmt) => stmts.push(stmt),
+        Err(error) => {
+          self.block_errors.push(error);
+          self.synchronize(start, true);
+        }
+      }
     }
 
     self.consume(TokenKind::RightBrace)?;
     Ok(stmts)
   }
 
-  fn scope_block(&mut self) -> Result<Stmt, ParseError> {
+  fn scope_block(&mut self) -> Result<StmtKind, ParseError> {
     let stmts = self.block_of_stmts()?;
-    Ok(Stmt::ScopeBlock(stmts))
+    Ok(StmtKind::ScopeBlock(stmts))
   }
 
-  fn while_stmt(&mut self) -> Result<Stmt, ParseError> {
+  fn while_stmt(&mut self, line: usize) -> Result<StmtKind, ParseError> {
     self.consume(TokenKind::LeftParen)?;
     let condition = self.expression()?;
     self.consume(TokenKind::RightParen)?;
 
     let body = self.statement().map(Box::new)?;
 
-    Ok(Stmt::While { condition, body })
+    Ok(StmtKind::While {
+      condition,
+      body,
+      line,
+    })
   }
 
-  fn for_stmt(&mut self) -> Result<Stmt, ParseError> {
+  fn do_while_stmt(&mut self, line: usize) -> Result<StmtKind, ParseError> {
+    let body = self.statement().map(Box::new)?;
+
+    self.consume(TokenKind::While)?;
+    self.consume(TokenKind::LeftParen)?;
+    let condition = self.expression()?;
+    self.consume(TokenKind::RightParen)?;
+    self.consume_semicolon("loop condition")?;
+
+    Ok(StmtKind::DoWhile {
+      body,
+      condition,
+      line,
+    })
+  }
+
+  fn for_stmt(&mut self, line: usize) -> Result<StmtKind, ParseError> {
     self.consume(TokenKind::LeftParen)?;
 
     // Var declaration -- for (HERE;;) {}
-    let declaration = match self.advance_if_match(&[TokenKind::Semicolon]) {
+    let initializer_start = self.start();
+    let initializer = match self.advance_if_match(&[TokenKind::Semicolon]) {
       Some(_) => None,
       None => {
-        if let Some(_) = self.advance_if_match(&[TokenKind::Var]) {
-          Some(self.var_declaration()?)
+        let initializer = if self.advance_if_match(&[TokenKind::Var]).is_some() {
+          self.var_declaration()?
         } else {
-          Some(self.expression_stmt()?)
-        }
+          self.expression_stmt()?
+        };
+        Some(Box::new(self.stmt(initializer_start, initializer)))
       }
     };
 
@@ -205,7 +597,7 @@ impl LoxParser {
       .map(|_| ())
       .map(|_| self.expression())
       .transpose()?;
-    self.consume(TokenKind::Semicolon)?;
+    self.consume_semicolon("loop condition")?;
 
     // Increment -- for (;;HERE) {}
     let increment = self
@@ -217,43 +609,84 @@ impl LoxParser {
     self.consume(TokenKind::RightParen)?;
 
     // Body -- for (;;) HERE
-    let for_body = self.statement()?;
+    let body = self.statement().map(Box::new)?;
 
-    // Assemble all together
-    let while_body = match increment {
-      Some(inc) => Stmt::ScopeBlock(vec![for_body, Stmt::Expr(inc)]),
-      None => for_body,
-    };
+    Ok(StmtKind::For {
+      initializer,
+      condition,
+      increment,
+      body,
+      line,
+    })
+  }
 
-    let while_stmt = Stmt::While {
-      condition: condition.unwrap_or(Expr::LiteralBool { value: true }),
-      body: Box::new(while_body),
+  fn return_stmt(&mut self, line: usize) -> Result<StmtKind, ParseError> {
+    let expr = if let Some(TokenKind::Semicolon) = self.peek_kind() {
+      // A bare `return` gives `nil`, which stands where the `return` is.
+      self.expr(self.previous_span(), ExprKind::LiteralNil)
+    } else {
+      self.expression()?
     };
+    self.consume_semicolon("return value")?;
+    Ok(StmtKind::Return(expr, line))
+  }
 
-    let mut stmts = match declaration {
-      Some(stmt) => vec![stmt],
-      None => vec![],
-    };
+  fn assert_stmt(&mut self, line: usize) -> Result<StmtKind, ParseError> {
+    self.consume(TokenKind::LeftParen)?;
+    let condition = self.expression()?;
+    let message = self
+      .advance_if_match(&[TokenKind::Comma])
+      .map(|_| ())
+      .map(|_| self.expression())
+      .transpose()?;
+    self.consume(TokenKind::RightParen)?;
+    self.consume_semicolon("assertion")?;
+    Ok(StmtKind::Assert {
+      condition,
+      message,
+      line,
+    })
+  }
 
-    stmts.push(while_stmt);
-    Ok(Stmt::ScopeBlock(stmts))
+  fn try_stmt(&mut self, line: usize) -> Result<StmtKind, ParseError> {
+    self.consume(TokenKind::LeftBrace)?;
+    let body = self.block_of_stmts()?;
+    self.consume(TokenKind::Catch)?;
+    self.consume(TokenKind::LeftParen)?;
+    let identifier = self.next_token()?;
+    let TokenKind::Identifier(name) = identifier.kind() else {
+      return Err(ParseError::MalformedExpression(
+        identifier.span(),
+        format!("Expected error name, got {}", identifier.lexeme()),
+      ));
+    };
+    let name = name.to_string();
+    self.consume(TokenKind::RightParen)?;
+    self.consume(TokenKind::LeftBrace)?;
+    let handler = self.block_of_stmts()?;
+    Ok(StmtKind::Try {
+      body,
+      name,
+      handler,
+      line,
+    })
   }
 
-  fn return_stmt(&mut self) -> Result<Stmt, ParseError> {
-    let expr = if let Some(TokenKind::Semicolon) = self.peek_kind() {
-      Expr::LiteralNil
-    } else {
-      self.expression()?
+  fn import_stmt(&mut self, line: usize) -> Result<StmtKind, ParseError> {
+    let token = self.next_token()?;
+    let TokenKind::String(path) = token.kind() else {
+      return Err(ParseError::MissingImportPath(token.span()));
     };
-    self.consume(TokenKind::Semicolon)?;
-    Ok(Stmt::Return(expr))
+    let path = path.to_string();
+    self.consume_semicolon("import path")?;
+    Ok(StmtKind::Import(path, line))
   }
 
-  fn expression_stmt(&mut self) -> Result<Stmt, ParseError> {
-    let stmt = Stmt::Expr(self.expression()?);
+  fn expression_stmt(&mut self) -> Result<StmtKind, ParseError> {
+    let stmt = StmtKind::Expr(self.expression()?);
 
     if !self.is_at_end() {
-      self.consume(TokenKind::Semicolon)?;
+      self.consume_semicolon("expression")?;
     }
     Ok(stmt)
   }
@@ -263,211 +696,373 @@ impl LoxParser {
   }
 
   fn assignment(&mut self) -> Result<Expr, ParseError> {
+    self.nested(Self::nested_assignment)
+  }
+
+  fn nested_assignment(&mut self) -> Result<Expr, ParseError> {
     let left = self.or()?;
+    let start = left.span;
 
     if let Some(TokenKind::Equal) = self.peek_kind() {
       let equals = self.next_token()?;
-      let equals_line = equals.line();
+      let equals_span = equals.span();
 
       // This line eagerly consumes to the right;
       let right = self.assignment()?;
 
-      if let Expr::Variable { name, line } = left {
-        return Ok(Expr::Assign {
+      let kind = match left.kind {
+        ExprKind::Variable { name, line } => ExprKind::Assign {
           name,
           value: Box::new(right),
           line,
-        });
-      } else {
-        return Err(ParseError::MalformedExpression(
-          equals_line,
-          "Invalid assignment target.".to_string(),
-        ));
-      }
+        },
+        ExprKind::Get { object, name, line } => ExprKind::Set {
+          object,
+          name,
+          value: Box::new(right),
+          line,
+        },
+        _ => {
+          return Err(ParseError::MalformedExpression(
+            equals_span,
+            "Invalid assignment target.".to_string(),
+          ))
+        }
+      };
+      return Ok(self.expr(start, kind));
     }
 
     Ok(left)
   }
 
   fn or(&mut self) -> Result<Expr, ParseError> {
-    let mut left = self.and()?;
-
-    while let Some(operator) = self.advance_if_match(&[TokenKind::Or]) {
-      let operator = operator.clone();
-      let right = self.and()?;
-      left = Expr::Logical {
-        left: Box::new(left),
-        operator,
-        right: Box::new(right),
-      };
-    }
-
-    Ok(left)
+    self.logical(TokenKind::Or, Self::and)
   }
 
   fn and(&mut self) -> Result<Expr, ParseError> {
-    let mut left = self.equality()?;
-
-    while let Some(operator) = self.advance_if_match(&[TokenKind::And]) {
-      let operator = operator.clone();
-      let right = self.equality()?;
-      left = Expr::Logical {
-        left: Box::new(left),
-        operator,
-        right: Box::new(right),
-      };
-    }
+    self.logical(TokenKind::And, Self::equality)
+  }
+
+  /// `operator` between operands parsed by `operand`, grouped to the left like other binary
+  /// operators.
+  fn logical(&mut self, operator: TokenKind, operand: Operand<Self>) -> Result<Expr, ParseError> {
+    self.chain(|parser| {
+      let mut left = operand(parser)?;
+      let start = left.span;
+
+      while let Some(operator) = parser.advance_if_match(&[operator.clone()]) {
+        let operator = operator.clone();
+        parser.deepen()?;
+        let right = operand(parser)?;
+        let kind = ExprKind::Logical {
+          left: Box::new(left),
+          operator,
+          right: Box::new(right),
+        };
+        left = parser.expr(start, kind);
+      }
 
-    Ok(left)
+      Ok(left)
+    })
   }
 
   fn equality(&mut self) -> Result<Expr, ParseError> {
-    let mut left = self.comparison()?;
+    self.left_associative(
+      &[TokenKind::EqualEqual, TokenKind::BangEqual],
+      Self::comparison,
+    )
+  }
 
-    while let Some(operator) = self.advance_if_match(&[TokenKind::EqualEqual, TokenKind::BangEqual])
-    {
-      let operator = operator.clone();
-      let right = self.comparison()?;
-      left = Expr::Binary {
-        left: Box::new(left),
-        operator,
-        right: Box::new(right),
-      };
-    }
+  fn comparison(&mut self) -> Result<Expr, ParseError> {
+    self.left_associative(
+      &[
+        TokenKind::Less,
+        TokenKind::LessEqual,
+        TokenKind::Greater,
+        TokenKind::GreaterEqual,
+      ],
+      Self::bit_or,
+    )
+  }
 
-    Ok(left)
+  /// Bitwise operators bind tighter than comparisons, so `a & 1 == 0` is `(a & 1) == 0`.
+  fn bit_or(&mut self) -> Result<Expr, ParseError> {
+    self.left_associative(&[TokenKind::Pipe], Self::bit_xor)
   }
 
-  fn comparison(&mut self) -> Result<Expr, ParseError> {
-    let mut left = self.term()?;
-
-    while let Some(operator) = self.advance_if_match(&[
-      TokenKind::Less,
-      TokenKind::LessEqual,
-      TokenKind::Greater,
-      TokenKind::GreaterEqual,
-    ]) {
-      let operator = operator.clone();
-      let right = self.term()?;
-      left = Expr::Binary {
-        left: Box::new(left),
-        operator,
-        right: Box::new(right),
-      };
-    }
+  fn bit_xor(&mut self) -> Result<Expr, ParseError> {
+    self.left_associative(&[TokenKind::Caret], Self::bit_and)
+  }
 
-    Ok(left)
+  fn bit_and(&mut self) -> Result<Expr, ParseError> {
+    self.left_associative(&[TokenKind::Ampersand], Self::shift)
+  }
+
+  fn shift(&mut self) -> Result<Expr, ParseError> {
+    self.left_associative(
+      &[TokenKind::LessLess, TokenKind::GreaterGreater],
+      Self::term,
+    )
   }
 
   fn term(&mut self) -> Result<Expr, ParseError> {
-    let mut left = self.factor()?;
+    self.left_associative(&[TokenKind::Plus, TokenKind::Minus], Self::factor)
+  }
 
-    while let Some(operator) = self.advance_if_match(&[TokenKind::Plus, TokenKind::Minus]) {
-      let operator = operator.clone();
-      let right = self.factor()?;
-      left = Expr::Binary {
-        left: Box::new(left),
-        operator,
-        right: Box::new(right),
-      };
-    }
+  fn factor(&mut self) -> Result<Expr, ParseError> {
+    self.left_associative(&[TokenKind::Star, TokenKind::Slash], Self::power)
+  }
+
+  /// Binary operators in `operators`, grouped to the left, between operands parsed by `operand`.
+  fn left_associative(
+    &mut self,
+    operators: &[TokenKind],
+    operand: Operand<Self>,
+  ) -> Result<Expr, ParseError> {
+    self.chain(|parser| {
+      let mut left = operand(parser)?;
+      let start = left.span;
+
+      while let Some(operator) = parser.advance_if_match(operators) {
+        let operator = operator.clone();
+        parser.deepen()?;
+        let right = operand(parser)?;
+        let kind = ExprKind::Binary {
+          left: Box::new(left),
+          operator,
+          right: Box::new(right),
+        };
+        left = parser.expr(start, kind);
+      }
 
-    Ok(left)
+      Ok(left)
+    })
   }
 
-  fn factor(&mut self) -> Result<Expr, ParseError> {
-    let mut left = self.unary()?;
+  /// `**` groups to the right, so `2 ** 3 ** 2` is `2 ** (3 ** 2)`. Unary operators bind tighter:
+  /// `-2 ** 2` is `(-2) ** 2`.
+  fn power(&mut self) -> Result<Expr, ParseError> {
+    let base = self.unary()?;
+    let start = base.span;
 
-    while let Some(operator) = self.advance_if_match(&[TokenKind::Star, TokenKind::Slash]) {
+    if let Some(operator) = self.advance_if_match(&[TokenKind::StarStar]) {
       let operator = operator.clone();
-      let right = self.unary()?;
-      left = Expr::Binary {
-        left: Box::new(left),
+      let exponent = self.nested(Self::power)?;
+      let kind = ExprKind::Binary {
+        left: Box::new(base),
         operator,
-        right: Box::new(right),
+        right: Box::new(exponent),
       };
+      return Ok(self.expr(start, kind));
     }
 
-    Ok(left)
+    Ok(base)
   }
 
   fn unary(&mut self) -> Result<Expr, ParseError> {
-    if let Some(operator) = self.advance_if_match(&[TokenKind::Minus, TokenKind::Bang]) {
+    if let Some(operator) = self.advance_if_match(&[TokenKind::PlusPlus, TokenKind::MinusMinus]) {
       let operator = operator.clone();
-      let expr = self.unary()?;
-      return Ok(Expr::Unary {
-        operator,
+      let target = self.nested(Self::unary)?;
+      return self.increment(operator, target, false);
+    }
+
+    if let Some(operator) =
+      self.advance_if_match(&[TokenKind::Minus, TokenKind::Bang, TokenKind::Tilde])
+    {
+      let operator = operator.clone();
+      let expr = self.nested(Self::unary)?;
+      let kind = ExprKind::Unary {
+        operator: operator.clone(),
         right: Box::new(expr),
-      });
+      };
+      return Ok(self.expr(operator.span(), kind));
     }
 
     self.call()
   }
 
   fn call(&mut self) -> Result<Expr, ParseError> {
-    let mut expr = self.primary()?;
+    let expr = self.chain(|parser| {
+      let mut expr = parser.primary()?;
+      loop {
+        expr = match parser.peek_kind() {
+          Some(TokenKind::LeftParen) => {
+            parser.deepen()?;
+            parser.finish_call(expr)?
+          }
+          Some(TokenKind::Dot) => {
+            parser.deepen()?;
+            parser.property(expr)?
+          }
+          _ => return Ok(expr),
+        }
+      }
+    })?;
 
-    while let Some(TokenKind::LeftParen) = self.peek_kind() {
-      let paren_line = self.consume(TokenKind::LeftParen)?.line();
+    match self.advance_if_match(&[TokenKind::PlusPlus, TokenKind::MinusMinus]) {
+      Some(operator) => {
+        let operator = operator.clone();
+        self.increment(operator, expr, true)
+      }
+      None => Ok(expr),
+    }
+  }
 
-      // let args = vec![];
-      let args = if let Some(_) = self.advance_if_match(&[TokenKind::RightParen]) {
-        vec![]
-      } else {
-        let mut args = vec![];
+  fn property(&mut self, object: Expr) -> Result<Expr, ParseError> {
+    let dot_line = self.consume(TokenKind::Dot)?.line();
+    let identifier = self.next_token()?;
 
-        loop {
-          if let Some(_) = self.advance_if_match(&[TokenKind::RightParen]) {
-            break;
-          }
+    if let TokenKind::Identifier(name) = identifier.kind() {
+      let start = object.span;
+      let kind = ExprKind::Get {
+        object: Box::new(object),
+        name: name.to_string(),
+        line: dot_line,
+      };
+      Ok(self.expr(start, kind))
+    } else {
+      Err(ParseError::MalformedExpression(
+        identifier.span(),
+        format!(
+          "Expected property name after '.', got {}",
+          identifier.lexeme()
+        ),
+      ))
+    }
+  }
 
-          args.push(self.expression()?);
-          self.advance_if_match(&[TokenKind::Comma]);
-        }
+  fn finish_call(&mut self, callee: Expr) -> Result<Expr, ParseError> {
+    let paren_line = self.consume(TokenKind::LeftParen)?.line();
 
-        args
-      };
+    let mut args = vec![];
+    if self.advance_if_match(&[TokenKind::RightParen]).is_none() {
+      loop {
+        if args.len() == MAX_ARGUMENTS {
+          let span = self.peek().ok_or(ParseError::UnexpectedEndOfFile)?.span();
+          return Err(ParseError::TooManyArguments(span));
+        }
+        args.push(self.expression()?);
 
-      expr = Expr::Call { line: paren_line, callee: Box::new(expr), args }
+        if self.end_of_list()? {
+          break;
+        }
+      }
     }
-    Ok(expr)
+
+    let start = callee.span;
+    let kind = ExprKind::Call {
+      line: paren_line,
+      callee: Box::new(callee),
+      args,
+    };
+    Ok(self.expr(start, kind))
   }
 
   fn primary(&mut self) -> Result<Expr, ParseError> {
     let token = self.next_token()?.clone();
 
-    match token.kind() {
-      TokenKind::Number(repr) => Ok(Expr::LiteralNumber {
-        value: repr.parse().unwrap(),
-      }),
-      TokenKind::True => Ok(Expr::LiteralBool { value: true }),
-      TokenKind::False => Ok(Expr::LiteralBool { value: false }),
-      TokenKind::String(repr) => Ok(Expr::LiteralString {
+    let kind = match token.kind() {
+      TokenKind::Number(repr) => ExprKind::LiteralNumber {
+        value: parse_number(repr)
+          .ok_or_else(|| ParseError::InvalidNumber(token.span(), repr.to_string()))?,
+        lexeme: repr.to_string(),
+      },
+      TokenKind::True => ExprKind::LiteralBool { value: true },
+      TokenKind::False => ExprKind::LiteralBool { value: false },
+      TokenKind::String(repr) => ExprKind::LiteralString {
         value: repr.to_string(),
-      }),
-      TokenKind::Nil => Ok(Expr::LiteralNil),
-      TokenKind::Identifier(name) => Ok(Expr::Variable {
+      },
+      TokenKind::Nil => ExprKind::LiteralNil,
+      TokenKind::This => ExprKind::This { line: token.line() },
+      TokenKind::Super => {
+        self.consume(TokenKind::Dot)?;
+        let identifier = self.next_token()?;
+        if let TokenKind::Identifier(method) = identifier.kind() {
+          ExprKind::Super {
+            method: method.to_string(),
+            line: token.line(),
+          }
+        } else {
+          return Err(ParseError::MalformedExpression(
+            identifier.span(),
+            format!(
+              "Expected superclass method name, got {}",
+              identifier.lexeme()
+            ),
+          ));
+        }
+      }
+      TokenKind::Identifier(name) => ExprKind::Variable {
         name: name.clone(),
         line: token.line(),
-      }),
+      },
       TokenKind::LeftParen => {
         let res = self.expression()?;
 
         self.consume(TokenKind::RightParen).map_err(|_| {
-          ParseError::MalformedExpression(token.line(), "Missing closing parenthesis".to_string())
+          ParseError::MalformedExpression(token.span(), "Missing closing parenthesis".to_string())
         })?;
 
-        Ok(Expr::Group {
+        ExprKind::Group {
           expression: Box::new(res),
-        })
+        }
       }
-      TokenKind::Eof => Err(ParseError::MalformedExpression(
-        token.line(),
-        "Unexpected end of file".to_string(),
-      )),
-      _ => Err(ParseError::MalformedExpression(
-        token.line(),
-        format!("Expected expression got `{}`", token.symbol()),
+      TokenKind::Eof => {
+        return Err(ParseError::MalformedExpression(
+          token.span(),
+          "Unexpected end of file".to_string(),
+        ))
+      }
+      _ => {
+        if let Some(operand) = Self::right_operand(token.kind()) {
+          // The right-hand side is parsed only to skip it, so parsing resumes after the whole
+          // expression. Errors in it would be caused by the missing operand as well.
+          let _ = self.nested(operand);
+          return Err(ParseError::MissingLeftOperand(
+            token.span(),
+            token.kind().symbol(),
+          ));
+        }
+        return Err(ParseError::MalformedExpression(
+          token.span(),
+          format!("Expected expression got `{}`", token.lexeme()),
+        ));
+      }
+    };
+    Ok(self.expr(token.span(), kind))
+  }
+
+  /// How the right-hand side of a binary `operator` is parsed, or `None` if it isn't one. `-` is
+  /// left out because it's also a unary operator.
+  fn right_operand(operator: &TokenKind) -> Option<Operand<Self>> {
+    let operand: Operand<Self> = match operator {
+      TokenKind::Or => Self::and,
+      TokenKind::And => Self::equality,
+      TokenKind::EqualEqual | TokenKind::BangEqual => Self::comparison,
+      TokenKind::Less | TokenKind::LessEqual | TokenKind::Greater | TokenKind::GreaterEqual => {
+        Self::bit_or
+      }
+      TokenKind::Pipe => Self::bit_xor,
+      TokenKind::Caret => Self::bit_and,
+      TokenKind::Ampersand => Self::shift,
+      TokenKind::LessLess | TokenKind::GreaterGreater => Self::term,
+      TokenKind::Plus => Self::factor,
+      TokenKind::Star | TokenKind::Slash | TokenKind::StarStar => Self::power,
+      _ => return None,
+    };
+    Some(operand)
+  }
+
+  /// After an item of an argument or parameter list: `)` ends it and `,` has to be followed by
+  /// another item, so neither missing nor trailing commas are allowed.
+  fn end_of_list(&mut self) -> Result<bool, ParseError> {
+    let next = self.next_token()?;
+    match next.kind() {
+      TokenKind::RightParen => Ok(true),
+      TokenKind::Comma => Ok(false),
+      other => Err(ParseError::MalformedExpression(
+        next.span(),
+        format!("Expected ',' or ')', got {}", other.symbol()),
       )),
     }
   }
@@ -475,22 +1070,23 @@ impl LoxParser {
   fn advance_if_match(&mut self, options: &[TokenKind]) -> Option<&Token> {
     if let Some(token) = self.peek() {
       if options.iter().any(|opt| opt == token.kind()) {
-        let res = Some(token);
         self.inc();
-        return res;
+        return self.previous.as_ref();
       }
     }
     None
   }
 
   fn next_token(&mut self) -> Result<&Token, ParseError> {
-    let res = self.tokens.get(self.pos());
     self.inc();
-    res.ok_or(ParseError::UnexpectedEndOfFile)
+    self
+      .previous
+      .as_ref()
+      .ok_or(ParseError::UnexpectedEndOfFile)
   }
 
   fn peek(&self) -> Option<&Token> {
-    self.tokens.get(self.pos())
+    self.next.as_ref()
   }
 
   fn peek_kind(&self) -> Option<&TokenKind> {
@@ -501,6 +1097,60 @@ impl LoxParser {
     self.peek().is_some_and(|t| *t.kind() == TokenKind::Eof)
   }
 
+  /// `++a` and `a++` and their decrements. Only variables can be incremented.
+  fn increment(
+    &mut self,
+    operator: Token,
+    target: Expr,
+    postfix: bool,
+  ) -> Result<Expr, ParseError> {
+    let ExprKind::Variable { name, line } = target.kind else {
+      return Err(ParseError::MalformedExpression(
+        operator.span(),
+        "Invalid increment target.".to_string(),
+      ));
+    };
+    let start = if postfix {
+      target.span
+    } else {
+      operator.span()
+    };
+    let increment = ExprKind::Increment {
+      name,
+      operator,
+      postfix,
+      line,
+    };
+    Ok(self.expr(start, increment))
+  }
+
+  /// Consumes the `;` that ends a statement. A missing one is reported right after `what` ends,
+  /// where the `;` goes, instead of at the next token, which may be lines below.
+  fn consume_semicolon(&mut self, what: &str) -> Result<(), ParseError> {
+    if self.peek_kind() == Some(&TokenKind::Semicolon) {
+      self.inc();
+      return Ok(());
+    }
+    Err(ParseError::MissingSemicolon(
+      self.end_of_previous(),
+      what.to_string(),
+    ))
+  }
+
+  /// Empty span right after the last consumed token.
+  fn end_of_previous(&self) -> Span {
+    let Some(token) = &self.previous else {
+      return Span::default();
+    };
+    let span = token.span();
+    // Column 0 means the column is unknown, so it stays unknown.
+    let column = match span.column {
+      0 => 0,
+      column => column + token.lexeme().chars().count(),
+    };
+    Span::new(span.line, column, span.byte_offset + span.length, 0)
+  }
+
   fn consume(&mut self, kind: TokenKind) -> Result<&Token, ParseError> {
     let next = self.next_token()?;
 
@@ -509,7 +1159,7 @@ impl LoxParser {
     }
 
     Err(ParseError::MalformedExpression(
-      next.line(),
+      next.span(),
       format!("Expected {}, got {}", kind.symbol(), next.kind().symbol()),
     ))
   }
@@ -520,6 +1170,7 @@ mod tests {
   use super::*;
   use crate::parse::print_ast::PrintAst;
   use crate::scan::scanner::Scanner;
+  use crate::scan::span::Span;
   use std::io::Cursor;
 
   fn parser(tokens: Vec<Token>) -> LoxParser {
@@ -531,9 +1182,9 @@ mod tests {
     tokens.push(Token::new(TokenKind::Eof, 1));
     let parser = parser(tokens);
     let res = parser.parse().unwrap().pop().unwrap();
-    let visitor = PrintAst {};
-    match res {
-      Stmt::Expr(expr) => visitor.print_expr(&expr),
+    let mut visitor = PrintAst {};
+    match res.kind {
+      StmtKind::Expr(expr) => visitor.print_expr(&expr),
       _ => panic!("should not be this"),
     }
   }
@@ -545,6 +1196,18 @@ mod tests {
     assert_eq!(representation, "1.2");
   }
 
+  #[test]
+  fn a_malformed_number_token_is_a_parse_error() {
+    let number_token = Token::new(TokenKind::Number("0x".to_string()), 1);
+    let tokens = vec![
+      number_token,
+      Token::new(TokenKind::Semicolon, 1),
+      Token::new(TokenKind::Eof, 1),
+    ];
+    let errors = parser(tokens).parse().unwrap_err();
+    assert!(matches!(&errors[..], [ParseError::InvalidNumber(_, repr)] if repr == "0x"));
+  }
+
   #[test]
   fn parse_true_returns_a_boolean_expr() {
     let bool_token = Token::new(TokenKind::True, 1);
@@ -820,7 +1483,10 @@ mod tests {
     assert!(res.is_err());
     assert_eq!(
       res.unwrap_err(),
-      ParseError::MalformedExpression(1, "Missing closing parenthesis".to_string())
+      vec![ParseError::MalformedExpression(
+        Span::at_line(1),
+        "Missing closing parenthesis".to_string()
+      )]
     );
   }
 
@@ -838,7 +1504,10 @@ mod tests {
     assert!(res.is_err());
     assert_eq!(
       res.unwrap_err(),
-      ParseError::MalformedExpression(1, "Unexpected end of file".to_string())
+      vec![ParseError::MalformedExpression(
+        Span::at_line(1),
+        "Unexpected end of file".to_string()
+      )]
     );
   }
 
@@ -872,6 +1541,15 @@ mod tests {
     assert_eq!(ast, "(+ 1.0 1.0) (+ 2.0 2.0)");
   }
 
+  #[test]
+  fn functions_can_be_declared_inside_blocks_and_functions() {
+    let ast = parse_from_code("{ fun f() { fun g() {} return g; } }");
+    assert_eq!(
+      ast,
+      "(block_scope (fun_def `f` () ((fun_def `g` () ()) (return `g`))))"
+    );
+  }
+
   #[test]
   fn can_parse_variables() {
     let ast = parse_from_code("var foo = 1;");
@@ -941,6 +1619,12 @@ mod tests {
     assert_eq!(ast, "(while (< `a` 10.0) 1.0)");
   }
 
+  #[test]
+  fn can_parse_do_while_stmts() {
+    let ast = parse_from_code("do { 1; } while (a < 10);");
+    assert_eq!(ast, "(do_while (block_scope 1.0) (< `a` 10.0))");
+  }
+
   #[test]
   fn can_parse_and_expr() {
     let ast = parse_from_code("10 and 1;");
@@ -956,7 +1640,10 @@ mod tests {
   #[test]
   fn can_parse_a_for_expr() {
     let ast = parse_from_code("for (var i = 0; i < 3; i = i + 1) print i;");
-    assert_eq!(ast, "(block_scope (def_var `i` 0.0) (while (< `i` 3.0) (block_scope (print `i`) (assign_var `i` (+ `i` 1.0)))))");
+    assert_eq!(
+      ast,
+      "(for (def_var `i` 0.0) (< `i` 3.0) (assign_var `i` (+ `i` 1.0)) (print `i`))"
+    );
   }
 
   #[test]
@@ -964,7 +1651,7 @@ mod tests {
     let ast = parse_from_code("for (; i < 3; i = i + 1) print i;");
     assert_eq!(
       ast,
-      "(block_scope (while (< `i` 3.0) (block_scope (print `i`) (assign_var `i` (+ `i` 1.0)))))"
+      "(for () (< `i` 3.0) (assign_var `i` (+ `i` 1.0)) (print `i`))"
     );
   }
 
@@ -973,17 +1660,20 @@ mod tests {
     let ast = parse_from_code("for (var i = 0;; i = i + 1) print i;");
     assert_eq!(
       ast,
-      "(block_scope (def_var `i` 0.0) (while true (block_scope (print `i`) (assign_var `i` (+ `i` 1.0)))))"
+      "(for (def_var `i` 0.0) () (assign_var `i` (+ `i` 1.0)) (print `i`))"
     );
   }
 
   #[test]
   fn can_parse_a_for_with_no_increment() {
     let ast = parse_from_code("for (i = 0; i < 3;) print i;");
-    assert_eq!(
-      ast,
-      "(block_scope (assign_var `i` 0.0) (while (< `i` 3.0) (print `i`)))"
-    );
+    assert_eq!(ast, "(for (assign_var `i` 0.0) (< `i` 3.0) () (print `i`))");
+  }
+
+  #[test]
+  fn can_parse_break_and_continue() {
+    let ast = parse_from_code("while (true) { continue; break; }");
+    assert_eq!(ast, "(while true (block_scope (continue) (break)))");
   }
 
   #[test]
@@ -1044,4 +1734,537 @@ mod tests {
     let ast = parse_from_code("fun foo () { return;}");
     assert_eq!(ast, "(fun_def `foo` () ((return nil)))")
   }
+
+  #[test]
+  fn can_parse_an_empty_class() {
+    let ast = parse_from_code("class Foo {}");
+    assert_eq!(ast, "(class_def `Foo` ())")
+  }
+
+  #[test]
+  fn can_parse_a_class_with_methods() {
+    let ast = parse_from_code("class Foo { bar() { return 1; } baz(a) {} }");
+    assert_eq!(
+      ast,
+      "(class_def `Foo` ((fun_def `bar` () ((return 1.0))) (fun_def `baz` (`a`) ())))"
+    )
+  }
+
+  #[test]
+  fn class_without_name_is_an_error() {
+    let mut cursor = Cursor::new("class {}");
+    let tokens = Scanner::new(&mut cursor).scan_tokens().0;
+    let res = LoxParser::new(tokens).parse();
+    assert_eq!(
+      res.unwrap_err(),
+      vec![ParseError::MissingClassName(Span::new(1, 7, 6, 1))]
+    );
+  }
+
+  #[test]
+  fn can_parse_property_access() {
+    let ast = parse_from_code("a.b.c;");
+    assert_eq!(ast, "(get (get `a` `b`) `c`)")
+  }
+
+  #[test]
+  fn can_parse_method_calls() {
+    let ast = parse_from_code("a.b(1).c();");
+    assert_eq!(ast, "(call (get (call (get `a` `b`) (1.0)) `c`) ())")
+  }
+
+  #[test]
+  fn can_parse_property_assignment() {
+    let ast = parse_from_code("a.b.c = 1 + 2;");
+    assert_eq!(ast, "(set (get `a` `b`) `c` (+ 1.0 2.0))")
+  }
+
+  #[test]
+  fn property_name_has_to_be_an_identifier() {
+    let mut cursor = Cursor::new("a.1;");
+    let tokens = Scanner::new(&mut cursor).scan_tokens().0;
+    let res = LoxParser::new(tokens).parse();
+    assert_eq!(
+      res.unwrap_err(),
+      vec![ParseError::MalformedExpression(
+        Span::new(1, 3, 2, 1),
+        "Expected property name after '.', got 1".to_string()
+      )]
+    );
+  }
+
+  #[test]
+  fn can_parse_this() {
+    let ast = parse_from_code("class Foo { bar() { return this.baz; } }");
+    assert_eq!(
+      ast,
+      "(class_def `Foo` ((fun_def `bar` () ((return (get this `baz`))))))"
+    )
+  }
+
+  #[test]
+  fn can_parse_class_methods_and_getters() {
+    let ast = parse_from_code("class Foo { class make() {} bar() {} baz { return 1; } }");
+    assert_eq!(
+      ast,
+      "(class_def `Foo` ((fun_def `bar` () ())) (class_methods (fun_def `make` () ())) (getters (fun_def `baz` () ((return 1.0)))))"
+    )
+  }
+
+  #[test]
+  fn can_parse_a_subclass() {
+    let ast = parse_from_code("class B < A { foo() { super.foo(); } }");
+    assert_eq!(
+      ast,
+      "(class_def `B` < `A` ((fun_def `foo` () ((call (super `foo`) ())))))"
+    )
+  }
+
+  #[test]
+  fn class_cannot_inherit_from_itself() {
+    let mut cursor = Cursor::new("class A < A {}");
+    let tokens = Scanner::new(&mut cursor).scan_tokens().0;
+    let res = LoxParser::new(tokens).parse();
+    assert_eq!(
+      res.unwrap_err(),
+      vec![ParseError::InheritFromSelf(Span::new(1, 11, 10, 1))]
+    );
+  }
+
+  #[test]
+  fn calls_can_pass_up_to_255_arguments() {
+    let args = vec!["1"; 255].join(", ");
+    let mut cursor = Cursor::new(format!("f({args});"));
+    let tokens = Scanner::new(&mut cursor).scan_tokens().0;
+    assert!(LoxParser::new(tokens).parse().is_ok());
+  }
+
+  #[test]
+  fn calls_with_more_than_255_arguments_are_an_error() {
+    let args = vec!["1"; 256].join(", ");
+    let errors = parse_errors(&format!("f({args});"));
+    assert_eq!(
+      errors,
+      vec![ParseError::TooManyArguments(Span::new(1, 768, 767, 1))]
+    );
+  }
+
+  #[test]
+  fn functions_with_more_than_255_parameters_are_an_error() {
+    let params: Vec<String> = (0..256).map(|i| format!("p{i}")).collect();
+    let errors = parse_errors(&format!("\nfun f({}) {{}}", params.join(",")));
+    assert!(matches!(
+      errors.as_slice(),
+      [ParseError::TooManyParameters(Span { line: 2, .. })]
+    ));
+  }
+
+  #[test]
+  fn call_arguments_must_be_separated_by_commas() {
+    assert_eq!(
+      parse_errors("somefunc(1 2 3);"),
+      vec![ParseError::MalformedExpression(
+        Span::new(1, 12, 11, 1),
+        "Expected ',' or ')', got 2".to_string()
+      )]
+    );
+  }
+
+  #[test]
+  fn call_arguments_cannot_end_with_a_comma() {
+    assert_eq!(
+      parse_errors("f(1,);"),
+      vec![ParseError::MalformedExpression(
+        Span::new(1, 5, 4, 1),
+        "Expected expression got `)`".to_string()
+      )]
+    );
+  }
+
+  #[test]
+  fn parameters_must_be_separated_by_commas() {
+    assert_eq!(
+      parse_errors("fun f(a b) {}"),
+      vec![ParseError::MalformedExpression(
+        Span::new(1, 9, 8, 1),
+        "Expected ',' or ')', got b".to_string()
+      )]
+    );
+  }
+
+  #[test]
+  fn parameters_cannot_end_with_a_comma() {
+    assert_eq!(
+      parse_errors("fun f(a,) {}"),
+      vec![ParseError::MissingParameterName(Span::new(1, 9, 8, 1))]
+    );
+  }
+
+  #[test]
+  fn function_bodies_must_be_blocks() {
+    assert_eq!(
+      parse_errors("fun f(a) return a;"),
+      vec![ParseError::MissingFunctionBody(Span::new(1, 10, 9, 6))]
+    );
+  }
+
+  fn parse_expression(code: &str) -> Result<Expr, ParseError> {
+    let mut cursor = Cursor::new(code);
+    let tokens = Scanner::new(&mut cursor).scan_tokens().0;
+    LoxParser::new(tokens).parse_expression()
+  }
+
+  #[test]
+  fn parse_expression_parses_a_single_expression() {
+    let expr = parse_expression("1 + 2 * x").unwrap();
+    assert_eq!(PrintAst::new().print_expr(&expr), "(+ 1.0 (* 2.0 `x`))");
+  }
+
+  #[test]
+  fn parse_expression_rejects_trailing_tokens() {
+    assert_eq!(
+      parse_expression("1 + 2; 3").unwrap_err(),
+      ParseError::MalformedExpression(
+        Span::new(1, 6, 5, 1),
+        "Expected end of expression, got ;".to_string()
+      )
+    );
+  }
+
+  #[test]
+  fn parse_expression_of_nothing_is_an_error() {
+    assert!(parse_expression("").is_err());
+    assert!(parse_expression("var a = 1;").is_err());
+  }
+
+  #[test]
+  fn nodes_span_from_their_first_token_to_their_last() {
+    let mut cursor = Cursor::new("var x;\nprint a + bc;");
+    let tokens = Scanner::new(&mut cursor).scan_tokens().0;
+    let stmts = LoxParser::new(tokens).parse().unwrap();
+    assert_eq!(stmts[1].span, Span::new(2, 1, 7, 13));
+    let StmtKind::Print(sum, _) = &stmts[1].kind else {
+      panic!("expected a print statement");
+    };
+    assert_eq!(sum.span, Span::new(2, 7, 13, 6));
+    assert_eq!(stmts[0].span, Span::new(1, 1, 0, 6));
+  }
+
+  #[test]
+  fn every_node_gets_its_own_id() {
+    let mut cursor = Cursor::new("a = -b;");
+    let tokens = Scanner::new(&mut cursor).scan_tokens().0;
+    let stmt = LoxParser::new(tokens).parse().unwrap().remove(0);
+    let StmtKind::Expr(assign) = &stmt.kind else {
+      panic!("expected an expression statement");
+    };
+    let ExprKind::Assign {
+      value: negation, ..
+    } = &assign.kind
+    else {
+      panic!("expected an assignment");
+    };
+    let ExprKind::Unary { right: b, .. } = &negation.kind else {
+      panic!("expected a negation");
+    };
+    let ids = [b.id, negation.id, assign.id, stmt.id];
+    assert_eq!(ids, [NodeId(1), NodeId(2), NodeId(3), NodeId(4)]);
+  }
+
+  #[test]
+  fn imports_take_a_path_string() {
+    let mut cursor = Cursor::new("import \"lib/util.lox\";");
+    let tokens = Scanner::new(&mut cursor).scan_tokens().0;
+    let stmts = LoxParser::new(tokens).parse().unwrap();
+    assert_eq!(stmts.len(), 1);
+    assert_eq!(
+      stmts[0].kind,
+      StmtKind::Import("lib/util.lox".to_string(), 1)
+    );
+    assert_eq!(
+      parse_errors("import util;"),
+      vec![ParseError::MissingImportPath(Span::new(1, 8, 7, 4))]
+    );
+  }
+
+  #[test]
+  fn assert_takes_a_condition_and_an_optional_message() {
+    let mut cursor = Cursor::new("assert(a); assert(a == 1, \"one\");");
+    let tokens = Scanner::new(&mut cursor).scan_tokens().0;
+    let stmts = LoxParser::new(tokens).parse().unwrap();
+    assert_eq!(
+      PrintAst::new().print_stmts(&stmts),
+      "(assert `a`) (assert (== `a` 1.0) one)"
+    );
+    assert!(!parse_errors("assert();").is_empty());
+  }
+
+  #[test]
+  fn try_takes_a_block_and_a_catch_with_the_error_name() {
+    let mut cursor = Cursor::new("try { print 1; } catch (e) { print e; }");
+    let tokens = Scanner::new(&mut cursor).scan_tokens().0;
+    let stmts = LoxParser::new(tokens).parse().unwrap();
+    assert_eq!(
+      PrintAst::new().print_stmts(&stmts),
+      "(try ((print 1.0)) `e` ((print `e`)))"
+    );
+    assert!(!parse_errors("try { print 1; }").is_empty());
+    assert!(!parse_errors("throw;").is_empty());
+    assert!(!parse_errors("try { print 1; } catch { print 2; }").is_empty());
+  }
+
+  #[test]
+  fn comments_from_trivia_tokens_are_skipped() {
+    let mut cursor = Cursor::new("print 1; // one\n// done\nprint 2;");
+    let tokens = Scanner::new(&mut cursor).with_trivia().scan_tokens().0;
+    let stmts = LoxParser::new(tokens).parse().unwrap();
+    assert_eq!(
+      PrintAst::new().print_stmts(&stmts),
+      "(print 1.0) (print 2.0)"
+    );
+  }
+
+  fn parse_errors(code: &str) -> Vec<ParseError> {
+    let mut cursor = Cursor::new(code);
+    let tokens = Scanner::new(&mut cursor).scan_tokens().0;
+    LoxParser::new(tokens).parse().unwrap_err()
+  }
+
+  #[test]
+  fn exponents_group_to_the_right_between_factors_and_unary() {
+    let mut cursor = Cursor::new("2 * -a ** b ** 3;");
+    let tokens = Scanner::new(&mut cursor).scan_tokens().0;
+    let stmts = LoxParser::new(tokens).parse().unwrap();
+    assert_eq!(
+      PrintAst::new().print_stmts(&stmts),
+      "(* 2.0 (** (- `a`) (** `b` 3.0)))"
+    );
+  }
+
+  #[test]
+  fn bitwise_operators_bind_tighter_than_comparisons() {
+    let mut cursor = Cursor::new("a | b ^ c & d << 1 + 2 == ~e < f;");
+    let tokens = Scanner::new(&mut cursor).scan_tokens().0;
+    let stmts = LoxParser::new(tokens).parse().unwrap();
+    assert_eq!(
+      PrintAst::new().print_stmts(&stmts),
+      "(== (| `a` (^ `b` (& `c` (<< `d` (+ 1.0 2.0))))) (< (~ `e`) `f`))"
+    );
+  }
+
+  #[test]
+  fn increments_of_variables() {
+    let mut cursor = Cursor::new("++a; a--;");
+    let tokens = Scanner::new(&mut cursor).scan_tokens().0;
+    let stmts = LoxParser::new(tokens).parse().unwrap();
+    assert_eq!(PrintAst::new().print_stmts(&stmts), "(++ `a`) (`a` --)");
+    assert_eq!(
+      parse_errors("++a.b;"),
+      vec![ParseError::MalformedExpression(
+        Span::new(1, 1, 0, 2),
+        "Invalid increment target.".to_string()
+      )]
+    );
+    assert!(!parse_errors("1++;").is_empty());
+  }
+
+  #[test]
+  fn every_statement_with_errors_is_reported() {
+    let errors = parse_errors("print 1 +;\nvar a = 2;\nvar = 3;\nprint a;\n(4;");
+    assert_eq!(
+      errors,
+      vec![
+        ParseError::MalformedExpression(
+          Span::new(1, 10, 9, 1),
+          "Expected expression got `;`".to_string()
+        ),
+        ParseError::MalformedExpression(
+          Span::new(3, 5, 26, 1),
+          "Expected identifier, got =".to_string()
+        ),
+        ParseError::MalformedExpression(
+          Span::new(5, 1, 40, 1),
+          "Missing closing parenthesis".to_string()
+        ),
+      ]
+    );
+  }
+
+  #[test]
+  fn a_missing_semicolon_is_reported_where_it_goes() {
+    let errors = parse_errors("print \"total\"\n\n  + 1\nvar b = 2;\nreturn b\n");
+    assert_eq!(
+      errors,
+      vec![
+        ParseError::MissingSemicolon(Span::new(3, 6, 20, 0), "value".to_string()),
+        ParseError::MissingSemicolon(Span::new(5, 9, 40, 0), "return value".to_string()),
+      ]
+    );
+    assert_eq!(
+      errors[0].to_string(),
+      "[line 3, column 6]: Expected ';' after value. Add one at the end of the statement."
+    );
+  }
+
+  #[test]
+  fn a_binary_operator_without_left_operand_skips_its_right_operand() {
+    let errors = parse_errors("+ 3;\nvar a = * 2 * 4;\nprint a == == 1;\nprint a;");
+    assert_eq!(
+      errors,
+      vec![
+        ParseError::MissingLeftOperand(Span::new(1, 1, 0, 1), "+".to_string()),
+        ParseError::MissingLeftOperand(Span::new(2, 9, 13, 1), "*".to_string()),
+        ParseError::MissingLeftOperand(Span::new(3, 12, 33, 2), "==".to_string()),
+      ]
+    );
+    assert_eq!(
+      errors[0].to_string(),
+      "[line 1, column 1]: Binary operator '+' missing left-hand operand."
+    );
+  }
+
+  #[test]
+  fn recovery_skips_to_the_next_statement_keyword() {
+    let errors = parse_errors("var a = 1 2 3 print 4;\nclass {}\nfun () {}");
+    assert_eq!(
+      errors,
+      vec![
+        ParseError::MissingSemicolon(Span::new(1, 10, 9, 0), "variable declaration".to_string()),
+        ParseError::MissingClassName(Span::new(2, 7, 29, 1)),
+        ParseError::MissingFunctionName(Span::new(3, 5, 36, 1)),
+      ]
+    );
+  }
+
+  #[test]
+  fn every_error_in_a_block_is_reported() {
+    let src = "fun f() {\n  var a = ;\n  print a\n  var b = 1 +;\n  print b;\n}\nprint 2 +;";
+    let errors = parse_errors(src);
+    let lines = errors
+      .iter()
+      .map(|e| e.span().map(|span| span.line))
+      .collect::<Vec<_>>();
+    assert_eq!(lines, [Some(2), Some(3), Some(4), Some(7)]);
+    assert!(matches!(errors[1], ParseError::MissingSemicolon(..)));
+  }
+
+  #[test]
+  fn errors_inside_blocks_do_not_stop_the_parser() {
+    let errors = parse_errors("fun foo() {\n  print 1 +;\n}\nprint 2 +;");
+    assert_eq!(
+      errors,
+      vec![
+        ParseError::MalformedExpression(
+          Span::new(2, 12, 23, 1),
+          "Expected expression got `;`".to_string()
+        ),
+        ParseError::MalformedExpression(
+          Span::new(4, 10, 36, 1),
+          "Expected expression got `;`".to_string()
+        ),
+      ]
+    );
+  }
+
+  #[test]
+  fn parses_tokens_straight_from_a_scanner() {
+    let mut cursor = Cursor::new("var a = 1; // one\nprint a;");
+    let scanner = Scanner::new(&mut cursor).with_trivia();
+    let stmts = LoxParser::from_tokens(scanner).parse().unwrap();
+    assert_eq!(
+      PrintAst::new().print_stmts(&stmts),
+      "(def_var `a` 1.0) (print `a`)"
+    );
+  }
+
+  #[test]
+  fn scan_errors_are_reported_with_the_parse_errors() {
+    let mut cursor = Cursor::new("print @;\nprint );");
+    let errors = LoxParser::from_tokens(Scanner::new(&mut cursor))
+      .parse()
+      .unwrap_err();
+    assert_eq!(
+      errors[0],
+      ParseError::Scan("[line 1] Error: Unexpected character: @".to_string())
+    );
+    assert!(matches!(errors[1], ParseError::MalformedExpression(..)));
+  }
+
+  /// Debug builds need more stack than test threads get to parse code nested as deep as the
+  /// default limit, so these tests get as much as the CLI gives the interpreter.
+  fn on_big_stack(run: impl FnOnce() + Send + 'static) {
+    std::thread::Builder::new()
+      .stack_size(64 * 1024 * 1024)
+      .spawn(run)
+      .unwrap()
+      .join()
+      .unwrap();
+  }
+
+  #[test]
+  fn nesting_past_the_limit_is_an_error_instead_of_a_stack_overflow() {
+    on_big_stack(|| {
+      let deep = format!("print {}1{};", "(".repeat(100_000), ")".repeat(100_000));
+      let errors = parse_errors(&deep);
+      assert_eq!(errors.len(), 1);
+      assert!(matches!(errors[0], ParseError::TooDeeplyNested(_)));
+      assert!(parse_errors(&format!("print {}1;", "-".repeat(100_000)))
+        .iter()
+        .any(|e| matches!(e, ParseError::TooDeeplyNested(_))));
+      assert!(
+        parse_errors(&format!("{}{}", "{".repeat(100_000), "}".repeat(100_000)))
+          .iter()
+          .any(|e| matches!(e, ParseError::TooDeeplyNested(_)))
+      );
+    });
+  }
+
+  #[test]
+  fn long_chains_of_operators_and_calls_count_as_nesting() {
+    let chained = |code: &str| {
+      parse_errors(code)
+        .iter()
+        .any(|e| matches!(e, ParseError::TooDeeplyNested(_)))
+    };
+    assert!(chained(&format!("print 1{};", " + 1".repeat(20_000))));
+    assert!(chained(&format!("print a{};", " or a".repeat(20_000))));
+    assert!(chained(&format!("f{};", "()".repeat(100_000))));
+    assert!(chained(&format!("print a{};", ".b".repeat(100_000))));
+
+    for code in [
+      format!("print 1{};", " + 1".repeat(200)),
+      format!("f{};", "()".repeat(200)),
+    ] {
+      let mut cursor = Cursor::new(code);
+      let tokens = Scanner::new(&mut cursor).scan_tokens().0;
+      assert!(LoxParser::new(tokens).parse().is_ok());
+    }
+  }
+
+  #[test]
+  fn the_nesting_limit_can_be_changed() {
+    let parse = |code: &str, max_depth| {
+      let mut cursor = Cursor::new(code);
+      let tokens = Scanner::new(&mut cursor).scan_tokens().0;
+      LoxParser::new(tokens).with_max_depth(max_depth).parse()
+    };
+    assert!(parse("print ((1));", 4).is_ok());
+    assert!(matches!(
+      parse("print (((1)));", 4).unwrap_err()[..],
+      [ParseError::TooDeeplyNested(Span { column: 10, .. })]
+    ));
+  }
+
+  #[test]
+  fn code_nested_up_to_the_default_limit_still_runs() {
+    on_big_stack(|| {
+      let nesting = DEFAULT_MAX_NESTING - 2;
+      let code = format!("print {}1{};", "(".repeat(nesting), ")".repeat(nesting));
+      let mut cursor = Cursor::new(code);
+      let tokens = Scanner::new(&mut cursor).scan_tokens().0;
+      let stmts = LoxParser::new(tokens).parse().unwrap();
+      let mut interpreter = crate::interpret::interpreter::Interpreter::new(vec![]);
+      interpreter.interpret_stmts(&stmts).unwrap();
+    });
+  }
 }
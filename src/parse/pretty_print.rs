@@ -0,0 +1,680 @@
+use crate::parse::expr::{Expr, ExprKind};
+use crate::parse::stmt::{Stmt, StmtKind};
+use crate::scan::token_kind::TokenKind;
+
+const INDENT: usize = 2;
+const MAX_WIDTH: usize = 80;
+
+/// Renders an AST back as canonical Lox source: two space indentation, one statement per line,
+/// blank lines around functions and classes, and long expressions wrapped to fit in 80 columns.
+#[derive(Default)]
+pub struct PrettyPrinter {}
+
+impl PrettyPrinter {
+  pub fn new() -> PrettyPrinter {
+    PrettyPrinter {}
+  }
+
+  pub fn print_stmts(&self, stmts: &[Stmt]) -> String {
+    let mut lines = vec![];
+    self.stmts(stmts, 0, &mut lines);
+    lines.join("\n")
+  }
+
+  pub fn print_expr(&self, expr: &Expr) -> String {
+    self.expr(expr, 0, 0)
+  }
+
+  fn stmts(&self, stmts: &[Stmt], depth: usize, lines: &mut Vec<String>) {
+    for (i, stmt) in stmts.iter().enumerate() {
+      if i > 0 && (is_declaration(stmt) || is_declaration(&stmts[i - 1])) {
+        lines.push(String::new());
+      }
+      self.stmt(stmt, depth, lines);
+    }
+  }
+
+  fn stmt(&self, stmt: &Stmt, depth: usize, lines: &mut Vec<String>) {
+    let indent = " ".repeat(depth * INDENT);
+    match &stmt.kind {
+      StmtKind::Expr(expr) => {
+        lines.push(format!("{indent}{};", self.expr(expr, depth, indent.len())));
+      }
+      StmtKind::Print(expr, _) => {
+        let expr = self.expr(expr, depth, indent.len() + "print ".len());
+        lines.push(format!("{indent}print {expr};"));
+      }
+      StmtKind::Var(..) => lines.push(format!("{indent}{};", self.var(stmt, depth, indent.len()))),
+      StmtKind::ScopeBlock(stmts) if stmts.is_empty() => lines.push(format!("{indent}{{}}")),
+      StmtKind::ScopeBlock(stmts) => {
+        lines.push(format!("{indent}{{"));
+        self.stmts(stmts, depth + 1, lines);
+        lines.push(format!("{indent}}}"));
+      }
+      StmtKind::If {
+        condition,
+        then,
+        els,
+        ..
+      } => {
+        let condition = self.expr(condition, depth, indent.len() + "if (".len());
+        self.body(format!("if ({condition})"), then, depth, lines);
+        if let Some(els) = els {
+          self.else_branch(els, depth, lines);
+        }
+      }
+      StmtKind::While {
+        condition, body, ..
+      } => {
+        let condition = self.expr(condition, depth, indent.len() + "while (".len());
+        self.body(format!("while ({condition})"), body, depth, lines);
+      }
+      StmtKind::DoWhile {
+        body, condition, ..
+      } => {
+        self.body("do".to_string(), body, depth, lines);
+        let condition = self.expr(condition, depth, indent.len() + "} while (".len());
+        match lines.last_mut() {
+          Some(last) if last.ends_with('}') => last.push_str(&format!(" while ({condition});")),
+          _ => lines.push(format!("{indent}while ({condition});")),
+        }
+      }
+      StmtKind::For {
+        initializer,
+        condition,
+        increment,
+        body,
+        ..
+      } => {
+        let initializer = match initializer.as_deref() {
+          Some(stmt) => match &stmt.kind {
+            StmtKind::Expr(expr) => self.expr(expr, depth, indent.len()),
+            _ => self.var(stmt, depth, indent.len()),
+          },
+          None => String::new(),
+        };
+        let clause = |expr: &Option<Expr>| {
+          expr
+            .as_ref()
+            .map(|expr| format!(" {}", self.expr(expr, depth, indent.len())))
+            .unwrap_or_default()
+        };
+        let head = format!(
+          "for ({initializer};{};{})",
+          clause(condition),
+          clause(increment)
+        );
+        self.body(head, body, depth, lines);
+      }
+      StmtKind::Break(_) => lines.push(format!("{indent}break;")),
+      StmtKind::Continue(_) => lines.push(format!("{indent}continue;")),
+      StmtKind::Assert {
+        condition, message, ..
+      } => {
+        let column = indent.len() + "assert(".len();
+        let mut args = self.expr(condition, depth, column);
+        if let Some(message) = message {
+          args = format!(
+            "{args}, {}",
+            self.expr(message, depth, column + args.len() + 2)
+          );
+        }
+        lines.push(format!("{indent}assert({args});"));
+      }
+      StmtKind::Try {
+        body,
+        name,
+        handler,
+        ..
+      } => {
+        lines.push(format!("{indent}try {{"));
+        self.stmts(body, depth + 1, lines);
+        lines.push(format!("{indent}}} catch ({name}) {{"));
+        self.stmts(handler, depth + 1, lines);
+        lines.push(format!("{indent}}}"));
+      }
+      StmtKind::Import(path, _) => lines.push(format!("{indent}import \"{path}\";")),
+      StmtKind::Return(expr, _) if expr.kind == ExprKind::LiteralNil => {
+        lines.push(format!("{indent}return;"))
+      }
+      StmtKind::Return(expr, _) => {
+        let expr = self.expr(expr, depth, indent.len() + "return ".len());
+        lines.push(format!("{indent}return {expr};"));
+      }
+      StmtKind::Throw(expr, _) => {
+        let expr = self.expr(expr, depth, indent.len() + "throw ".len());
+        lines.push(format!("{indent}throw {expr};"));
+      }
+      StmtKind::Function { .. } => self.function(stmt, "fun ", depth, lines),
+      StmtKind::Class {
+        name,
+        superclass,
+        methods,
+        class_methods,
+        getters,
+      } => {
+        let superclass = superclass
+          .as_ref()
+          .map(|superclass| format!(" < {}", self.expr(superclass, depth, 0)))
+          .unwrap_or_default();
+        // Getters have no keyword, since they are printed without a parameter list.
+        let members = class_methods
+          .iter()
+          .map(|method| (method, Some("class ")))
+          .chain(methods.iter().map(|method| (method, Some(""))))
+          .chain(getters.iter().map(|getter| (getter, None)))
+          .collect::<Vec<_>>();
+        if members.is_empty() {
+          lines.push(format!("{indent}class {name}{superclass} {{}}"));
+          return;
+        }
+
+        lines.push(format!("{indent}class {name}{superclass} {{"));
+        for (i, (member, keyword)) in members.into_iter().enumerate() {
+          if i > 0 {
+            lines.push(String::new());
+          }
+          match keyword {
+            Some(keyword) => self.function(member, keyword, depth + 1, lines),
+            None => self.getter(member, depth + 1, lines),
+          }
+        }
+        lines.push(format!("{indent}}}"));
+      }
+    }
+  }
+
+  /// `var` declarations without their `;`, since `for` loops use them too.
+  fn var(&self, stmt: &Stmt, depth: usize, column: usize) -> String {
+    match &stmt.kind {
+      StmtKind::Var(name, None, _) => format!("var {name}"),
+      StmtKind::Var(name, Some(value), _) => {
+        let prefix = format!("var {name} = ");
+        let value = self.expr(value, depth, column + prefix.len());
+        format!("{prefix}{value}")
+      }
+      _ => unreachable!("only called with var declarations"),
+    }
+  }
+
+  fn function(&self, stmt: &Stmt, keyword: &str, depth: usize, lines: &mut Vec<String>) {
+    let StmtKind::Function { name, params, body } = &stmt.kind else {
+      return self.stmt(stmt, depth, lines);
+    };
+
+    let head = format!("{keyword}{name}({})", params.join(", "));
+    self.function_body(head, body, depth, lines);
+  }
+
+  /// Getters are methods without a parameter list.
+  fn getter(&self, stmt: &Stmt, depth: usize, lines: &mut Vec<String>) {
+    let StmtKind::Function { name, body, .. } = &stmt.kind else {
+      return self.stmt(stmt, depth, lines);
+    };
+    self.function_body(name.to_string(), body, depth, lines);
+  }
+
+  fn function_body(&self, head: String, body: &[Stmt], depth: usize, lines: &mut Vec<String>) {
+    let indent = " ".repeat(depth * INDENT);
+    if body.is_empty() {
+      lines.push(format!("{indent}{head} {{}}"));
+      return;
+    }
+
+    lines.push(format!("{indent}{head} {{"));
+    self.stmts(body, depth + 1, lines);
+    lines.push(format!("{indent}}}"));
+  }
+
+  /// Blocks open on the same line as their statement. Any other body goes on the same line too,
+  /// as in `if (done) return;`.
+  fn body(&self, head: String, body: &Stmt, depth: usize, lines: &mut Vec<String>) {
+    let indent = " ".repeat(depth * INDENT);
+    let mut body_lines = vec![];
+    self.stmt(body, depth, &mut body_lines);
+
+    let first = body_lines.remove(0);
+    lines.push(format!("{indent}{head} {}", first.trim_start()));
+    lines.extend(body_lines);
+  }
+
+  /// `else` goes right after a closing brace, or on its own line after any other statement.
+  fn else_branch(&self, els: &Stmt, depth: usize, lines: &mut Vec<String>) {
+    let indent = " ".repeat(depth * INDENT);
+    let mut else_lines = vec![];
+    self.stmt(els, depth, &mut else_lines);
+    let first = else_lines.remove(0);
+
+    match lines.last_mut() {
+      Some(last) if last.ends_with('}') => {
+        last.push_str(" else ");
+        last.push_str(first.trim_start());
+      }
+      _ => lines.push(format!("{indent}else {}", first.trim_start())),
+    }
+    lines.extend(else_lines);
+  }
+
+  /// `column` is where the expression starts. When it doesn't fit in the line, calls put each
+  /// argument on its own line and binary operations break after the operator.
+  fn expr(&self, expr: &Expr, depth: usize, column: usize) -> String {
+    let flat = self.flat(expr);
+    if column + flat.len() < MAX_WIDTH {
+      return flat;
+    }
+
+    let inner_column = (depth + 1) * INDENT;
+    let inner_indent = " ".repeat(inner_column);
+    match &expr.kind {
+      ExprKind::Call { callee, args, .. } if !args.is_empty() => {
+        let args = args
+          .iter()
+          .map(|arg| format!("{inner_indent}{}", self.expr(arg, depth + 1, inner_column)))
+          .collect::<Vec<_>>()
+          .join(",\n");
+        format!(
+          "{}(\n{args}\n{})",
+          self.expr(callee, depth, column),
+          " ".repeat(depth * INDENT)
+        )
+      }
+      ExprKind::Binary {
+        left,
+        operator,
+        right,
+      }
+      | ExprKind::Logical {
+        left,
+        operator,
+        right,
+      } => format!(
+        "{} {}\n{inner_indent}{}",
+        self.expr(left, depth, column),
+        operator.kind().symbol(),
+        self.expr(right, depth + 1, inner_column)
+      ),
+      _ => flat,
+    }
+  }
+
+  #[allow(clippy::only_used_in_recursion)]
+  fn flat(&self, expr: &Expr) -> String {
+    match &expr.kind {
+      ExprKind::LiteralNumber { lexeme, .. } => lexeme.clone(),
+      ExprKind::LiteralString { value } => format!("\"{value}\""),
+      ExprKind::LiteralBool { value } => value.to_string(),
+      ExprKind::LiteralNil => "nil".to_string(),
+      ExprKind::Unary { operator, right } => {
+        let right = self.flat(right);
+        // `-(-a)` can't be printed as `--a`, which is a decrement.
+        let gap = match operator.kind() {
+          TokenKind::Minus if right.starts_with('-') => " ",
+          _ => "",
+        };
+        format!("{}{gap}{right}", operator.kind().symbol())
+      }
+      ExprKind::Binary {
+        left,
+        operator,
+        right,
+      }
+      | ExprKind::Logical {
+        left,
+        operator,
+        right,
+      } => format!(
+        "{} {} {}",
+        self.flat(left),
+        operator.kind().symbol(),
+        self.flat(right)
+      ),
+      ExprKind::Group { expression } => format!("({})", self.flat(expression)),
+      ExprKind::Call { callee, args, .. } => {
+        let args = args.iter().map(|a| self.flat(a)).collect::<Vec<_>>();
+        format!("{}({})", self.flat(callee), args.join(", "))
+      }
+      ExprKind::Variable { name, .. } => name.to_string(),
+      ExprKind::Assign { name, value, .. } => format!("{name} = {}", self.flat(value)),
+      ExprKind::Increment {
+        name,
+        operator,
+        postfix: true,
+        ..
+      } => format!("{name}{}", operator.kind().symbol()),
+      ExprKind::Increment { name, operator, .. } => format!("{}{name}", operator.kind().symbol()),
+      ExprKind::This { .. } => "this".to_string(),
+      ExprKind::Super { method, .. } => format!("super.{method}"),
+      ExprKind::Get { object, name, .. } => format!("{}.{name}", self.flat(object)),
+      ExprKind::Set {
+        object,
+        name,
+        value,
+        ..
+      } => format!("{}.{name} = {}", self.flat(object), self.flat(value)),
+    }
+  }
+}
+
+/// Valid Lox source for an AST node: parsing it gives back the same node, except for positions and
+/// ids.
+pub trait ToLoxSource {
+  fn to_lox_source(&self) -> String;
+}
+
+impl ToLoxSource for Expr {
+  fn to_lox_source(&self) -> String {
+    PrettyPrinter::new().print_expr(self)
+  }
+}
+
+impl ToLoxSource for Stmt {
+  fn to_lox_source(&self) -> String {
+    PrettyPrinter::new().print_stmts(std::slice::from_ref(self))
+  }
+}
+
+impl ToLoxSource for [Stmt] {
+  fn to_lox_source(&self) -> String {
+    PrettyPrinter::new().print_stmts(self)
+  }
+}
+
+fn is_declaration(stmt: &Stmt) -> bool {
+  matches!(
+    stmt.kind,
+    StmtKind::Function { .. } | StmtKind::Class { .. }
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parse::parser::LoxParser;
+  use crate::scan::scanner::Scanner;
+  use std::io::Cursor;
+
+  fn parse(src: &str) -> Vec<Stmt> {
+    let mut cursor = Cursor::new(src);
+    let tokens = Scanner::new(&mut cursor).scan_tokens().0;
+    LoxParser::new(tokens).parse().unwrap()
+  }
+
+  fn format(src: &str) -> String {
+    PrettyPrinter::new().print_stmts(&parse(src))
+  }
+
+  /// The AST as JSON, with every line and span zeroed: printing moves code around, but the tree
+  /// must stay the same.
+  fn without_positions(stmts: &[Stmt]) -> serde_json::Value {
+    fn strip(value: &mut serde_json::Value) {
+      match value {
+        serde_json::Value::Object(fields) => {
+          for (key, field) in fields.iter_mut() {
+            match (key.as_str(), field) {
+              ("line", field) | ("Break", field) | ("Continue", field) => *field = 0.into(),
+              ("span", field) | ("id", field) => *field = serde_json::Value::Null,
+              // Tuple variants with the line last.
+              (
+                "Var" | "Print" | "Return" | "Throw" | "Import",
+                serde_json::Value::Array(items),
+              ) => {
+                *items.last_mut().unwrap() = 0.into();
+                items.iter_mut().for_each(strip);
+              }
+              (_, field) => strip(field),
+            }
+          }
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(strip),
+        _ => {}
+      }
+    }
+
+    let mut value = serde_json::to_value(stmts).unwrap();
+    strip(&mut value);
+    value
+  }
+
+  fn assert_round_trips(src: &str) {
+    let stmts = parse(src);
+    let printed = stmts.to_lox_source();
+    assert_eq!(
+      without_positions(&parse(&printed)),
+      without_positions(&stmts),
+      "printed as:\n{printed}"
+    );
+  }
+
+  #[test]
+  fn normalizes_spacing_and_indentation() {
+    let formatted = format("var a=1;{print a*(2+3);var b;{b=-a;}}");
+    assert_eq!(
+      formatted,
+      concat!(
+        "var a = 1;\n",
+        "{\n",
+        "  print a * (2 + 3);\n",
+        "  var b;\n",
+        "  {\n",
+        "    b = -a;\n",
+        "  }\n",
+        "}",
+      )
+    );
+  }
+
+  #[test]
+  fn functions_and_classes_are_separated_by_blank_lines() {
+    let formatted = format(
+      "var x = 1; fun f(a,b){return a+b;} class A < B { init(){this.x=1;} get(){return super.get();} } f(1,2);",
+    );
+    assert_eq!(
+      formatted,
+      concat!(
+        "var x = 1;\n",
+        "\n",
+        "fun f(a, b) {\n",
+        "  return a + b;\n",
+        "}\n",
+        "\n",
+        "class A < B {\n",
+        "  init() {\n",
+        "    this.x = 1;\n",
+        "  }\n",
+        "\n",
+        "  get() {\n",
+        "    return super.get();\n",
+        "  }\n",
+        "}\n",
+        "\n",
+        "f(1, 2);",
+      )
+    );
+  }
+
+  #[test]
+  fn if_else_chains_keep_else_next_to_the_brace() {
+    let formatted =
+      format("if (a) { print 1; } else if (b) print 2; else { print 3; } if (c) return; else x();");
+    assert_eq!(
+      formatted,
+      concat!(
+        "if (a) {\n",
+        "  print 1;\n",
+        "} else if (b) print 2;\n",
+        "else {\n",
+        "  print 3;\n",
+        "}\n",
+        "if (c) return;\n",
+        "else x();",
+      )
+    );
+  }
+
+  #[test]
+  fn for_loops_are_printed_back_as_for_loops() {
+    let formatted = format("for (var i = 0; i < 3; i = i + 1) { print i; } for (;;) break;");
+    assert_eq!(
+      formatted,
+      concat!(
+        "for (var i = 0; i < 3; i = i + 1) {\n",
+        "  print i;\n",
+        "}\n",
+        "for (;;) break;",
+      )
+    );
+  }
+
+  #[test]
+  fn only_blocks_from_for_loops_are_printed_as_for_loops() {
+    let formatted = format("for (i = 0; i < 3;) i = i + 1; { i = 0; while (i < 3) i = i + 1; }");
+    assert_eq!(
+      formatted,
+      concat!(
+        "for (i = 0; i < 3;) i = i + 1;\n",
+        "{\n",
+        "  i = 0;\n",
+        "  while (i < 3) i = i + 1;\n",
+        "}",
+      )
+    );
+  }
+
+  #[test]
+  fn long_calls_put_each_argument_on_its_own_line() {
+    let formatted = format(
+      "fun f() { return combine(first_argument_name, second_argument_name, third_argument_name); }",
+    );
+    assert_eq!(
+      formatted,
+      concat!(
+        "fun f() {\n",
+        "  return combine(\n",
+        "    first_argument_name,\n",
+        "    second_argument_name,\n",
+        "    third_argument_name\n",
+        "  );\n",
+        "}",
+      )
+    );
+  }
+
+  #[test]
+  fn long_operations_break_after_the_operator() {
+    let formatted = format(
+      "print a_long_variable_name + another_long_variable_name + yet_another_long_variable_name;",
+    );
+    assert_eq!(
+      formatted,
+      concat!(
+        "print a_long_variable_name + another_long_variable_name +\n",
+        "  yet_another_long_variable_name;",
+      )
+    );
+  }
+
+  #[test]
+  fn formatting_is_idempotent() {
+    let once = format("class A{m(){for(var i=0;i<1;i=i+1)if(i)print i;else{print nil;}}}");
+    assert_eq!(format(&once), once);
+  }
+
+  #[test]
+  fn every_kind_of_node_round_trips() {
+    assert_round_trips(
+      "
+      var a; var b = -1.5 + 2 * (3 - 4) / 5;
+      print !true == false and nil != \"multi
+      line\" or a >= b;
+      { a = b = 3; }
+      if (a < b) if (b <= a) print 1; else print 2;
+      if (a > b) { print 3; } else if (a) print 4; else { print 5; }
+      while (a) { a = a - 1; if (a) continue; break; }
+      for (var i = 0; i < 3; i = i + 1) print i;
+      for (a = 0; a < 3;) a = a + 1;
+      for (; a;) print a;
+      for (;;) break;
+      do { a = a - 1; } while (a > 0);
+      do print a; while (a);
+      fun f(x, y) { return; }
+      fun g() { return f(1, \"s\")(2); }
+      class A { init(x) { this.x = x; } }
+      class B < A { get() { return super.get().y; } }
+      B(1).x = f().y = 2;
+      ",
+    );
+  }
+
+  #[test]
+  fn wrapped_code_round_trips() {
+    assert_round_trips(
+      "print first_long_function_name(an_argument, another_argument) + second_long_function_name(more, args) * and_some_more_long_operands;",
+    );
+  }
+
+  #[test]
+  fn examples_round_trip() {
+    let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("examples");
+    for entry in std::fs::read_dir(dir).unwrap() {
+      let path = entry.unwrap().path();
+      if path.extension().is_some_and(|ext| ext == "lox") {
+        let src = std::fs::read_to_string(&path).unwrap();
+        let mut cursor = Cursor::new(src.as_str());
+        let tokens = Scanner::new(&mut cursor).scan_tokens().0;
+        if LoxParser::new(tokens).parse().is_ok() {
+          assert_round_trips(&src);
+        }
+      }
+    }
+  }
+
+  #[test]
+  fn try_catch_keeps_catch_next_to_the_brace() {
+    let src = "try { risky(); } catch (e) { print e.message; }";
+    assert_eq!(
+      format(src),
+      "try {\n  risky();\n} catch (e) {\n  print e.message;\n}"
+    );
+    assert_round_trips(src);
+    assert_round_trips("try { throw \"bad\"; } catch (e) { throw e; }");
+  }
+
+  #[test]
+  fn double_negation_is_not_printed_as_a_decrement() {
+    assert_eq!(format("print - -a;"), "print - -a;");
+    assert_round_trips("print - -a; print -(-a); print !!a;");
+  }
+
+  #[test]
+  fn increments_are_printed_as_written() {
+    assert_eq!(
+      format("for(var i=0;i<3;i++) print --i;"),
+      "for (var i = 0; i < 3; i++) print --i;"
+    );
+    assert_round_trips("print - --a; print a-- - 1; print ++a;");
+  }
+
+  #[test]
+  fn numbers_keep_how_they_were_written() {
+    assert_eq!(
+      format("print 0x10|0b101+1.50;"),
+      "print 0x10 | 0b101 + 1.50;"
+    );
+  }
+
+  #[test]
+  fn asserts_keep_their_message() {
+    assert_eq!(format("assert (a>1,\"big\") ;"), "assert(a > 1, \"big\");");
+    assert_round_trips("assert(a); assert(a, b);");
+  }
+
+  #[test]
+  fn single_nodes_convert_to_source() {
+    let stmts = parse("if (a) print f(1, 2);");
+    assert_eq!(stmts[0].to_lox_source(), "if (a) print f(1, 2);");
+    let StmtKind::If { condition, .. } = &stmts[0].kind else {
+      panic!("expected an if");
+    };
+    assert_eq!(condition.to_lox_source(), "a");
+  }
+}
@@ -1,6 +1,10 @@
 use crate::parse::expr::Expr;
 use crate::parse::stmt::Stmt;
+use crate::parse::visitor::{ClassDeclaration, ExprVisitor, ForLoop, StmtVisitor};
+use crate::scan::number_format::format_literal;
+use crate::scan::token::Token;
 
+#[derive(Default)]
 pub struct PrintAst {}
 
 impl PrintAst {
@@ -8,83 +12,237 @@ impl PrintAst {
     PrintAst {}
   }
 
-  pub fn print_stmts(&self, stmts: &[Stmt]) -> String {
+  pub fn print_stmts(&mut self, stmts: &[Stmt]) -> String {
     let mut lines = vec![];
     for stmt in stmts {
-      let line = self.print_stmt(stmt);
+      let line = stmt.accept(self);
       lines.push(line);
     }
     lines.join(" ")
   }
 
-  fn print_stmt(&self, stmt: &Stmt) -> String {
-    match stmt {
-      Stmt::Expr(expr) => self.print_expr(expr),
-      Stmt::Print(expr) => format!("(print {})", self.print_expr(expr)),
-      Stmt::Var(name, value, _) => format!("(def_var `{}` {})", name, self.print_expr(value)),
-      Stmt::ScopeBlock(stmts) => format!("(block_scope {})", self.print_stmts(stmts)),
-      Stmt::If {
-        condition,
-        then,
-        els,
-      } => format!(
-        "(if {} {} {})",
-        self.print_expr(condition),
-        self.print_stmt(then),
-        els
-          .as_ref()
-          .map(|stmt| self.print_stmt(&stmt))
-          .unwrap_or("".to_string()),
-      ),
-      Stmt::While { condition, body } => format!(
-        "(while {} {})",
+  pub fn print_expr(&mut self, root: &Expr) -> String {
+    root.accept(self)
+  }
+}
+
+impl StmtVisitor<String> for PrintAst {
+  fn visit_expression(&mut self, expr: &Expr) -> String {
+    self.print_expr(expr)
+  }
+
+  fn visit_print(&mut self, expr: &Expr, _line: usize) -> String {
+    format!("(print {})", self.print_expr(expr))
+  }
+
+  fn visit_var(&mut self, name: &str, value: Option<&Expr>, _line: usize) -> String {
+    let value = value.map_or("nil".to_string(), |value| self.print_expr(value));
+    format!("(def_var `{}` {})", name, value)
+  }
+
+  fn visit_block(&mut self, stmts: &[Stmt]) -> String {
+    format!("(block_scope {})", self.print_stmts(stmts))
+  }
+
+  fn visit_if(
+    &mut self,
+    condition: &Expr,
+    then: &Stmt,
+    els: Option<&Stmt>,
+    _line: usize,
+  ) -> String {
+    format!(
+      "(if {} {} {})",
+      self.print_expr(condition),
+      then.accept(self),
+      els.map(|stmt| stmt.accept(self)).unwrap_or("".to_string()),
+    )
+  }
+
+  fn visit_while(&mut self, condition: &Expr, body: &Stmt, _line: usize) -> String {
+    format!(
+      "(while {} {})",
+      self.print_expr(condition),
+      body.accept(self)
+    )
+  }
+
+  fn visit_do_while(&mut self, body: &Stmt, condition: &Expr, _line: usize) -> String {
+    format!(
+      "(do_while {} {})",
+      body.accept(self),
+      self.print_expr(condition)
+    )
+  }
+
+  /// The parts a loop leaves out are shown as `()`.
+  fn visit_for(&mut self, for_loop: ForLoop) -> String {
+    format!(
+      "(for {} {} {} {})",
+      for_loop
+        .initializer
+        .map_or("()".to_string(), |stmt| stmt.accept(self)),
+      for_loop
+        .condition
+        .map_or("()".to_string(), |expr| self.print_expr(expr)),
+      for_loop
+        .increment
+        .map_or("()".to_string(), |expr| self.print_expr(expr)),
+      for_loop.body.accept(self)
+    )
+  }
+
+  fn visit_assert(&mut self, condition: &Expr, message: Option<&Expr>, _line: usize) -> String {
+    match message {
+      None => format!("(assert {})", self.print_expr(condition)),
+      Some(message) => format!(
+        "(assert {} {})",
         self.print_expr(condition),
-        self.print_stmt(body)
+        self.print_expr(message)
       ),
-      Stmt::Function { name, params, body } =>
-        format!("(fun_def `{}` ({}) ({}))", name, params.iter().map(|s| format!("`{}`", s) ).collect::<Vec<_>>().join(" "), self.print_stmts(body)),
-      Stmt::Return(expr) => format!("(return {})", self.print_expr(expr))
     }
   }
 
-  pub fn print_expr(&self, root: &Expr) -> String {
-    match root {
-      Expr::LiteralNumber { value } => format!("{:?}", value),
-      Expr::LiteralString { value } => format!("{value}"),
-      Expr::LiteralBool { value } => format!("{value}"),
-      Expr::LiteralNil => "nil".to_string(),
-      Expr::Unary { operator, right } => {
-        format!("({} {})", operator.kind().symbol(), self.print_expr(right))
-      }
-      Expr::Binary {
-        left,
-        operator,
-        right,
-      } => format!(
-        "({} {} {})",
-        operator.kind().symbol(),
-        self.print_expr(left),
-        self.print_expr(right)
-      ),
-      Expr::Group { expression } => format!("(group {})", self.print_expr(expression)),
-      Expr::Variable { name, .. } => format!("`{}`", name),
-      Expr::Assign { name, value, .. } => {
-        format!("(assign_var `{}` {})", name, self.print_expr(value))
-      }
-      Expr::Logical {
-        left,
-        operator,
-        right,
-      } => format!(
-        "({} {} {})",
-        operator.symbol(),
-        self.print_expr(left),
-        self.print_expr(right)
-      ),
-      Expr::Call { callee, args, .. } => {
-        let args: Vec<String> = args.iter().map(|a| self.print_expr(a)).collect();
-        format!("(call {} ({}))", self.print_expr(callee), args.join(" "))
-      }
+  fn visit_break(&mut self, _line: usize) -> String {
+    "(break)".to_string()
+  }
+
+  fn visit_continue(&mut self, _line: usize) -> String {
+    "(continue)".to_string()
+  }
+
+  fn visit_return(&mut self, value: &Expr, _line: usize) -> String {
+    format!("(return {})", self.print_expr(value))
+  }
+
+  fn visit_throw(&mut self, value: &Expr, _line: usize) -> String {
+    format!("(throw {})", self.print_expr(value))
+  }
+
+  fn visit_try(&mut self, body: &[Stmt], name: &str, handler: &[Stmt], _line: usize) -> String {
+    format!(
+      "(try ({}) `{}` ({}))",
+      self.print_stmts(body),
+      name,
+      self.print_stmts(handler)
+    )
+  }
+
+  fn visit_import(&mut self, path: &str, _line: usize) -> String {
+    format!("(import \"{path}\")")
+  }
+
+  fn visit_function(&mut self, name: &str, params: &[String], body: &[Stmt]) -> String {
+    format!(
+      "(fun_def `{}` ({}) ({}))",
+      name,
+      params
+        .iter()
+        .map(|s| format!("`{}`", s))
+        .collect::<Vec<_>>()
+        .join(" "),
+      self.print_stmts(body)
+    )
+  }
+
+  fn visit_class(&mut self, class: ClassDeclaration) -> String {
+    let superclass = class
+      .superclass
+      .map(|superclass| format!(" < {}", self.print_expr(superclass)))
+      .unwrap_or_default();
+    let mut members = format!("({})", self.print_stmts(class.methods));
+    if !class.class_methods.is_empty() {
+      members += &format!(" (class_methods {})", self.print_stmts(class.class_methods));
+    }
+    if !class.getters.is_empty() {
+      members += &format!(" (getters {})", self.print_stmts(class.getters));
+    }
+    format!("(class_def `{}`{superclass} {members})", class.name)
+  }
+}
+
+impl ExprVisitor<String> for PrintAst {
+  fn visit_literal_number(&mut self, value: f64) -> String {
+    format_literal(value)
+  }
+
+  fn visit_literal_bool(&mut self, value: bool) -> String {
+    format!("{value}")
+  }
+
+  fn visit_literal_string(&mut self, value: &str) -> String {
+    value.to_string()
+  }
+
+  fn visit_literal_nil(&mut self) -> String {
+    "nil".to_string()
+  }
+
+  fn visit_binary(&mut self, left: &Expr, operator: &Token, right: &Expr) -> String {
+    format!(
+      "({} {} {})",
+      operator.kind().symbol(),
+      self.print_expr(left),
+      self.print_expr(right)
+    )
+  }
+
+  fn visit_logical(&mut self, left: &Expr, operator: &Token, right: &Expr) -> String {
+    self.visit_binary(left, operator, right)
+  }
+
+  fn visit_unary(&mut self, operator: &Token, right: &Expr) -> String {
+    format!("({} {})", operator.kind().symbol(), self.print_expr(right))
+  }
+
+  fn visit_call(&mut self, callee: &Expr, args: &[Expr], _line: usize) -> String {
+    let args: Vec<String> = args.iter().map(|a| self.print_expr(a)).collect();
+    format!("(call {} ({}))", self.print_expr(callee), args.join(" "))
+  }
+
+  fn visit_group(&mut self, expression: &Expr) -> String {
+    format!("(group {})", self.print_expr(expression))
+  }
+
+  fn visit_variable(&mut self, name: &str, _line: usize) -> String {
+    format!("`{}`", name)
+  }
+
+  fn visit_assign(&mut self, name: &str, value: &Expr, _line: usize) -> String {
+    format!("(assign_var `{}` {})", name, self.print_expr(value))
+  }
+
+  fn visit_increment(
+    &mut self,
+    name: &str,
+    operator: &Token,
+    postfix: bool,
+    _line: usize,
+  ) -> String {
+    match postfix {
+      true => format!("(`{}` {})", name, operator.kind().symbol()),
+      false => format!("({} `{}`)", operator.kind().symbol(), name),
     }
   }
+
+  fn visit_this(&mut self, _line: usize) -> String {
+    "this".to_string()
+  }
+
+  fn visit_super(&mut self, method: &str, _line: usize) -> String {
+    format!("(super `{}`)", method)
+  }
+
+  fn visit_get(&mut self, object: &Expr, name: &str, _line: usize) -> String {
+    format!("(get {} `{}`)", self.print_expr(object), name)
+  }
+
+  fn visit_set(&mut self, object: &Expr, name: &str, value: &Expr, _line: usize) -> String {
+    format!(
+      "(set {} `{}` {})",
+      self.print_expr(object),
+      name,
+      self.print_expr(value)
+    )
+  }
 }
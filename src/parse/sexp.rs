@@ -0,0 +1,229 @@
+use serde_json::{Map, Number, Value};
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// Canonical s-expression encoding of the serde data model:
+///
+/// - `nil`, `true`, `false` and numbers are written as atoms.
+/// - Strings are quoted using JSON escapes.
+/// - Sequences are lists: `(a b c)`.
+/// - Maps are lists of keyword/value pairs: `(:key1 a :key2 b)`.
+///
+/// Map keys are written in sorted order, so the same AST always produces the same text. An empty
+/// list is always read back as an empty sequence.
+pub fn to_sexp(value: &Value) -> String {
+  match value {
+    Value::Array(items) => {
+      let lines = items
+        .iter()
+        .map(|item| format!("  {}", write_value(item)))
+        .collect::<Vec<_>>();
+      if lines.is_empty() {
+        "()".to_string()
+      } else {
+        format!("(\n{}\n)", lines.join("\n"))
+      }
+    }
+    _ => write_value(value),
+  }
+}
+
+fn write_value(value: &Value) -> String {
+  match value {
+    Value::Null => "nil".to_string(),
+    Value::Bool(value) => value.to_string(),
+    Value::Number(value) => value.to_string(),
+    Value::String(value) => Value::String(value.to_string()).to_string(),
+    Value::Array(items) => {
+      let items = items.iter().map(write_value).collect::<Vec<_>>();
+      format!("({})", items.join(" "))
+    }
+    Value::Object(fields) => {
+      let fields = fields
+        .iter()
+        .map(|(key, value)| format!(":{} {}", key, write_value(value)))
+        .collect::<Vec<_>>();
+      format!("({})", fields.join(" "))
+    }
+  }
+}
+
+pub fn from_sexp(src: &str) -> Result<Value, String> {
+  let mut reader = SexpReader {
+    chars: src.chars().peekable(),
+    line: 1,
+  };
+  let value = reader.read_value()?;
+  reader.skip_whitespace();
+  if let Some(c) = reader.chars.peek() {
+    return Err(format!("[line {}] Unexpected `{}` after end of input", reader.line, c));
+  }
+  Ok(value)
+}
+
+struct SexpReader<'a> {
+  chars: Peekable<Chars<'a>>,
+  line: usize,
+}
+
+impl<'a> SexpReader<'a> {
+  fn read_value(&mut self) -> Result<Value, String> {
+    self.skip_whitespace();
+    match self.chars.peek() {
+      None => Err(format!("[line {}] Unexpected end of input", self.line)),
+      Some('(') => {
+        self.chars.next();
+        self.read_list()
+      }
+      Some(')') => Err(format!("[line {}] Unexpected `)`", self.line)),
+      Some('"') => self.read_string().map(Value::String),
+      Some(_) => self.read_atom(),
+    }
+  }
+
+  fn read_list(&mut self) -> Result<Value, String> {
+    self.skip_whitespace();
+    if self.chars.peek() == Some(&':') {
+      return self.read_map();
+    }
+
+    let mut items = vec![];
+    loop {
+      self.skip_whitespace();
+      if self.chars.peek() == Some(&')') {
+        self.chars.next();
+        return Ok(Value::Array(items));
+      }
+      items.push(self.read_value()?);
+    }
+  }
+
+  fn read_map(&mut self) -> Result<Value, String> {
+    let mut fields = Map::new();
+    loop {
+      self.skip_whitespace();
+      match self.chars.next() {
+        Some(')') => return Ok(Value::Object(fields)),
+        Some(':') => {
+          let key = self.take_atom_text();
+          if key.is_empty() {
+            return Err(format!("[line {}] Expected key after `:`", self.line));
+          }
+          let value = self.read_value()?;
+          fields.insert(key, value);
+        }
+        Some(c) => return Err(format!("[line {}] Expected key, got `{}`", self.line, c)),
+        None => return Err(format!("[line {}] Unexpected end of input", self.line)),
+      }
+    }
+  }
+
+  fn read_string(&mut self) -> Result<String, String> {
+    let mut raw = String::new();
+    raw.push(self.chars.next().unwrap_or('"'));
+    loop {
+      match self.chars.next() {
+        Some('"') => break,
+        Some('\\') => {
+          raw.push('\\');
+          if let Some(escaped) = self.chars.next() {
+            raw.push(escaped);
+          }
+        }
+        Some('\n') => {
+          self.line += 1;
+          raw.push('\n');
+        }
+        Some(c) => raw.push(c),
+        None => return Err(format!("[line {}] Unterminated string", self.line)),
+      }
+    }
+    raw.push('"');
+    serde_json::from_str(&raw).map_err(|e| format!("[line {}] Invalid string: {}", self.line, e))
+  }
+
+  fn read_atom(&mut self) -> Result<Value, String> {
+    let atom = self.take_atom_text();
+    match atom.as_str() {
+      "nil" => Ok(Value::Null),
+      "true" => Ok(Value::Bool(true)),
+      "false" => Ok(Value::Bool(false)),
+      _ => serde_json::from_str::<Number>(&atom)
+        .map(Value::Number)
+        .map_err(|_| format!("[line {}] Unexpected atom `{}`", self.line, atom)),
+    }
+  }
+
+  fn take_atom_text(&mut self) -> String {
+    let mut atom = String::new();
+    while let Some(c) = self.chars.peek() {
+      if c.is_whitespace() || *c == '(' || *c == ')' || *c == '"' || *c == ';' {
+        break;
+      }
+      atom.push(*c);
+      self.chars.next();
+    }
+    atom
+  }
+
+  fn skip_whitespace(&mut self) {
+    while let Some(c) = self.chars.peek() {
+      match c {
+        '\n' => self.line += 1,
+        ';' => {
+          while self.chars.peek().is_some_and(|c| *c != '\n') {
+            self.chars.next();
+          }
+          continue;
+        }
+        c if c.is_whitespace() => {}
+        _ => break,
+      }
+      self.chars.next();
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  #[test]
+  fn atoms_are_written_plainly() {
+    assert_eq!(to_sexp(&json!(null)), "nil");
+    assert_eq!(to_sexp(&json!(true)), "true");
+    assert_eq!(to_sexp(&json!(1.5)), "1.5");
+    assert_eq!(to_sexp(&json!("a \"b\"")), "\"a \\\"b\\\"\"");
+  }
+
+  #[test]
+  fn maps_are_written_as_keyword_lists() {
+    let value = json!({"Binary": {"left": 1.0, "right": [1, 2]}});
+    assert_eq!(to_sexp(&value), "(:Binary (:left 1.0 :right (1 2)))");
+  }
+
+  #[test]
+  fn top_level_list_is_written_one_item_per_line() {
+    let value = json!([{"Print": "LiteralNil"}, "LiteralNil"]);
+    assert_eq!(to_sexp(&value), "(\n  (:Print \"LiteralNil\")\n  \"LiteralNil\"\n)");
+  }
+
+  #[test]
+  fn reading_a_written_value_returns_the_same_value() {
+    let value = json!([{"a": [1, -2.5, 1e300, "x\ny"], "b": null, "c": false}, []]);
+    assert_eq!(from_sexp(&to_sexp(&value)).unwrap(), value);
+  }
+
+  #[test]
+  fn comments_are_ignored() {
+    let value = from_sexp("; a comment\n(1 ; another\n 2)").unwrap();
+    assert_eq!(value, json!([1, 2]));
+  }
+
+  #[test]
+  fn unbalanced_input_is_an_error() {
+    assert_eq!(from_sexp("(1 2").unwrap_err(), "[line 1] Unexpected end of input");
+    assert_eq!(from_sexp("(1) )").unwrap_err(), "[line 1] Unexpected `)` after end of input");
+  }
+}
@@ -1,24 +1,96 @@
 use crate::parse::expr::Expr;
+use crate::parse::node_id::NodeId;
+use crate::scan::span::Span;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, PartialEq, Clone)]
-pub enum Stmt {
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Stmt {
+  pub id: NodeId,
+  /// From the first token of the statement to its last one, `;` included.
+  pub span: Span,
+  pub kind: StmtKind,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub enum StmtKind {
   Expr(Expr),
-  Print(Expr),
-  Var(String, Expr, usize),
+  Print(Expr, usize),
+  /// `var a;` has no initializer. The variable starts as `nil` all the same.
+  Var(String, Option<Expr>, usize),
   ScopeBlock(Vec<Stmt>),
   If {
     condition: Expr,
     then: Box<Stmt>,
     els: Option<Box<Stmt>>,
+    line: usize,
   },
   While {
     condition: Expr,
     body: Box<Stmt>,
+    line: usize,
+  },
+  /// `do body while (condition);`: like `while`, but the condition is checked after each
+  /// iteration, so the body always runs at least once.
+  DoWhile {
+    body: Box<Stmt>,
+    condition: Expr,
+    line: usize,
+  },
+  /// `for (initializer; condition; increment) body`, with each part optional. The initializer
+  /// runs in a scope of its own around the loop, and the increment runs after every iteration,
+  /// even when the body hits `continue`. Without a condition the loop runs until it's stopped.
+  For {
+    initializer: Option<Box<Stmt>>,
+    condition: Option<Expr>,
+    increment: Option<Expr>,
+    body: Box<Stmt>,
+    line: usize,
+  },
+  /// Fails with the source of `condition` when it's falsey, and `message` if there is one.
+  Assert {
+    condition: Expr,
+    message: Option<Expr>,
+    line: usize,
+  },
+  Break(usize),
+  Continue(usize),
+  Return(Expr, usize),
+  /// Raises the value as an error, for `catch` to receive as it is.
+  Throw(Expr, usize),
+  /// Runs `body`. If it fails with a runtime error, runs `handler` with the error in `name`.
+  Try {
+    body: Vec<Stmt>,
+    name: String,
+    handler: Vec<Stmt>,
+    line: usize,
   },
-  Return(Expr),
+  /// Runs another file in the global scope, once. The path is relative to the importing file.
+  Import(String, usize),
   Function {
     name: String,
     params: Vec<String>,
     body: Vec<Stmt>
   },
+  Class {
+    name: String,
+    superclass: Option<Expr>,
+    methods: Vec<Stmt>,
+    /// Methods called on the class itself, declared with `class` in front of their name.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    class_methods: Vec<Stmt>,
+    /// Methods without a parameter list, that run when their property is read.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    getters: Vec<Stmt>,
+  },
+}
+
+impl Stmt {
+  pub fn new(id: NodeId, span: Span, kind: StmtKind) -> Stmt {
+    Stmt { id, span, kind }
+  }
+
+  /// Line where the statement starts.
+  pub fn line(&self) -> usize {
+    self.span.line
+  }
 }
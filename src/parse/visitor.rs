@@ -0,0 +1,243 @@
+use crate::parse::expr::{Expr, ExprKind};
+use crate::parse::stmt::{Stmt, StmtKind};
+use crate::scan::token::Token;
+
+/// One method per kind of expression, so tools can walk the AST without matching on [`Expr`]
+/// themselves. [`Expr::accept`] calls the method for the kind of the expression. Visitors recurse
+/// by calling `accept` on the children they care about.
+pub trait ExprVisitor<T> {
+  fn visit_literal_number(&mut self, value: f64) -> T;
+  fn visit_literal_bool(&mut self, value: bool) -> T;
+  fn visit_literal_string(&mut self, value: &str) -> T;
+  fn visit_literal_nil(&mut self) -> T;
+  fn visit_binary(&mut self, left: &Expr, operator: &Token, right: &Expr) -> T;
+  fn visit_logical(&mut self, left: &Expr, operator: &Token, right: &Expr) -> T;
+  fn visit_unary(&mut self, operator: &Token, right: &Expr) -> T;
+  fn visit_call(&mut self, callee: &Expr, args: &[Expr], line: usize) -> T;
+  fn visit_group(&mut self, expression: &Expr) -> T;
+  fn visit_variable(&mut self, name: &str, line: usize) -> T;
+  fn visit_assign(&mut self, name: &str, value: &Expr, line: usize) -> T;
+  fn visit_increment(&mut self, name: &str, operator: &Token, postfix: bool, line: usize) -> T;
+  fn visit_this(&mut self, line: usize) -> T;
+  fn visit_super(&mut self, method: &str, line: usize) -> T;
+  fn visit_get(&mut self, object: &Expr, name: &str, line: usize) -> T;
+  fn visit_set(&mut self, object: &Expr, name: &str, value: &Expr, line: usize) -> T;
+}
+
+/// One method per kind of statement, like [`ExprVisitor`] for expressions. [`Stmt::accept`]
+/// calls the method for the kind of the statement.
+pub trait StmtVisitor<T> {
+  fn visit_expression(&mut self, expr: &Expr) -> T;
+  fn visit_print(&mut self, expr: &Expr, line: usize) -> T;
+  fn visit_var(&mut self, name: &str, value: Option<&Expr>, line: usize) -> T;
+  fn visit_block(&mut self, stmts: &[Stmt]) -> T;
+  fn visit_if(&mut self, condition: &Expr, then: &Stmt, els: Option<&Stmt>, line: usize) -> T;
+  fn visit_while(&mut self, condition: &Expr, body: &Stmt, line: usize) -> T;
+  fn visit_do_while(&mut self, body: &Stmt, condition: &Expr, line: usize) -> T;
+  fn visit_for(&mut self, for_loop: ForLoop) -> T;
+  fn visit_assert(&mut self, condition: &Expr, message: Option<&Expr>, line: usize) -> T;
+  fn visit_break(&mut self, line: usize) -> T;
+  fn visit_continue(&mut self, line: usize) -> T;
+  fn visit_return(&mut self, value: &Expr, line: usize) -> T;
+  fn visit_throw(&mut self, value: &Expr, line: usize) -> T;
+  fn visit_try(&mut self, body: &[Stmt], name: &str, handler: &[Stmt], line: usize) -> T;
+  fn visit_import(&mut self, path: &str, line: usize) -> T;
+  fn visit_function(&mut self, name: &str, params: &[String], body: &[Stmt]) -> T;
+  fn visit_class(&mut self, class: ClassDeclaration) -> T;
+}
+
+/// The parts of a [`StmtKind::For`], borrowed from it.
+pub struct ForLoop<'a> {
+  pub initializer: Option<&'a Stmt>,
+  pub condition: Option<&'a Expr>,
+  pub increment: Option<&'a Expr>,
+  pub body: &'a Stmt,
+  pub line: usize,
+}
+
+/// The parts of a [`StmtKind::Class`], borrowed from it.
+pub struct ClassDeclaration<'a> {
+  pub name: &'a str,
+  pub superclass: Option<&'a Expr>,
+  pub methods: &'a [Stmt],
+  pub class_methods: &'a [Stmt],
+  pub getters: &'a [Stmt],
+}
+
+impl Expr {
+  pub fn accept<T>(&self, visitor: &mut impl ExprVisitor<T>) -> T {
+    match &self.kind {
+      ExprKind::LiteralNumber { value, .. } => visitor.visit_literal_number(*value),
+      ExprKind::LiteralBool { value } => visitor.visit_literal_bool(*value),
+      ExprKind::LiteralString { value } => visitor.visit_literal_string(value),
+      ExprKind::LiteralNil => visitor.visit_literal_nil(),
+      ExprKind::Binary {
+        left,
+        operator,
+        right,
+      } => visitor.visit_binary(left, operator, right),
+      ExprKind::Logical {
+        left,
+        operator,
+        right,
+      } => visitor.visit_logical(left, operator, right),
+      ExprKind::Unary { operator, right } => visitor.visit_unary(operator, right),
+      ExprKind::Call { line, callee, args } => visitor.visit_call(callee, args, *line),
+      ExprKind::Group { expression } => visitor.visit_group(expression),
+      ExprKind::Variable { name, line } => visitor.visit_variable(name, *line),
+      ExprKind::Assign { name, value, line } => visitor.visit_assign(name, value, *line),
+      ExprKind::Increment {
+        name,
+        operator,
+        postfix,
+        line,
+      } => visitor.visit_increment(name, operator, *postfix, *line),
+      ExprKind::This { line } => visitor.visit_this(*line),
+      ExprKind::Super { method, line } => visitor.visit_super(method, *line),
+      ExprKind::Get { object, name, line } => visitor.visit_get(object, name, *line),
+      ExprKind::Set {
+        object,
+        name,
+        value,
+        line,
+      } => visitor.visit_set(object, name, value, *line),
+    }
+  }
+}
+
+impl Stmt {
+  pub fn accept<T>(&self, visitor: &mut impl StmtVisitor<T>) -> T {
+    match &self.kind {
+      StmtKind::Expr(expr) => visitor.visit_expression(expr),
+      StmtKind::Print(expr, line) => visitor.visit_print(expr, *line),
+      StmtKind::Var(name, value, line) => visitor.visit_var(name, value.as_ref(), *line),
+      StmtKind::ScopeBlock(stmts) => visitor.visit_block(stmts),
+      StmtKind::If {
+        condition,
+        then,
+        els,
+        line,
+      } => visitor.visit_if(condition, then, els.as_deref(), *line),
+      StmtKind::While {
+        condition,
+        body,
+        line,
+      } => visitor.visit_while(condition, body, *line),
+      StmtKind::DoWhile {
+        body,
+        condition,
+        line,
+      } => visitor.visit_do_while(body, condition, *line),
+      StmtKind::For {
+        initializer,
+        condition,
+        increment,
+        body,
+        line,
+      } => visitor.visit_for(ForLoop {
+        initializer: initializer.as_deref(),
+        condition: condition.as_ref(),
+        increment: increment.as_ref(),
+        body,
+        line: *line,
+      }),
+      StmtKind::Assert {
+        condition,
+        message,
+        line,
+      } => visitor.visit_assert(condition, message.as_ref(), *line),
+      StmtKind::Break(line) => visitor.visit_break(*line),
+      StmtKind::Continue(line) => visitor.visit_continue(*line),
+      StmtKind::Return(value, line) => visitor.visit_return(value, *line),
+      StmtKind::Throw(value, line) => visitor.visit_throw(value, *line),
+      StmtKind::Try {
+        body,
+        name,
+        handler,
+        line,
+      } => visitor.visit_try(body, name, handler, *line),
+      StmtKind::Import(path, line) => visitor.visit_import(path, *line),
+      StmtKind::Function { name, params, body } => visitor.visit_function(name, params, body),
+      StmtKind::Class {
+        name,
+        superclass,
+        methods,
+        class_methods,
+        getters,
+      } => visitor.visit_class(ClassDeclaration {
+        name,
+        superclass: superclass.as_ref(),
+        methods,
+        class_methods,
+        getters,
+      }),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parse::parser::LoxParser;
+  use crate::scan::scanner::Scanner;
+  use std::io::Cursor;
+
+  /// Names of the variables an expression reads, visiting only what it needs to.
+  #[derive(Default)]
+  struct ReadVariables(Vec<String>);
+
+  impl ExprVisitor<()> for ReadVariables {
+    fn visit_literal_number(&mut self, _value: f64) {}
+    fn visit_literal_bool(&mut self, _value: bool) {}
+    fn visit_literal_string(&mut self, _value: &str) {}
+    fn visit_literal_nil(&mut self) {}
+    fn visit_binary(&mut self, left: &Expr, _operator: &Token, right: &Expr) {
+      left.accept(self);
+      right.accept(self);
+    }
+    fn visit_logical(&mut self, left: &Expr, operator: &Token, right: &Expr) {
+      self.visit_binary(left, operator, right)
+    }
+    fn visit_unary(&mut self, _operator: &Token, right: &Expr) {
+      right.accept(self)
+    }
+    fn visit_call(&mut self, callee: &Expr, args: &[Expr], _line: usize) {
+      callee.accept(self);
+      args.iter().for_each(|arg| arg.accept(self));
+    }
+    fn visit_group(&mut self, expression: &Expr) {
+      expression.accept(self)
+    }
+    fn visit_variable(&mut self, name: &str, _line: usize) {
+      self.0.push(name.to_string())
+    }
+    fn visit_assign(&mut self, _name: &str, value: &Expr, _line: usize) {
+      value.accept(self)
+    }
+    fn visit_increment(&mut self, name: &str, _operator: &Token, _postfix: bool, _line: usize) {
+      self.0.push(name.to_string())
+    }
+    fn visit_this(&mut self, _line: usize) {}
+    fn visit_super(&mut self, _method: &str, _line: usize) {}
+    fn visit_get(&mut self, object: &Expr, _name: &str, _line: usize) {
+      object.accept(self)
+    }
+    fn visit_set(&mut self, object: &Expr, _name: &str, value: &Expr, _line: usize) {
+      object.accept(self);
+      value.accept(self);
+    }
+  }
+
+  #[test]
+  fn expressions_call_the_visitor_for_their_kind() {
+    let mut cursor = Cursor::new("f(a, -b) + (c.d = e or 1);");
+    let tokens = Scanner::new(&mut cursor).scan_tokens().0;
+    let stmts = LoxParser::new(tokens).parse().unwrap();
+    let StmtKind::Expr(expr) = &stmts[0].kind else {
+      panic!("expected an expression statement");
+    };
+    let mut visitor = ReadVariables::default();
+    expr.accept(&mut visitor);
+    assert_eq!(visitor.0, ["f", "a", "b", "c", "e"]);
+  }
+}
@@ -0,0 +1,153 @@
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Name of the file that makes a directory a Lox project.
+pub const MANIFEST_FILE: &str = "lox.toml";
+
+/// A project's `lox.toml`, like:
+///
+/// ```toml
+/// # The program `lox run` runs when it's given no file.
+/// main = "src/main.lox"
+/// # Where imports that aren't found next to the importer are looked for, in order.
+/// sources = ["src", "lib"]
+/// ```
+///
+/// Only that subset of TOML is read: `key = value` lines with strings and arrays of strings.
+#[derive(Debug, PartialEq)]
+pub struct Manifest {
+  /// Entry point of the project.
+  pub main: PathBuf,
+  /// Directories imports are looked for in.
+  pub sources: Vec<PathBuf>,
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum ManifestError {
+  #[error("No {MANIFEST_FILE} found in {0} or its parents")]
+  NotFound(String),
+  #[error("Cannot read {0}")]
+  Read(String),
+  #[error("[line {0}]: {1} in {MANIFEST_FILE}")]
+  Invalid(usize, String),
+  #[error("{MANIFEST_FILE} has no `main`")]
+  MissingMain,
+}
+
+impl Manifest {
+  /// Finds the `lox.toml` in `dir` or the closest of its parents, and reads it.
+  pub fn discover(dir: &Path) -> Result<Manifest, ManifestError> {
+    let path = dir
+      .ancestors()
+      .map(|dir| dir.join(MANIFEST_FILE))
+      .find(|path| path.is_file())
+      .ok_or_else(|| ManifestError::NotFound(dir.display().to_string()))?;
+    let src = std::fs::read_to_string(&path)
+      .map_err(|_| ManifestError::Read(path.display().to_string()))?;
+    Manifest::parse(path.parent().unwrap_or(dir), &src)
+  }
+
+  /// Reads a manifest. Its paths are relative to `root`, the directory it's in.
+  pub fn parse(root: &Path, src: &str) -> Result<Manifest, ManifestError> {
+    let mut main = None;
+    let mut sources = vec![];
+    for (i, text) in src.lines().enumerate() {
+      let line = i + 1;
+      let text = text.trim();
+      if text.is_empty() || text.starts_with('#') {
+        continue;
+      }
+      let invalid = |message: &str| ManifestError::Invalid(line, message.to_string());
+      let (key, value) = text
+        .split_once('=')
+        .ok_or_else(|| invalid("Expected `key = value`"))?;
+      let mut value = ValueReader::new(value.trim());
+      match key.trim() {
+        "main" => {
+          main = Some(root.join(value.string().ok_or_else(|| invalid("Expected a string"))?))
+        }
+        "sources" => {
+          let dirs = value
+            .strings()
+            .ok_or_else(|| invalid("Expected an array of strings"))?;
+          sources = dirs.into_iter().map(|dir| root.join(dir)).collect();
+        }
+        key => return Err(invalid(&format!("Unknown key `{key}`"))),
+      }
+      if !value.at_end() {
+        return Err(invalid("Unexpected text after the value"));
+      }
+    }
+    let main = main.ok_or(ManifestError::MissingMain)?;
+    Ok(Manifest { main, sources })
+  }
+}
+
+/// Reads the value of a manifest line, which may end with a comment.
+struct ValueReader<'a> {
+  rest: &'a str,
+}
+
+impl<'a> ValueReader<'a> {
+  fn new(text: &'a str) -> ValueReader<'a> {
+    ValueReader { rest: text }
+  }
+
+  /// A string in double quotes. Escapes aren't supported.
+  fn string(&mut self) -> Option<String> {
+    let quoted = self.rest.trim_start().strip_prefix('"')?;
+    let (value, rest) = quoted.split_once('"')?;
+    self.rest = rest;
+    Some(value.to_string())
+  }
+
+  fn strings(&mut self) -> Option<Vec<String>> {
+    self.rest = self.rest.trim_start().strip_prefix('[')?;
+    let mut values = vec![];
+    loop {
+      if let Some(rest) = self.rest.trim_start().strip_prefix(']') {
+        self.rest = rest;
+        return Some(values);
+      }
+      values.push(self.string()?);
+      let rest = self.rest.trim_start();
+      self.rest = rest.strip_prefix(',').unwrap_or(rest);
+    }
+  }
+
+  fn at_end(&self) -> bool {
+    let rest = self.rest.trim();
+    rest.is_empty() || rest.starts_with('#')
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn paths_are_relative_to_the_manifest() {
+    let src = "# A project\nmain = \"src/main.lox\"\nsources = [\"src\", \"lib\"] # in order\n";
+    assert_eq!(
+      Manifest::parse(Path::new("/project"), src).unwrap(),
+      Manifest {
+        main: PathBuf::from("/project/src/main.lox"),
+        sources: vec![PathBuf::from("/project/src"), PathBuf::from("/project/lib")],
+      }
+    );
+  }
+
+  #[test]
+  fn invalid_lines_are_reported() {
+    let parse = |src| Manifest::parse(Path::new(""), src).unwrap_err();
+    assert_eq!(
+      parse("main = \"a.lox\"\nsources = \"src\""),
+      ManifestError::Invalid(2, "Expected an array of strings".to_string())
+    );
+    assert_eq!(
+      parse("name = \"app\""),
+      ManifestError::Invalid(1, "Unknown key `name`".to_string())
+    );
+    assert_eq!(parse("sources = []"), ManifestError::MissingMain);
+  }
+}
@@ -0,0 +1,92 @@
+/// Accumulates REPL lines until they form a complete input: every `(` and `{` is closed and
+/// there is no unterminated string.
+#[derive(Debug, Default)]
+pub struct InputBuffer {
+  text: String,
+}
+
+impl InputBuffer {
+  pub fn new() -> InputBuffer {
+    InputBuffer::default()
+  }
+
+  pub fn push(&mut self, line: &str) {
+    self.text.push_str(line);
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.text.is_empty()
+  }
+
+  pub fn take(&mut self) -> String {
+    std::mem::take(&mut self.text)
+  }
+
+  pub fn is_complete(&self) -> bool {
+    let mut depth: i64 = 0;
+    let mut in_string = false;
+    let mut chars = self.text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+      match c {
+        '"' => in_string = !in_string,
+        _ if in_string => {}
+        '/' if chars.peek() == Some(&'/') => {
+          while chars.next_if(|c| *c != '\n').is_some() {}
+        }
+        '(' | '{' => depth += 1,
+        ')' | '}' => depth -= 1,
+        _ => {}
+      }
+    }
+
+    !in_string && depth <= 0
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn buffer_with(text: &str) -> InputBuffer {
+    let mut buffer = InputBuffer::new();
+    buffer.push(text);
+    buffer
+  }
+
+  #[test]
+  fn single_statement_is_complete() {
+    assert!(buffer_with("print 1;\n").is_complete());
+  }
+
+  #[test]
+  fn open_brace_is_not_complete() {
+    assert!(!buffer_with("fun foo() {\n").is_complete());
+    assert!(buffer_with("fun foo() {\n  print 1;\n}\n").is_complete());
+  }
+
+  #[test]
+  fn open_paren_is_not_complete() {
+    assert!(!buffer_with("print (1 +\n").is_complete());
+    assert!(buffer_with("print (1 +\n 2);\n").is_complete());
+  }
+
+  #[test]
+  fn unterminated_string_is_not_complete() {
+    assert!(!buffer_with("print \"foo\n").is_complete());
+    assert!(buffer_with("print \"foo\nbar\";\n").is_complete());
+  }
+
+  #[test]
+  fn braces_in_strings_and_comments_are_ignored() {
+    assert!(buffer_with("print \"{\";\n").is_complete());
+    assert!(buffer_with("print 1; // {\n").is_complete());
+  }
+
+  #[test]
+  fn take_empties_the_buffer() {
+    let mut buffer = buffer_with("print 1;\n");
+    assert_eq!(buffer.take(), "print 1;\n");
+    assert!(buffer.is_empty());
+  }
+}
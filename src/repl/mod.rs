@@ -0,0 +1,2 @@
+pub mod input_buffer;
+pub mod session;
@@ -0,0 +1,268 @@
+use crate::interpret::error::RuntimeError;
+use crate::interpret::interpreter::Interpreter;
+use crate::parse::parser::LoxParser;
+use crate::parse::pretty_print::PrettyPrinter;
+use crate::parse::stmt::Stmt;
+use crate::repl::input_buffer::InputBuffer;
+use crate::resolve::resolver::Resolver;
+use crate::scan::scanner::Scanner;
+use std::io::{self, BufRead, Cursor, Write};
+
+const PROMPT: &str = "> ";
+const CONTINUATION_PROMPT: &str = "... ";
+
+/// Interactive session. The interpreter (and with it every global and function definition) lives
+/// as long as the session, so each input can use what previous inputs defined.
+///
+/// Inputs starting with `:` are commands instead of Lox: `:save file.lox` writes the statements
+/// entered so far to a file, and `:load file.lox` runs a file as if it was typed in.
+pub struct Repl<R: BufRead, W: Write> {
+  input: R,
+  interpreter: Interpreter<W>,
+  /// Statements of the inputs that ran without errors, in order, for `:save`.
+  history: Vec<Stmt>,
+}
+
+impl<R: BufRead, W: Write> Repl<R, W> {
+  pub fn new(input: R, output: W) -> Repl<R, W> {
+    Repl {
+      input,
+      interpreter: Interpreter::new(output).with_echo_expression_results(true),
+      history: vec![],
+    }
+  }
+
+  /// Runs inputs until there are no more or one calls `exit()`. Returns the exit code.
+  pub fn run(&mut self) -> io::Result<u8> {
+    while let Some(source) = self.read_input()? {
+      let exit = match source.trim().strip_prefix(':') {
+        Some(command) => self.command(command)?,
+        None => self.eval(&source)?,
+      };
+      if let Some(code) = exit {
+        return Ok(code);
+      }
+    }
+    Ok(0)
+  }
+
+  /// Runs a `:` command. The exit code if a loaded file called `exit()`.
+  fn command(&mut self, command: &str) -> io::Result<Option<u8>> {
+    let (name, path) = command.split_once(' ').unwrap_or((command, ""));
+    let path = path.trim();
+    match name {
+      "save" | "load" if path.is_empty() => {
+        writeln!(self.output(), "Usage: :{name} file.lox")?;
+      }
+      "save" => {
+        let mut source = PrettyPrinter::new().print_stmts(&self.history);
+        if !source.is_empty() {
+          source.push('\n');
+        }
+        match std::fs::write(path, source) {
+          Ok(()) => writeln!(self.output(), "Saved the session to {path}")?,
+          Err(e) => writeln!(self.output(), "Can't save to {path}: {e}")?,
+        }
+      }
+      "load" => match std::fs::read_to_string(path) {
+        Ok(source) => return self.eval(&source),
+        Err(e) => writeln!(self.output(), "Can't load {path}: {e}")?,
+      },
+      _ => writeln!(
+        self.output(),
+        "Unknown command ':{name}'. The commands are :save and :load."
+      )?,
+    }
+    Ok(None)
+  }
+
+  fn read_input(&mut self) -> io::Result<Option<String>> {
+    let mut buffer = InputBuffer::new();
+    loop {
+      let prompt = if buffer.is_empty() {
+        PROMPT
+      } else {
+        CONTINUATION_PROMPT
+      };
+      self.output().write_all(prompt.as_bytes())?;
+      self.output().flush()?;
+
+      let mut line = String::new();
+      if self.input.read_line(&mut line)? == 0 {
+        return Ok((!buffer.is_empty()).then(|| buffer.take()));
+      }
+
+      buffer.push(&line);
+      if buffer.is_complete() {
+        return Ok(Some(buffer.take()));
+      }
+    }
+  }
+
+  /// The exit code if the input called `exit()`.
+  fn eval(&mut self, source: &str) -> io::Result<Option<u8>> {
+    let mut cursor = Cursor::new(source);
+    let (tokens, errors) = Scanner::new(&mut cursor).scan_tokens();
+    if !errors.is_empty() {
+      for error in errors {
+        writeln!(self.output(), "{error}")?;
+      }
+      return Ok(None);
+    }
+
+    let stmts = match LoxParser::new(tokens).parse() {
+      Ok(stmts) => stmts,
+      Err(errors) => {
+        for error in errors {
+          writeln!(self.output(), "{error}")?;
+        }
+        return Ok(None);
+      }
+    };
+
+    if let Err(errors) = Resolver::new().resolve(&stmts) {
+      for error in errors {
+        writeln!(self.output(), "{error}")?;
+      }
+      return Ok(None);
+    }
+
+    match self.interpreter.interpret_stmts(&stmts) {
+      Ok(_) => {
+        self.history.extend(stmts);
+        Ok(None)
+      }
+      Err(RuntimeError::Exit(code)) => Ok(Some(code)),
+      Err(error) => {
+        writeln!(self.output(), "{error}")?;
+        Ok(None)
+      }
+    }
+  }
+
+  fn output(&mut self) -> &mut W {
+    self.interpreter.stdout()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn run_session(input: &str) -> String {
+    let mut output: Vec<u8> = vec![];
+    let mut repl = Repl::new(Cursor::new(input), &mut output);
+    repl.run().unwrap();
+    drop(repl);
+    String::from_utf8(output).unwrap()
+  }
+
+  #[test]
+  fn empty_input_only_prints_the_prompt() {
+    assert_eq!(run_session(""), "> ");
+  }
+
+  #[test]
+  fn expression_results_are_printed() {
+    assert_eq!(run_session("1 + 2\n"), "> 3\n> ");
+  }
+
+  #[test]
+  fn exit_ends_the_session_with_its_code() {
+    let mut output: Vec<u8> = vec![];
+    let mut repl = Repl::new(Cursor::new("exit(4);\nprint 1;\n"), &mut output);
+    assert_eq!(repl.run().unwrap(), 4);
+    drop(repl);
+    assert_eq!(String::from_utf8(output).unwrap(), "> ");
+  }
+
+  #[test]
+  fn statements_do_not_print_results() {
+    assert_eq!(run_session("var a = 1;\n"), "> > ");
+  }
+
+  #[test]
+  fn definitions_persist_across_inputs() {
+    let res = run_session("var a = 1;\nfun inc(x) { return x + 1; }\nprint inc(a);\n");
+    assert_eq!(res, "> > > 2\n> ");
+  }
+
+  #[test]
+  fn multi_line_inputs_wait_for_closing_braces() {
+    let res = run_session("fun foo() {\n  return 10;\n}\nfoo()\n");
+    assert_eq!(res, "> ... ... > 10\n> ");
+  }
+
+  #[test]
+  fn errors_are_reported_and_session_continues() {
+    let res = run_session("print -\"a\";\nprint 1 +;\nprint 2;\n");
+    assert_eq!(
+      res,
+      concat!(
+        "> [line 1, column 7]: Expected a number, got a String\n",
+        "> Malformed expression [line 1, column 10]: Expected expression got `;`\n",
+        "> 2\n",
+        "> "
+      )
+    );
+  }
+
+  #[test]
+  fn top_level_return_is_reported_before_running() {
+    let res = run_session("print 1; return 2;\nprint 3;\n");
+    assert_eq!(
+      res,
+      "> [line 1]: Can't return from top-level code.\n> 3\n> "
+    );
+  }
+
+  fn session_file(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("lox-repl-{name}-{}.lox", std::process::id()))
+  }
+
+  #[test]
+  fn save_writes_the_statements_that_ran() {
+    let path = session_file("save");
+    let input = format!(
+      "var a = 1;\nprint b;\nfun f() {{ return a; }}\n:save {}\n",
+      path.display()
+    );
+    let res = run_session(&input);
+    let saved = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(res.ends_with(&format!("> Saved the session to {}\n> ", path.display())));
+    assert_eq!(saved, "var a = 1;\n\nfun f() {\n  return a;\n}\n");
+  }
+
+  #[test]
+  fn load_runs_a_file_in_the_session() {
+    let path = session_file("load");
+    std::fs::write(&path, "var a = 20;\nfun twice(x) { return x * 2; }\n").unwrap();
+    let res = run_session(&format!(":load {}\ntwice(a)\n", path.display()));
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(res, "> > 40\n> ");
+  }
+
+  #[test]
+  fn unknown_commands_are_reported() {
+    assert_eq!(
+      run_session(":quit\n:load\n"),
+      concat!(
+        "> Unknown command ':quit'. The commands are :save and :load.\n",
+        "> Usage: :load file.lox\n",
+        "> "
+      )
+    );
+  }
+
+  #[test]
+  fn runtime_error_inside_block_keeps_global_scope() {
+    let res = run_session("var a = 1;\n{ var a = 2; -nil; }\nprint a;\n");
+    assert_eq!(
+      res,
+      "> > [line 1, column 14]: Expected a number, got a nil\n> 1\n> "
+    );
+  }
+}
@@ -0,0 +1,145 @@
+use crate::compile::compile_error::CompileError;
+use crate::interpret::error::RuntimeError;
+use crate::parse::parse_error::ParseError;
+use crate::resolve::resolve_error::ResolveError;
+use crate::scan::scan_error::ScanError;
+use crate::scan::span::Span;
+use serde::Serialize;
+
+/// An error from any stage of the pipeline, in the shape editors and graders read: what went
+/// wrong and where, each in its own field, instead of one line of text.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Diagnostic {
+  /// Stable code of the error, like `E1002`. `explain` describes what it means.
+  pub code: &'static str,
+  /// What went wrong, without where it happened.
+  pub message: String,
+  pub line: Option<usize>,
+  pub column: Option<usize>,
+  pub span: Option<Span>,
+}
+
+impl Diagnostic {
+  /// A diagnostic for errors outside the source, like a file that can't be read.
+  pub fn new(code: &'static str, message: String) -> Diagnostic {
+    Diagnostic {
+      code,
+      message,
+      line: None,
+      column: None,
+      span: None,
+    }
+  }
+
+  fn at_line(mut self, line: Option<usize>) -> Diagnostic {
+    self.line = line;
+    self
+  }
+
+  /// Column 0 means the column is unknown, so it's left out.
+  fn at(mut self, span: Option<Span>) -> Diagnostic {
+    if let Some(span) = span {
+      self.line = Some(span.line);
+      self.column = (span.column > 0).then_some(span.column);
+      self.span = Some(span);
+    }
+    self
+  }
+
+  /// The diagnostic as a single line of JSON.
+  pub fn to_json(&self) -> String {
+    serde_json::to_string(self).expect("diagnostics are always valid JSON")
+  }
+}
+
+impl From<&ScanError> for Diagnostic {
+  fn from(error: &ScanError) -> Self {
+    Diagnostic::new(error.code(), without_location(&error.to_string())).at(error.span())
+  }
+}
+
+impl From<&ParseError> for Diagnostic {
+  fn from(error: &ParseError) -> Self {
+    let message = match error {
+      ParseError::MalformedExpression(_, message) => format!("Malformed expression: {message}"),
+      error => without_location(&error.to_string()),
+    };
+    Diagnostic::new(error.code(), message).at(error.span())
+  }
+}
+
+impl From<&ResolveError> for Diagnostic {
+  fn from(error: &ResolveError) -> Self {
+    Diagnostic::new(error.code(), without_location(&error.to_string())).at_line(Some(error.line()))
+  }
+}
+
+impl From<&CompileError> for Diagnostic {
+  fn from(error: &CompileError) -> Self {
+    Diagnostic::new(error.code(), without_location(&error.to_string())).at_line(Some(error.line()))
+  }
+}
+
+/// Only the error itself is kept, not its stack trace.
+impl From<&RuntimeError> for Diagnostic {
+  fn from(error: &RuntimeError) -> Self {
+    Diagnostic::new(error.code(), error.message())
+      .at_line(error.line())
+      .at(error.span())
+  }
+}
+
+/// Drops the `[line 1] Error: ` or `[line 1, column 2]: ` in front of error messages.
+fn without_location(text: &str) -> String {
+  let message = match text.split_once(']') {
+    Some((location, message)) if location.starts_with('[') => message,
+    _ => return text.to_string(),
+  };
+  let message = message.trim_start_matches(':').trim_start();
+  message
+    .strip_prefix("Error: ")
+    .unwrap_or(message)
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn scan_errors_keep_their_span_apart_from_the_message() {
+    let span = Span::new(2, 5, 12, 1);
+    let diagnostic = Diagnostic::from(&ScanError::UnexpectedCharacter(span, '@'));
+    assert_eq!(
+      diagnostic,
+      Diagnostic {
+        code: "E0001",
+        message: "Unexpected character: @".to_string(),
+        line: Some(2),
+        column: Some(5),
+        span: Some(span),
+      }
+    );
+  }
+
+  #[test]
+  fn errors_that_only_know_the_line_have_no_column() {
+    let diagnostic = Diagnostic::from(&RuntimeError::UndefinedVariable(3, "x".to_string()));
+    assert_eq!(
+      diagnostic.to_json(),
+      r#"{"code":"E1002","message":"Undefined variable: x","line":3,"column":null,"span":null}"#
+    );
+  }
+
+  #[test]
+  fn parse_errors_drop_the_location_from_the_message() {
+    let span = Span::new(1, 7, 6, 1);
+    let missing_name = Diagnostic::from(&ParseError::MissingFunctionName(span));
+    assert_eq!(missing_name.message, "Expected function name after fun.");
+    let malformed = ParseError::MalformedExpression(span, "Expected ';'".to_string());
+    assert_eq!(
+      Diagnostic::from(&malformed).message,
+      "Malformed expression: Expected ';'"
+    );
+  }
+}
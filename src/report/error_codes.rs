@@ -0,0 +1,550 @@
+/// What an error code means, for `explain`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ErrorCode {
+  pub code: &'static str,
+  pub name: &'static str,
+  /// What causes the error and how to fix it, with an example.
+  pub explanation: &'static str,
+}
+
+const fn error(code: &'static str, name: &'static str, explanation: &'static str) -> ErrorCode {
+  ErrorCode {
+    code,
+    name,
+    explanation,
+  }
+}
+
+/// Every error code, in order. `E00xx` are scan errors, `E01xx` parse errors, `E02xx` resolve
+/// errors, `E03xx` bytecode compile errors and `E1xxx` runtime errors. Codes are never reused, so they can be searched for.
+pub const ERROR_CODES: &[ErrorCode] = &[
+  error(
+    "E0001",
+    "UnexpectedCharacter",
+    "The source has a character that isn't part of any Lox token, outside of a string or a
+comment.
+
+    var price = 10 $ 2;   // `$` means nothing in Lox
+
+Remove the character, or put it inside a string if it's meant to be text.",
+  ),
+  error(
+    "E0002",
+    "UnterminatedString",
+    "A string starts with `\"` but the file ends before the closing `\"`.
+
+    print \"hello;
+
+Close the string: `print \"hello\";`. Strings can span lines, so the missing quote can be far
+above the end of the file.",
+  ),
+  error(
+    "E0003",
+    "InvalidNumberLiteral",
+    "A hexadecimal or binary number has digits that don't belong to its base, or no digits at all.
+
+    print 0xFG;   // G is not a hexadecimal digit
+    print 0b102;  // 2 is not a binary digit
+
+Hexadecimal numbers use 0-9 and A-F after `0x`, binary numbers 0 and 1 after `0b`.",
+  ),
+  error(
+    "E0004",
+    "InvalidUtf8",
+    "The source file has bytes that aren't valid UTF-8. The scanner skips them.
+
+Save the file as UTF-8. This usually happens with files written in another encoding.",
+  ),
+  error(
+    "E0005",
+    "Io",
+    "The source couldn't be read, like a file that was removed or a closed stdin.",
+  ),
+  error(
+    "E0101",
+    "MalformedExpression",
+    "The parser found a token it didn't expect where the code is, usually a missing `;`, `)` or
+operand.
+
+    print 1 +;      // `+` needs a right operand
+    var a = (1 + 2; // `(` is never closed
+
+The message says what was expected and what was found instead.",
+  ),
+  error(
+    "E0102",
+    "UnexpectedEndOfFile",
+    "The file ends in the middle of a statement, like a block without its closing `}`.
+
+    fun f() {
+      print 1;
+
+Close every block and finish the last statement.",
+  ),
+  error(
+    "E0103",
+    "MissingFunctionName",
+    "Function declarations need a name after `fun`.
+
+    fun (a) { return a; }
+
+Name the function: `fun identity(a) { return a; }`.",
+  ),
+  error(
+    "E0104",
+    "MissingParameterName",
+    "Function parameters must be names, separated by commas.
+
+    fun add(a, 1) { return a; }
+
+Use a name for every parameter: `fun add(a, b) { return a + b; }`.",
+  ),
+  error(
+    "E0105",
+    "MissingFunctionBody",
+    "The body of a function or method must be a block in braces.
+
+    fun twice(x) return x * 2;
+
+Put the body in a block: `fun twice(x) { return x * 2; }`.",
+  ),
+  error(
+    "E0106",
+    "MissingClassName",
+    "Class declarations need a name after `class`.
+
+    class { init() {} }
+
+Name the class: `class Point { init() {} }`.",
+  ),
+  error(
+    "E0107",
+    "MissingSuperclassName",
+    "A class that inherits needs the name of its superclass after `<`.
+
+    class Dog < { }
+
+Name the superclass: `class Dog < Animal { }`.",
+  ),
+  error(
+    "E0108",
+    "InheritFromSelf",
+    "A class can't be its own superclass.
+
+    class Node < Node { }
+
+Inherit from another class, or remove `< Node`.",
+  ),
+  error(
+    "E0109",
+    "TooManyArguments",
+    "A call can have at most 255 arguments.
+
+Pass a list or an instance with the values instead of each value on its own.",
+  ),
+  error(
+    "E0110",
+    "TooManyParameters",
+    "A function can have at most 255 parameters.
+
+Take a list or an instance with the values instead of each value on its own.",
+  ),
+  error(
+    "E0111",
+    "MissingImportPath",
+    "`import` needs the path of the file to import as a string.
+
+    import utils;
+
+Quote the path: `import \"utils.lox\";`.",
+  ),
+  error(
+    "E0112",
+    "TooDeeplyNested",
+    "Expressions or blocks are nested deeper than the parser allows, like hundreds of
+parentheses inside each other or hundreds of operators or calls in a row. Parsing them would
+overflow the stack.
+
+Move the inner parts into variables or functions.",
+  ),
+  error(
+    "E0113",
+    "InvalidNumber",
+    "The parser got a number token it can't read as a number. The scanner doesn't produce them,
+so the tokens were built some other way.",
+  ),
+  error(
+    "E0114",
+    "Scan",
+    "A scan error found while parsing straight from the scanner. The message is the scan error.",
+  ),
+  error(
+    "E0115",
+    "MissingSemicolon",
+    "Statements end with `;`. The error points right after the code that's missing it, even when
+the next statement is lines below.
+
+    var a = 1
+    print a;
+
+Add the `;`: `var a = 1;`.",
+  ),
+  error(
+    "E0116",
+    "MissingLeftOperand",
+    "An expression starts with an operator that needs a value on each side.
+
+    var total = * 2;
+
+Put the missing value in front of the operator, `var total = price * 2;`, or remove the
+operator. `-` is the only one of them that also works with a single value: `-2`.",
+  ),
+  error(
+    "E0201",
+    "TopLevelReturn",
+    "`return` only makes sense inside a function.
+
+    print 1;
+    return;
+
+Remove the `return`, or move the code into a function.",
+  ),
+  error(
+    "E0202",
+    "BreakOutsideLoop",
+    "`break` stops the loop it's in, so it has to be inside a `while` or `for`.
+
+    if (done) break;
+
+Use it inside a loop: `while (true) { if (done) break; }`.",
+  ),
+  error(
+    "E0203",
+    "ContinueOutsideLoop",
+    "`continue` skips to the next iteration of the loop it's in, so it has to be inside a `while`
+or `for`.
+
+    fun f() { continue; }",
+  ),
+  error(
+    "E0204",
+    "ReadInOwnInitializer",
+    "A local variable is read in the expression that gives it its first value, before it has one.
+
+    var a = 1;
+    {
+      var a = a + 1;  // the inner `a` doesn't exist yet
+    }
+
+Give the inner variable another name.",
+  ),
+  error(
+    "E0205",
+    "AlreadyDeclared",
+    "A local scope declares the same name twice.
+
+    fun f() {
+      var a = 1;
+      var a = 2;
+    }
+
+Assign to the variable instead of declaring it again: `a = 2;`. Global variables can be declared
+again.",
+  ),
+  error(
+    "E0206",
+    "ImportOutsideTopLevel",
+    "Imports run a whole file in the global scope, so they can't be inside a block or a function.
+
+    fun setup() { import \"utils.lox\"; }
+
+Import at the top of the file instead.",
+  ),
+  error(
+    "E0207",
+    "ReadBeforeAssignment",
+    "Only with `--strict`. A variable declared without a value is read before anything is
+assigned to it, so it would silently be `nil`.
+
+    var total;
+    print total + 1;
+
+Give the variable a value where it's declared, or assign it before reading it. Write
+`var total = nil;` if it's meant to start as `nil`.",
+  ),
+  error(
+    "E0301",
+    "Unsupported",
+    "Only from `disassemble`. The program uses a statement the bytecode compiler can't compile
+yet, so its listing would be missing code.
+
+    import \"utils.lox\";
+
+`run` supports every statement. Disassemble the parts of the program without it.",
+  ),
+  error(
+    "E1001",
+    "NotANumber",
+    "An operator that only works with numbers got another value.
+
+    print -\"five\";
+
+Convert the value first, or check its type.",
+  ),
+  error(
+    "E1002",
+    "UndefinedVariable",
+    "The program reads or assigns a variable that was never declared, or that was declared in a
+scope that already ended.
+
+    {
+      var count = 1;
+    }
+    print count;   // `count` only exists inside the block
+
+Declare the variable with `var` before using it, in a scope that includes the use. Check the
+spelling too: names are case sensitive.",
+  ),
+  error(
+    "E1003",
+    "WrongBinaryOperationType",
+    "A binary operator got values it can't combine. Arithmetic and comparisons take two numbers,
+and `+` also takes two strings.
+
+    print \"total: \" + 3;
+
+Convert the number to a string first, or run with `--lenient-concat` to let `+` join strings with
+any value.",
+  ),
+  error(
+    "E1004",
+    "InvalidBitwiseOperand",
+    "Bitwise operators take whole numbers.
+
+    print 1.5 & 1;
+
+Round the number first with `floor()`.",
+  ),
+  error(
+    "E1005",
+    "ZeroDivision",
+    "A number was divided by zero.
+
+    var average = total / count;  // fails when `count` is 0
+
+Check the divisor before dividing.",
+  ),
+  error(
+    "E1006",
+    "NotAFunction",
+    "Only functions and classes can be called.
+
+    var name = \"lox\";
+    name();
+
+Check that the variable holds the function you meant to call.",
+  ),
+  error(
+    "E1007",
+    "WrongNumberOfArguments",
+    "A function was called with more or fewer arguments than it has parameters. Classes take the
+arguments of their `init` method.
+
+    fun add(a, b) { return a + b; }
+    add(1);
+
+Pass one argument per parameter.",
+  ),
+  error(
+    "E1008",
+    "NotAnInstance",
+    "Only instances have properties, so `.` only works on them.
+
+    var n = 3;
+    print n.size;",
+  ),
+  error(
+    "E1009",
+    "UndefinedProperty",
+    "The instance has no field or method with that name.
+
+    class Point { init(x) { this.x = x; } }
+    print Point(1).y;
+
+Assign the field before reading it, or check the spelling.",
+  ),
+  error(
+    "E1010",
+    "WrongArgumentType",
+    "A native function got an argument of a type it doesn't take.
+
+    print len(3);
+
+The message says which type was expected.",
+  ),
+  error(
+    "E1011",
+    "IndexOutOfRange",
+    "An index is past the end of what it indexes, or negative.
+
+    print argv(5);  // with fewer than 6 arguments
+
+Check the length first, like with `argc()`.",
+  ),
+  error(
+    "E1012",
+    "FileAccess",
+    "A native function couldn't read or write a file: it doesn't exist, there are no permissions
+for it, or the program wasn't allowed to touch files.
+
+Run with `--allow-fs` to let the program use `readFile()`, `writeFile()` and `appendFile()`.",
+  ),
+  error(
+    "E1013",
+    "SuperclassMustBeAClass",
+    "The value after `<` in a class declaration isn't a class.
+
+    var Base = \"base\";
+    class Derived < Base {}",
+  ),
+  error(
+    "E1014",
+    "StackOverflow",
+    "Too many calls are in progress at once, usually because of recursion that never stops.
+
+    fun forever(n) { return forever(n + 1); }
+
+Make sure every recursive function has a case that returns without calling itself again.",
+  ),
+  error(
+    "E1015",
+    "ImportFailed",
+    "An imported file can't be read, or has scan, parse or resolve errors. The message has the
+reason.
+
+Imports are relative to the file that imports them.",
+  ),
+  error(
+    "E1016",
+    "ImportCycle",
+    "Files import each other in a cycle, so none of them can finish running first.
+
+    // a.lox
+    import \"b.lox\";
+    // b.lox
+    import \"a.lox\";
+
+Move what both files need into a third one.",
+  ),
+  error(
+    "E1017",
+    "AssertionFailed",
+    "The condition of an `assert` was false.
+
+    assert(1 + 1 == 3, \"math is broken\");
+
+The message is the one after the comma, if there is one.",
+  ),
+  error(
+    "E1018",
+    "Thrown",
+    "The program threw a value with `throw` and no `try` caught it.
+
+    throw \"not implemented\";
+
+Catch it with `try { ... } catch (e) { ... }`.",
+  ),
+  error(
+    "E1019",
+    "ExecutionLimitExceeded",
+    "The program ran past the limit of `--max-steps` or `--timeout`.
+
+Raise the limit, or look for a loop that never ends.",
+  ),
+  error(
+    "E1020",
+    "CannotWriteToStdout",
+    "`print` couldn't write, like when the program's output is piped to a process that exited.",
+  ),
+  error(
+    "E1021",
+    "CannotReadFromStdin",
+    "A native function that reads input couldn't read from stdin.",
+  ),
+  error(
+    "E1900",
+    "InvalidExpression",
+    "The interpreter got a tree the parser never produces. This is a bug in the interpreter, or
+the AST was built by hand.",
+  ),
+  error(
+    "E1901",
+    "MissingScope",
+    "The interpreter used a scope that was already dropped. This is a bug in the interpreter.",
+  ),
+  error(
+    "E1902",
+    "Return",
+    "Used inside the interpreter to unwind a `return`. It's never reported.",
+  ),
+  error(
+    "E1903",
+    "Break",
+    "Used inside the interpreter to unwind a `break`. It's never reported.",
+  ),
+  error(
+    "E1904",
+    "Continue",
+    "Used inside the interpreter to unwind a `continue`. It's never reported.",
+  ),
+  error(
+    "E1905",
+    "Exit",
+    "Raised by `exit()` to stop the program. It becomes the exit code of the process instead of
+being reported.",
+  ),
+];
+
+/// The error with `code`, in any case, like `E1002` or `e1002`.
+pub fn find_error_code(code: &str) -> Option<&'static ErrorCode> {
+  ERROR_CODES
+    .iter()
+    .find(|error| error.code.eq_ignore_ascii_case(code))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::interpret::error::RuntimeError;
+  use crate::scan::scan_error::ScanError;
+  use crate::scan::span::Span;
+
+  #[test]
+  fn codes_are_unique_and_in_order() {
+    let codes = ERROR_CODES.iter().map(|e| e.code).collect::<Vec<_>>();
+    let mut sorted = codes.clone();
+    sorted.sort();
+    sorted.dedup();
+    assert_eq!(codes, sorted);
+  }
+
+  #[test]
+  fn errors_have_the_code_of_their_explanation() {
+    let undefined = RuntimeError::UndefinedVariable(1, "a".to_string());
+    assert_eq!(
+      find_error_code(undefined.code()).unwrap().name,
+      "UndefinedVariable"
+    );
+    let unexpected = ScanError::UnexpectedCharacter(Span::at_line(1), '@');
+    assert_eq!(
+      find_error_code(unexpected.code()).unwrap().name,
+      "UnexpectedCharacter"
+    );
+  }
+
+  #[test]
+  fn codes_are_found_in_any_case() {
+    assert_eq!(find_error_code("e1002").map(|e| e.code), Some("E1002"));
+    assert_eq!(find_error_code("E9999"), None);
+  }
+}
@@ -0,0 +1,2 @@
+pub mod resolve_error;
+pub mod resolver;
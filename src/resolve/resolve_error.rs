@@ -0,0 +1,47 @@
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ResolveError {
+  #[error("[line {0}]: Can't return from top-level code.")]
+  TopLevelReturn(usize),
+  #[error("[line {0}]: Can't use 'break' outside of a loop.")]
+  BreakOutsideLoop(usize),
+  #[error("[line {0}]: Can't use 'continue' outside of a loop.")]
+  ContinueOutsideLoop(usize),
+  #[error("[line {0}]: Can't read local variable in its own initializer.")]
+  ReadInOwnInitializer(usize),
+  #[error("[line {0}]: Already a variable named '{1}' in this scope.")]
+  AlreadyDeclared(usize, String),
+  #[error("[line {0}]: Can only import at the top level.")]
+  ImportOutsideTopLevel(usize),
+  /// Only in strict mode.
+  #[error("[line {0}]: Variable '{1}' is read before anything is assigned to it.")]
+  ReadBeforeAssignment(usize, String),
+}
+
+impl ResolveError {
+  /// Stable code of the error, which `explain` describes.
+  pub fn code(&self) -> &'static str {
+    match self {
+      ResolveError::TopLevelReturn(_) => "E0201",
+      ResolveError::BreakOutsideLoop(_) => "E0202",
+      ResolveError::ContinueOutsideLoop(_) => "E0203",
+      ResolveError::ReadInOwnInitializer(_) => "E0204",
+      ResolveError::AlreadyDeclared(..) => "E0205",
+      ResolveError::ImportOutsideTopLevel(_) => "E0206",
+      ResolveError::ReadBeforeAssignment(..) => "E0207",
+    }
+  }
+
+  pub fn line(&self) -> usize {
+    match self {
+      ResolveError::TopLevelReturn(line)
+      | ResolveError::BreakOutsideLoop(line)
+      | ResolveError::ContinueOutsideLoop(line)
+      | ResolveError::ReadInOwnInitializer(line)
+      | ResolveError::AlreadyDeclared(line, _)
+      | ResolveError::ImportOutsideTopLevel(line)
+      | ResolveError::ReadBeforeAssignment(line, _) => *line,
+    }
+  }
+}
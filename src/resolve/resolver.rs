@@ -0,0 +1,513 @@
+use crate::parse::expr::{Expr, ExprKind};
+use crate::parse::stmt::{Stmt, StmtKind};
+use crate::resolve::resolve_error::ResolveError;
+use std::collections::HashMap;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+enum FunctionKind {
+  #[default]
+  None,
+  Function,
+}
+
+/// A variable seen in strict mode, to find reads that happen before anything is assigned to it.
+struct Declaration {
+  name: String,
+  /// How many local scopes were open where it was declared. Globals are at 0.
+  scope_depth: usize,
+  function_depth: usize,
+  assigned: bool,
+}
+
+/// Static checks that run between parsing and interpretation. Every error found is collected, so
+/// a program is reported in full instead of stopping at the first problem.
+#[derive(Default)]
+pub struct Resolver {
+  current_function: FunctionKind,
+  loop_depth: usize,
+  function_depth: usize,
+  /// Local scopes, innermost last, mapping each name to whether its initializer already ran.
+  /// Globals are not tracked: they can be redeclared, like in the REPL.
+  scopes: Vec<HashMap<String, bool>>,
+  strict: bool,
+  /// Every variable in scope, globals included, innermost last. Only tracked in strict mode.
+  declarations: Vec<Declaration>,
+  errors: Vec<ResolveError>,
+}
+
+impl Resolver {
+  pub fn new() -> Resolver {
+    Resolver::default()
+  }
+
+  /// Makes reading a variable declared without a value, like `var a;`, an error until something
+  /// is assigned to it. Assignments count from where they are in the source, so one in either
+  /// branch of an `if` is enough. Functions can read variables declared outside of them, since
+  /// they may only be called after the assignment.
+  pub fn with_strict(mut self, strict: bool) -> Resolver {
+    self.strict = strict;
+    self
+  }
+
+  pub fn resolve(mut self, stmts: &[Stmt]) -> Result<(), Vec<ResolveError>> {
+    self.resolve_stmts(stmts);
+    if self.errors.is_empty() {
+      Ok(())
+    } else {
+      Err(self.errors)
+    }
+  }
+
+  fn resolve_stmts(&mut self, stmts: &[Stmt]) {
+    for stmt in stmts {
+      self.resolve_stmt(stmt);
+    }
+  }
+
+  fn resolve_stmt(&mut self, stmt: &Stmt) {
+    match &stmt.kind {
+      StmtKind::Expr(expr) | StmtKind::Print(expr, _) | StmtKind::Throw(expr, _) => {
+        self.resolve_expr(expr)
+      }
+      StmtKind::Var(name, value, line) => {
+        self.declare(name, *line);
+        if let Some(value) = value {
+          self.resolve_expr(value);
+        }
+        self.define(name);
+        self.track(name, value.is_some());
+      }
+      StmtKind::ScopeBlock(stmts) => {
+        self.scopes.push(HashMap::new());
+        self.resolve_stmts(stmts);
+        self.end_scope();
+      }
+      StmtKind::If {
+        condition,
+        then,
+        els,
+        ..
+      } => {
+        self.resolve_expr(condition);
+        self.resolve_stmt(then);
+        if let Some(els) = els {
+          self.resolve_stmt(els);
+        }
+      }
+      StmtKind::While {
+        condition, body, ..
+      } => {
+        self.resolve_expr(condition);
+        self.resolve_loop_body(body);
+      }
+      StmtKind::DoWhile {
+        body, condition, ..
+      } => {
+        self.resolve_loop_body(body);
+        self.resolve_expr(condition);
+      }
+      StmtKind::For {
+        initializer,
+        condition,
+        increment,
+        body,
+        ..
+      } => {
+        self.scopes.push(HashMap::new());
+        if let Some(initializer) = initializer {
+          self.resolve_stmt(initializer);
+        }
+        if let Some(condition) = condition {
+          self.resolve_expr(condition);
+        }
+        self.resolve_loop_body(body);
+        if let Some(increment) = increment {
+          self.resolve_expr(increment);
+        }
+        self.end_scope();
+      }
+      StmtKind::Break(line) => {
+        if self.loop_depth == 0 {
+          self.errors.push(ResolveError::BreakOutsideLoop(*line));
+        }
+      }
+      StmtKind::Continue(line) => {
+        if self.loop_depth == 0 {
+          self.errors.push(ResolveError::ContinueOutsideLoop(*line));
+        }
+      }
+      StmtKind::Assert {
+        condition, message, ..
+      } => {
+        self.resolve_expr(condition);
+        if let Some(message) = message {
+          self.resolve_expr(message);
+        }
+      }
+      StmtKind::Try {
+        body,
+        name,
+        handler,
+        line,
+      } => {
+        self.scopes.push(HashMap::new());
+        self.resolve_stmts(body);
+        self.end_scope();
+        self.scopes.push(HashMap::new());
+        self.declare(name, *line);
+        self.define(name);
+        self.track(name, true);
+        self.resolve_stmts(handler);
+        self.end_scope();
+      }
+      StmtKind::Import(_, line) => {
+        if !self.scopes.is_empty() || self.current_function != FunctionKind::None {
+          self.errors.push(ResolveError::ImportOutsideTopLevel(*line));
+        }
+      }
+      StmtKind::Return(value, line) => {
+        if self.current_function == FunctionKind::None {
+          self.errors.push(ResolveError::TopLevelReturn(*line));
+        }
+        self.resolve_expr(value);
+      }
+      StmtKind::Function { name, params, body } => {
+        self.define(name);
+        self.track(name, true);
+        self.resolve_function(params, body);
+      }
+      StmtKind::Class {
+        name,
+        superclass,
+        methods,
+        class_methods,
+        getters,
+      } => {
+        self.define(name);
+        self.track(name, true);
+        if let Some(superclass) = superclass {
+          self.resolve_expr(superclass);
+        }
+        for method in methods.iter().chain(class_methods).chain(getters) {
+          if let StmtKind::Function { params, body, .. } = &method.kind {
+            self.resolve_function(params, body);
+          }
+        }
+      }
+    }
+  }
+
+  /// Loops don't cross function boundaries: a `break` in a function declared inside a loop body is
+  /// still outside of any loop.
+  fn resolve_function(&mut self, params: &[String], body: &[Stmt]) {
+    let enclosing = self.current_function;
+    let enclosing_loop_depth = self.loop_depth;
+    self.current_function = FunctionKind::Function;
+    self.loop_depth = 0;
+    self.function_depth += 1;
+
+    // Parameters share their scope with the body, so the body can't redeclare them.
+    self
+      .scopes
+      .push(params.iter().map(|p| (p.clone(), true)).collect());
+    for param in params {
+      self.track(param, true);
+    }
+    self.resolve_stmts(body);
+    self.end_scope();
+
+    self.current_function = enclosing;
+    self.loop_depth = enclosing_loop_depth;
+    self.function_depth -= 1;
+  }
+
+  fn resolve_expr(&mut self, expr: &Expr) {
+    match &expr.kind {
+      ExprKind::LiteralNumber { .. }
+      | ExprKind::LiteralBool { .. }
+      | ExprKind::LiteralString { .. }
+      | ExprKind::LiteralNil
+      | ExprKind::This { .. }
+      | ExprKind::Super { .. } => {}
+      ExprKind::Binary { left, right, .. } | ExprKind::Logical { left, right, .. } => {
+        self.resolve_expr(left);
+        self.resolve_expr(right);
+      }
+      ExprKind::Unary { right, .. } => self.resolve_expr(right),
+      ExprKind::Group { expression } => self.resolve_expr(expression),
+      ExprKind::Call { callee, args, .. } => {
+        self.resolve_expr(callee);
+        for arg in args {
+          self.resolve_expr(arg);
+        }
+      }
+      ExprKind::Variable { name, line } => self.read(name, *line),
+      ExprKind::Assign { name, value, .. } => {
+        self.resolve_expr(value);
+        self.assign(name);
+      }
+      ExprKind::Increment { name, line, .. } => {
+        self.read(name, *line);
+        self.assign(name);
+      }
+      ExprKind::Get { object, .. } => self.resolve_expr(object),
+      ExprKind::Set { object, value, .. } => {
+        self.resolve_expr(object);
+        self.resolve_expr(value);
+      }
+    }
+  }
+
+  fn read(&mut self, name: &str, line: usize) {
+    let in_own_initializer = self
+      .scopes
+      .last()
+      .is_some_and(|scope| scope.get(name) == Some(&false));
+    if in_own_initializer {
+      self.errors.push(ResolveError::ReadInOwnInitializer(line));
+    }
+    let function_depth = self.function_depth;
+    if let Some(declaration) = self.declaration_mut(name) {
+      if !declaration.assigned && declaration.function_depth == function_depth {
+        // Reported once, not at every read.
+        declaration.assigned = true;
+        self
+          .errors
+          .push(ResolveError::ReadBeforeAssignment(line, name.to_string()));
+      }
+    }
+  }
+
+  fn assign(&mut self, name: &str) {
+    if let Some(declaration) = self.declaration_mut(name) {
+      declaration.assigned = true;
+    }
+  }
+
+  fn declare(&mut self, name: &str, line: usize) {
+    let Some(scope) = self.scopes.last_mut() else {
+      return;
+    };
+    if scope.contains_key(name) {
+      self
+        .errors
+        .push(ResolveError::AlreadyDeclared(line, name.to_string()));
+    }
+    scope.insert(name.to_string(), false);
+  }
+
+  fn define(&mut self, name: &str) {
+    if let Some(scope) = self.scopes.last_mut() {
+      scope.insert(name.to_string(), true);
+    }
+  }
+
+  fn resolve_loop_body(&mut self, body: &Stmt) {
+    self.loop_depth += 1;
+    self.resolve_stmt(body);
+    self.loop_depth -= 1;
+  }
+
+  fn end_scope(&mut self) {
+    self.scopes.pop();
+    let depth = self.scopes.len();
+    self.declarations.retain(|d| d.scope_depth <= depth);
+  }
+
+  fn track(&mut self, name: &str, assigned: bool) {
+    if self.strict {
+      self.declarations.push(Declaration {
+        name: name.to_string(),
+        scope_depth: self.scopes.len(),
+        function_depth: self.function_depth,
+        assigned,
+      });
+    }
+  }
+
+  /// The declaration a use of `name` refers to, in strict mode.
+  fn declaration_mut(&mut self, name: &str) -> Option<&mut Declaration> {
+    self.declarations.iter_mut().rev().find(|d| d.name == name)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parse::parser::LoxParser;
+  use crate::scan::scanner::Scanner;
+  use std::io::Cursor;
+
+  fn resolve_program(src: &str) -> Result<(), Vec<ResolveError>> {
+    let mut cursor = Cursor::new(src);
+    let tokens = Scanner::new(&mut cursor).scan_tokens().0;
+    let stmts = LoxParser::new(tokens).parse().unwrap();
+    Resolver::new().resolve(&stmts)
+  }
+
+  #[test]
+  fn return_inside_function_is_valid() {
+    assert_eq!(
+      resolve_program("fun foo() { if (true) { return 1; } return; }"),
+      Ok(())
+    );
+  }
+
+  #[test]
+  fn functions_can_be_declared_inside_blocks_and_functions() {
+    let src = "
+      fun makeCounter() {
+        var i = 0;
+        fun count() { i = i + 1; return i; }
+        return count;
+      }
+      { fun local() { return makeCounter()(); } local(); }
+    ";
+    assert_eq!(resolve_program(src), Ok(()));
+  }
+
+  #[test]
+  fn return_inside_method_is_valid() {
+    assert_eq!(resolve_program("class A { foo() { return 1; } }"), Ok(()));
+  }
+
+  #[test]
+  fn imports_are_only_allowed_at_the_top_level() {
+    assert_eq!(resolve_program("import \"a.lox\";"), Ok(()));
+    assert_eq!(
+      resolve_program("{\n  import \"a.lox\";\n}\nfun f() { import \"b.lox\"; }"),
+      Err(vec![
+        ResolveError::ImportOutsideTopLevel(2),
+        ResolveError::ImportOutsideTopLevel(4)
+      ])
+    );
+  }
+
+  #[test]
+  fn catch_declares_the_error_in_the_handler_scope() {
+    assert_eq!(
+      resolve_program("{ try {} catch (e) { var e = 1; } }"),
+      Err(vec![ResolveError::AlreadyDeclared(1, "e".to_string())])
+    );
+    assert_eq!(
+      resolve_program("try {} catch (e) { { var e = 1; } }"),
+      Ok(())
+    );
+  }
+
+  #[test]
+  fn return_at_top_level_is_an_error() {
+    assert_eq!(
+      resolve_program("print 1;\nreturn 1;"),
+      Err(vec![ResolveError::TopLevelReturn(2)])
+    );
+  }
+
+  #[test]
+  fn return_in_top_level_block_is_an_error() {
+    assert_eq!(
+      resolve_program("while (true) {\n  return;\n}"),
+      Err(vec![ResolveError::TopLevelReturn(2)])
+    );
+  }
+
+  #[test]
+  fn break_and_continue_inside_loops_are_valid() {
+    let res = resolve_program("while (true) { if (true) break; { continue; } }");
+    assert_eq!(res, Ok(()));
+    assert_eq!(resolve_program("for (;;) break;"), Ok(()));
+  }
+
+  #[test]
+  fn break_and_continue_outside_loops_are_errors() {
+    assert_eq!(
+      resolve_program("break;\nif (true) continue;"),
+      Err(vec![
+        ResolveError::BreakOutsideLoop(1),
+        ResolveError::ContinueOutsideLoop(2)
+      ])
+    );
+  }
+
+  #[test]
+  fn break_inside_a_function_declared_in_a_loop_is_an_error() {
+    let res = resolve_program("while (true) {\n  fun foo() { break; }\n}");
+    assert_eq!(res, Err(vec![ResolveError::BreakOutsideLoop(2)]));
+  }
+
+  #[test]
+  fn return_after_a_function_body_is_an_error() {
+    let res = resolve_program("fun foo() { return 1; }\nreturn foo();\nreturn;");
+    assert_eq!(
+      res,
+      Err(vec![
+        ResolveError::TopLevelReturn(2),
+        ResolveError::TopLevelReturn(3)
+      ])
+    );
+  }
+
+  #[test]
+  fn reading_a_local_in_its_own_initializer_is_an_error() {
+    assert_eq!(
+      resolve_program("var a = 1;\n{\n  var a = a + 1;\n}"),
+      Err(vec![ResolveError::ReadInOwnInitializer(3)])
+    );
+  }
+
+  #[test]
+  fn globals_can_be_read_in_their_own_initializer() {
+    assert_eq!(resolve_program("var a = 1; var a = a + 1;"), Ok(()));
+  }
+
+  #[test]
+  fn shadowing_doesnt_allow_reading_the_outer_local() {
+    let res = resolve_program("{ var a = 1; { var a = a + 1; } }");
+    assert_eq!(res, Err(vec![ResolveError::ReadInOwnInitializer(1)]));
+  }
+
+  #[test]
+  fn redeclaring_a_local_in_the_same_scope_is_an_error() {
+    assert_eq!(
+      resolve_program("{\n  var a = 1;\n  var a = 2;\n}\nfun f(b) {\n  var b;\n}"),
+      Err(vec![
+        ResolveError::AlreadyDeclared(3, "a".to_string()),
+        ResolveError::AlreadyDeclared(6, "b".to_string())
+      ])
+    );
+  }
+
+  fn resolve_strict(src: &str) -> Result<(), Vec<ResolveError>> {
+    let mut cursor = Cursor::new(src);
+    let tokens = Scanner::new(&mut cursor).scan_tokens().0;
+    let stmts = LoxParser::new(tokens).parse().unwrap();
+    Resolver::new().with_strict(true).resolve(&stmts)
+  }
+
+  #[test]
+  fn strict_mode_rejects_reading_a_variable_nothing_was_assigned_to() {
+    let src = "var a;\nprint a;\n{ var b; print b; print b; }";
+    assert!(resolve_program(src).is_ok());
+    assert_eq!(
+      resolve_strict(src),
+      Err(vec![
+        ResolveError::ReadBeforeAssignment(2, "a".to_string()),
+        ResolveError::ReadBeforeAssignment(3, "b".to_string()),
+      ])
+    );
+  }
+
+  #[test]
+  fn strict_mode_allows_variables_assigned_before_they_are_read() {
+    assert!(resolve_strict("var a = nil; print a;").is_ok());
+    assert!(resolve_strict("var a; if (true) a = 1; print a;").is_ok());
+    assert!(resolve_strict("var a; { var a = 1; print a; }").is_ok());
+    assert!(resolve_strict("var a; fun f() { print a; } a = 1; f();").is_ok());
+    assert!(resolve_strict("fun f(x) { var y; y = x; return y; }").is_ok());
+  }
+
+  #[test]
+  fn globals_can_be_redeclared() {
+    assert_eq!(resolve_program("var a = 1; var a = 2;"), Ok(()));
+  }
+}
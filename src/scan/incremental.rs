@@ -0,0 +1,276 @@
+use crate::scan::scan_error::ScanError;
+use crate::scan::scanner::Scanner;
+use crate::scan::span::Span;
+use crate::scan::token::Token;
+use std::ops::Range;
+
+/// Replaces the bytes in `range` of the source with `text`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextEdit {
+  pub range: Range<usize>,
+  pub text: String,
+}
+
+/// What an edit did to the tokens: the ones in `old` were replaced by the ones now in `new`.
+/// Tokens after them only moved.
+#[derive(Debug)]
+pub struct TokenChange {
+  pub old: Range<usize>,
+  pub new: Range<usize>,
+  /// Errors in the part of the source that was scanned again.
+  pub errors: Vec<ScanError>,
+}
+
+/// A source and its tokens, kept in sync as the source is edited. An edit only scans again from
+/// a little before it until the tokens are the same as before, so editors can rescan on every
+/// keystroke. The tokens are the same as scanning the whole source again would give.
+pub struct IncrementalScanner {
+  source: String,
+  tokens: Vec<Token>,
+  keep_trivia: bool,
+  /// Where the string that is never closed opens. A quote anywhere after it closes it, so edits
+  /// after it scan again from there.
+  unterminated: Option<usize>,
+}
+
+impl IncrementalScanner {
+  /// `tokens` have to be the ones [`Scanner`] gives for `source`.
+  pub fn new(source: String, tokens: Vec<Token>) -> IncrementalScanner {
+    // Only the last quote can open a string that isn't closed. It does if no token has it, or it
+    // may be in a comment, which is only scanned again for nothing.
+    let unterminated = source.rfind('"').filter(|&quote| {
+      let before = tokens.partition_point(|token| token.span().byte_offset <= quote);
+      before == 0 || {
+        let span = tokens[before - 1].span();
+        span.byte_offset + span.length <= quote
+      }
+    });
+    IncrementalScanner {
+      source,
+      tokens,
+      keep_trivia: false,
+      unterminated,
+    }
+  }
+
+  /// For tokens scanned with [`Scanner::with_trivia`].
+  pub fn with_trivia(mut self) -> Self {
+    self.keep_trivia = true;
+    self
+  }
+
+  pub fn source(&self) -> &str {
+    &self.source
+  }
+
+  pub fn tokens(&self) -> &[Token] {
+    &self.tokens
+  }
+
+  /// Applies `edit` to the source and scans the tokens it touched again.
+  ///
+  /// # Panics
+  ///
+  /// If the range of the edit isn't in the source or doesn't fall on char boundaries, like
+  /// [`String::replace_range`].
+  pub fn edit(&mut self, edit: TextEdit) -> TokenChange {
+    let TextEdit { range, text } = edit;
+    self.source.replace_range(range.clone(), &text);
+    let edit_end = range.start + text.len();
+    let old_offset = |offset: usize| offset - text.len() + range.len();
+
+    let first = match self.unterminated {
+      Some(quote) if quote < range.start => self.first_affected(quote),
+      _ => self.first_affected(range.start),
+    };
+    let (start, mut trivia) = match first {
+      0 => (Span::new(1, 1, 0, 0), None),
+      i => (
+        self.tokens[i].span(),
+        Some(self.tokens[i].leading_trivia().to_string()),
+      ),
+    };
+
+    let mut input = &self.source.as_bytes()[start.byte_offset..];
+    let mut scanner = Scanner::new(&mut input).starting_at(start);
+    if self.keep_trivia {
+      scanner = scanner.with_trivia();
+    }
+
+    let mut scanned = vec![];
+    let mut errors = vec![];
+    let mut old = first;
+    let mut resynced = None;
+    for item in scanner {
+      let mut token = match item {
+        Ok(token) => token,
+        Err(error) => {
+          errors.push(error);
+          continue;
+        }
+      };
+      // The trivia before the first token scanned again is in front of where scanning started.
+      if let Some(before) = trivia.take() {
+        let trivia = format!("{before}{}", token.leading_trivia());
+        token = token.with_leading_trivia(trivia);
+      }
+
+      // Past the edit, a token that starts where an old one did is followed by the same tokens.
+      let offset = token.span().byte_offset;
+      if offset >= edit_end {
+        let offset = old_offset(offset);
+        while self
+          .tokens
+          .get(old)
+          .is_some_and(|t| t.span().byte_offset < offset)
+        {
+          old += 1;
+        }
+        if self
+          .tokens
+          .get(old)
+          .is_some_and(|t| t.span().byte_offset == offset)
+        {
+          resynced = Some(token);
+          break;
+        }
+      }
+      scanned.push(token);
+    }
+
+    let new = first..first + scanned.len();
+    let scanned_end = resynced
+      .as_ref()
+      .map_or(usize::MAX, |t| t.span().byte_offset);
+    self.unterminated = match self.unterminated {
+      Some(quote) if quote >= range.end && quote + text.len() - range.len() >= scanned_end => {
+        Some(quote + text.len() - range.len())
+      }
+      _ => errors.iter().find_map(|error| match error {
+        ScanError::UnterminatedString(span) => Some(span.byte_offset),
+        _ => None,
+      }),
+    };
+    let old = match resynced {
+      Some(token) => {
+        self.move_tokens_after(old, token);
+        first..old
+      }
+      None => first..self.tokens.len(),
+    };
+    self.tokens.splice(old.clone(), scanned);
+    TokenChange { old, new, errors }
+  }
+
+  /// Index of the first token an edit starting at `offset` can change. The token right before the
+  /// edit can grow into it, like `an` and `d` becoming `and`, and so can the one before that: `1`
+  /// and `.` followed by a `5` become `1.5`.
+  fn first_affected(&self, offset: usize) -> usize {
+    let before = self
+      .tokens
+      .partition_point(|token| token.span().byte_offset < offset);
+    before.saturating_sub(2)
+  }
+
+  /// Replaces the token at `index` with `token`, the same one scanned again, and moves the ones
+  /// after it as much as it moved.
+  fn move_tokens_after(&mut self, index: usize, token: Token) {
+    let (from, to) = (self.tokens[index].span(), token.span());
+    self.tokens[index] = token;
+    for token in &mut self.tokens[index + 1..] {
+      token.move_to(token.span().moved(from, to));
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn scan(source: &str, trivia: bool) -> Vec<Token> {
+    let mut bytes = source.as_bytes();
+    let scanner = Scanner::new(&mut bytes);
+    let scanner = if trivia {
+      scanner.with_trivia()
+    } else {
+      scanner
+    };
+    scanner.scan_tokens().0
+  }
+
+  /// Replaces the first `old` in `source` with `new`, and checks the tokens are the same as
+  /// scanning the edited source from scratch.
+  fn edit(source: &str, old: &str, new: &str) -> TokenChange {
+    let start = source.find(old).unwrap();
+    let edit = TextEdit {
+      range: start..start + old.len(),
+      text: new.to_string(),
+    };
+    let edited = source.replacen(old, new, 1);
+
+    let mut with_trivia =
+      IncrementalScanner::new(source.to_string(), scan(source, true)).with_trivia();
+    with_trivia.edit(edit.clone());
+    assert_eq!(with_trivia.tokens(), scan(&edited, true));
+
+    let mut incremental = IncrementalScanner::new(source.to_string(), scan(source, false));
+    let change = incremental.edit(edit);
+    assert_eq!(incremental.source(), edited);
+    assert_eq!(incremental.tokens(), scan(&edited, false));
+    change
+  }
+
+  #[test]
+  fn only_the_tokens_around_an_edit_are_scanned_again() {
+    let change = edit("var a = 1;\nvar b = 2;\nvar c = 3;\n", "2", "20");
+    assert_eq!(change.old, 6..9);
+    assert_eq!(change.new, 6..9);
+    assert!(change.errors.is_empty());
+  }
+
+  #[test]
+  fn tokens_after_an_edit_move_lines_and_columns() {
+    edit("print a;\nprint b; print c;", "a", "a +\n  1");
+    edit("{\n  print a;\n}\nprint b;", "{\n", "");
+    edit("print 1; print 2;", "print 1;", "");
+  }
+
+  #[test]
+  fn edits_can_merge_and_split_tokens() {
+    edit("an d = 1;", " ", "");
+    edit("1. + 2", " ", "5");
+    edit("a = = b;", " =", "=");
+    edit("var and;", "and", "an d");
+  }
+
+  #[test]
+  fn strings_and_comments_change_the_tokens_after_them() {
+    edit("print a;\nprint \"b\";", "a", "\"a");
+    edit("print 1;\nprint 2;\nprint 3;", "print 2;", "// print 2;");
+    edit("print 1; // print 2;\nprint 3;", "// ", "");
+  }
+
+  #[test]
+  fn edits_at_the_ends_of_the_source() {
+    edit("  print 1;", "  ", "var a;\n");
+    edit("print 1;", ";", "; print 2;");
+    edit("", "", "print 1;");
+  }
+
+  #[test]
+  fn closing_an_unterminated_string_scans_again_from_its_quote() {
+    edit("print 1 \"a;\nprint 2; print 3;", "3", "\"");
+    edit("print \"a;\nprint 2;\n", "2", "\" + 2");
+    edit("// \"a\nprint 1; print 2;", "2", "\"");
+    edit("print \"a\" + \"b;\nprint 1;", "\"a\"", "1");
+  }
+
+  #[test]
+  fn errors_in_the_scanned_part_are_returned() {
+    let change = edit("print 1;", "1", "1 @");
+    assert!(matches!(
+      change.errors[..],
+      [ScanError::UnexpectedCharacter(_, '@')]
+    ));
+  }
+}
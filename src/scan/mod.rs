@@ -1,3 +1,8 @@
+pub mod incremental;
+pub mod number_format;
+pub mod scan_error;
 pub mod scanner;
+pub mod span;
 pub mod token;
+pub mod token_format;
 pub mod token_kind;
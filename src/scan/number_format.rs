@@ -0,0 +1,88 @@
+/// How Lox prints numbers, like jlox: integers without a trailing `.0`, other numbers with the
+/// fewest digits that read back as the same value (`1/3` is `0.3333333333333333`), and infinities
+/// as `Infinity` and `-Infinity`.
+pub fn format_number(value: f64) -> String {
+  if value.is_infinite() {
+    let sign = if value < 0.0 { "-" } else { "" };
+    format!("{sign}Infinity")
+  } else {
+    value.to_string()
+  }
+}
+
+/// How token literals and the AST show numbers: always with their decimal part, like `1.0`.
+pub fn format_literal(value: f64) -> String {
+  if value.is_finite() {
+    format!("{value:?}")
+  } else {
+    format_number(value)
+  }
+}
+
+/// Value of a number literal as written: decimal, or hexadecimal and binary after a `0x` or `0b`
+/// prefix. Long literals lose precision like decimal ones instead of overflowing.
+pub fn parse_number(lexeme: &str) -> Option<f64> {
+  let prefixed = |prefix: &str| {
+    lexeme
+      .strip_prefix(prefix)
+      .or_else(|| lexeme.strip_prefix(&prefix.to_uppercase()))
+  };
+  let radix_digits = match (prefixed("0x"), prefixed("0b")) {
+    (Some(digits), _) => Some((16, digits)),
+    (_, Some(digits)) => Some((2, digits)),
+    _ => None,
+  };
+
+  match radix_digits {
+    Some((_, "")) => None,
+    Some((radix, digits)) => digits.chars().try_fold(0.0, |value, c| {
+      Some(value * f64::from(radix) + f64::from(c.to_digit(radix)?))
+    }),
+    None => lexeme.parse().ok(),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn integers_have_no_decimal_part() {
+    assert_eq!(format_number(10.0), "10");
+    assert_eq!(format_number(-3.0), "-3");
+    assert_eq!(format_number(-0.0), "-0");
+    assert_eq!(format_number(1e21), "1000000000000000000000");
+  }
+
+  #[test]
+  fn fractions_use_full_precision() {
+    assert_eq!(format_number(1.0 / 3.0), "0.3333333333333333");
+    assert_eq!(format_number(0.1 + 0.2), "0.30000000000000004");
+    assert_eq!(format_number(2.5), "2.5");
+  }
+
+  #[test]
+  fn special_values_are_spelled_like_jlox() {
+    assert_eq!(format_number(f64::INFINITY), "Infinity");
+    assert_eq!(format_number(f64::NEG_INFINITY), "-Infinity");
+    assert_eq!(format_number(f64::NAN), "NaN");
+  }
+
+  #[test]
+  fn literals_keep_their_decimal_part() {
+    assert_eq!(format_literal(1.0), "1.0");
+    assert_eq!(format_literal(3.25), "3.25");
+    assert_eq!(format_literal(f64::INFINITY), "Infinity");
+  }
+
+  #[test]
+  fn literals_can_be_hexadecimal_or_binary() {
+    assert_eq!(parse_number("0xFF"), Some(255.0));
+    assert_eq!(parse_number("0Xff"), Some(255.0));
+    assert_eq!(parse_number("0b1010"), Some(10.0));
+    assert_eq!(parse_number("12.5"), Some(12.5));
+    assert_eq!(parse_number("0x"), None);
+    assert_eq!(parse_number("0b102"), None);
+    assert_eq!(parse_number("0xFG"), None);
+  }
+}
@@ -0,0 +1,60 @@
+use crate::scan::span::Span;
+use thiserror::Error;
+
+/// Mistakes in the source, like an unexpected char, are reported and scanning goes on after them.
+/// An I/O error stops the scanner.
+#[derive(Error, Debug)]
+pub enum ScanError {
+  #[error("[line {}] Error: Unexpected character: {1}", .0.line)]
+  UnexpectedCharacter(Span, char),
+  /// The span goes from the opening quote to the end of the source.
+  #[error("[line {}] Error: Unterminated string.", .0.line)]
+  UnterminatedString(Span),
+  #[error("[line {}] Error: Invalid number literal: {1}", .0.line)]
+  InvalidNumber(Span, String),
+  /// The span covers the bytes that were skipped.
+  #[error("[line {}] Error: Invalid UTF-8 sequence.", .0.line)]
+  InvalidUtf8(Span),
+  #[error("Cannot read the source: {0}")]
+  Io(#[from] std::io::Error),
+}
+
+impl ScanError {
+  /// Stable code of the error, which `explain` describes.
+  pub fn code(&self) -> &'static str {
+    match self {
+      ScanError::UnexpectedCharacter(..) => "E0001",
+      ScanError::UnterminatedString(_) => "E0002",
+      ScanError::InvalidNumber(..) => "E0003",
+      ScanError::InvalidUtf8(_) => "E0004",
+      ScanError::Io(_) => "E0005",
+    }
+  }
+
+  /// Where the error is in the source. I/O errors aren't anywhere in it.
+  pub fn span(&self) -> Option<Span> {
+    match self {
+      ScanError::UnexpectedCharacter(span, _)
+      | ScanError::UnterminatedString(span)
+      | ScanError::InvalidNumber(span, _)
+      | ScanError::InvalidUtf8(span) => Some(*span),
+      ScanError::Io(_) => None,
+    }
+  }
+}
+
+/// `io::Error` can't be compared, so I/O errors are equal when they are of the same kind.
+impl PartialEq for ScanError {
+  fn eq(&self, other: &Self) -> bool {
+    match (self, other) {
+      (ScanError::UnexpectedCharacter(s1, c1), ScanError::UnexpectedCharacter(s2, c2)) => {
+        s1 == s2 && c1 == c2
+      }
+      (ScanError::UnterminatedString(s1), ScanError::UnterminatedString(s2)) => s1 == s2,
+      (ScanError::InvalidNumber(s1, n1), ScanError::InvalidNumber(s2, n2)) => s1 == s2 && n1 == n2,
+      (ScanError::InvalidUtf8(s1), ScanError::InvalidUtf8(s2)) => s1 == s2,
+      (ScanError::Io(e1), ScanError::Io(e2)) => e1.kind() == e2.kind(),
+      _ => false,
+    }
+  }
+}
@@ -1,32 +1,84 @@
+use crate::scan::number_format::parse_number;
+use crate::scan::scan_error::ScanError;
+use crate::scan::span::Span;
 use crate::scan::token::Token;
 use crate::scan::token_kind::TokenKind;
+use std::collections::VecDeque;
 use std::io::Read;
 use utf8_read::{Char, Reader};
 
+/// Turns source text into tokens. Either scan everything at once with
+/// [`Scanner::scan_tokens`], or iterate over the scanner to get the tokens and errors in order as
+/// the input is read.
 pub struct Scanner<'r, R: Read> {
   input: Reader<&'r mut R>,
-  tokens: Vec<Token>,
-  peeked: Option<char>,
+  /// Tokens scanned but not handed out yet.
+  tokens: VecDeque<Token>,
+  /// Chars already read from the input that haven't been scanned yet.
+  pending: VecDeque<char>,
   current_line: usize,
-  errors: Vec<String>,
+  current_column: usize,
+  current_offset: usize,
+  token_start: Span,
+  /// Source text of the token being scanned.
+  lexeme: String,
+  errors: Vec<ScanError>,
+  /// Whether comments and whitespace are kept, for tools that need to give back the source.
+  keep_trivia: bool,
+  /// Whitespace seen since the last token, when keeping trivia.
+  trivia: String,
+  /// Why the input couldn't be read further, if it failed before its end.
+  io_error: Option<std::io::Error>,
+  /// Whether the whole input was scanned.
+  done: bool,
+}
+
+/// Gives each token or error as soon as it's scanned, so a program doesn't have to be scanned
+/// whole before parsing it. Scanning goes on after errors in the source. An I/O error is the last
+/// item.
+impl<R: Read> Iterator for Scanner<'_, R> {
+  type Item = Result<Token, ScanError>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    loop {
+      if !self.errors.is_empty() {
+        return Some(Err(self.errors.remove(0)));
+      }
+      if let Some(token) = self.tokens.pop_front() {
+        return Some(Ok(token));
+      }
+      if self.done {
+        return self.io_error.take().map(|error| Err(ScanError::Io(error)));
+      }
+      self.scan_step();
+    }
+  }
 }
 
 fn reserved_words(input: &str) -> Option<TokenKind> {
   match input {
     "and" => Some(TokenKind::And),
+    "assert" => Some(TokenKind::Assert),
+    "break" => Some(TokenKind::Break),
+    "catch" => Some(TokenKind::Catch),
     "class" => Some(TokenKind::Class),
+    "continue" => Some(TokenKind::Continue),
+    "do" => Some(TokenKind::Do),
     "else" => Some(TokenKind::Else),
     "false" => Some(TokenKind::False),
     "fun" => Some(TokenKind::Fun),
     "for" => Some(TokenKind::For),
     "if" => Some(TokenKind::If),
+    "import" => Some(TokenKind::Import),
     "nil" => Some(TokenKind::Nil),
     "or" => Some(TokenKind::Or),
     "print" => Some(TokenKind::Print),
     "return" => Some(TokenKind::Return),
     "super" => Some(TokenKind::Super),
     "this" => Some(TokenKind::This),
+    "throw" => Some(TokenKind::Throw),
     "true" => Some(TokenKind::True),
+    "try" => Some(TokenKind::Try),
     "var" => Some(TokenKind::Var),
     "while" => Some(TokenKind::While),
     _ => None,
@@ -37,54 +89,123 @@ impl<'r, R: Read> Scanner<'r, R> {
   pub fn new(read: &'r mut R) -> Scanner<'r, R> {
     Scanner {
       input: Reader::new(read),
-      tokens: vec![],
-      peeked: None,
+      tokens: VecDeque::new(),
+      pending: VecDeque::new(),
       current_line: 1,
+      current_column: 1,
+      current_offset: 0,
+      token_start: Span::new(1, 1, 0, 0),
+      lexeme: String::new(),
       errors: vec![],
+      keep_trivia: false,
+      trivia: String::new(),
+      io_error: None,
+      done: false,
     }
   }
 
-  pub fn scan_tokens(mut self) -> (Vec<Token>, Vec<String>) {
-    while !self.eof() {
-      let next_char = self.take_char();
-      if next_char.is_some() {
-        self.scan_char(next_char.unwrap())
-      };
+  /// Keeps comments as [`TokenKind::Comment`] tokens and attaches whitespace to the token after
+  /// it, so the source can be rebuilt from the tokens. The parser skips comments.
+  pub fn with_trivia(mut self) -> Self {
+    self.keep_trivia = true;
+    self
+  }
+
+  /// Scans input that starts at `start` in a bigger source, so spans are positions in that source.
+  pub(crate) fn starting_at(mut self, start: Span) -> Self {
+    self.current_line = start.line;
+    self.current_column = start.column;
+    self.current_offset = start.byte_offset;
+    self.token_start = Span { length: 0, ..start };
+    self
+  }
+
+  /// Scans the whole input. If it can't be read to the end, the tokens read until then are kept
+  /// and the failure is reported with the other errors. Use [`Scanner::try_scan_tokens`] to tell
+  /// both kinds of errors apart.
+  pub fn scan_tokens(mut self) -> (Vec<Token>, Vec<ScanError>) {
+    while !self.done {
+      self.scan_step();
+    }
+    if let Some(error) = self.io_error.take() {
+      self.errors.push(ScanError::Io(error));
     }
+    (self.tokens.into(), self.errors)
+  }
 
-    self.push_token_current_line(TokenKind::Eof);
+  /// Like [`Scanner::scan_tokens`], but fails if the input can't be read to the end instead of
+  /// scanning only part of the program.
+  pub fn try_scan_tokens(mut self) -> Result<(Vec<Token>, Vec<ScanError>), ScanError> {
+    while !self.done {
+      self.scan_step();
+    }
+    match self.io_error.take() {
+      Some(error) => Err(ScanError::Io(error)),
+      None => Ok((self.tokens.into(), self.errors)),
+    }
+  }
 
-    (self.tokens, self.errors)
+  /// Scans the next char, or ends with an [`TokenKind::Eof`] token once the input is over. There
+  /// is no `Eof` token when the input failed.
+  fn scan_step(&mut self) {
+    if !self.eof() {
+      self.mark_token_start();
+      if let Some(next_char) = self.take_char() {
+        self.scan_char(next_char)
+      };
+      return;
+    }
+
+    if self.io_error.is_none() {
+      self.mark_token_start();
+      self.push_token(TokenKind::Eof);
+    }
+    self.done = true;
   }
 
   fn eof(&self) -> bool {
-    self.input.eof()
+    self.pending.is_empty() && (self.input.eof() || self.io_error.is_some())
   }
 
   fn scan_char(&mut self, a_char: char) {
     match a_char {
-      '(' => self.push_token_current_line(TokenKind::LeftParen),
-      ')' => self.push_token_current_line(TokenKind::RightParen),
-      '{' => self.push_token_current_line(TokenKind::LeftBrace),
-      '}' => self.push_token_current_line(TokenKind::RightBrace),
-      ',' => self.push_token_current_line(TokenKind::Comma),
-      '.' => self.push_token_current_line(TokenKind::Dot),
-      '-' => self.push_token_current_line(TokenKind::Minus),
-      '+' => self.push_token_current_line(TokenKind::Plus),
-      ';' => self.push_token_current_line(TokenKind::Semicolon),
+      '(' => self.push_token(TokenKind::LeftParen),
+      ')' => self.push_token(TokenKind::RightParen),
+      '{' => self.push_token(TokenKind::LeftBrace),
+      '}' => self.push_token(TokenKind::RightBrace),
+      ',' => self.push_token(TokenKind::Comma),
+      '.' => self.push_token(TokenKind::Dot),
+      '-' => self.scan_maybe_two_chars('-', TokenKind::Minus, TokenKind::MinusMinus),
+      '+' => self.scan_maybe_two_chars('+', TokenKind::Plus, TokenKind::PlusPlus),
+      ';' => self.push_token(TokenKind::Semicolon),
       '/' => self.scan_slash_or_comment(),
-      '*' => self.push_token_current_line(TokenKind::Star),
-      '!' => self.scan_maybe_two_chars(TokenKind::Bang, TokenKind::BangEqual),
-      '=' => self.scan_maybe_two_chars(TokenKind::Equal, TokenKind::EqualEqual),
-      '>' => self.scan_maybe_two_chars(TokenKind::Greater, TokenKind::GreaterEqual),
-      '<' => self.scan_maybe_two_chars(TokenKind::Less, TokenKind::LessEqual),
+      '*' => self.scan_maybe_two_chars('*', TokenKind::Star, TokenKind::StarStar),
+      '!' => self.scan_maybe_two_chars('=', TokenKind::Bang, TokenKind::BangEqual),
+      '=' => self.scan_maybe_two_chars('=', TokenKind::Equal, TokenKind::EqualEqual),
+      '>' => self.scan_angle(
+        '>',
+        TokenKind::Greater,
+        TokenKind::GreaterEqual,
+        TokenKind::GreaterGreater,
+      ),
+      '<' => self.scan_angle(
+        '<',
+        TokenKind::Less,
+        TokenKind::LessEqual,
+        TokenKind::LessLess,
+      ),
+      '&' => self.push_token(TokenKind::Ampersand),
+      '|' => self.push_token(TokenKind::Pipe),
+      '^' => self.push_token(TokenKind::Caret),
+      '~' => self.push_token(TokenKind::Tilde),
       '"' => self.scan_string(),
+      ' ' | '\n' | '\r' | '\t' if self.keep_trivia => self.trivia.push(a_char),
       ' ' => {}
       '\n' => {}
       '\r' => {}
       '\t' => {}
       a_char => {
-        if a_char.is_digit(10) {
+        if a_char.is_ascii_digit() {
           self.scan_number(a_char);
         } else if Self::char_is_alphanumeric(&a_char) {
           self.scan_identifier(a_char);
@@ -95,29 +216,56 @@ impl<'r, R: Read> Scanner<'r, R> {
     }
   }
 
-  fn push_token_current_line(&mut self, kind: TokenKind) {
-    self.push_token_at(kind, self.current_line)
+  fn mark_token_start(&mut self) {
+    self.token_start = Span::new(
+      self.current_line,
+      self.current_column,
+      self.current_offset,
+      0,
+    );
+    self.lexeme.clear();
+  }
+
+  /// Pushes a token spanning from the last marked start to the current position.
+  /// From the start of the token being scanned to the current position.
+  fn token_span(&self) -> Span {
+    Span {
+      length: self.current_offset - self.token_start.byte_offset,
+      ..self.token_start
+    }
   }
 
-  fn push_token_at(&mut self, kind: TokenKind, line_number: usize) {
-    self.tokens.push(Token::new(kind, line_number))
+  fn push_token(&mut self, kind: TokenKind) {
+    let lexeme = std::mem::take(&mut self.lexeme);
+    let token = Token::with_span(kind, self.token_span()).with_lexeme(lexeme);
+    if self.keep_trivia {
+      let trivia = std::mem::take(&mut self.trivia);
+      self.tokens.push_back(token.with_leading_trivia(trivia));
+    } else {
+      self.tokens.push_back(token);
+    }
   }
 
   fn scan_slash_or_comment(&mut self) {
     let next = self.peek_char();
 
     if next.is_some_and(|n| n == '/') {
-      self.take_chars_until('\n');
+      self.take_char();
+      let mut text = String::new();
+      while let Some(c) = self.peek_char().filter(|c| *c != '\n') {
+        self.take_char();
+        text.push(c);
+      }
+      if self.keep_trivia {
+        self.push_token(TokenKind::Comment(text));
+      }
     } else {
-      self.push_token_current_line(TokenKind::Slash);
+      self.push_token(TokenKind::Slash);
     }
   }
 
   fn scan_unexpected_character(&mut self, a_char: char) {
-    let error = format!(
-      "[line {}] Error: Unexpected character: {}",
-      self.current_line, a_char
-    );
+    let error = ScanError::UnexpectedCharacter(self.token_span(), a_char);
     self.errors.push(error);
   }
 
@@ -125,36 +273,66 @@ impl<'r, R: Read> Scanner<'r, R> {
     let mut buf = String::from(a_char);
     self.take_following_alphanumeric(&mut buf);
     let token = reserved_words(&buf).unwrap_or(TokenKind::Identifier(buf));
-    self.push_token_current_line(token);
+    self.push_token(token);
   }
 
+  /// Strings can span lines, so an unterminated one takes the rest of the file. After reporting
+  /// it, scanning goes back to the end of the line with the opening quote, as if the string ended
+  /// there, so the code after it still gets scanned.
   fn scan_string(&mut self) {
-    let start = self.current_line;
-    if let Some(content) = self.take_chars_until('"') {
-      self.push_token_at(TokenKind::String(content), start);
-    } else {
-      self
-        .errors
-        .push(format!("[line {start}] Error: Unterminated string."));
+    let (line, column, offset) = (self.current_line, self.current_column, self.current_offset);
+    let mut content = String::new();
+    while let Some(c) = self.take_char() {
+      if c == '"' {
+        self.push_token(TokenKind::String(content));
+        return;
+      }
+      content.push(c);
+    }
+
+    let error = ScanError::UnterminatedString(self.token_span());
+    self.errors.push(error);
+    if let Some(newline) = content.find('\n') {
+      let (first_line, rest) = content.split_at(newline);
+      self.current_line = line;
+      self.current_column = column + first_line.chars().count();
+      self.current_offset = offset + first_line.len();
+      self.pending.extend(rest.chars());
     }
   }
 
   fn scan_number(&mut self, a_char: char) {
     let mut numerical_str = String::from(a_char);
+    if a_char == '0' && self.peek_char().is_some_and(|c| "xXbB".contains(c)) {
+      return self.scan_prefixed_number(numerical_str);
+    }
     self.take_following_digits(&mut numerical_str);
 
     if self.peek_char().is_some_and(|p| p == '.') {
-      numerical_str.push(self.take_char().unwrap());
+      numerical_str.extend(self.take_char());
       self.take_following_digits(&mut numerical_str);
     }
 
-    self.push_token_current_line(TokenKind::Number(numerical_str));
+    self.push_token(TokenKind::Number(numerical_str));
+  }
+
+  /// Hexadecimal and binary literals, like `0xFF` and `0b1010`. Letters right after the prefix
+  /// are part of the literal, so `0xFG` is an invalid number rather than `0xF` and `G`.
+  fn scan_prefixed_number(&mut self, mut numerical_str: String) {
+    numerical_str.extend(self.take_char());
+    self.take_following_alphanumeric(&mut numerical_str);
+    if parse_number(&numerical_str).is_some() {
+      self.push_token(TokenKind::Number(numerical_str));
+    } else {
+      let error = ScanError::InvalidNumber(self.token_span(), numerical_str);
+      self.errors.push(error);
+    }
   }
 
   fn take_following_digits(&mut self, buf: &mut String) {
     loop {
       let peeked = self.peek_char();
-      let maybe_digit = peeked.filter(|a| a.is_digit(10));
+      let maybe_digit = peeked.filter(|a| a.is_ascii_digit());
       if let Some(digit) = maybe_digit {
         self.take_char();
         buf.push(digit)
@@ -181,48 +359,83 @@ impl<'r, R: Read> Scanner<'r, R> {
     a.is_alphanumeric() || *a == '_'
   }
 
-  fn take_chars_until(&mut self, limit: char) -> Option<String> {
-    let mut buf = String::new();
+  /// Invalid UTF-8 is reported and skipped. Reading stops at the first I/O error.
+  fn read_char(&mut self) -> Option<char> {
     loop {
-      let taken = self.take_char()?;
-      if taken == limit {
-        break;
-      } else {
-        buf.push(taken)
+      match self.input.next_char() {
+        Ok(Char::Char(res)) => return Some(res),
+        Ok(_) => return None,
+        Err(utf8_read::Error::MalformedUtf8(_, bytes)) => {
+          let span = Span::new(
+            self.current_line,
+            self.current_column,
+            self.current_offset,
+            bytes,
+          );
+          self.current_offset += bytes;
+          self.errors.push(ScanError::InvalidUtf8(span));
+        }
+        Err(utf8_read::Error::IoError(error)) => {
+          self.io_error = Some(error);
+          return None;
+        }
       }
     }
-    Some(buf)
   }
 
+  /// Consumes the next char and moves the current position past it.
   fn take_char(&mut self) -> Option<char> {
-    let next_char = self.peeked.take().or_else(|| match self.input.next_char() {
-      Ok(Char::Char(res)) => Some(res),
-      _ => None,
-    });
+    let next_char = self.pending.pop_front().or_else(|| self.read_char())?;
 
-    if next_char.is_some_and(|c| c == '\n') {
-      self.current_line += 1
+    self.current_offset += next_char.len_utf8();
+    self.lexeme.push(next_char);
+    if next_char == '\n' {
+      self.current_line += 1;
+      self.current_column = 1;
+    } else {
+      self.current_column += 1;
     }
 
-    next_char
+    Some(next_char)
   }
 
+  /// Looks at the next char without moving the current position.
   fn peek_char(&mut self) -> Option<char> {
-    if self.peeked.is_some() {
-      return self.peeked.clone();
+    if self.pending.is_empty() {
+      let next_char = self.read_char()?;
+      self.pending.push_back(next_char);
+    }
+    self.pending.front().copied()
+  }
+
+  /// `<` and `>` are followed by `=` to compare, or doubled to shift.
+  fn scan_angle(
+    &mut self,
+    angle: char,
+    single: TokenKind,
+    or_equal: TokenKind,
+    doubled: TokenKind,
+  ) {
+    match self.peek_char() {
+      Some('=') => {
+        self.take_char();
+        self.push_token(or_equal);
+      }
+      Some(c) if c == angle => {
+        self.take_char();
+        self.push_token(doubled);
+      }
+      _ => self.push_token(single),
     }
-
-    let next_char = self.take_char();
-    self.peeked.replace(next_char?);
-    self.peeked.clone()
   }
 
-  fn scan_maybe_two_chars(&mut self, token1: TokenKind, token2: TokenKind) {
-    if self.peek_char().is_some_and(|c| c == '=') {
+  /// `token2` when the next char is `second`, as in `!=` or `++`.
+  fn scan_maybe_two_chars(&mut self, second: char, token1: TokenKind, token2: TokenKind) {
+    if self.peek_char().is_some_and(|c| c == second) {
       self.take_char();
-      self.push_token_current_line(token2);
+      self.push_token(token2);
     } else {
-      self.push_token_current_line(token1);
+      self.push_token(token1);
     }
   }
 }
@@ -232,20 +445,27 @@ mod tests {
   use super::*;
   use std::io::Cursor;
 
+  /// Tokens without their columns, so kinds and lines can be compared with `Token::new`.
   fn scan_program_clean(code: &str) -> Vec<Token> {
+    scan_spans(code)
+      .into_iter()
+      .map(|t| Token::new(t.kind().clone(), t.line()))
+      .collect()
+  }
+
+  fn scan_spans(code: &str) -> Vec<Token> {
     let program = String::from(code);
     let mut cursor = Cursor::new(program);
     let scan = Scanner::new(&mut cursor);
-    let tokens = scan.scan_tokens().0;
-
-    tokens
+    scan.scan_tokens().0
   }
 
   fn scan_program_with_errors(code: &str) -> Vec<String> {
     let program = String::from(code);
     let mut cursor = Cursor::new(program);
     let scan = Scanner::new(&mut cursor);
-    scan.scan_tokens().1
+    let errors = scan.scan_tokens().1;
+    errors.iter().map(|e| e.to_string()).collect()
   }
 
   #[test]
@@ -260,6 +480,85 @@ mod tests {
     );
   }
 
+  #[test]
+  fn doubled_operators_are_one_token() {
+    let kinds = scan_program_clean("a++ - --b+c**d*e")
+      .into_iter()
+      .map(|t| t.kind().clone())
+      .collect::<Vec<_>>();
+    assert_eq!(
+      kinds,
+      vec![
+        TokenKind::Identifier("a".to_string()),
+        TokenKind::PlusPlus,
+        TokenKind::Minus,
+        TokenKind::MinusMinus,
+        TokenKind::Identifier("b".to_string()),
+        TokenKind::Plus,
+        TokenKind::Identifier("c".to_string()),
+        TokenKind::StarStar,
+        TokenKind::Identifier("d".to_string()),
+        TokenKind::Star,
+        TokenKind::Identifier("e".to_string()),
+        TokenKind::Eof,
+      ]
+    );
+  }
+
+  #[test]
+  fn angles_compare_or_shift() {
+    let kinds = scan_program_clean("< <= << > >= >> & | ^ ~")
+      .into_iter()
+      .map(|t| t.kind().clone())
+      .collect::<Vec<_>>();
+    assert_eq!(
+      kinds,
+      vec![
+        TokenKind::Less,
+        TokenKind::LessEqual,
+        TokenKind::LessLess,
+        TokenKind::Greater,
+        TokenKind::GreaterEqual,
+        TokenKind::GreaterGreater,
+        TokenKind::Ampersand,
+        TokenKind::Pipe,
+        TokenKind::Caret,
+        TokenKind::Tilde,
+        TokenKind::Eof,
+      ]
+    );
+  }
+
+  #[test]
+  fn hex_and_binary_numbers_keep_their_lexeme() {
+    let tokens = scan_program_clean("0xFF 0b101 0");
+    let formatted = tokens
+      .iter()
+      .map(|t| t.kind().full_format())
+      .collect::<Vec<_>>();
+    assert_eq!(
+      formatted,
+      [
+        "NUMBER 0xFF 255.0",
+        "NUMBER 0b101 5.0",
+        "NUMBER 0 0.0",
+        "EOF  null"
+      ]
+    );
+  }
+
+  #[test]
+  fn numbers_with_invalid_digits_are_errors() {
+    let errors = scan_program_with_errors("0b102;\n0x;\n0xCAFE;");
+    assert_eq!(
+      errors,
+      vec![
+        "[line 1] Error: Invalid number literal: 0b102",
+        "[line 2] Error: Invalid number literal: 0x"
+      ]
+    );
+  }
+
   #[test]
   fn test_right_paren() {
     let tokens = scan_program_clean(")");
@@ -709,6 +1008,19 @@ mod tests {
     );
   }
 
+  #[test]
+  fn break_and_continue_test() {
+    let tokens = scan_program_clean("break continue");
+    assert_eq!(
+      tokens,
+      vec![
+        Token::new(TokenKind::Break, 1),
+        Token::new(TokenKind::Continue, 1),
+        Token::new(TokenKind::Eof, 1)
+      ]
+    );
+  }
+
   #[test]
   fn super_test() {
     let tokens = scan_program_clean("super");
@@ -816,4 +1128,205 @@ mod tests {
     let errors = scan_program_with_errors("\"bar\" \"unterminated");
     assert_eq!(errors, vec!["[line 1] Error: Unterminated string."]);
   }
+
+  #[test]
+  fn scanning_goes_on_after_an_unterminated_string() {
+    let src = "print \"oops;\nprint 1;\nprint @;";
+    assert_eq!(
+      scan_program_with_errors(src),
+      vec![
+        "[line 1] Error: Unterminated string.",
+        "[line 3] Error: Unexpected character: @"
+      ]
+    );
+    let tokens = scan_spans(src);
+    let kinds = tokens.iter().map(|t| t.kind().clone()).collect::<Vec<_>>();
+    assert_eq!(
+      kinds[1..5],
+      [
+        TokenKind::Print,
+        TokenKind::Number("1".to_string()),
+        TokenKind::Semicolon,
+        TokenKind::Print
+      ]
+    );
+    assert_eq!(tokens[1].span(), Span::new(2, 1, 13, 5));
+  }
+
+  #[test]
+  fn tokens_carry_their_span() {
+    let spans = scan_spans("var ab = 1.5;\n  x >= \"s\";")
+      .iter()
+      .map(|t| t.span())
+      .collect::<Vec<_>>();
+    assert_eq!(
+      spans,
+      vec![
+        Span::new(1, 1, 0, 3),
+        Span::new(1, 5, 4, 2),
+        Span::new(1, 8, 7, 1),
+        Span::new(1, 10, 9, 3),
+        Span::new(1, 13, 12, 1),
+        Span::new(2, 3, 16, 1),
+        Span::new(2, 5, 18, 2),
+        Span::new(2, 8, 21, 3),
+        Span::new(2, 11, 24, 1),
+        Span::new(2, 12, 25, 0),
+      ]
+    );
+  }
+
+  #[test]
+  fn columns_count_chars_and_offsets_count_bytes() {
+    let tokens = scan_spans("\"ñú\" a");
+    assert_eq!(tokens[0].span(), Span::new(1, 1, 0, 6));
+    assert_eq!(tokens[1].span(), Span::new(1, 6, 7, 1));
+  }
+
+  #[test]
+  fn multi_line_strings_start_at_the_opening_quote() {
+    let tokens = scan_spans("\"a\nb\" c");
+    assert_eq!(tokens[0].span(), Span::new(1, 1, 0, 5));
+    assert_eq!(tokens[1].span(), Span::new(2, 4, 6, 1));
+  }
+
+  #[test]
+  fn peeking_a_new_line_does_not_move_the_token_to_the_next_line() {
+    let tokens = scan_program_clean("12\nfoo");
+    assert_eq!(
+      tokens,
+      vec![
+        Token::new(TokenKind::Number("12".to_string()), 1),
+        Token::new(TokenKind::Identifier("foo".to_string()), 2),
+        Token::new(TokenKind::Eof, 2),
+      ]
+    );
+  }
+
+  fn scan_with_trivia(code: &str) -> Vec<Token> {
+    let mut cursor = Cursor::new(code);
+    Scanner::new(&mut cursor).with_trivia().scan_tokens().0
+  }
+
+  #[test]
+  fn trivia_mode_keeps_comments_as_tokens() {
+    let tokens = scan_with_trivia("a // note\nb");
+    assert_eq!(
+      tokens.iter().map(|t| t.kind().clone()).collect::<Vec<_>>(),
+      vec![
+        TokenKind::Identifier("a".to_string()),
+        TokenKind::Comment(" note".to_string()),
+        TokenKind::Identifier("b".to_string()),
+        TokenKind::Eof,
+      ]
+    );
+    assert_eq!(tokens[1].span(), Span::new(1, 3, 2, 7));
+  }
+
+  #[test]
+  fn trivia_mode_attaches_whitespace_to_the_next_token() {
+    let tokens = scan_with_trivia("  var\tx ;\n");
+    let trivia = tokens
+      .iter()
+      .map(|t| t.leading_trivia())
+      .collect::<Vec<_>>();
+    assert_eq!(trivia, ["  ", "\t", " ", "\n"]);
+  }
+
+  #[test]
+  fn source_can_be_rebuilt_from_trivia_tokens() {
+    let src = "// header\nfun f(a) {\n  return \"s\" + 1.50; // trailing\n}\n\n";
+    let rebuilt = scan_with_trivia(src)
+      .iter()
+      .map(|t| t.leading_trivia().to_string() + &t.kind().lexeme())
+      .collect::<String>();
+    assert_eq!(rebuilt, src);
+  }
+
+  #[test]
+  fn invalid_utf8_is_reported_and_skipped() {
+    let mut cursor = Cursor::new(b"print 1;\nprint \xff2;".to_vec());
+    let (tokens, errors) = Scanner::new(&mut cursor).scan_tokens();
+    assert_eq!(errors, [ScanError::InvalidUtf8(Span::new(2, 7, 15, 1))]);
+    let kinds = tokens.iter().map(|t| t.kind().clone()).collect::<Vec<_>>();
+    assert_eq!(
+      kinds[3..5],
+      [TokenKind::Print, TokenKind::Number("2".to_string())]
+    );
+  }
+
+  struct FailingRead {
+    sent: bool,
+  }
+
+  impl Read for FailingRead {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+      if self.sent {
+        return Err(std::io::Error::other("disk on fire"));
+      }
+      self.sent = true;
+      buf[..6].copy_from_slice(b"print ");
+      Ok(6)
+    }
+  }
+
+  #[test]
+  fn io_errors_are_returned_instead_of_ending_the_program_early() {
+    let mut read = FailingRead { sent: false };
+    let result = Scanner::new(&mut read).try_scan_tokens();
+    assert_eq!(
+      result.unwrap_err().to_string(),
+      "Cannot read the source: disk on fire"
+    );
+  }
+
+  #[test]
+  fn io_errors_are_reported_with_the_other_errors() {
+    let mut read = FailingRead { sent: false };
+    let (_, errors) = Scanner::new(&mut read).scan_tokens();
+    assert!(matches!(errors[..], [ScanError::Io(_)]));
+  }
+
+  #[test]
+  fn iterating_gives_tokens_and_errors_in_order() {
+    let mut cursor = Cursor::new("1 @ 2");
+    let items = Scanner::new(&mut cursor)
+      .map(|item| match item {
+        Ok(token) => token.kind().lexeme(),
+        Err(error) => error.to_string(),
+      })
+      .collect::<Vec<_>>();
+    assert_eq!(
+      items,
+      ["1", "[line 1] Error: Unexpected character: @", "2", ""]
+    );
+  }
+
+  #[test]
+  fn iterating_ends_with_the_io_error() {
+    let mut read = FailingRead { sent: false };
+    let items = Scanner::new(&mut read).collect::<Vec<_>>();
+    assert!(matches!(items[..], [Ok(_), Err(ScanError::Io(_))]));
+  }
+
+  #[test]
+  fn errors_point_at_what_could_not_be_scanned() {
+    let mut cursor = Cursor::new("var a = 0xZ;\nprint \"open");
+    let errors = Scanner::new(&mut cursor).scan_tokens().1;
+    assert_eq!(
+      errors,
+      [
+        ScanError::InvalidNumber(Span::new(1, 9, 8, 3), "0xZ".to_string()),
+        ScanError::UnterminatedString(Span::new(2, 7, 19, 5)),
+      ]
+    );
+  }
+
+  #[test]
+  fn tokens_keep_their_source_text() {
+    let tokens = scan_spans("var s = \"a b\" + 0x1F;");
+    let lexemes = tokens.iter().map(|t| t.lexeme()).collect::<Vec<_>>();
+    assert_eq!(lexemes, ["var", "s", "=", "\"a b\"", "+", "0x1F", ";", ""]);
+    assert_eq!(tokens[5].to_string(), "NUMBER 0x1F 31.0");
+  }
 }
@@ -0,0 +1,61 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+
+/// Where a token lives in the source. Lines and columns start at 1 and columns count chars, so
+/// they match what an editor shows. `byte_offset` and `length` are measured in bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct Span {
+  pub line: usize,
+  pub column: usize,
+  pub byte_offset: usize,
+  pub length: usize,
+}
+
+impl Span {
+  pub fn new(line: usize, column: usize, byte_offset: usize, length: usize) -> Span {
+    Span {
+      line,
+      column,
+      byte_offset,
+      length,
+    }
+  }
+
+  /// Span for code that only knows the line, like tokens built by hand. Column 0 means unknown.
+  pub fn at_line(line: usize) -> Span {
+    Span {
+      line,
+      ..Span::default()
+    }
+  }
+
+  /// Where this span is after an edit moved the source from `from` on to `to`. The span has to
+  /// start at or after `from`. Only spans on the same line as `from` move columns.
+  pub fn moved(self, from: Span, to: Span) -> Span {
+    let column = if self.line == from.line {
+      self.column + to.column - from.column
+    } else {
+      self.column
+    };
+    Span {
+      line: self.line + to.line - from.line,
+      column,
+      byte_offset: self.byte_offset + to.byte_offset - from.byte_offset,
+      length: self.length,
+    }
+  }
+
+  /// Span from the start of this one to the end of `end`.
+  pub fn to(self, end: Span) -> Span {
+    Span {
+      length: (end.byte_offset + end.length).saturating_sub(self.byte_offset),
+      ..self
+    }
+  }
+}
+
+impl Display for Span {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(f, "line {}, column {}", self.line, self.column)
+  }
+}
@@ -1,18 +1,49 @@
+use crate::scan::span::Span;
 use crate::scan::token_kind::TokenKind;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Token {
   kind: TokenKind,
-  line: usize,
+  span: Span,
+  /// The token as written in the source. Tokens built by hand use the lexeme of their kind.
+  #[serde(default)]
+  lexeme: String,
+  /// Whitespace between the previous token and this one. Only kept when scanning with trivia.
+  #[serde(default, skip_serializing_if = "String::is_empty")]
+  leading_trivia: String,
 }
 
 impl Token {
   pub fn new(kind: TokenKind, line: usize) -> Token {
-    Token { kind, line }
+    Token::with_span(kind, Span::at_line(line))
   }
 
-  pub fn to_string(&self) -> String {
-    self.kind.full_format()
+  pub fn with_span(kind: TokenKind, span: Span) -> Token {
+    Token {
+      lexeme: kind.lexeme(),
+      kind,
+      span,
+      leading_trivia: String::new(),
+    }
+  }
+
+  /// Same token, written as `lexeme` in the source.
+  pub fn with_lexeme(mut self, lexeme: String) -> Token {
+    self.lexeme = lexeme;
+    self
+  }
+
+  /// Same token, with the whitespace that came before it in the source.
+  pub fn with_leading_trivia(mut self, trivia: String) -> Token {
+    self.leading_trivia = trivia;
+    self
+  }
+
+  /// Moves the token to `span`, for tokens whose text didn't change but the source before them did.
+  pub(crate) fn move_to(&mut self, span: Span) {
+    self.span = span;
   }
 
   pub fn kind(&self) -> &TokenKind {
@@ -20,10 +51,32 @@ impl Token {
   }
 
   pub fn line(&self) -> usize {
-    self.line
+    self.span.line
   }
 
-  pub fn symbol(&self) -> String {
-    self.kind.symbol()
+  pub fn span(&self) -> Span {
+    self.span
+  }
+
+  pub fn leading_trivia(&self) -> &str {
+    &self.leading_trivia
+  }
+
+  pub fn lexeme(&self) -> &str {
+    &self.lexeme
+  }
+}
+
+/// Codecrafters style, like [`TokenKind::full_format`] but with the lexeme from the source.
+impl Display for Token {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    let literal = self.kind.literal();
+    write!(
+      f,
+      "{} {} {}",
+      self.kind.name(),
+      self.lexeme,
+      literal.as_deref().unwrap_or("null")
+    )
   }
 }
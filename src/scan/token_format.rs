@@ -0,0 +1,136 @@
+use crate::scan::number_format::parse_number;
+use crate::scan::token::Token;
+use crate::scan::token_kind::TokenKind;
+use clap::ValueEnum;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum TokenFormat {
+  /// One `KIND lexeme literal` line per token, as codecrafters expects
+  Text,
+  Json,
+  Csv,
+}
+
+#[derive(Serialize)]
+struct TokenRecord {
+  kind: &'static str,
+  lexeme: String,
+  literal: serde_json::Value,
+  line: usize,
+  column: usize,
+}
+
+impl From<&Token> for TokenRecord {
+  fn from(token: &Token) -> Self {
+    let literal = match token.kind() {
+      TokenKind::Number(value) => {
+        parse_number(value).map_or(serde_json::Value::Null, |n| serde_json::json!(n))
+      }
+      TokenKind::String(value) => serde_json::json!(value),
+      _ => serde_json::Value::Null,
+    };
+
+    TokenRecord {
+      kind: token.kind().name(),
+      lexeme: token.lexeme().to_string(),
+      literal,
+      line: token.span().line,
+      column: token.span().column,
+    }
+  }
+}
+
+pub fn format_tokens(tokens: &[Token], format: TokenFormat) -> String {
+  match format {
+    TokenFormat::Text => tokens
+      .iter()
+      .map(|t| t.to_string())
+      .collect::<Vec<_>>()
+      .join("\n"),
+    TokenFormat::Json => {
+      let records = tokens.iter().map(TokenRecord::from).collect::<Vec<_>>();
+      serde_json::to_string_pretty(&records).expect("token records always serialize")
+    }
+    TokenFormat::Csv => {
+      let mut lines = vec!["kind,lexeme,literal,line,column".to_string()];
+      for token in tokens {
+        let kind = token.kind();
+        lines.push(format!(
+          "{},{},{},{},{}",
+          kind.name(),
+          csv_field(token.lexeme()),
+          csv_field(&kind.literal().unwrap_or_default()),
+          token.line(),
+          token.span().column
+        ));
+      }
+      lines.join("\n")
+    }
+  }
+}
+
+/// Quotes fields that would break the row, doubling any quote inside them.
+fn csv_field(value: &str) -> String {
+  if value.contains([',', '"', '\n', '\r']) {
+    format!("\"{}\"", value.replace('"', "\"\""))
+  } else {
+    value.to_string()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::scan::scanner::Scanner;
+  use std::io::Cursor;
+
+  fn tokens(src: &str) -> Vec<Token> {
+    let mut cursor = Cursor::new(src);
+    Scanner::new(&mut cursor).scan_tokens().0
+  }
+
+  #[test]
+  fn text_is_the_codecrafters_format() {
+    assert_eq!(
+      format_tokens(&tokens("var a = 1;"), TokenFormat::Text),
+      "VAR var null\nIDENTIFIER a null\nEQUAL = null\nNUMBER 1 1.0\nSEMICOLON ; null\nEOF  null"
+    );
+  }
+
+  #[test]
+  fn json_has_one_object_per_token() {
+    let json = format_tokens(&tokens("print \"hi\" + 2;"), TokenFormat::Json);
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(
+      value[1],
+      serde_json::json!({"kind": "STRING", "lexeme": "\"hi\"", "literal": "hi", "line": 1, "column": 7})
+    );
+    assert_eq!(value[3]["literal"], serde_json::json!(2.0));
+    assert_eq!(value[0]["literal"], serde_json::Value::Null);
+    assert_eq!(value.as_array().unwrap().len(), 6);
+  }
+
+  #[test]
+  fn csv_has_a_header_and_one_row_per_token() {
+    assert_eq!(
+      format_tokens(&tokens("x\n  >= 2.5"), TokenFormat::Csv),
+      concat!(
+        "kind,lexeme,literal,line,column\n",
+        "IDENTIFIER,x,,1,1\n",
+        "GREATER_EQUAL,>=,,2,3\n",
+        "NUMBER,2.5,2.5,2,6\n",
+        "EOF,,,2,9",
+      )
+    );
+  }
+
+  #[test]
+  fn csv_quotes_fields_with_commas_and_quotes() {
+    let csv = format_tokens(&tokens("\"a, b\""), TokenFormat::Csv);
+    assert_eq!(
+      csv.lines().nth(1).unwrap(),
+      "STRING,\"\"\"a, b\"\"\",\"a, b\",1,1"
+    );
+  }
+}
@@ -1,4 +1,7 @@
-#[derive(Debug, Clone, PartialEq)]
+use crate::scan::number_format::{format_literal, parse_number};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum TokenKind {
   // Single-character tokens.
   LeftParen,
@@ -12,8 +15,15 @@ pub enum TokenKind {
   Semicolon,
   Slash,
   Star,
+  Ampersand,
+  Pipe,
+  Caret,
+  Tilde,
 
   // One or Two tokens
+  MinusMinus,
+  PlusPlus,
+  StarStar,
   Bang,
   BangEqual,
   Equal,
@@ -22,15 +32,24 @@ pub enum TokenKind {
   GreaterEqual,
   Less,
   LessEqual,
+  GreaterGreater,
+  LessLess,
 
   // Literals
   Number(String),
   String(String),
   Identifier(String),
+  /// A `//` comment, without the slashes. Only scanned when keeping trivia.
+  Comment(String),
 
   // Keywords
   And,
+  Assert,
+  Break,
+  Catch,
   Class,
+  Continue,
+  Do,
 
   Eof,
   Else,
@@ -38,13 +57,16 @@ pub enum TokenKind {
   Fun,
   For,
   If,
+  Import,
   Nil,
   Or,
   Print,
   Return,
   Super,
   This,
+  Throw,
   True,
+  Try,
   Var,
   While,
 }
@@ -63,7 +85,14 @@ impl TokenKind {
       TokenKind::Semicolon => ";".to_string(),
       TokenKind::Slash => "/".to_string(),
       TokenKind::Star => "*".to_string(),
+      TokenKind::Ampersand => "&".to_string(),
+      TokenKind::Pipe => "|".to_string(),
+      TokenKind::Caret => "^".to_string(),
+      TokenKind::Tilde => "~".to_string(),
       TokenKind::Bang => "!".to_string(),
+      TokenKind::MinusMinus => "--".to_string(),
+      TokenKind::PlusPlus => "++".to_string(),
+      TokenKind::StarStar => "**".to_string(),
       TokenKind::BangEqual => "!=".to_string(),
       TokenKind::Equal => "=".to_string(),
       TokenKind::EqualEqual => "==".to_string(),
@@ -71,70 +100,126 @@ impl TokenKind {
       TokenKind::GreaterEqual => ">=".to_string(),
       TokenKind::Less => "<".to_string(),
       TokenKind::LessEqual => "<=".to_string(),
+      TokenKind::GreaterGreater => ">>".to_string(),
+      TokenKind::LessLess => "<<".to_string(),
       TokenKind::Number(value) => value.to_string(),
       TokenKind::String(value) => value.to_string(),
       TokenKind::Identifier(value) => value.to_string(),
+      TokenKind::Comment(text) => format!("//{text}"),
       TokenKind::And => "and".to_string(),
+      TokenKind::Assert => "assert".to_string(),
+      TokenKind::Break => "break".to_string(),
+      TokenKind::Catch => "catch".to_string(),
       TokenKind::Class => "class".to_string(),
+      TokenKind::Continue => "continue".to_string(),
+      TokenKind::Do => "do".to_string(),
       TokenKind::Else => "else".to_string(),
       TokenKind::False => "false".to_string(),
       TokenKind::Fun => "fun".to_string(),
       TokenKind::For => "for".to_string(),
       TokenKind::If => "if".to_string(),
+      TokenKind::Import => "import".to_string(),
       TokenKind::Nil => "nil".to_string(),
       TokenKind::Or => "or".to_string(),
       TokenKind::Print => "print".to_string(),
       TokenKind::Return => "return".to_string(),
       TokenKind::Super => "super".to_string(),
       TokenKind::This => "this".to_string(),
+      TokenKind::Throw => "throw".to_string(),
       TokenKind::True => "true".to_string(),
+      TokenKind::Try => "try".to_string(),
       TokenKind::Var => "var".to_string(),
       TokenKind::While => "while".to_string(),
       TokenKind::Eof => "".to_string(), // The double space is on purpose. The representation is empty.
     }
   }
 
+  /// Codecrafters style: kind, lexeme and literal (`null` when there is none).
   pub fn full_format(&self) -> String {
+    format!(
+      "{} {} {}",
+      self.name(),
+      self.lexeme(),
+      self.literal().unwrap_or_else(|| "null".to_string())
+    )
+  }
+
+  pub fn name(&self) -> &'static str {
     match self {
-      TokenKind::LeftParen => "LEFT_PAREN ( null".to_string(),
-      TokenKind::RightParen => "RIGHT_PAREN ) null".to_string(),
-      TokenKind::LeftBrace => "LEFT_BRACE { null".to_string(),
-      TokenKind::RightBrace => "RIGHT_BRACE } null".to_string(),
-      TokenKind::Comma => "COMMA , null".to_string(),
-      TokenKind::Dot => "DOT . null".to_string(),
-      TokenKind::Minus => "MINUS - null".to_string(),
-      TokenKind::Plus => "PLUS + null".to_string(),
-      TokenKind::Semicolon => "SEMICOLON ; null".to_string(),
-      TokenKind::Slash => "SLASH / null".to_string(),
-      TokenKind::Star => "STAR * null".to_string(),
-      TokenKind::Bang => "BANG ! null".to_string(),
-      TokenKind::BangEqual => "BANG_EQUAL != null".to_string(),
-      TokenKind::Equal => "EQUAL = null".to_string(),
-      TokenKind::EqualEqual => "EQUAL_EQUAL == null".to_string(),
-      TokenKind::Greater => "GREATER > null".to_string(),
-      TokenKind::GreaterEqual => "GREATER_EQUAL >= null".to_string(),
-      TokenKind::Less => "LESS < null".to_string(),
-      TokenKind::LessEqual => "LESS_EQUAL <= null".to_string(),
-      TokenKind::Number(value) => format!("NUMBER {value} {:?}", value.parse::<f64>().unwrap()),
-      TokenKind::String(value) => format!("STRING \"{value}\" {value}"),
-      TokenKind::Identifier(value) => format!("IDENTIFIER {value} null"),
-      TokenKind::And => "AND and null".to_string(),
-      TokenKind::Class => "CLASS class null".to_string(),
-      TokenKind::Else => "ELSE else null".to_string(),
-      TokenKind::False => "FALSE false null".to_string(),
-      TokenKind::Fun => "FUN fun null".to_string(),
-      TokenKind::For => "FOR for null".to_string(),
-      TokenKind::If => "IF if null".to_string(),
-      TokenKind::Nil => "NIL nil null".to_string(),
-      TokenKind::Or => "OR or null".to_string(),
-      TokenKind::Print => "PRINT print null".to_string(),
-      TokenKind::Return => "RETURN return null".to_string(),
-      TokenKind::Super => "SUPER super null".to_string(),
-      TokenKind::This => "THIS this null".to_string(),
-      TokenKind::True => "TRUE true null".to_string(),
-      TokenKind::Var => "VAR var null".to_string(),
-      TokenKind::While => "WHILE while null".to_string(),
-      TokenKind::Eof => "EOF  null".to_string(), // The double space is on purpose. The representation is empty.
+      TokenKind::LeftParen => "LEFT_PAREN",
+      TokenKind::RightParen => "RIGHT_PAREN",
+      TokenKind::LeftBrace => "LEFT_BRACE",
+      TokenKind::RightBrace => "RIGHT_BRACE",
+      TokenKind::Comma => "COMMA",
+      TokenKind::Dot => "DOT",
+      TokenKind::Minus => "MINUS",
+      TokenKind::Plus => "PLUS",
+      TokenKind::Semicolon => "SEMICOLON",
+      TokenKind::Slash => "SLASH",
+      TokenKind::Star => "STAR",
+      TokenKind::Ampersand => "AMPERSAND",
+      TokenKind::Pipe => "PIPE",
+      TokenKind::Caret => "CARET",
+      TokenKind::Tilde => "TILDE",
+      TokenKind::Bang => "BANG",
+      TokenKind::MinusMinus => "MINUS_MINUS",
+      TokenKind::PlusPlus => "PLUS_PLUS",
+      TokenKind::StarStar => "STAR_STAR",
+      TokenKind::BangEqual => "BANG_EQUAL",
+      TokenKind::Equal => "EQUAL",
+      TokenKind::EqualEqual => "EQUAL_EQUAL",
+      TokenKind::Greater => "GREATER",
+      TokenKind::GreaterEqual => "GREATER_EQUAL",
+      TokenKind::Less => "LESS",
+      TokenKind::LessEqual => "LESS_EQUAL",
+      TokenKind::GreaterGreater => "GREATER_GREATER",
+      TokenKind::LessLess => "LESS_LESS",
+      TokenKind::Number(_) => "NUMBER",
+      TokenKind::String(_) => "STRING",
+      TokenKind::Identifier(_) => "IDENTIFIER",
+      TokenKind::Comment(_) => "COMMENT",
+      TokenKind::And => "AND",
+      TokenKind::Assert => "ASSERT",
+      TokenKind::Break => "BREAK",
+      TokenKind::Catch => "CATCH",
+      TokenKind::Class => "CLASS",
+      TokenKind::Continue => "CONTINUE",
+      TokenKind::Do => "DO",
+      TokenKind::Else => "ELSE",
+      TokenKind::False => "FALSE",
+      TokenKind::Fun => "FUN",
+      TokenKind::For => "FOR",
+      TokenKind::If => "IF",
+      TokenKind::Import => "IMPORT",
+      TokenKind::Nil => "NIL",
+      TokenKind::Or => "OR",
+      TokenKind::Print => "PRINT",
+      TokenKind::Return => "RETURN",
+      TokenKind::Super => "SUPER",
+      TokenKind::This => "THIS",
+      TokenKind::Throw => "THROW",
+      TokenKind::True => "TRUE",
+      TokenKind::Try => "TRY",
+      TokenKind::Var => "VAR",
+      TokenKind::While => "WHILE",
+      TokenKind::Eof => "EOF",
+    }
+  }
+
+  /// The text of the token as written in the source.
+  pub fn lexeme(&self) -> String {
+    match self {
+      TokenKind::String(value) => format!("\"{value}\""),
+      _ => self.symbol(),
+    }
+  }
+
+  /// Value of literal tokens. Numbers always show their decimal part, like `1.0`.
+  pub fn literal(&self) -> Option<String> {
+    match self {
+      TokenKind::Number(value) => parse_number(value).map(format_literal),
+      TokenKind::String(value) => Some(value.to_string()),
+      _ => None,
     }
   }
 }
@@ -210,6 +295,14 @@ mod tests {
     )
   }
 
+  #[test]
+  fn comment_to_string() {
+    assert_eq!(
+      &TokenKind::Comment(" note".to_string()).full_format(),
+      "COMMENT // note null"
+    )
+  }
+
   #[test]
   fn super_to_string() {
     assert_eq!(&TokenKind::Super.full_format(), "SUPER super null")
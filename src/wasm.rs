@@ -0,0 +1,40 @@
+use crate::interpret::clock::Clock;
+use crate::interpret::interpreter::Interpreter;
+use crate::lox::Lox;
+use std::time::Duration;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+extern "C" {
+  #[wasm_bindgen(js_namespace = Date, js_name = now)]
+  fn date_now() -> f64;
+}
+
+/// `SystemTime` panics on `wasm32-unknown-unknown`, so the browser asks JavaScript instead.
+struct JsClock;
+
+impl Clock for JsClock {
+  fn now(&self) -> f64 {
+    date_now() / 1000.0
+  }
+
+  /// The page would freeze while blocked, so sleeping returns at once.
+  fn sleep(&self, _duration: Duration) {}
+}
+
+/// Runs a program and returns everything it printed, followed by the error that stopped it, if
+/// any. Meant for a browser playground, which just shows the text.
+#[wasm_bindgen]
+pub fn run_source(source: &str) -> String {
+  let mut out: Vec<u8> = vec![];
+  let mut interpreter = Interpreter::new(&mut out).with_clock(JsClock);
+  let result = Lox::run_in(source, &mut interpreter);
+  drop(interpreter);
+
+  let mut text = String::from_utf8_lossy(&out).into_owned();
+  if let Err(error) = result {
+    text.push_str(&error.to_string());
+    text.push('\n');
+  }
+  text
+}
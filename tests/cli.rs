@@ -0,0 +1,621 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+use std::time::Duration;
+
+fn interpreter(args: &[&str], stdin: &str) -> Output {
+  let mut child = Command::new(env!("CARGO_BIN_EXE_codecrafters-interpreter"))
+    .args(args)
+    .stdin(Stdio::piped())
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    .spawn()
+    .expect("Cannot execute the interpreter");
+  child
+    .stdin
+    .take()
+    .unwrap()
+    .write_all(stdin.as_bytes())
+    .unwrap();
+  child.wait_with_output().unwrap()
+}
+
+#[test]
+fn run_accepts_inline_code() {
+  let output = interpreter(&["run", "-e", "print 1 + 2;"], "");
+  assert!(output.status.success());
+  assert_eq!(String::from_utf8_lossy(&output.stdout), "3\n\n");
+}
+
+#[test]
+fn run_reads_the_program_from_stdin_with_a_dash() {
+  let output = interpreter(&["run", "-"], "var a = \"from stdin\";\nprint a;");
+  assert!(output.status.success());
+  assert_eq!(String::from_utf8_lossy(&output.stdout), "from stdin\n\n");
+}
+
+#[test]
+fn evaluate_accepts_inline_code() {
+  let output = interpreter(&["evaluate", "--eval", "10 / 4"], "");
+  assert!(output.status.success());
+  assert_eq!(String::from_utf8_lossy(&output.stdout), "2.5\n");
+}
+
+#[test]
+fn inline_code_and_a_file_cannot_be_combined() {
+  let output = interpreter(&["run", "program.lox", "-e", "print 1;"], "");
+  assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn run_can_send_output_and_errors_to_separate_files() {
+  let dir = std::env::temp_dir().join(format!("lox-cli-{}", std::process::id()));
+  std::fs::create_dir_all(&dir).unwrap();
+  let output_path = dir.join("output.txt");
+  let errors_path = dir.join("errors.txt");
+
+  let output = interpreter(
+    &[
+      "run",
+      "-e",
+      "print 1;\nprint -\"a\";",
+      "--output",
+      output_path.to_str().unwrap(),
+      "--error-output",
+      errors_path.to_str().unwrap(),
+    ],
+    "",
+  );
+
+  assert_eq!(output.status.code(), Some(70));
+  assert_eq!(std::fs::read_to_string(&output_path).unwrap(), "1\n");
+  assert_eq!(
+    std::fs::read_to_string(&errors_path).unwrap(),
+    "[line 2, column 7]: Expected a number, got a String\n"
+  );
+  assert!(output.stderr.is_empty());
+  std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn unbounded_recursion_is_a_runtime_error() {
+  let output = interpreter(&["run", "-e", "fun f() { f(); }\nf();"], "");
+  assert_eq!(output.status.code(), Some(70));
+  let stderr = String::from_utf8_lossy(&output.stderr);
+  assert!(stderr.starts_with("[line 1]: Stack overflow\n[line 1] in f()\n"));
+  assert!(stderr.contains("... 981 more frames\n"));
+  assert!(stderr.ends_with("[line 1] in f()\n[line 2] in script\n"));
+}
+
+#[test]
+fn runtime_errors_in_functions_print_a_stack_trace() {
+  let src = "fun fib(n) {\n  if (n < 2) return n + nil;\n  return fib(n - 1);\n}\nfib(2);";
+  let output = interpreter(&["run", "-e", src], "");
+  assert_eq!(output.status.code(), Some(70));
+  assert_eq!(
+    String::from_utf8_lossy(&output.stderr),
+    concat!(
+      "[line 2, column 23]: Operation + expected 2 numbers. Received Number and nil\n",
+      "[line 2] in fib()\n",
+      "[line 3] in fib()\n",
+      "[line 5] in script\n"
+    )
+  );
+}
+
+#[test]
+fn run_fixed_clock_makes_clock_return_the_given_time() {
+  let output = interpreter(
+    &[
+      "run",
+      "-e",
+      "print clock(); print clock();",
+      "--fixed-clock",
+      "12.5",
+    ],
+    "",
+  );
+  assert!(output.status.success());
+  assert_eq!(String::from_utf8_lossy(&output.stdout), "12.5\n12.5\n\n");
+}
+
+#[test]
+fn run_quiet_discards_the_output_but_keeps_the_exit_status() {
+  let output = interpreter(&["run", "-q", "-e", "print 1; print 2;"], "");
+  assert!(output.status.success());
+  assert_eq!(String::from_utf8_lossy(&output.stdout), "");
+
+  let output = interpreter(&["run", "-q", "-e", "print 1; exit(3);"], "");
+  assert_eq!(output.status.code(), Some(3));
+  assert_eq!(String::from_utf8_lossy(&output.stdout), "");
+}
+
+#[test]
+fn check_reports_static_errors_without_running_the_program() {
+  let output = interpreter(&["check", "-"], "print \"ran\";");
+  assert!(output.status.success());
+  assert_eq!(
+    String::from_utf8_lossy(&output.stdout),
+    "No problems found\n"
+  );
+
+  let output = interpreter(&["check", "-"], "print \"ran\";\nreturn 1;");
+  assert_eq!(output.status.code(), Some(65));
+  assert_eq!(String::from_utf8_lossy(&output.stdout), "");
+
+  let output = interpreter(&["check", "--strict", "-"], "var a;\nprint a;");
+  assert_eq!(output.status.code(), Some(65));
+}
+
+#[test]
+fn run_stops_programs_past_the_step_limit() {
+  let output = interpreter(&["run", "-e", "while (true) {}", "--max-steps", "1000"], "");
+  assert_eq!(output.status.code(), Some(70));
+  assert_eq!(
+    String::from_utf8_lossy(&output.stderr),
+    "Execution limit exceeded\n"
+  );
+}
+
+#[test]
+fn run_stops_programs_past_the_timeout() {
+  let output = interpreter(&["run", "-e", "while (true) {}", "--timeout", "50"], "");
+  assert_eq!(output.status.code(), Some(70));
+  assert_eq!(
+    String::from_utf8_lossy(&output.stderr),
+    "Execution limit exceeded\n"
+  );
+}
+
+#[test]
+fn test_bless_writes_golden_files_for_failing_programs() {
+  let dir = std::env::temp_dir().join(format!("lox-bless-{}", std::process::id()));
+  std::fs::create_dir_all(&dir).unwrap();
+  std::fs::write(dir.join("ok.lox"), "print 1;").unwrap();
+  std::fs::write(dir.join("fails.lox"), "print 2;\nprint -nil;").unwrap();
+  let dir_arg = dir.to_str().unwrap();
+
+  let before = interpreter(&["test", dir_arg], "");
+  let blessed = interpreter(&["test", dir_arg, "--bless"], "");
+  let after = interpreter(&["test", dir_arg], "");
+
+  let golden =
+    |extension: &str| std::fs::read_to_string(dir.join("fails").with_extension(extension));
+  assert_eq!(before.status.code(), Some(1));
+  assert_eq!(
+    String::from_utf8_lossy(&blessed.stdout),
+    "0 passed, 2 blessed\n"
+  );
+  assert_eq!(golden("out").unwrap(), "2\n");
+  assert_eq!(golden("exit").unwrap(), "70\n");
+  assert!(golden("err").unwrap().contains("Expected a number"));
+  assert!(after.status.success());
+  assert_eq!(
+    String::from_utf8_lossy(&after.stdout),
+    "2 passed, 0 failed\n"
+  );
+  std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn run_time_reports_each_phase() {
+  let output = interpreter(&["run", "-e", "print 1;", "--time"], "");
+  assert!(output.status.success());
+  let stderr = String::from_utf8_lossy(&output.stderr);
+  let phases: Vec<&str> = stderr
+    .lines()
+    .filter_map(|line| line.split_whitespace().next())
+    .collect();
+  assert_eq!(phases, ["scan", "parse", "resolve", "interpret"]);
+}
+
+#[test]
+fn evaluate_rejects_input_that_is_not_one_expression() {
+  for code in ["", "var a = 1;", "1 + 2; 3", "1 +"] {
+    let output = interpreter(&["evaluate", "-e", code], "");
+    assert_eq!(output.status.code(), Some(65), "evaluating {code:?}");
+    assert!(!output.stderr.is_empty(), "evaluating {code:?}");
+  }
+}
+
+#[test]
+fn run_trace_logs_statements_and_values_to_stderr() {
+  let output = interpreter(&["run", "-e", "var a = 2;\nprint a * 3;", "--trace"], "");
+  assert!(output.status.success());
+  assert_eq!(String::from_utf8_lossy(&output.stdout), "6\n\n");
+  assert_eq!(
+    String::from_utf8_lossy(&output.stderr),
+    concat!(
+      "[line 1] exec var a = 2;\n",
+      "[line 2] exec print a * 3;\n",
+      "[line 2] eval a => 2\n",
+      "[line 2] eval a * 3 => 6\n",
+    )
+  );
+}
+
+#[test]
+fn run_profile_reports_calls_per_function() {
+  let src = "fun f() {} fun g() { f(); f(); } g(); g();";
+  let output = interpreter(&["run", "-e", src, "--profile"], "");
+  assert!(output.status.success());
+  let stderr = String::from_utf8_lossy(&output.stderr);
+  let calls: Vec<(&str, &str)> = stderr
+    .lines()
+    .skip(1)
+    .map(|line| {
+      let mut columns = line.split_whitespace();
+      (columns.next().unwrap(), columns.next().unwrap())
+    })
+    .collect();
+  assert_eq!(calls, [("g", "2"), ("f", "4")]);
+}
+
+#[test]
+fn run_coverage_writes_an_lcov_file() {
+  let path = std::env::temp_dir().join(format!("lox-coverage-{}.info", std::process::id()));
+  let src = "var a = 1;\nif (a > 1)\n  print a;";
+  let output = interpreter(
+    &["run", "-e", src, "--coverage", path.to_str().unwrap()],
+    "",
+  );
+  assert!(output.status.success());
+  assert_eq!(
+    std::fs::read_to_string(&path).unwrap(),
+    "SF:<eval>\nDA:1,1\nDA:2,1\nDA:3,0\nLH:2\nLF:3\nend_of_record\n"
+  );
+  std::fs::remove_file(path).unwrap();
+}
+
+/// Writes `files` into a fresh directory and runs `main.lox` from it.
+fn write_files(name: &str, files: &[(&str, &str)]) -> PathBuf {
+  let dir = std::env::temp_dir().join(format!("lox-{name}-{}", std::process::id()));
+  for (path, src) in files {
+    let path = dir.join(path);
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, src).unwrap();
+  }
+  dir
+}
+
+fn run_files(name: &str, files: &[(&str, &str)]) -> Output {
+  let dir = write_files(name, files);
+  let output = interpreter(&["run", dir.join("main.lox").to_str().unwrap()], "");
+  std::fs::remove_dir_all(dir).unwrap();
+  output
+}
+
+#[test]
+fn imports_run_each_file_once_relative_to_the_importer() {
+  let output = run_files(
+    "imports",
+    &[
+      (
+        "main.lox",
+        "import \"lib/util.lox\";\nimport \"shared.lox\";\nprint double(counter);",
+      ),
+      (
+        "lib/util.lox",
+        "import \"../shared.lox\";\nfun double(x) { return x * 2; }",
+      ),
+      ("shared.lox", "var counter = 0;\ncounter = counter + 21;"),
+    ],
+  );
+  assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+  assert_eq!(String::from_utf8_lossy(&output.stdout), "42\n\n");
+}
+
+#[test]
+fn run_without_a_file_runs_the_main_of_the_project_manifest() {
+  let dir = write_files(
+    "manifest",
+    &[
+      (
+        "lox.toml",
+        "main = \"app/main.lox\"\nsources = [\"app\", \"lib\"]",
+      ),
+      ("app/main.lox", "import \"util.lox\";\nprint double(21);"),
+      ("lib/util.lox", "fun double(x) { return x * 2; }"),
+      ("docs/.keep", ""),
+    ],
+  );
+  let output = Command::new(env!("CARGO_BIN_EXE_codecrafters-interpreter"))
+    .arg("run")
+    .current_dir(dir.join("docs"))
+    .output()
+    .unwrap();
+  std::fs::remove_dir_all(dir).unwrap();
+  assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+  assert_eq!(String::from_utf8_lossy(&output.stdout), "42\n\n");
+}
+
+#[test]
+fn run_watch_runs_again_when_an_imported_file_changes() {
+  let dir = write_files(
+    "watch",
+    &[
+      ("main.lox", "import \"value.lox\";\nprint value;"),
+      ("value.lox", "var value = 1;"),
+    ],
+  );
+  let mut child = Command::new(env!("CARGO_BIN_EXE_codecrafters-interpreter"))
+    .args(["run", "--watch", dir.join("main.lox").to_str().unwrap()])
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    .spawn()
+    .unwrap();
+  std::thread::sleep(Duration::from_millis(1000));
+  std::fs::write(dir.join("value.lox"), "var value = 2;").unwrap();
+  std::thread::sleep(Duration::from_millis(1000));
+  child.kill().unwrap();
+  let output = child.wait_with_output().unwrap();
+  std::fs::remove_dir_all(dir).unwrap();
+
+  assert_eq!(String::from_utf8_lossy(&output.stdout), "1\n2\n");
+  let stderr = String::from_utf8_lossy(&output.stderr);
+  assert_eq!(stderr.matches("for changes").count(), 2, "{stderr}");
+}
+
+#[test]
+fn import_cycles_are_runtime_errors() {
+  let output = run_files(
+    "import-cycle",
+    &[
+      ("main.lox", "import \"a.lox\";"),
+      ("a.lox", "import \"b.lox\";"),
+      ("b.lox", "import \"a.lox\";"),
+    ],
+  );
+  assert_eq!(output.status.code(), Some(70));
+  let stderr = String::from_utf8_lossy(&output.stderr);
+  assert!(stderr.starts_with("[line 1]: Import cycle: "), "{stderr}");
+  assert!(stderr.contains("a.lox -> "), "{stderr}");
+  assert!(stderr.trim_end().ends_with("a.lox"), "{stderr}");
+}
+
+#[test]
+fn importing_a_missing_or_invalid_file_is_a_runtime_error() {
+  let output = run_files(
+    "import-missing",
+    &[("main.lox", "print 1;\nimport \"nope.lox\";")],
+  );
+  assert_eq!(output.status.code(), Some(70));
+  assert!(String::from_utf8_lossy(&output.stderr).starts_with("[line 2]: Cannot import nope.lox: "));
+
+  let output = run_files(
+    "import-invalid",
+    &[("main.lox", "import \"bad.lox\";"), ("bad.lox", "var = 1;")],
+  );
+  assert_eq!(output.status.code(), Some(70));
+  assert!(String::from_utf8_lossy(&output.stderr)
+    .starts_with("[line 1]: Cannot import bad.lox: Malformed expression"));
+}
+
+#[test]
+fn run_passes_arguments_after_the_separator_to_the_program() {
+  let src = "print argc(); print argv(0) + argv(1);";
+  let output = interpreter(&["run", "-e", src, "--", "a", "--b"], "");
+  assert!(output.status.success());
+  assert_eq!(String::from_utf8_lossy(&output.stdout), "2\na--b\n\n");
+}
+
+#[test]
+fn run_only_lets_programs_touch_files_with_allow_fs() {
+  let path = std::env::temp_dir().join(format!("lox-allow-fs-{}.txt", std::process::id()));
+  let src = format!(
+    "writeFile({:?}, \"saved\"); print readFile({:?});",
+    path, path
+  );
+
+  let denied = interpreter(&["run", "-e", &src], "");
+  assert_eq!(denied.status.code(), Some(70));
+  assert!(String::from_utf8_lossy(&denied.stderr).contains("file access is not allowed"));
+  assert!(!path.exists());
+
+  let allowed = interpreter(&["run", "-e", &src, "--allow-fs"], "");
+  assert!(allowed.status.success());
+  assert_eq!(String::from_utf8_lossy(&allowed.stdout), "saved\n\n");
+  std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn run_exits_with_the_code_passed_to_exit() {
+  let output = interpreter(
+    &[
+      "run",
+      "-e",
+      "print \"bye\"; exit(3); print \"unreachable\";",
+    ],
+    "",
+  );
+  assert_eq!(output.status.code(), Some(3));
+  assert_eq!(String::from_utf8_lossy(&output.stdout), "bye\n");
+  assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+
+  let success = interpreter(&["run", "-e", "exit(0); print \"unreachable\";"], "");
+  assert!(success.status.success());
+  assert_eq!(String::from_utf8_lossy(&success.stdout), "\n");
+}
+
+#[test]
+fn run_joins_strings_with_other_values_with_lenient_concat() {
+  let src = "print \"count: \" + 3;";
+  let strict = interpreter(&["run", "-e", src], "");
+  assert_eq!(strict.status.code(), Some(70));
+
+  let lenient = interpreter(&["run", "-e", src, "--lenient-concat"], "");
+  assert!(lenient.status.success());
+  assert_eq!(String::from_utf8_lossy(&lenient.stdout), "count: 3\n\n");
+}
+
+#[test]
+fn run_reports_the_dead_code_it_skips_with_warnings() {
+  let src = "fun f() {\n  return 1;\n  print 2;\n}\nprint f();";
+  let quiet = interpreter(&["run", "-e", src], "");
+  assert!(quiet.status.success());
+  assert_eq!(String::from_utf8_lossy(&quiet.stderr), "");
+
+  let output = interpreter(&["run", "-e", src, "--warnings"], "");
+  assert!(output.status.success());
+  assert_eq!(String::from_utf8_lossy(&output.stdout), "1\n\n");
+  assert_eq!(
+    String::from_utf8_lossy(&output.stderr),
+    "warning: [line 2]: Code after 'return' is never executed.\n"
+  );
+}
+
+#[test]
+fn error_format_json_prints_one_object_per_error() {
+  let args = [
+    "run",
+    "--error-format",
+    "json",
+    "-e",
+    "print 1 +;\nfun () {}",
+  ];
+  let output = interpreter(&args, "");
+  assert_eq!(output.status.code(), Some(65));
+  let stderr = String::from_utf8_lossy(&output.stderr);
+  let errors = stderr
+    .lines()
+    .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+    .collect::<Vec<_>>();
+  assert_eq!(errors.len(), 2);
+  assert_eq!(errors[0]["code"], "E0101");
+  assert_eq!(errors[1]["code"], "E0103");
+  assert_eq!(errors[1]["line"], 2);
+  assert_eq!(errors[1]["column"], 5);
+  assert_eq!(errors[1]["span"]["length"], 1);
+}
+
+#[test]
+fn error_format_json_applies_to_runtime_errors() {
+  let args = ["run", "--error-format", "json", "-e", "print 1;\nprint x;"];
+  let output = interpreter(&args, "");
+  assert_eq!(output.status.code(), Some(70));
+  assert_eq!(
+    String::from_utf8_lossy(&output.stderr),
+    "{\"code\":\"E1002\",\"message\":\"Undefined variable: x\",\"line\":2,\
+     \"column\":null,\"span\":null}\n"
+  );
+}
+
+#[test]
+fn explain_describes_an_error_code() {
+  let output = interpreter(&["explain", "e1002"], "");
+  assert!(output.status.success());
+  let stdout = String::from_utf8_lossy(&output.stdout);
+  assert!(stdout.starts_with("E1002: UndefinedVariable\n\n"));
+
+  let unknown = interpreter(&["explain", "E9999"], "");
+  assert_eq!(unknown.status.code(), Some(1));
+  assert_eq!(
+    String::from_utf8_lossy(&unknown.stderr),
+    "Unknown error code E9999\n"
+  );
+}
+
+#[test]
+fn run_strict_rejects_reading_variables_before_assigning_them() {
+  let src = "var total;\nprint total;";
+  let lax = interpreter(&["run", "-e", src], "");
+  assert!(lax.status.success());
+  assert_eq!(String::from_utf8_lossy(&lax.stdout), "nil\n\n");
+
+  let strict = interpreter(&["run", "-e", src, "--strict"], "");
+  assert_eq!(strict.status.code(), Some(65));
+  assert_eq!(
+    String::from_utf8_lossy(&strict.stderr),
+    "[line 2]: Variable 'total' is read before anything is assigned to it.\n"
+  );
+}
+
+#[test]
+fn parse_format_dot_prints_a_graphviz_graph() {
+  let dir = write_files("parse-dot", &[("main.lox", "print -1;")]);
+  let output = interpreter(
+    &[
+      "parse",
+      dir.join("main.lox").to_str().unwrap(),
+      "--format",
+      "dot",
+    ],
+    "",
+  );
+  std::fs::remove_dir_all(dir).unwrap();
+
+  assert!(output.status.success());
+  let stdout = String::from_utf8_lossy(&output.stdout);
+  assert!(stdout.starts_with("digraph ast {\n"));
+  assert!(stdout.contains("n2 [label=\"-\"];\n"));
+  assert!(stdout.ends_with("}\n"));
+}
+
+fn phases(output: &Output) -> Vec<String> {
+  String::from_utf8_lossy(&output.stderr)
+    .lines()
+    .filter_map(|line| line.split_whitespace().next())
+    .map(str::to_string)
+    .collect()
+}
+
+#[test]
+fn run_reuses_the_cache_until_the_source_changes() {
+  let dir = write_files("cache", &[("main.lox", "print 1 + 2;")]);
+  let main = dir.join("main.lox");
+  let main = main.to_str().unwrap();
+
+  let emitted = interpreter(&["run", main, "--emit-cache", "--time"], "");
+  assert!(dir.join("main.lox.loxc").exists());
+  assert_eq!(
+    phases(&emitted),
+    ["cache", "scan", "parse", "resolve", "interpret"]
+  );
+
+  let cached = interpreter(&["run", main, "--time"], "");
+  assert_eq!(String::from_utf8_lossy(&cached.stdout), "3\n\n");
+  assert_eq!(phases(&cached), ["cache", "resolve", "interpret"]);
+
+  std::fs::write(dir.join("main.lox"), "print 4;").unwrap();
+  let changed = interpreter(&["run", main, "--time"], "");
+  std::fs::remove_dir_all(&dir).unwrap();
+  assert_eq!(String::from_utf8_lossy(&changed.stdout), "4\n\n");
+  assert_eq!(
+    phases(&changed),
+    ["cache", "scan", "parse", "resolve", "interpret"]
+  );
+}
+
+#[test]
+fn the_cache_of_a_loxc_file_does_not_overwrite_it() {
+  let dir = write_files("cache-name", &[("main.loxc", "print 1;")]);
+  let main = dir.join("main.loxc");
+  let main = main.to_str().unwrap();
+
+  interpreter(&["run", main, "--emit-cache"], "");
+  let again = interpreter(&["run", main], "");
+  let source = std::fs::read_to_string(dir.join("main.loxc")).unwrap();
+  std::fs::remove_dir_all(&dir).unwrap();
+  assert_eq!(source, "print 1;");
+  assert_eq!(String::from_utf8_lossy(&again.stdout), "1\n\n");
+}
+
+#[test]
+fn run_seed_makes_random_reproducible() {
+  let src = "print random(); print random();";
+  let first = interpreter(&["run", "-e", src, "--seed", "42"], "");
+  let second = interpreter(&["run", "-e", src, "--seed", "42"], "");
+  assert!(first.status.success());
+  assert_eq!(first.stdout, second.stdout);
+
+  let set_seed = interpreter(&["run", "-e", &format!("setSeed(42); {src}")], "");
+  assert_eq!(first.stdout, set_seed.stdout);
+}
+
+#[test]
+fn long_operator_chains_are_reported_instead_of_overflowing_the_stack() {
+  let code = format!("print 1{};", " + 1".repeat(20_000));
+  let output = interpreter(&["run", "-e", &code], "");
+  assert_eq!(output.status.code(), Some(65));
+  assert!(String::from_utf8_lossy(&output.stderr).contains("Code is nested too deeply."));
+}
@@ -0,0 +1,114 @@
+use codecrafters_interpreter::interpret::error::RuntimeError;
+use codecrafters_interpreter::interpret::value::ValueConversionError;
+use codecrafters_interpreter::{Interpreter, Lox, LoxError, LoxParser, Scanner, Value};
+use std::io::Cursor;
+
+#[test]
+fn programs_can_be_run_from_other_crates() {
+  let mut out: Vec<u8> = vec![];
+  Lox::run(
+    "var greeting = \"hello\"; print greeting + \" world\";",
+    &mut out,
+  )
+  .unwrap();
+  assert_eq!(String::from_utf8(out).unwrap(), "hello world\n");
+}
+
+#[test]
+fn errors_can_be_displayed() {
+  let mut out: Vec<u8> = vec![];
+  let error = Lox::run("print -\"a\";", &mut out).unwrap_err();
+  assert!(matches!(error, LoxError::Runtime(_)));
+  assert_eq!(
+    error.to_string(),
+    "[line 1, column 7]: Expected a number, got a String"
+  );
+}
+
+fn interpreter_after(src: &str) -> Interpreter<Vec<u8>> {
+  let mut cursor = Cursor::new(src);
+  let tokens = Scanner::new(&mut cursor).scan_tokens().0;
+  let stmts = LoxParser::new(tokens).parse().unwrap();
+  let mut interpreter = Interpreter::new(vec![]);
+  interpreter.interpret_stmts(&stmts).unwrap();
+  interpreter
+}
+
+#[test]
+fn rust_values_convert_into_lox_values() {
+  assert_eq!(Value::from(1.5), Value::Number(1.5));
+  assert_eq!(Value::from(true), Value::Boolean(true));
+  assert_eq!(Value::from("text"), Value::String("text".into()));
+}
+
+#[test]
+fn lox_values_convert_into_rust_values() {
+  assert_eq!(f64::try_from(Value::Number(2.0)), Ok(2.0));
+  let name: String = Value::from("lox").try_into().unwrap();
+  assert_eq!(name, "lox");
+  assert_eq!(
+    f64::try_from(Value::Nil),
+    Err(ValueConversionError {
+      expected: "Number",
+      got: "nil"
+    })
+  );
+}
+
+#[test]
+fn globals_can_be_read_after_running() {
+  let interpreter =
+    interpreter_after("var total = 0; for (var i = 1; i <= 4; i = i + 1) total = total + i;");
+  assert_eq!(interpreter.get_global("total"), Some(Value::Number(10.0)));
+  assert_eq!(interpreter.get_global("i"), None);
+}
+
+#[test]
+fn globals_can_be_defined_before_running() {
+  let mut cursor = Cursor::new("var doubled = limit * 2;");
+  let tokens = Scanner::new(&mut cursor).scan_tokens().0;
+  let stmts = LoxParser::new(tokens).parse().unwrap();
+  let mut interpreter = Interpreter::new(vec![]);
+  interpreter.define_global("limit", 21.0);
+  interpreter.interpret_stmts(&stmts).unwrap();
+  assert_eq!(interpreter.get_global("doubled"), Some(Value::Number(42.0)));
+}
+
+#[test]
+fn lox_functions_can_be_called_by_name() {
+  let mut interpreter = interpreter_after("fun greet(name) { return \"hi \" + name; }");
+  let greeting = interpreter
+    .call_function("greet", vec!["ana".into()])
+    .unwrap();
+  assert_eq!(String::try_from(greeting).unwrap(), "hi ana");
+}
+
+#[test]
+fn calling_from_rust_reports_bad_calls() {
+  let mut interpreter = interpreter_after("var x = 1; fun f(a) {}");
+  assert_eq!(
+    interpreter.call_function("missing", vec![]),
+    Err(RuntimeError::UndefinedVariable(0, "missing".to_string()))
+  );
+  assert_eq!(
+    interpreter.call_function("x", vec![]),
+    Err(RuntimeError::NotAFunction(0, "Number".to_string()))
+  );
+  assert_eq!(
+    interpreter.call_function("f", vec![]),
+    Err(RuntimeError::WrongNumberOfArguments(
+      0,
+      "f".to_string(),
+      1,
+      0
+    ))
+  );
+}
+
+#[test]
+fn expressions_can_be_evaluated_without_a_program() {
+  assert_eq!(
+    Lox::evaluate("\"a\" + \"b\""),
+    Ok(Value::String("ab".into()))
+  );
+}
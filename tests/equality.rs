@@ -0,0 +1,91 @@
+use codecrafters_interpreter::Lox;
+
+fn run(src: &str) -> String {
+  let mut out: Vec<u8> = vec![];
+  Lox::run(src, &mut out).unwrap();
+  String::from_utf8(out).unwrap()
+}
+
+fn lines(results: &[bool]) -> String {
+  results.iter().map(|r| r.to_string() + "\n").collect()
+}
+
+#[test]
+fn nil_is_only_equal_to_nil() {
+  let res = run("print nil == nil; print nil != nil; print nil == false; print nil == 0;");
+  assert_eq!(res, lines(&[true, false, false, false]));
+}
+
+#[test]
+fn values_of_different_types_are_never_equal() {
+  let res = run(
+    "print 1 == \"1\"; print true == 1; print \"true\" == true; print 0 == false; print \"\" == nil;",
+  );
+  assert_eq!(res, lines(&[false, false, false, false, false]));
+}
+
+#[test]
+fn nan_is_not_equal_to_itself() {
+  let res = run("var nan = sqrt(-1); print nan == nan; print nan != nan; print nan == 1;");
+  assert_eq!(res, lines(&[false, true, false]));
+}
+
+#[test]
+fn zeroes_of_either_sign_are_equal() {
+  assert_eq!(run("print 0 == -0;"), lines(&[true]));
+}
+
+#[test]
+fn strings_are_equal_by_content() {
+  let res = run("var a = \"ab\"; print a == \"a\" + \"b\"; print a == \"AB\";");
+  assert_eq!(res, lines(&[true, false]));
+}
+
+#[test]
+fn functions_are_equal_only_to_themselves() {
+  let src = "
+    fun f() {}
+    fun g() {}
+    var h = f;
+    print f == f;
+    print f == h;
+    print f == g;
+    fun make() { fun inner() {} return inner; }
+    print make() == make();
+  ";
+  assert_eq!(run(src), lines(&[true, true, false, false]));
+}
+
+#[test]
+fn identical_looking_functions_are_different() {
+  let src = "
+    var first;
+    { fun same() { return 1; } first = same; }
+    var second;
+    { fun same() { return 1; } second = same; }
+    print first == second;
+  ";
+  assert_eq!(run(src), lines(&[false]));
+}
+
+#[test]
+fn natives_are_equal_only_to_themselves() {
+  let res = run("var c = clock; print c == clock; print clock == len; print clock == clock;");
+  assert_eq!(res, lines(&[true, false, true]));
+}
+
+#[test]
+fn classes_and_instances_are_equal_only_to_themselves() {
+  let src = "
+    class A {}
+    class B {}
+    var a = A();
+    var alias = a;
+    print A == A;
+    print A == B;
+    print a == alias;
+    print a == A();
+    print A == a;
+  ";
+  assert_eq!(run(src), lines(&[true, false, true, false, false]));
+}
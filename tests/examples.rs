@@ -0,0 +1,17 @@
+use std::process::Command;
+
+#[test]
+fn examples_match_their_golden_files() {
+  let output = Command::new(env!("CARGO_BIN_EXE_codecrafters-interpreter"))
+    .arg("test")
+    .arg(concat!(env!("CARGO_MANIFEST_DIR"), "/examples"))
+    .output()
+    .expect("Cannot execute the interpreter");
+
+  assert!(
+    output.status.success(),
+    "{}{}",
+    String::from_utf8_lossy(&output.stdout),
+    String::from_utf8_lossy(&output.stderr)
+  );
+}
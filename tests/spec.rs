@@ -0,0 +1,138 @@
+//! Runs the programs in `tests/spec/`, written in the format of the craftinginterpreters test
+//! suite: what each program should do is encoded in comments.
+//!
+//! - `// expect: <text>`: the program prints this line.
+//! - `// expect runtime error: <message>`: the program stops with a runtime error on this line.
+//! - `// Error ...` or `// [line N] Error ...`: the program is rejected before running, with an
+//!   error on this line (or on line N). Expectations only meant for clox (`// [c line N] ...`)
+//!   are ignored.
+//!
+//! Error messages differ from the reference implementations, so only the line they point to is
+//! checked, together with the exit code.
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+#[derive(Debug, Default)]
+struct Expectations {
+  output: Vec<String>,
+  runtime_error_line: Option<usize>,
+  static_error_lines: Vec<usize>,
+}
+
+impl Expectations {
+  fn parse(source: &str) -> Expectations {
+    let mut expectations = Expectations::default();
+
+    for (index, line) in source.lines().enumerate() {
+      let line_number = index + 1;
+      let Some((_, comment)) = line.split_once("// ") else {
+        continue;
+      };
+
+      if let Some(output) = comment.strip_prefix("expect: ") {
+        expectations.output.push(output.to_string());
+      } else if comment.starts_with("expect runtime error: ") {
+        expectations.runtime_error_line = Some(line_number);
+      } else if comment.starts_with("Error") {
+        expectations.static_error_lines.push(line_number);
+      } else if let Some(rest) = comment
+        .strip_prefix("[line ")
+        .or_else(|| comment.strip_prefix("[java line "))
+      {
+        let number: Option<usize> = rest.split(']').next().and_then(|n| n.parse().ok());
+        expectations.static_error_lines.extend(number);
+      }
+    }
+
+    expectations
+  }
+
+  fn exit_code(&self) -> i32 {
+    if !self.static_error_lines.is_empty() {
+      65
+    } else if self.runtime_error_line.is_some() {
+      70
+    } else {
+      0
+    }
+  }
+}
+
+fn spec_programs(dir: &Path) -> Vec<PathBuf> {
+  let mut programs = vec![];
+  for entry in fs::read_dir(dir).unwrap() {
+    let path = entry.unwrap().path();
+    if path.is_dir() {
+      programs.extend(spec_programs(&path));
+    } else if path.extension().is_some_and(|ext| ext == "lox") {
+      programs.push(path);
+    }
+  }
+  programs.sort();
+  programs
+}
+
+fn check(program: &Path) -> Vec<String> {
+  let expected = Expectations::parse(&fs::read_to_string(program).unwrap());
+  let output = Command::new(env!("CARGO_BIN_EXE_codecrafters-interpreter"))
+    .arg("run")
+    .arg(program)
+    .output()
+    .expect("Cannot execute the interpreter");
+  let stdout = String::from_utf8_lossy(&output.stdout);
+  let stderr = String::from_utf8_lossy(&output.stderr);
+  let exit_code = output.status.code().unwrap_or(-1);
+
+  let mut failures = vec![];
+  let mut printed: Vec<&str> = stdout.lines().collect();
+  // A successful run ends with an empty line after the program's own output.
+  if exit_code == 0 && printed.last() == Some(&"") {
+    printed.pop();
+  }
+  if printed != expected.output {
+    failures.push(format!(
+      "expected output {:?}, got {:?}",
+      expected.output, printed
+    ));
+  }
+  if exit_code != expected.exit_code() {
+    failures.push(format!(
+      "expected exit code {}, got {exit_code} (stderr: {stderr:?})",
+      expected.exit_code()
+    ));
+  }
+  let error_lines = expected
+    .static_error_lines
+    .iter()
+    .chain(expected.runtime_error_line.iter());
+  for line in error_lines {
+    if !stderr.contains(&format!("line {line}")) {
+      failures.push(format!("expected an error on line {line}, got {stderr:?}"));
+    }
+  }
+  failures
+}
+
+#[test]
+fn spec_programs_behave_as_their_comments_expect() {
+  let programs = spec_programs(&Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/spec"));
+  assert!(!programs.is_empty());
+
+  let mut report = vec![];
+  for program in &programs {
+    let failures = check(program);
+    if !failures.is_empty() {
+      report.push(format!("FAIL {}", program.display()));
+      report.extend(failures.into_iter().map(|f| format!("  {f}")));
+    }
+  }
+
+  assert!(
+    report.is_empty(),
+    "{}\n{} of {} spec programs failed",
+    report.join("\n"),
+    report.iter().filter(|l| l.starts_with("FAIL")).count(),
+    programs.len()
+  );
+}